@@ -1,3 +1,4 @@
+use rrad_xla::pjrt::error::PjrtResult;
 use rrad_xla::pjrt::loader::PjrtRuntime;
 use std::path::Path;
 use std::process::ExitCode;
@@ -17,6 +18,52 @@ fn main() -> ExitCode {
         return ExitCode::FAILURE;
     }
 
-    println!("done: {}", plugin);
-    ExitCode::SUCCESS
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.as_slice() {
+        [] => {
+            println!("done: {}", plugin);
+            ExitCode::SUCCESS
+        }
+        [command] if command == "info" => info(&rt),
+        [command, path] if command == "compile" => compile(&rt, Path::new(path)),
+        _ => {
+            eprintln!("usage: rrad_xla [info | compile <program file>]");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// Prints a summary of the default client's topology.
+fn info(rt: &PjrtRuntime) -> ExitCode {
+    report(topology_summary(rt), "failed to describe the topology")
+}
+
+// Compiles a program file and prints what the plugin reports about the executable.
+fn compile(rt: &PjrtRuntime, path: &Path) -> ExitCode {
+    let context = format!("failed to compile {}", path.display());
+    report(compile_metadata(rt, path), &context)
+}
+
+fn topology_summary(rt: &PjrtRuntime) -> PjrtResult<impl std::fmt::Debug> {
+    let client = rt.create_client_raii()?;
+    Ok(client.topology_description()?.summary())
+}
+
+fn compile_metadata(rt: &PjrtRuntime, path: &Path) -> PjrtResult<impl std::fmt::Debug> {
+    let client = rt.create_client_raii()?;
+    let executable = client.compile_file(path, &[])?;
+    Ok(executable.metadata()?)
+}
+
+fn report(result: PjrtResult<impl std::fmt::Debug>, context: &str) -> ExitCode {
+    match result {
+        Ok(value) => {
+            println!("{value:#?}");
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("{context}: {err}");
+            ExitCode::FAILURE
+        }
+    }
 }
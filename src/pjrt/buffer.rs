@@ -1,22 +1,60 @@
+use std::any::Any;
+use std::fmt;
 use std::mem;
 use std::ptr;
 use std::ptr::null_mut;
 use std::slice::from_raw_parts;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
 
+use crate::pjrt::client::fulfill_alias_buffer_raw;
 use crate::pjrt::device::PJRTDevice;
-use crate::pjrt::event::PJRTEvent;
+use crate::pjrt::dlpack::{
+    dl_data_type, dl_strides, export_managed_tensor, DLDevice, DLDeviceType, DLManagedTensor,
+};
+use crate::pjrt::error::{
+    pjrt_check, ContextFrame, OwnedPjrtError, PJRTError, PjrtErrorCode, PjrtResult,
+};
+use crate::pjrt::event::{EventStatus, PJRTEvent, WaitOutcome};
+use crate::pjrt::layout::MemoryLayout;
 use crate::pjrt::loader::{error_to_string, PjrtRuntime};
+use crate::pjrt::memory::PJRTMemory;
+#[cfg(feature = "npy")]
+use crate::pjrt::npy::{encode_npy_header, NpyHeader};
 use crate::pjrt::topology_desc::PJRTNamedAttribute;
+use crate::pjrt::utils::{
+    element_byte_size, element_count, host_byte_size, BufferType, DebugResult, LogicalShape,
+    PjrtScalar,
+};
 use crate::pjrt_sys::*;
+#[cfg(feature = "tokio")]
+use tokio::sync::oneshot;
+
+#[cfg(feature = "tokio")]
+type HostTransferResult = Result<Vec<u8>, (PjrtErrorCode, String)>;
+#[cfg(feature = "tokio")]
+type PendingHostTransfer = Mutex<Option<(Vec<u8>, oneshot::Sender<HostTransferResult>)>>;
 
 pub struct PJRTBuffer<'a> {
     pub rt: &'a PjrtRuntime,
     pub raw: *mut PJRT_Buffer,
+    // Host memory the runtime may still read from; fields drop after Drop::drop,
+    // so this outlives PJRT_Buffer_Destroy.
+    keepalive: Option<Box<dyn Any + Send>>,
 }
 
 impl<'a> PJRTBuffer<'a> {
     pub(crate) fn new(rt: &'a PjrtRuntime, raw: *mut PJRT_Buffer) -> Self {
-        Self { rt, raw }
+        Self {
+            rt,
+            raw,
+            keepalive: None,
+        }
+    }
+
+    pub(crate) fn with_keepalive(mut self, keepalive: Box<dyn Any + Send>) -> Self {
+        self.keepalive = Some(keepalive);
+        self
     }
 
     pub fn raw(&self) -> *mut PJRT_Buffer {
@@ -78,7 +116,11 @@ impl<'a> PJRTBuffer<'a> {
         }
     }
 
-    pub fn element_type(&self) -> Result<PJRT_Buffer_Type, String> {
+    pub fn element_type(&self) -> Result<BufferType, String> {
+        BufferType::try_from(self.element_type_raw()?)
+    }
+
+    pub fn element_type_raw(&self) -> Result<PJRT_Buffer_Type, String> {
         let raw = self.raw_checked()?;
 
         let f = self
@@ -167,6 +209,14 @@ impl<'a> PJRTBuffer<'a> {
         Ok(unsafe { from_raw_parts(args.unpadded_dims, args.num_dims).to_vec() })
     }
 
+    pub fn logical_shape(&self) -> Result<LogicalShape, String> {
+        LogicalShape::new(
+            self.dimensions()?,
+            self.unpadded_dimensions()?,
+            &self.dynamic_dimension_indices()?,
+        )
+    }
+
     pub fn dynamic_dimension_indices(&self) -> Result<Vec<usize>, String> {
         let raw = self.raw_checked()?;
 
@@ -230,12 +280,11 @@ impl<'a> PJRTBuffer<'a> {
     }
 
     pub fn device_ref(&self) -> Result<PJRTDevice<'a>, String> {
-        let raw_device = self.device()?;
-        Ok(PJRTDevice::new(self.rt, raw_device))
+        PJRTDevice::new(self.rt, self.device()?)
     }
 
     pub fn device_id(&self) -> Result<i32, String> {
-        self.device_ref()?.id()
+        Ok(self.device_ref()?.id())
     }
 
     pub fn device_kind(&self) -> Result<String, String> {
@@ -278,7 +327,7 @@ impl<'a> PJRTBuffer<'a> {
         }
     }
 
-    pub fn get_memory_layout(&self) -> Result<PJRT_Buffer_MemoryLayout, String> {
+    pub fn get_memory_layout_raw(&self) -> Result<PJRT_Buffer_MemoryLayout, String> {
         let raw = self.raw_checked()?;
 
         let f = self
@@ -302,6 +351,12 @@ impl<'a> PJRTBuffer<'a> {
         }
     }
 
+    pub fn get_memory_layout(&self) -> Result<MemoryLayout, String> {
+        let layout = self.get_memory_layout_raw()?;
+        // The arrays in `layout` are owned by the buffer, which outlives this call.
+        unsafe { MemoryLayout::from_raw(&layout) }
+    }
+
     pub fn ready_event(&self) -> Result<PJRTEvent<'a>, String> {
         let raw = self.raw_checked()?;
 
@@ -329,19 +384,84 @@ impl<'a> PJRTBuffer<'a> {
         Ok(PJRTEvent::new(self.rt, args.event))
     }
 
+    pub fn wait_until_ready(&self) -> Result<(), PJRTError<'a>> {
+        self.ready_event_or_error()?
+            .await_status()
+            .map_err(|(code, message)| PJRTError::with_code(self.rt, code, message))
+    }
+
+    // Built on PJRTEvent::await_timeout, which polls instead of registering OnReady, so
+    // timing out leaves no callback behind and the event is destroyed on return.
+    // Ok(false) means the buffer was still pending at the deadline.
+    pub fn wait_until_ready_timeout(&self, timeout: Duration) -> Result<bool, PJRTError<'a>> {
+        match self.ready_event_or_error()?.await_timeout(timeout)? {
+            WaitOutcome::Ready(status) => status.map(|()| true),
+            WaitOutcome::TimedOut => Ok(false),
+        }
+    }
+
+    fn ready_event_or_error(&self) -> Result<PJRTEvent<'a>, PJRTError<'a>> {
+        self.ready_event()
+            .map_err(|e| PJRTError::from_wrapper_message(self.rt, PjrtErrorCode::Unknown, e))
+    }
+
+    pub fn element_count(&self) -> Result<usize, String> {
+        element_count(&self.dimensions()?)
+    }
+
+    // Dense host size (no device padding), unlike on_device_size_in_bytes.
+    pub fn host_byte_size(&self) -> Result<usize, String> {
+        host_byte_size(&self.dimensions()?, self.element_type()?)
+    }
+
     pub fn to_host_buffer_async(&self, dst: &mut [u8]) -> Result<PJRTEvent<'a>, String> {
+        self.to_host_buffer_async_impl(dst, None)
+    }
+
+    pub fn to_host_buffer_async_with_layout(
+        &self,
+        dst: &mut [u8],
+        layout: &MemoryLayout,
+    ) -> Result<PJRTEvent<'a>, String> {
+        self.to_host_buffer_async_impl(dst, Some(layout))
+    }
+
+    fn to_host_buffer_async_impl(
+        &self,
+        dst: &mut [u8],
+        layout: Option<&MemoryLayout>,
+    ) -> Result<PJRTEvent<'a>, String> {
         let raw = self.raw_checked()?;
+        // Types without a fixed host size (sub-byte) and padded or tiled host layouts are left
+        // for the plugin to check.
+        let dense = layout.is_none_or(MemoryLayout::is_dense);
+        if let (true, Ok(needed)) = (dense, self.host_byte_size()) {
+            if dst.len() < needed {
+                return Err(format!(
+                    "destination holds {} bytes but the {} buffer with dims {:?} needs {}",
+                    dst.len(),
+                    self.element_type()?,
+                    self.dimensions()?,
+                    needed
+                ));
+            }
+        }
         let f = self
             .rt
             .api()
             .PJRT_Buffer_ToHostBuffer
             .ok_or("PJRT_Buffer_ToHostBuffer symbol not found")?;
 
+        // Keeps the layout arrays alive until the call returns.
+        let mut host_layout = layout.map(MemoryLayout::to_raw);
+
         let mut args = PJRT_Buffer_ToHostBuffer_Args {
             struct_size: PJRT_Buffer_ToHostBuffer_Args_STRUCT_SIZE as usize,
             extension_start: ptr::null_mut(),
             src: raw,
-            host_layout: ptr::null_mut(),
+            host_layout: host_layout
+                .as_mut()
+                .map_or(ptr::null_mut(), |l| l.as_mut_ptr()),
             dst: if dst.is_empty() {
                 ptr::null_mut()
             } else {
@@ -351,10 +471,11 @@ impl<'a> PJRTBuffer<'a> {
             event: ptr::null_mut(),
         };
 
-        let err = unsafe { f(&mut args) };
-        if !err.is_null() {
-            return Err(error_to_string(self.rt.api(), err));
-        }
+        pjrt_check!(
+            self.rt,
+            unsafe { f(&mut args) },
+            ContextFrame::new("PJRT_Buffer_ToHostBuffer").device(self.device_id().ok())
+        )?;
         if args.event.is_null() {
             return Err("PJRT_Buffer_ToHostBuffer returned null completion event".to_string());
         }
@@ -414,6 +535,149 @@ impl<'a> PJRTBuffer<'a> {
         event.ok()
     }
 
+    pub fn to_host_buffer_blocking_with_layout(
+        &self,
+        dst: &mut [u8],
+        layout: &MemoryLayout,
+    ) -> Result<(), String> {
+        let event = self.to_host_buffer_async_with_layout(dst, layout)?;
+        event.ok()
+    }
+
+    fn check_element_type<T: PjrtScalar>(&self) -> Result<(), String> {
+        let element_type = self.element_type()?;
+        if element_type != T::TYPE {
+            return Err(format!(
+                "buffer element type is {}, expected {}",
+                element_type,
+                T::TYPE
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn to_host_vec<T: PjrtScalar>(&self) -> Result<Vec<T>, String> {
+        self.check_element_type::<T>()?;
+        let mut bytes = vec![0u8; self.host_byte_size()?];
+        self.to_host_buffer_blocking(&mut bytes)?;
+        Ok(bytes.chunks_exact(T::BYTES).map(T::from_le_slice).collect())
+    }
+
+    // Reads straight into a caller-owned slice so repeated readbacks can reuse one
+    // allocation. Element bytes land in host byte order, which is what `T` expects.
+    pub fn to_host_into<T: PjrtScalar>(&self, dst: &mut [T]) -> Result<(), PJRTError<'a>> {
+        let event = self.to_host_into_async(dst)?;
+        event
+            .ok()
+            .map_err(|e| PJRTError::from_wrapper_message(self.rt, PjrtErrorCode::Unknown, e))
+    }
+
+    pub fn to_host_into_async<T: PjrtScalar>(
+        &self,
+        dst: &mut [T],
+    ) -> Result<PJRTEvent<'a>, PJRTError<'a>> {
+        let invalid =
+            |e: String| PJRTError::from_wrapper_message(self.rt, PjrtErrorCode::InvalidArgument, e);
+        self.check_element_type::<T>().map_err(invalid)?;
+        let needed = element_count(&self.dimensions().map_err(invalid)?).map_err(invalid)?;
+        if dst.len() != needed {
+            return Err(invalid(format!(
+                "destination holds {} elements but the buffer has {needed}",
+                dst.len()
+            )));
+        }
+
+        // PjrtScalar types are plain numbers (or bool, which PJRT writes as 0/1), so any
+        // bytes the plugin writes form valid values; the byte view covers exactly `dst`.
+        let bytes = unsafe {
+            std::slice::from_raw_parts_mut(dst.as_mut_ptr().cast::<u8>(), mem::size_of_val(dst))
+        };
+        self.to_host_buffer_async(bytes)
+            .map_err(|e| PJRTError::from_wrapper_message(self.rt, PjrtErrorCode::Unknown, e))
+    }
+
+    // Awaitable readback for async callers. The destination and the completion closure are
+    // owned by the event registration, so dropping the future before the transfer finishes
+    // leaves the plugin writing into memory that is still alive.
+    #[cfg(feature = "tokio")]
+    pub fn to_host_future<T: PjrtScalar>(
+        &self,
+    ) -> impl std::future::Future<Output = Result<Vec<T>, PJRTError<'a>>> + 'a {
+        let rt = self.rt;
+        let started = self.start_host_transfer::<T>();
+        async move {
+            match started?.await {
+                Ok(Ok(bytes)) => Ok(bytes.chunks_exact(T::BYTES).map(T::from_le_slice).collect()),
+                Ok(Err((code, message))) => Err(PJRTError::with_code(rt, code, message)),
+                Err(_) => Err(PJRTError::with_code(
+                    rt,
+                    PjrtErrorCode::Cancelled,
+                    "readback completion was dropped without running",
+                )),
+            }
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    fn start_host_transfer<T: PjrtScalar>(
+        &self,
+    ) -> Result<oneshot::Receiver<HostTransferResult>, PJRTError<'a>> {
+        let invalid =
+            |e: String| PJRTError::from_wrapper_message(self.rt, PjrtErrorCode::InvalidArgument, e);
+        self.check_element_type::<T>().map_err(invalid)?;
+        let mut bytes = vec![0u8; self.host_byte_size().map_err(invalid)?];
+        let event = self
+            .to_host_buffer_async(&mut bytes)
+            .map_err(|e| PJRTError::from_wrapper_message(self.rt, PjrtErrorCode::Unknown, e))?;
+
+        // The Vec's heap allocation does not move when the Vec is moved into `pending`.
+        let (tx, rx) = oneshot::channel();
+        let pending = Arc::new(Mutex::new(Some((bytes, tx))));
+        let complete = |pending: &PendingHostTransfer, status: EventStatus| {
+            let taken = pending.lock().map(|mut p| p.take());
+            if let Ok(Some((bytes, tx))) = taken {
+                let _ = tx.send(status.map(|_| bytes));
+            }
+        };
+
+        let from_event = Arc::clone(&pending);
+        let registered =
+            event.on_ready_boxed(Box::new(move |status| complete(&from_event, status)));
+        if let Err(e) = registered {
+            log::warn!("PJRT_Event_OnReady failed, waiting for readback synchronously: {e}");
+            complete(&pending, event.await_status());
+        }
+        Ok(rx)
+    }
+
+    #[cfg(feature = "npy")]
+    pub fn write_npy(&self, path: impl AsRef<std::path::Path>) -> Result<(), String> {
+        let header = NpyHeader {
+            element_type: self.element_type()?,
+            dims: self.dimensions()?,
+            fortran_order: false,
+        };
+        let mut out = encode_npy_header(&header)?;
+        let start = out.len();
+        out.resize(start + self.host_byte_size()?, 0);
+        self.to_host_buffer_blocking(&mut out[start..])?;
+
+        let path = path.as_ref();
+        std::fs::write(path, out).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    pub fn to_scalar<T: PjrtScalar>(&self) -> Result<T, String> {
+        self.check_element_type::<T>()?;
+        let dims = self.dimensions()?;
+        if !dims.is_empty() {
+            return Err(format!("buffer is not a scalar: dims {:?}", dims));
+        }
+
+        let mut bytes = vec![0u8; T::BYTES];
+        self.to_host_buffer_blocking(&mut bytes)?;
+        Ok(T::from_le_slice(&bytes))
+    }
+
     pub fn copy_raw_to_host_async(
         &self,
         dst: &mut [u8],
@@ -456,6 +720,31 @@ impl<'a> PJRTBuffer<'a> {
         Ok(PJRTEvent::new(self.rt, args.event))
     }
 
+    // A device-side copy on the buffer's own device. The copy owns separate storage, so
+    // deleting either buffer leaves the other readable.
+    pub fn duplicate(&self) -> Result<PJRTBuffer<'a>, String> {
+        let device = self.device_ref()?;
+        let copy = self.copy_to_device(&device)?;
+        if copy.is_null() {
+            return Err("PJRT_Buffer_CopyToDevice returned null dst_buffer".to_string());
+        }
+        let copy = PJRTBuffer::new(self.rt, copy);
+
+        let (src_type, dst_type) = (self.element_type_raw()?, copy.element_type_raw()?);
+        if src_type != dst_type {
+            return Err(format!(
+                "duplicate: copy has element type {dst_type}, source has {src_type}"
+            ));
+        }
+        let (src_dims, dst_dims) = (self.dimensions()?, copy.dimensions()?);
+        if src_dims != dst_dims {
+            return Err(format!(
+                "duplicate: copy has dims {dst_dims:?}, source has {src_dims:?}"
+            ));
+        }
+        Ok(copy)
+    }
+
     pub fn copy_to_device(&self, device: &PJRTDevice) -> Result<*mut PJRT_Buffer, String> {
         let raw = self.raw_checked()?;
         let dst_device = device.raw();
@@ -488,10 +777,13 @@ impl<'a> PJRTBuffer<'a> {
         }
     }
 
+    // Donates this buffer's storage to a new buffer whose use is gated on `dependency`.
+    // The new buffer is returned right away; the plugin's dependency callback runs from
+    // the event's OnReady handler, and the returned handle reports the event's status.
     pub fn donate_with_control_dependency(
         &self,
         dependency: &PJRTEvent<'a>,
-    ) -> Result<PJRTBuffer<'a>, String> {
+    ) -> Result<(PJRTBuffer<'a>, DonationHandle), String> {
         let raw = self.raw_checked()?;
 
         let f = self
@@ -522,35 +814,31 @@ impl<'a> PJRTBuffer<'a> {
                 "PJRT_Buffer_DonateWithControlDependency returned null out_buffer".to_string(),
             );
         }
+        let out = PJRTBuffer::new(self.rt, args.out_buffer);
 
-        let dependency_status = dependency.ok();
-        let callback_message = match &dependency_status {
-            Ok(()) => Vec::<u8>::new(),
-            Err(message) => message.as_bytes().to_vec(),
-        };
-        let mut callback_args = PJRT_Buffer_DonateWithControlDependency_Callback_Args {
-            struct_size: PJRT_Buffer_DonateWithControlDependency_Callback_Args_STRUCT_SIZE as usize,
+        let dependency_ready = DependencyReadyCallback {
+            callback,
             callback_data: args.callback_data,
-            error_code: if dependency_status.is_ok() {
-                PJRT_Error_Code_PJRT_Error_Code_OK
-            } else {
-                PJRT_Error_Code_PJRT_Error_Code_UNKNOWN
-            },
-            error_message: if callback_message.is_empty() {
-                ptr::null()
-            } else {
-                callback_message.as_ptr() as *const libc::c_char
-            },
-            error_message_size: callback_message.len(),
         };
-        unsafe { callback(&mut callback_args) };
+        let (tx, rx) = mpsc::channel();
+        let notify = move |status: EventStatus| {
+            dependency_ready.invoke(&status);
+            let _ = tx.send(status.map_err(|(code, message)| format!("{code}: {message}")));
+        };
+
+        let fallback = notify.clone();
+        if let Err(e) = dependency.on_ready_boxed(Box::new(notify)) {
+            // The plugin still needs its callback exactly once; block on the event instead.
+            log::warn!("PJRT_Event_OnReady failed, waiting for dependency synchronously: {e}");
+            fallback(dependency.await_status());
+        }
 
-        dependency_status?;
-        Ok(PJRTBuffer::new(self.rt, args.out_buffer))
+        Ok((out, DonationHandle { rx }))
     }
 
-    pub fn copy_to_memory(&self, dst_memory: *mut PJRT_Memory) -> Result<*mut PJRT_Buffer, String> {
+    pub fn copy_to_memory(&self, dst_memory: &PJRTMemory<'_>) -> Result<*mut PJRT_Buffer, String> {
         let raw = self.raw_checked()?;
+        let dst_memory = dst_memory.raw;
         if dst_memory.is_null() {
             return Err("copy_to_memory: dst_memory is null".to_string());
         }
@@ -578,20 +866,79 @@ impl<'a> PJRTBuffer<'a> {
         }
     }
 
+    // Accepts a MemoryKind or a plugin-specific kind string.
+    pub fn copy_to_memory_kind(&self, kind: impl AsRef<str>) -> Result<PJRTBuffer<'a>, String> {
+        let dst = self.find_memory_kind(kind.as_ref())?;
+        let out = self.copy_to_memory(&dst)?;
+        if out.is_null() {
+            return Err("PJRT_Buffer_CopyToMemory returned null dst_buffer".to_string());
+        }
+        Ok(PJRTBuffer::new(self.rt, out))
+    }
+
+    // Looks on the buffer's own device first. Buffers do not expose their client, so the
+    // fallback is every device that can address the buffer's current memory.
+    fn find_memory_kind(&self, kind: &str) -> Result<PJRTMemory<'a>, String> {
+        let mut candidates = self.device_ref()?.memories_with_kinds()?;
+        let current = PJRTMemory::new(self.rt, self.memory()?);
+        for device in current.addressable_by_devices()? {
+            candidates.extend(device.memories_with_kinds()?);
+        }
+
+        let mut available = Vec::new();
+        for (memory, memory_kind) in candidates {
+            if memory_kind == kind {
+                return Ok(memory);
+            }
+            if !available.contains(&memory_kind) {
+                available.push(memory_kind);
+            }
+        }
+        Err(format!(
+            "no memory of kind {kind:?} is addressable from this buffer; available kinds: [{}]",
+            available.join(", ")
+        ))
+    }
+
     pub fn copy_raw_to_host_blocking(&self, dst: &mut [u8], offset: i64) -> Result<(), String> {
         let event = self.copy_raw_to_host_async(dst, offset)?;
         event.ok()
     }
 
+    // Copies `dst.len()` bytes from offset 0 in sequential chunks, reporting
+    // (bytes transferred, total) after each one. On failure the error names the offset of
+    // the chunk that failed; everything before it has already landed in `dst`.
+    pub fn copy_raw_to_host_chunked(
+        &self,
+        dst: &mut [u8],
+        chunk_bytes: usize,
+        mut progress: impl FnMut(u64, u64),
+    ) -> Result<(), String> {
+        if chunk_bytes == 0 {
+            return Err("chunk_bytes must be > 0".to_string());
+        }
+        let total = dst.len() as u64;
+        let mut done = 0u64;
+        for chunk in dst.chunks_mut(chunk_bytes) {
+            let len = chunk.len() as u64;
+            let offset = i64::try_from(done)
+                .map_err(|_| format!("offset {done} does not fit i64 for CopyRawToHost"))?;
+            self.copy_raw_to_host_blocking(chunk, offset).map_err(|e| {
+                format!("copy_raw_to_host_chunked failed at offset {done} of {total}: {e}")
+            })?;
+            done += len;
+            progress(done, total);
+        }
+        Ok(())
+    }
+
+    // The destination is supplied later through the returned HostFutureDestination; the
+    // event fires once the bytes have been written there.
     pub fn copy_raw_to_host_future(
         &self,
         offset: i64,
         transfer_size: i64,
-        callback_data: *mut libc::c_void,
-        future_ready_callback: Option<
-            unsafe extern "C" fn(args: *mut PJRT_Buffer_CopyRawToHostFuture_Callback_Args),
-        >,
-    ) -> Result<PJRTEvent<'a>, String> {
+    ) -> Result<(PJRTEvent<'a>, HostFutureDestination), String> {
         let raw = self.raw_checked()?;
         if offset < 0 {
             return Err("offset must be >= 0".to_string());
@@ -613,8 +960,8 @@ impl<'a> PJRTBuffer<'a> {
             offset,
             transfer_size,
             event: ptr::null_mut(),
-            callback_data,
-            future_ready_callback,
+            callback_data: ptr::null_mut(),
+            future_ready_callback: None,
         };
 
         let err = unsafe { f(&mut args) };
@@ -624,7 +971,60 @@ impl<'a> PJRTBuffer<'a> {
         if args.event.is_null() {
             return Err("PJRT_Buffer_CopyRawToHostFuture returned null event".to_string());
         }
-        Ok(PJRTEvent::new(self.rt, args.event))
+        let event = PJRTEvent::new(self.rt, args.event);
+        if args.future_ready_callback.is_none() {
+            return Err(
+                "PJRT_Buffer_CopyRawToHostFuture returned no future_ready_callback".to_string(),
+            );
+        }
+        let destination = HostFutureDestination {
+            callback_data: args.callback_data,
+            callback: args.future_ready_callback,
+        };
+        Ok((event, destination))
+    }
+
+    // Copies `transfer_size` bytes starting at `offset` into a wrapper-owned allocation and
+    // hands it to `on_done` once the copy event fires, with the event's error code on
+    // failure. The allocation and closure are kept alive by the event registration, so
+    // dropping the returned event is fine.
+    pub fn copy_raw_to_host_with<F>(
+        &self,
+        offset: i64,
+        transfer_size: i64,
+        on_done: F,
+    ) -> PjrtResult<PJRTEvent<'a>>
+    where
+        F: FnOnce(PjrtResult<HostAllocation>) + Send + 'static,
+    {
+        let len = usize::try_from(transfer_size).map_err(|_| {
+            OwnedPjrtError::new(PjrtErrorCode::InvalidArgument, "transfer_size must be >= 0")
+        })?;
+        let (event, destination) = self.copy_raw_to_host_future(offset, transfer_size)?;
+
+        let mut bytes = vec![0u8; len];
+        // The Vec's heap allocation does not move when the Vec is moved into `pending`.
+        unsafe { destination.fulfill(bytes.as_mut_ptr().cast::<libc::c_void>()) };
+
+        let pending = Arc::new(Mutex::new(Some((bytes, on_done))));
+        let complete = move |pending: &Mutex<Option<(Vec<u8>, F)>>, status: EventStatus| {
+            let taken = pending.lock().map(|mut p| p.take());
+            if let Ok(Some((bytes, on_done))) = taken {
+                let status = status.map_err(|(code, message)| OwnedPjrtError::new(code, message));
+                on_done(status.map(|_| HostAllocation { offset, bytes }));
+            }
+        };
+
+        let from_event = Arc::clone(&pending);
+        let registered =
+            event.on_ready_boxed(Box::new(move |status| complete(&from_event, status)));
+        if let Err(e) = registered {
+            // The plugin may still be writing into the allocation; wait for it here instead.
+            log::warn!("PJRT_Event_OnReady failed, waiting for copy synchronously: {e}");
+            let status = event.await_status();
+            complete(&pending, status);
+        }
+        Ok(event)
     }
 
     pub fn is_on_cpu(&self) -> Result<bool, String> {
@@ -678,6 +1078,19 @@ impl<'a> PJRTBuffer<'a> {
         Ok(args.memory)
     }
 
+    // Where the buffer lives, for deciding where to run the computation that consumes it.
+    pub fn resident_device_and_memory(&self) -> Result<(PJRTDevice<'a>, PJRTMemory<'a>), String> {
+        let device = self.device_ref()?;
+        let memory = PJRTMemory::new(self.rt, self.memory()?);
+        debug_assert!(
+            memory
+                .devices()
+                .map_or(true, |devices| devices.contains(&device)),
+            "buffer's device is not among the devices addressing its memory"
+        );
+        Ok((device, memory))
+    }
+
     pub fn increase_external_ref(&self) -> Result<(), String> {
         let raw = self.raw_checked()?;
 
@@ -723,6 +1136,184 @@ impl<'a> PJRTBuffer<'a> {
             Err(error_to_string(self.rt.api(), err))
         }
     }
+
+    // The exported tensor owns the buffer: its deleter returns the external reference and
+    // destroys the buffer, so the consumer must call it exactly once.
+    pub fn to_dlpack(mut self) -> Result<*mut DLManagedTensor, String> {
+        let element_type = self.element_type()?;
+        let dtype = dl_data_type(element_type)
+            .ok_or_else(|| format!("element type {element_type} has no DLPack equivalent"))?;
+        let dims = self.dimensions()?;
+        let strides = dl_strides(
+            &self.get_memory_layout()?,
+            &dims,
+            element_byte_size(element_type)?,
+        )?;
+        let device = self.dl_device()?;
+
+        let guard = self.hold_external_reference()?;
+        let data = guard
+            .opaque_device_memory_data_pointer()?
+            .ok_or("PJRT_Buffer_OpaqueDeviceMemoryDataPointer returned null")?;
+        // From here on the reference and the buffer are owned by the exported tensor's deleter.
+        guard.leak();
+        let raw = mem::replace(&mut self.raw, null_mut());
+
+        Ok(export_managed_tensor(
+            self.rt.api() as *const PJRT_Api,
+            raw,
+            self.keepalive.take(),
+            data,
+            device,
+            dtype,
+            dims,
+            strides,
+        ))
+    }
+
+    fn dl_device(&self) -> Result<DLDevice, String> {
+        if self.is_on_cpu()? {
+            return Ok(DLDevice {
+                device_type: DLDeviceType::CPU,
+                device_id: 0,
+            });
+        }
+
+        // Buffers do not expose their client's platform, so go by the device kind.
+        let device = self.device_ref()?;
+        let kind = device.kind()?.to_ascii_lowercase();
+        let device_type = if ["rocm", "amd", "gfx"].iter().any(|k| kind.contains(k)) {
+            DLDeviceType::ROCM
+        } else if ["cuda", "nvidia", "gpu"].iter().any(|k| kind.contains(k)) {
+            DLDeviceType::CUDA
+        } else {
+            return Err(format!("device kind {kind:?} has no DLPack device type"));
+        };
+        Ok(DLDevice {
+            device_type,
+            device_id: device.local_hardware_id()?,
+        })
+    }
+
+    // Borrows CPU-resident device memory directly instead of copying it out. Returns None
+    // when the data is not on the host or not stored dense row-major.
+    pub fn host_view<T: PjrtScalar>(&self) -> Result<Option<HostView<'_, T>>, String> {
+        if !self.is_on_cpu()? {
+            return Ok(None);
+        }
+        self.check_element_type::<T>()?;
+        let dims = self.dimensions()?;
+        if self.get_memory_layout()? != MemoryLayout::row_major(dims.len()) {
+            return Ok(None);
+        }
+        self.ready_event()?.ok()?;
+
+        let guard = self.hold_external_reference()?;
+        let Some(ptr) = guard.opaque_device_memory_data_pointer()? else {
+            return Ok(None);
+        };
+        let ptr = ptr.cast::<T>().cast_const();
+        if !ptr.is_aligned() {
+            return Ok(None);
+        }
+        // The external reference keeps the memory alive and the ready event has fired, so
+        // the plugin is done writing; the view's borrow of `self` outlives neither.
+        let data = unsafe { from_raw_parts(ptr, element_count(&dims)?) };
+        Ok(Some(HostView {
+            _guard: guard,
+            data,
+        }))
+    }
+
+    // Keeps the device memory pinned until the guard is dropped or released.
+    pub fn hold_external_reference(&self) -> Result<ExternalReferenceGuard<'_>, String> {
+        self.increase_external_ref()?;
+        Ok(ExternalReferenceGuard {
+            buffer: self,
+            held: true,
+        })
+    }
+}
+
+// Balances increase_external_ref with exactly one decrease_external_ref, either through
+// release() or on drop.
+pub struct ExternalReferenceGuard<'b> {
+    buffer: &'b PJRTBuffer<'b>,
+    held: bool,
+}
+
+impl ExternalReferenceGuard<'_> {
+    pub fn buffer(&self) -> &PJRTBuffer<'_> {
+        self.buffer
+    }
+
+    pub fn opaque_device_memory_data_pointer(&self) -> Result<Option<*mut libc::c_void>, String> {
+        self.buffer.opaque_device_memory_data_pointer()
+    }
+
+    pub fn release(mut self) -> Result<(), String> {
+        self.held = false;
+        self.buffer.decrease_external_ref()
+    }
+
+    // Hands the reference to someone who will decrease it through the raw API.
+    pub(crate) fn leak(mut self) {
+        self.held = false;
+    }
+}
+
+impl Drop for ExternalReferenceGuard<'_> {
+    fn drop(&mut self) {
+        if !self.held {
+            return;
+        }
+        self.held = false;
+        if let Err(e) = self.buffer.decrease_external_ref() {
+            log::warn!("failed to release PJRT buffer external reference: {e}");
+        }
+    }
+}
+
+pub struct HostView<'b, T> {
+    _guard: ExternalReferenceGuard<'b>,
+    data: &'b [T],
+}
+
+impl<T> std::ops::Deref for HostView<'_, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.data
+    }
+}
+
+impl fmt::Debug for PJRTBuffer<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let device = self.device_id();
+        f.debug_struct("PJRTBuffer")
+            .field("dtype", &DebugResult::display(self.element_type()))
+            .field("dims", &DebugResult::debug(self.dimensions()))
+            .field("device", &DebugResult::debug(device))
+            .field("deleted", &DebugResult::debug(self.is_deleted()))
+            .finish()
+    }
+}
+
+// XLA shape notation, e.g. `f32[2,3]`.
+impl fmt::Display for PJRTBuffer<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.element_type() {
+            Ok(ty) => write!(f, "{ty}")?,
+            Err(_) => f.write_str("?")?,
+        }
+        match self.dimensions() {
+            Ok(dims) => {
+                let dims: Vec<String> = dims.iter().map(i64::to_string).collect();
+                write!(f, "[{}]", dims.join(","))
+            }
+            Err(_) => f.write_str("[?]"),
+        }
+    }
 }
 
 impl Drop for PJRTBuffer<'_> {
@@ -747,3 +1338,210 @@ impl Drop for PJRTBuffer<'_> {
         }
     }
 }
+
+// Placeholder buffer returned by PJRT_Client_CreateAliasBuffer. It can be passed to
+// executions before the real data exists and must be fulfilled exactly once; the
+// consuming methods enforce that, and Drop fulfills with CANCELLED so consumers
+// waiting on the alias never hang.
+pub struct AliasBuffer<'a> {
+    rt: &'a PjrtRuntime,
+    client: *mut PJRT_Client,
+    buffer: Option<PJRTBuffer<'a>>,
+    fulfill_cb: *mut PJRT_FulfillAliasBufferCallback,
+}
+
+impl<'a> AliasBuffer<'a> {
+    pub(crate) fn new(
+        rt: &'a PjrtRuntime,
+        client: *mut PJRT_Client,
+        buffer: PJRTBuffer<'a>,
+        fulfill_cb: *mut PJRT_FulfillAliasBufferCallback,
+    ) -> Self {
+        Self {
+            rt,
+            client,
+            buffer: Some(buffer),
+            fulfill_cb,
+        }
+    }
+
+    pub fn buffer(&self) -> &PJRTBuffer<'a> {
+        self.buffer
+            .as_ref()
+            .expect("AliasBuffer holds its buffer until consumed")
+    }
+
+    pub fn fulfill(mut self, source: &PJRTBuffer<'_>) -> Result<PJRTBuffer<'a>, String> {
+        let source_raw = source.raw_checked()?;
+        // Cleared only on success so a failed fulfill still leaves Drop to cancel the alias.
+        fulfill_alias_buffer_raw(
+            self.rt,
+            self.client,
+            self.fulfill_cb,
+            Some(source_raw),
+            PjrtErrorCode::Ok,
+            None,
+        )?;
+        self.fulfill_cb = ptr::null_mut();
+        Ok(self
+            .buffer
+            .take()
+            .expect("AliasBuffer holds its buffer until consumed"))
+    }
+
+    pub fn fulfill_error(mut self, code: PjrtErrorCode, message: &str) -> Result<(), String> {
+        if code == PjrtErrorCode::Ok {
+            return Err("fulfill_error requires a non-OK error code".to_string());
+        }
+        fulfill_alias_buffer_raw(
+            self.rt,
+            self.client,
+            self.fulfill_cb,
+            None,
+            code,
+            Some(message),
+        )?;
+        self.fulfill_cb = ptr::null_mut();
+        Ok(())
+    }
+}
+
+impl Drop for AliasBuffer<'_> {
+    fn drop(&mut self) {
+        if self.fulfill_cb.is_null() {
+            return;
+        }
+
+        // Drop must not panic; best effort so consumers observe an error instead of hanging.
+        let _ = fulfill_alias_buffer_raw(
+            self.rt,
+            self.client,
+            self.fulfill_cb,
+            None,
+            PjrtErrorCode::Cancelled,
+            Some("alias buffer dropped without being fulfilled"),
+        );
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostAllocation {
+    offset: i64,
+    bytes: Vec<u8>,
+}
+
+impl HostAllocation {
+    pub fn offset(&self) -> i64 {
+        self.offset
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+// The caller's half of PJRT_Buffer_CopyRawToHostFuture. The plugin starts the copy once a
+// destination (or an error) is supplied; dropping it unfulfilled cancels the copy.
+pub struct HostFutureDestination {
+    callback_data: *mut libc::c_void,
+    callback:
+        Option<unsafe extern "C" fn(args: *mut PJRT_Buffer_CopyRawToHostFuture_Callback_Args)>,
+}
+
+impl HostFutureDestination {
+    /// # Safety
+    ///
+    /// `dst` must be valid for writes of `transfer_size` bytes until the event returned
+    /// alongside this destination fires.
+    pub unsafe fn fulfill(mut self, dst: *mut libc::c_void) {
+        self.resolve(dst, PjrtErrorCode::Ok, "");
+    }
+
+    pub fn fail(mut self, code: PjrtErrorCode, message: &str) {
+        self.resolve(ptr::null_mut(), code, message);
+    }
+
+    fn resolve(&mut self, dst: *mut libc::c_void, code: PjrtErrorCode, message: &str) {
+        let Some(callback) = self.callback.take() else {
+            return;
+        };
+        let mut args = PJRT_Buffer_CopyRawToHostFuture_Callback_Args {
+            struct_size: PJRT_Buffer_CopyRawToHostFuture_Callback_Args_STRUCT_SIZE as usize,
+            callback_data: self.callback_data,
+            error_code: code.raw(),
+            error_message: message.as_ptr() as *const libc::c_char,
+            error_message_size: message.len(),
+            dst,
+        };
+        unsafe { callback(&mut args) };
+    }
+}
+
+impl Drop for HostFutureDestination {
+    fn drop(&mut self) {
+        self.resolve(
+            ptr::null_mut(),
+            PjrtErrorCode::Cancelled,
+            "host destination dropped without being fulfilled",
+        );
+    }
+}
+
+// The plugin-provided half of PJRT_Buffer_DonateWithControlDependency. The plugin expects
+// it to be called once, from any thread, when the dependency resolves.
+#[derive(Clone, Copy)]
+struct DependencyReadyCallback {
+    callback: unsafe extern "C" fn(*mut PJRT_Buffer_DonateWithControlDependency_Callback_Args),
+    callback_data: *mut libc::c_void,
+}
+
+unsafe impl Send for DependencyReadyCallback {}
+
+impl DependencyReadyCallback {
+    fn invoke(self, status: &EventStatus) {
+        let (error_code, message) = match status {
+            Ok(()) => (PjrtErrorCode::Ok, ""),
+            Err((code, message)) => (*code, message.as_str()),
+        };
+        let mut args = PJRT_Buffer_DonateWithControlDependency_Callback_Args {
+            struct_size: PJRT_Buffer_DonateWithControlDependency_Callback_Args_STRUCT_SIZE as usize,
+            callback_data: self.callback_data,
+            error_code: error_code.raw(),
+            error_message: if message.is_empty() {
+                ptr::null()
+            } else {
+                message.as_ptr() as *const libc::c_char
+            },
+            error_message_size: message.len(),
+        };
+        unsafe { (self.callback)(&mut args) };
+    }
+}
+
+// Completion handle for donate_with_control_dependency; resolves once the dependency
+// event has fired and the plugin callback has run.
+pub struct DonationHandle {
+    rx: mpsc::Receiver<Result<(), String>>,
+}
+
+impl DonationHandle {
+    pub fn wait(self) -> Result<(), String> {
+        self.rx
+            .recv()
+            .map_err(|_| "donation dependency callback was dropped without running".to_string())?
+    }
+
+    pub fn try_wait(&self) -> Option<Result<(), String>> {
+        match self.rx.try_recv() {
+            Ok(status) => Some(status),
+            Err(mpsc::TryRecvError::Empty) => None,
+            Err(mpsc::TryRecvError::Disconnected) => Some(Err(
+                "donation dependency callback was dropped without running".to_string(),
+            )),
+        }
+    }
+}
@@ -1,29 +1,229 @@
-use crate::pjrt::buffer::PJRTBuffer;
-use crate::pjrt::compile::PJRTCompiler;
+use crate::pjrt::buffer::{AliasBuffer, PJRTBuffer};
+use crate::pjrt::compile::{
+    CompileError, CompileFileError, CompileScope, PJRTCompiler, ProgramFormat,
+};
+use crate::pjrt::compile_options::{CompileOptionsBuilder, OptionOverride};
+use crate::pjrt::device::PJRTDevice;
+use crate::pjrt::dlpack::{
+    buffer_type_from_dl, layout_from_dl_strides, platform_from_dl, DLManagedTensor, ImportedTensor,
+};
+use crate::pjrt::error::{pjrt_check, ContextFrame, PJRTError, PjrtErrorCode};
 use crate::pjrt::event::PJRTEvent;
-use crate::pjrt::executable::PJRTLoadedExecutable;
+use crate::pjrt::executable::{deserialize_and_load_raw, PJRTLoadedExecutable};
+use crate::pjrt::executable_file::{
+    decode_executable_file, ExecutableFileError, ExecutableFileHeader,
+};
 use crate::pjrt::host_to_device_manager::PjrtHtoDeviceManager;
+use crate::pjrt::layout::{MemoryLayout, RawMemoryLayout};
 use crate::pjrt::loader::{error_to_string, PjrtRuntime};
 use crate::pjrt::memory::PJRTMemory;
+#[cfg(feature = "npy")]
+use crate::pjrt::npy::parse_npy;
+use crate::pjrt::platform::Platform;
+use crate::pjrt::stream::DeviceStream;
 use crate::pjrt::topology_desc::{PJRTNamedAttribute, PJRTTopologyDescription};
+use crate::pjrt::utils::{
+    byte_strides, check_data_len, check_element_type, DebugResult, MajorOrder, PjrtScalar,
+};
+#[cfg(feature = "npy")]
+use crate::pjrt::utils::{element_byte_size, host_byte_size};
 use crate::pjrt_sys::*;
+use std::cell::OnceCell;
+use std::collections::BTreeMap;
 use std::ffi::c_void;
+use std::fmt;
+use std::path::Path;
 use std::ptr;
 use std::ptr::null_mut;
-//raii wrapper for PJRT_Client
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HostBufferSemantics {
+    // Host data may be released as soon as the upload call returns.
+    ImmutableOnlyDuringCall,
+    // Host data must stay alive and unmodified until the done event fires.
+    ImmutableUntilTransferCompletes,
+    // The runtime may alias host data for the lifetime of the buffer.
+    ImmutableZeroCopy,
+    // As ImmutableZeroCopy, but the runtime may also write through the alias.
+    MutableZeroCopy,
+}
+
+impl HostBufferSemantics {
+    pub fn raw(self) -> PJRT_HostBufferSemantics {
+        match self {
+            Self::ImmutableOnlyDuringCall => {
+                PJRT_HostBufferSemantics_PJRT_HostBufferSemantics_kImmutableOnlyDuringCall
+            }
+            Self::ImmutableUntilTransferCompletes => {
+                PJRT_HostBufferSemantics_PJRT_HostBufferSemantics_kImmutableUntilTransferCompletes
+            }
+            Self::ImmutableZeroCopy => {
+                PJRT_HostBufferSemantics_PJRT_HostBufferSemantics_kImmutableZeroCopy
+            }
+            Self::MutableZeroCopy => {
+                PJRT_HostBufferSemantics_PJRT_HostBufferSemantics_kMutableZeroCopy
+            }
+        }
+    }
+
+    // True when the host data must outlive the upload call.
+    pub fn requires_keepalive(self) -> bool {
+        !matches!(self, Self::ImmutableOnlyDuringCall)
+    }
+}
+
+// Host data the client can keep alive on behalf of the caller until the runtime
+// signals done_with_host_buffer.
+pub enum OwnedHostData<T> {
+    Vec(Vec<T>),
+    Arc(Arc<[T]>),
+}
+
+impl<T> OwnedHostData<T> {
+    fn as_slice(&self) -> &[T] {
+        match self {
+            Self::Vec(v) => v,
+            Self::Arc(a) => a,
+        }
+    }
+}
+
+impl<T> From<Vec<T>> for OwnedHostData<T> {
+    fn from(v: Vec<T>) -> Self {
+        Self::Vec(v)
+    }
+}
+
+impl<T> From<Arc<[T]>> for OwnedHostData<T> {
+    fn from(a: Arc<[T]>) -> Self {
+        Self::Arc(a)
+    }
+}
+
+// One borrowed host array for PJRTClient::upload_batch.
+pub struct HostArray<'d> {
+    data: *const c_void,
+    len: usize,
+    element_type: PJRT_Buffer_Type,
+    dims: &'d [i64],
+    _data: std::marker::PhantomData<&'d [u8]>,
+}
+
+impl<'d> HostArray<'d> {
+    pub fn new<T: PjrtScalar>(data: &'d [T], dims: &'d [i64]) -> Self {
+        Self {
+            data: data.as_ptr().cast::<c_void>(),
+            len: data.len(),
+            element_type: T::TYPE.raw(),
+            dims,
+            _data: std::marker::PhantomData,
+        }
+    }
+}
+
+pub type OnDeleteCallback = Box<dyn FnOnce(*mut c_void) + Send>;
+
+unsafe extern "C" fn on_delete_trampoline(device_buffer_ptr: *mut c_void, user_arg: *mut c_void) {
+    if user_arg.is_null() {
+        return;
+    }
+    let callback = unsafe { Box::from_raw(user_arg.cast::<OnDeleteCallback>()) };
+    callback(device_buffer_ptr);
+}
 
+//raii wrapper for PJRT_Client
 pub struct PJRTClient<'a> {
     pub rt: &'a PjrtRuntime,
     pub raw_client: *mut PJRT_Client,
+    // The device lists are fixed for the client's lifetime, so they are fetched once.
+    devices: OnceCell<Vec<*mut PJRT_Device>>,
+    addressable_devices: OnceCell<Vec<*mut PJRT_Device>>,
 }
 
 impl<'a> PJRTClient<'a> {
     pub(crate) fn new(rt: &'a PjrtRuntime, raw_client: *mut PJRT_Client) -> Self {
-        Self { rt, raw_client }
+        Self {
+            rt,
+            raw_client,
+            devices: OnceCell::new(),
+            addressable_devices: OnceCell::new(),
+        }
     }
 
     pub fn devices(&self) -> Result<Vec<*mut PJRT_Device>, String> {
-        self.rt.client_devices(self.raw_client)
+        Ok(self.cached_devices()?.clone())
+    }
+
+    fn cached_devices(&self) -> Result<&Vec<*mut PJRT_Device>, String> {
+        if let Some(devices) = self.devices.get() {
+            return Ok(devices);
+        }
+        let devices = self.rt.client_devices(self.raw_checked()?)?;
+        Ok(self.devices.get_or_init(|| devices))
+    }
+
+    fn cached_addressable_devices(&self) -> Result<&Vec<*mut PJRT_Device>, String> {
+        if let Some(devices) = self.addressable_devices.get() {
+            return Ok(devices);
+        }
+        let devices = self
+            .compiler()
+            .addressable_devices()?
+            .iter()
+            .map(PJRTDevice::raw)
+            .collect();
+        Ok(self.addressable_devices.get_or_init(|| devices))
+    }
+
+    pub fn device_count(&self) -> Result<usize, String> {
+        Ok(self.cached_devices()?.len())
+    }
+
+    pub fn addressable_device_count(&self) -> Result<usize, String> {
+        Ok(self.cached_addressable_devices()?.len())
+    }
+
+    // Every device of the topology keyed by the process that owns it.
+    pub fn devices_by_process(&self) -> Result<BTreeMap<i32, Vec<PJRTDevice<'a>>>, String> {
+        let mut groups: BTreeMap<i32, Vec<PJRTDevice<'a>>> = BTreeMap::new();
+        for device in self.device_refs()? {
+            groups
+                .entry(device.process_index()?)
+                .or_default()
+                .push(device);
+        }
+        Ok(groups)
+    }
+
+    // Devices owned by this process, in the client's device order.
+    pub fn local_devices(&self) -> Result<Vec<PJRTDevice<'a>>, String> {
+        let process_index = self.process_index()?;
+        let mut local = Vec::new();
+        for device in self.device_refs()? {
+            if device.process_index()? == process_index {
+                local.push(device);
+            }
+        }
+        Ok(local)
+    }
+
+    pub fn device_refs(&self) -> Result<Vec<PJRTDevice<'a>>, String> {
+        self.devices()?
+            .into_iter()
+            .map(|raw| PJRTDevice::new(self.rt, raw))
+            .collect()
+    }
+
+    pub fn addressable_devices(&self) -> Result<Vec<*mut PJRT_Device>, String> {
+        Ok(self.cached_addressable_devices()?.clone())
+    }
+
+    pub fn addressable_device_refs(&self) -> Result<Vec<PJRTDevice<'a>>, String> {
+        self.cached_addressable_devices()?
+            .iter()
+            .map(|&raw| PJRTDevice::new(self.rt, raw))
+            .collect()
     }
 
     pub fn raw(&self) -> *mut PJRT_Client {
@@ -45,13 +245,90 @@ impl<'a> PJRTClient<'a> {
     pub fn compile(
         &self,
         program_code: &str,
-        format: &str,
+        format: ProgramFormat,
         compile_options: &[u8],
-    ) -> Result<PJRTLoadedExecutable<'a>, String> {
+    ) -> Result<PJRTLoadedExecutable<'a>, CompileError<'a>> {
         self.compiler()
             .compile(program_code, format, compile_options)
     }
 
+    #[deprecated(note = "use compile with a ProgramFormat")]
+    #[allow(deprecated)]
+    pub fn compile_with_format_name(
+        &self,
+        program_code: &str,
+        format: &str,
+        compile_options: &[u8],
+    ) -> Result<PJRTLoadedExecutable<'a>, CompileError<'a>> {
+        self.compiler()
+            .compile_with_format_name(program_code, format, compile_options)
+    }
+
+    pub fn compile_bytes(
+        &self,
+        program_code: &[u8],
+        format: ProgramFormat,
+        compile_options: &[u8],
+    ) -> Result<PJRTLoadedExecutable<'a>, CompileError<'a>> {
+        self.compiler()
+            .compile_bytes(program_code, format, compile_options)
+    }
+
+    // Runs `f` with a scope for compiling on separate threads. Every compile started in the
+    // scope is joined before this returns, even if its handle was leaked.
+    pub fn compile_scope<R>(&self, f: impl for<'s> FnOnce(&CompileScope<'s, '_, 'a>) -> R) -> R {
+        CompileScope::run(self, f)
+    }
+
+    pub fn compile_file(
+        &self,
+        path: &Path,
+        compile_options: &[u8],
+    ) -> Result<PJRTLoadedExecutable<'a>, CompileFileError<'a>> {
+        self.compiler().compile_file(path, None, compile_options)
+    }
+
+    pub fn compile_file_with_format(
+        &self,
+        path: &Path,
+        format: ProgramFormat,
+        compile_options: &[u8],
+    ) -> Result<PJRTLoadedExecutable<'a>, CompileFileError<'a>> {
+        self.compiler()
+            .compile_file(path, Some(format), compile_options)
+    }
+
+    pub fn compile_with(
+        &self,
+        program_code: &str,
+        format: ProgramFormat,
+        options: &CompileOptionsBuilder,
+    ) -> Result<PJRTLoadedExecutable<'a>, CompileError<'a>> {
+        let compile_options = options.build().map_err(|e| {
+            CompileError::new(
+                PJRTError::with_code(self.rt, PjrtErrorCode::InvalidArgument, e),
+                format.as_str(),
+                program_code.as_bytes(),
+            )
+        })?;
+        self.compile(program_code, format, &compile_options)
+    }
+
+    // Default compile options plus XLA flag overrides passed as env_option_overrides.
+    pub fn compile_with_flags(
+        &self,
+        program_code: &str,
+        format: ProgramFormat,
+        flags: &[(&str, OptionOverride)],
+    ) -> Result<PJRTLoadedExecutable<'a>, CompileError<'a>> {
+        let options = flags
+            .iter()
+            .fold(CompileOptionsBuilder::new(), |options, (name, value)| {
+                options.override_flag(*name, value.clone())
+            });
+        self.compile_with(program_code, format, &options)
+    }
+
     pub fn topology_description(&self) -> Result<PJRTTopologyDescription<'a>, String> {
         if self.raw_client.is_null() {
             return Err("PJRT_Client is null".to_string());
@@ -129,61 +406,6 @@ impl<'a> PJRTClient<'a> {
         self.topology_description()?.attributes()
     }
 
-    pub fn fulfill_alias_buffer(
-        &self,
-        fulfill_alias_buffer_cb: *mut PJRT_FulfillAliasBufferCallback,
-        buffer: Option<*mut PJRT_Buffer>,
-        status_code: PJRT_Error_Code,
-        error_message: Option<&str>,
-    ) -> Result<(), String> {
-        let client = self.raw_checked()?;
-
-        if fulfill_alias_buffer_cb.is_null() {
-            return Err("fulfill_alias_buffer_cb is null".to_string());
-        }
-
-        let f = self
-            .rt
-            .api()
-            .PJRT_Client_FulfillAliasBuffer
-            .ok_or("PJRT_Client_FulfillAliasBuffer symbol not found")?;
-
-        let raw_buffer = buffer.unwrap_or(ptr::null_mut());
-        if status_code == PJRT_Error_Code_PJRT_Error_Code_OK && raw_buffer.is_null() {
-            return Err(
-                "buffer must be non-null when status_code is PJRT_Error_Code_OK".to_string(),
-            );
-        }
-
-        let error_message_bytes = if status_code == PJRT_Error_Code_PJRT_Error_Code_OK {
-            &[][..]
-        } else {
-            error_message.map(str::as_bytes).unwrap_or(&[])
-        };
-
-        let mut args = PJRT_Client_FulfillAliasBuffer_Args {
-            struct_size: PJRT_Client_FulfillAliasBuffer_Args_STRUCT_SIZE as usize,
-            extension_start: ptr::null_mut(),
-            client,
-            buffer: raw_buffer,
-            status_code,
-            error_message: if error_message_bytes.is_empty() {
-                ptr::null()
-            } else {
-                error_message_bytes.as_ptr() as *const libc::c_char
-            },
-            error_message_size: error_message_bytes.len(),
-            fulfill_alias_buffer_cb,
-        };
-
-        let err = unsafe { f(&mut args) };
-        if err.is_null() {
-            Ok(())
-        } else {
-            Err(error_to_string(self.rt.api(), err))
-        }
-    }
-
     pub fn process_index(&self) -> Result<i32, String> {
         let client = self.raw_checked()?;
 
@@ -265,6 +487,17 @@ impl<'a> PJRTClient<'a> {
         Ok(args.addressable_device)
     }
 
+    pub fn lookup_device_ref(&self, id: i32) -> Result<PJRTDevice<'a>, String> {
+        PJRTDevice::new(self.rt, self.lookup_device(id)?)
+    }
+
+    pub fn lookup_addressable_device_ref(
+        &self,
+        local_hardware_id: i32,
+    ) -> Result<PJRTDevice<'a>, String> {
+        PJRTDevice::new(self.rt, self.lookup_addressable_device(local_hardware_id)?)
+    }
+
     pub fn addressable_memories(&self) -> Result<Vec<*mut PJRT_Memory>, String> {
         let client = self.raw_checked()?;
 
@@ -416,7 +649,10 @@ impl<'a> PJRTClient<'a> {
         }
     }
 
-    pub fn create_uninitialized_buffer(&self, element_type: PJRT_Buffer_Type) -> Result<PJRTBuffer<'a>, String> {
+    pub fn create_uninitialized_buffer(
+        &self,
+        element_type: PJRT_Buffer_Type,
+    ) -> Result<PJRTBuffer<'a>, String> {
         let client = self.raw_checked()?;
 
         let funct = self
@@ -438,20 +674,19 @@ impl<'a> PJRTClient<'a> {
             buffer: null_mut(),
         };
 
-        let err = unsafe {
-            funct(&mut args)
-        };
+        let err = unsafe { funct(&mut args) };
 
         if !err.is_null() {
             Err(error_to_string(self.rt.api(), err))
         } else {
-            Ok(PJRTBuffer {
-                rt: self.rt,
-                raw: args.buffer,
-            })
+            Ok(PJRTBuffer::new(self.rt, args.buffer))
         }
     }
 
+    // `stream`, if nonzero, is a platform stream (see stream::DeviceStream) with work that
+    // produces the data; the view becomes ready once that work finishes. On GPU pass the
+    // stream from stream::device_ready_event_stream, or 0 if the data is already there.
+    #[allow(clippy::too_many_arguments)]
     pub fn create_view_of_device_buffer(
         &self,
         device_buffer_ptr: *mut c_void,
@@ -460,7 +695,7 @@ impl<'a> PJRTClient<'a> {
         device: Option<*mut PJRT_Device>,
         memory: Option<*mut PJRT_Memory>,
         layout: Option<*mut PJRT_Buffer_MemoryLayout>,
-        stream: isize,
+        stream: DeviceStream,
         on_delete_callback: Option<
             unsafe extern "C" fn(device_buffer_ptr: *mut c_void, user_arg: *mut c_void),
         >,
@@ -521,14 +756,175 @@ impl<'a> PJRTClient<'a> {
         Ok(PJRTBuffer::new(self.rt, args.buffer))
     }
 
-    pub fn buffer_from_host_buffer(
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_view_of_device_buffer_with_on_delete(
+        &self,
+        device_buffer_ptr: *mut c_void,
+        dims: &[i64],
+        element_type: PJRT_Buffer_Type,
+        device: Option<*mut PJRT_Device>,
+        memory: Option<*mut PJRT_Memory>,
+        layout: Option<*mut PJRT_Buffer_MemoryLayout>,
+        stream: DeviceStream,
+        on_delete: Option<OnDeleteCallback>,
+    ) -> Result<PJRTBuffer<'a>, String> {
+        let Some(on_delete) = on_delete else {
+            return self.create_view_of_device_buffer(
+                device_buffer_ptr,
+                dims,
+                element_type,
+                device,
+                memory,
+                layout,
+                stream,
+                None,
+                null_mut(),
+            );
+        };
+
+        // Ownership of the box passes to the plugin, which hands it back exactly once
+        // through the trampoline when the view is deleted.
+        let user_arg = Box::into_raw(Box::new(on_delete)).cast::<c_void>();
+        let result = self.create_view_of_device_buffer(
+            device_buffer_ptr,
+            dims,
+            element_type,
+            device,
+            memory,
+            layout,
+            stream,
+            Some(on_delete_trampoline),
+            user_arg,
+        );
+        if result.is_err() {
+            // The plugin never took the callback; reclaim it without invoking.
+            drop(unsafe { Box::from_raw(user_arg.cast::<OnDeleteCallback>()) });
+        }
+        result
+    }
+
+    /// # Safety
+    ///
+    /// `tensor` must point to a valid DLManagedTensor whose data lives on one of this
+    /// client's devices. On success the returned buffer takes ownership and calls the
+    /// tensor's deleter when it is destroyed; on error ownership stays with the caller.
+    pub unsafe fn buffer_from_dlpack(
+        &self,
+        tensor: *mut DLManagedTensor,
+    ) -> Result<PJRTBuffer<'a>, String> {
+        if tensor.is_null() {
+            return Err("buffer_from_dlpack: tensor is null".to_string());
+        }
+        let dl = unsafe { &(*tensor).dl_tensor };
+
+        let platform = self.platform()?;
+        if platform_from_dl(dl.device.device_type).as_ref() != Some(&platform) {
+            return Err(format!(
+                "DLPack device type {} does not match client platform {platform:?}",
+                dl.device.device_type.0
+            ));
+        }
+        let element_type = buffer_type_from_dl(dl.dtype).ok_or_else(|| {
+            format!(
+                "DLPack dtype (code {}, bits {}, lanes {}) has no PJRT equivalent",
+                dl.dtype.code, dl.dtype.bits, dl.dtype.lanes
+            )
+        })?;
+
+        let ndim = usize::try_from(dl.ndim).map_err(|_| "DLPack ndim is negative".to_string())?;
+        let dims = match ndim {
+            0 => Vec::new(),
+            _ if dl.shape.is_null() => return Err("DLPack shape is null".to_string()),
+            _ => unsafe { std::slice::from_raw_parts(dl.shape, ndim) }.to_vec(),
+        };
+        let layout = if dl.strides.is_null() {
+            None
+        } else {
+            let strides = unsafe { std::slice::from_raw_parts(dl.strides, ndim) };
+            Some(layout_from_dl_strides(&dims, strides)?)
+        };
+        let mut raw_layout = layout.as_ref().map(|l| l.to_raw());
+
+        let devices = self.addressable_device_refs()?;
+        let device = if platform.is_cpu() {
+            usize::try_from(dl.device.device_id)
+                .ok()
+                .and_then(|i| devices.get(i))
+        } else {
+            let mut found = None;
+            for device in &devices {
+                if device.local_hardware_id()? == dl.device.device_id {
+                    found = Some(device);
+                    break;
+                }
+            }
+            found
+        }
+        .ok_or_else(|| {
+            format!(
+                "no addressable device with DLPack id {}",
+                dl.device.device_id
+            )
+        })?;
+
+        let data = unsafe { dl.data.cast::<u8>().add(dl.byte_offset as usize) }.cast::<c_void>();
+        let imported = unsafe { ImportedTensor::new(tensor) };
+        self.create_view_of_device_buffer_with_on_delete(
+            data,
+            &dims,
+            element_type.raw(),
+            Some(device.raw()),
+            None,
+            raw_layout.as_mut().map(|l| l.as_mut_ptr()),
+            0,
+            Some(Box::new(move |_| imported.delete())),
+        )
+    }
+
+    /// # Safety
+    ///
+    /// `data` must point to host memory laid out as described by `element_type`, `dims`
+    /// and `byte_strides`, and it must remain valid for as long as `host_buffer_semantics`
+    /// requires: until the call returns for `ImmutableOnlyDuringCall`, until the returned
+    /// event fires for `ImmutableUntilTransferCompletes`, and until the event fires (or the
+    /// buffer is destroyed when no event is returned) for the zero-copy variants. For
+    /// `MutableZeroCopy` the caller must not read or write the memory during that time.
+    pub unsafe fn buffer_from_host_buffer(
         &self,
         data: *const c_void,
         element_type: PJRT_Buffer_Type,
         dims: &[i64],
         byte_strides: Option<&[i64]>,
-        host_buffer_semantics: PJRT_HostBufferSemantics,
+        host_buffer_semantics: HostBufferSemantics,
         device: Option<*mut PJRT_Device>,
+    ) -> Result<(PJRTBuffer<'a>, Option<PJRTEvent<'a>>), String> {
+        unsafe {
+            self.buffer_from_host_buffer_with_layout(
+                data,
+                element_type,
+                dims,
+                byte_strides,
+                host_buffer_semantics,
+                device,
+                None,
+            )
+        }
+    }
+
+    /// # Safety
+    ///
+    /// Same requirements as `buffer_from_host_buffer`. `device_layout` only selects how the
+    /// device copy is laid out; the host data is still described by `byte_strides`.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn buffer_from_host_buffer_with_layout(
+        &self,
+        data: *const c_void,
+        element_type: PJRT_Buffer_Type,
+        dims: &[i64],
+        byte_strides: Option<&[i64]>,
+        host_buffer_semantics: HostBufferSemantics,
+        device: Option<*mut PJRT_Device>,
+        device_layout: Option<&MemoryLayout>,
     ) -> Result<(PJRTBuffer<'a>, Option<PJRTEvent<'a>>), String> {
         let client = self.raw_checked()?;
 
@@ -565,6 +961,7 @@ impl<'a> PJRTClient<'a> {
                 .ok_or("PJRT_Client has no devices")?,
         };
 
+        let mut raw_layout = device_layout.map(MemoryLayout::to_raw);
         let mut args = PJRT_Client_BufferFromHostBuffer_Args {
             struct_size: PJRT_Client_BufferFromHostBuffer_Args_STRUCT_SIZE as usize,
             extension_start: ptr::null_mut(),
@@ -575,18 +972,22 @@ impl<'a> PJRTClient<'a> {
             num_dims: dims.len(),
             byte_strides: byte_strides_ptr,
             num_byte_strides,
-            host_buffer_semantics,
+            host_buffer_semantics: host_buffer_semantics.raw(),
             device,
             memory: ptr::null_mut(),
-            device_layout: ptr::null_mut(),
+            device_layout: raw_layout
+                .as_mut()
+                .map_or(ptr::null_mut(), RawMemoryLayout::as_mut_ptr),
             done_with_host_buffer: ptr::null_mut(),
             buffer: ptr::null_mut(),
         };
 
-        let err = unsafe { buf_from_host(&mut args) };
-        if !err.is_null() {
-            return Err(error_to_string(self.rt.api(), err));
-        }
+        pjrt_check!(
+            self.rt,
+            unsafe { buf_from_host(&mut args) },
+            ContextFrame::new("PJRT_Client_BufferFromHostBuffer")
+                .device(PJRTDevice::new(self.rt, device).ok().map(|d| d.id()))
+        )?;
         if args.buffer.is_null() {
             return Err(
                 "PJRT_Client_BufferFromHostBuffer succeeded but returned null buffer".into(),
@@ -608,7 +1009,7 @@ impl<'a> PJRTClient<'a> {
         shape_element_type: PJRT_Buffer_Type,
         memory: Option<*mut PJRT_Memory>,
         shape_layout: Option<*mut PJRT_Buffer_MemoryLayout>,
-    ) -> Result<(PJRTBuffer<'a>, *mut PJRT_FulfillAliasBufferCallback), String> {
+    ) -> Result<AliasBuffer<'a>, String> {
         let client = self.raw_checked()?;
 
         let f = self
@@ -647,7 +1048,9 @@ impl<'a> PJRTClient<'a> {
             );
         }
 
-        Ok((
+        Ok(AliasBuffer::new(
+            self.rt,
+            client,
             PJRTBuffer::new(self.rt, args.alias_buffer),
             args.fulfill_alias_buffer_cb,
         ))
@@ -655,7 +1058,7 @@ impl<'a> PJRTClient<'a> {
 
     pub fn create_error_buffer(
         &self,
-        error_code: PJRT_Error_Code,
+        error_code: PjrtErrorCode,
         error_message: &str,
         shape_dims: &[i64],
         shape_element_type: PJRT_Buffer_Type,
@@ -675,7 +1078,7 @@ impl<'a> PJRTClient<'a> {
             struct_size: PJRT_Client_CreateErrorBuffer_Args_STRUCT_SIZE as usize,
             extension_start: ptr::null_mut(),
             client,
-            error_code,
+            error_code: error_code.raw(),
             error_message: if error_message_bytes.is_empty() {
                 ptr::null()
             } else {
@@ -705,6 +1108,26 @@ impl<'a> PJRTClient<'a> {
         Ok(PJRTBuffer::new(self.rt, args.buffer))
     }
 
+    pub fn error_buffer_from(
+        &self,
+        err: &PJRTError<'_>,
+        shape_dims: &[i64],
+        shape_element_type: PJRT_Buffer_Type,
+    ) -> Result<PJRTBuffer<'a>, String> {
+        let code = err.code();
+        if code == PjrtErrorCode::Ok {
+            return Err("error_buffer_from requires an error with a non-OK code".to_string());
+        }
+        self.create_error_buffer(
+            code,
+            err.message(),
+            shape_dims,
+            shape_element_type,
+            None,
+            None,
+        )
+    }
+
     pub fn update_global_process_info(
         &self,
         process_infos: &mut [PJRT_ProcessInfo],
@@ -799,39 +1222,388 @@ impl<'a> PJRTClient<'a> {
         Ok(out)
     }
 
-    pub fn buffer_from_host_slice_copy<T: Copy>(
+    pub fn buffer_from_host_slice_copy<T: PjrtScalar>(
         &self,
         data: &[T],
         element_type: PJRT_Buffer_Type,
         dims: &[i64],
         device: Option<*mut PJRT_Device>,
     ) -> Result<PJRTBuffer<'a>, String> {
-        let (buf, done) = self.buffer_from_host_buffer(
-            data.as_ptr().cast::<c_void>(),
+        self.buffer_from_host_slice(
+            data,
             element_type,
             dims,
-            None,
-            PJRT_HostBufferSemantics_PJRT_HostBufferSemantics_kImmutableOnlyDuringCall,
+            HostBufferSemantics::ImmutableOnlyDuringCall,
             device,
-        )?;
+        )
+    }
+
+    // Borrowed uploads only support the copying semantics; the zero-copy variants need
+    // owned data (see buffer_from_host_owned_with) or the unsafe buffer_from_host_buffer.
+    pub fn buffer_from_host_slice<T: PjrtScalar>(
+        &self,
+        data: &[T],
+        element_type: PJRT_Buffer_Type,
+        dims: &[i64],
+        semantics: HostBufferSemantics,
+        device: Option<*mut PJRT_Device>,
+    ) -> Result<PJRTBuffer<'a>, String> {
+        if matches!(
+            semantics,
+            HostBufferSemantics::ImmutableZeroCopy | HostBufferSemantics::MutableZeroCopy
+        ) {
+            return Err(format!(
+                "{:?} cannot be used with borrowed host data; pass owned data instead",
+                semantics
+            ));
+        }
+        check_element_type::<T>(element_type)?;
+        check_data_len(dims, data.len())?;
+
+        // The slice outlives this call, and we wait on the done event below before
+        // returning, which covers ImmutableUntilTransferCompletes.
+        let (buf, done) = unsafe {
+            self.buffer_from_host_buffer(
+                data.as_ptr().cast::<c_void>(),
+                element_type,
+                dims,
+                None,
+                semantics,
+                device,
+            )?
+        };
 
         if let Some(ev) = done {
-            // In this mode, it should be safe to drop the host memory after the call returns,
-            // but we still await to avoid plugins that implement the transfer asynchronously.
+            // In ImmutableOnlyDuringCall it should be safe to drop the host memory after the
+            // call returns, but we still await to avoid plugins that transfer asynchronously.
             ev.await_ready()?;
         }
 
         Ok(buf)
     }
 
+    // Copies `data` into a device buffer laid out as `device_layout`, e.g. a layout from
+    // layouts::default_layout_for.
+    pub fn buffer_from_host_slice_with_layout<T: PjrtScalar>(
+        &self,
+        data: &[T],
+        element_type: PJRT_Buffer_Type,
+        dims: &[i64],
+        device_layout: &MemoryLayout,
+        device: Option<*mut PJRT_Device>,
+    ) -> Result<PJRTBuffer<'a>, String> {
+        check_element_type::<T>(element_type)?;
+        check_data_len(dims, data.len())?;
+        let (buf, done) = unsafe {
+            self.buffer_from_host_buffer_with_layout(
+                data.as_ptr().cast::<c_void>(),
+                element_type,
+                dims,
+                None,
+                HostBufferSemantics::ImmutableOnlyDuringCall,
+                device,
+                Some(device_layout),
+            )?
+        };
+        if let Some(ev) = done {
+            ev.await_ready()?;
+        }
+        Ok(buf)
+    }
+
+    pub fn buffer_from_scalar<T: PjrtScalar>(
+        &self,
+        value: T,
+        device: Option<&PJRTDevice<'_>>,
+    ) -> Result<PJRTBuffer<'a>, String> {
+        self.buffer_from_host_slice_copy(&[value], T::TYPE.raw(), &[], device.map(|d| d.raw()))
+    }
+
+    pub fn buffer_from_host_2d<T: PjrtScalar>(
+        &self,
+        rows: i64,
+        cols: i64,
+        data: &[T],
+        order: MajorOrder,
+        element_type: PJRT_Buffer_Type,
+        device: Option<*mut PJRT_Device>,
+    ) -> Result<PJRTBuffer<'a>, String> {
+        self.buffer_from_host_nd(&[rows, cols], data, order, element_type, device)
+    }
+
+    pub fn buffer_from_host_nd<T: PjrtScalar>(
+        &self,
+        dims: &[i64],
+        data: &[T],
+        order: MajorOrder,
+        element_type: PJRT_Buffer_Type,
+        device: Option<*mut PJRT_Device>,
+    ) -> Result<PJRTBuffer<'a>, String> {
+        check_element_type::<T>(element_type)?;
+        check_data_len(dims, data.len())?;
+        let strides = byte_strides(dims, std::mem::size_of::<T>(), order)?;
+
+        // `data` covers every element addressed by `strides` and is only read during the call.
+        let (buf, done) = unsafe {
+            self.buffer_from_host_buffer(
+                data.as_ptr().cast::<c_void>(),
+                element_type,
+                dims,
+                Some(&strides),
+                HostBufferSemantics::ImmutableOnlyDuringCall,
+                device,
+            )?
+        };
+
+        if let Some(ev) = done {
+            ev.await_ready()?;
+        }
+        Ok(buf)
+    }
+
+    // Fortran-ordered arrays are uploaded with column-major byte strides, so the device
+    // buffer always has the shape recorded in the file.
+    #[cfg(feature = "npy")]
+    pub fn buffer_from_npy(
+        &self,
+        path: impl AsRef<Path>,
+        device: Option<&PJRTDevice<'_>>,
+    ) -> Result<PJRTBuffer<'a>, String> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        let (header, data) = parse_npy(&bytes).map_err(|e| format!("{}: {e}", path.display()))?;
+
+        let needed = host_byte_size(&header.dims, header.element_type)?;
+        if data.len() < needed {
+            return Err(format!(
+                "{}: data section holds {} bytes but a {} array of shape {:?} needs {needed}",
+                path.display(),
+                data.len(),
+                header.element_type,
+                header.dims
+            ));
+        }
+        let order = if header.fortran_order {
+            MajorOrder::ColumnMajor
+        } else {
+            MajorOrder::RowMajor
+        };
+        let strides = byte_strides(&header.dims, element_byte_size(header.element_type)?, order)?;
+
+        // `data` covers every element addressed by `strides` and is only read during the call.
+        let (buf, done) = unsafe {
+            self.buffer_from_host_buffer(
+                data.as_ptr().cast::<c_void>(),
+                header.element_type.raw(),
+                &header.dims,
+                Some(&strides),
+                HostBufferSemantics::ImmutableOnlyDuringCall,
+                device.map(PJRTDevice::raw),
+            )?
+        };
+
+        if let Some(ev) = done {
+            ev.await_ready()?;
+        }
+        Ok(buf)
+    }
+
+    // Issues every transfer before waiting on any of them so the plugin can overlap the
+    // copies. On failure the buffers created so far are destroyed and the error names the
+    // failing index.
+    pub fn upload_batch(
+        &self,
+        items: &[HostArray<'_>],
+        device: Option<&PJRTDevice<'_>>,
+    ) -> Result<Vec<PJRTBuffer<'a>>, String> {
+        for (i, item) in items.iter().enumerate() {
+            check_data_len(item.dims, item.len).map_err(|e| format!("upload_batch[{i}]: {e}"))?;
+        }
+
+        let device = match device {
+            Some(d) => d.raw(),
+            None => self
+                .devices()?
+                .into_iter()
+                .next()
+                .ok_or("PJRT_Client has no devices")?,
+        };
+
+        let mut buffers = Vec::with_capacity(items.len());
+        let mut pending = Vec::new();
+        let mut failure = None;
+        for (i, item) in items.iter().enumerate() {
+            // `items` borrows the host data until we return, and every done event is awaited
+            // below before that, on the error path too.
+            let res = unsafe {
+                self.buffer_from_host_buffer(
+                    item.data,
+                    item.element_type,
+                    item.dims,
+                    None,
+                    HostBufferSemantics::ImmutableUntilTransferCompletes,
+                    Some(device),
+                )
+            };
+            match res {
+                Ok((buf, done)) => {
+                    buffers.push(buf);
+                    pending.extend(done.map(|ev| (i, ev)));
+                }
+                Err(e) => {
+                    failure = Some(format!("upload_batch[{i}]: {e}"));
+                    break;
+                }
+            }
+        }
+
+        for (i, ev) in pending {
+            if let Err(e) = ev.await_ready() {
+                failure.get_or_insert_with(|| format!("upload_batch[{i}]: {e}"));
+            }
+        }
+
+        match failure {
+            Some(e) => Err(e),
+            None => Ok(buffers),
+        }
+    }
+
+    // Non-blocking upload: the Vec is kept alive until done_with_host_buffer fires, even if
+    // the returned buffer is dropped first.
+    pub fn buffer_from_host_owned<T: PjrtScalar>(
+        &self,
+        data: Vec<T>,
+        element_type: PJRT_Buffer_Type,
+        dims: &[i64],
+        device: Option<*mut PJRT_Device>,
+    ) -> Result<PJRTBuffer<'a>, String> {
+        self.buffer_from_host_owned_with(
+            data,
+            element_type,
+            dims,
+            HostBufferSemantics::ImmutableUntilTransferCompletes,
+            device,
+        )
+    }
+
+    pub fn buffer_from_host_owned_with<T: PjrtScalar>(
+        &self,
+        data: impl Into<OwnedHostData<T>>,
+        element_type: PJRT_Buffer_Type,
+        dims: &[i64],
+        semantics: HostBufferSemantics,
+        device: Option<*mut PJRT_Device>,
+    ) -> Result<PJRTBuffer<'a>, String> {
+        let data = data.into();
+        check_element_type::<T>(element_type)?;
+        check_data_len(dims, data.as_slice().len())?;
+        if semantics == HostBufferSemantics::MutableZeroCopy
+            && matches!(data, OwnedHostData::Arc(_))
+        {
+            return Err("MutableZeroCopy requires uniquely owned data (Vec)".to_string());
+        }
+
+        // The heap allocation behind Vec/Arc does not move when `data` is moved below.
+        let ptr = data.as_slice().as_ptr().cast::<c_void>();
+        let (buf, done) = unsafe {
+            self.buffer_from_host_buffer(ptr, element_type, dims, None, semantics, device)?
+        };
+
+        if !semantics.requires_keepalive() {
+            if let Some(ev) = done {
+                ev.await_ready()?;
+            }
+            return Ok(buf);
+        }
+
+        match done {
+            Some(ev) => {
+                let data = Arc::new(data);
+                let keepalive = Arc::clone(&data);
+                if let Err(e) = ev.on_ready_boxed(Box::new(move |_| drop(keepalive))) {
+                    // The event never took the data; tie it to the buffer instead, since the
+                    // runtime is done with it once the buffer is destroyed.
+                    log::warn!("keeping host data alive on the buffer: {e}");
+                    return Ok(buf.with_keepalive(Box::new(data)));
+                }
+                Ok(buf)
+            }
+            None => Ok(buf.with_keepalive(Box::new(data))),
+        }
+    }
+
     // destory errors
-    pub fn close(self) -> Result<(), String> {
-        let raw = self.raw_client;
+    pub fn close(mut self) -> Result<(), String> {
+        // Drop skips a null client, so only the device caches are freed here.
+        let raw = std::mem::replace(&mut self.raw_client, null_mut());
         let rt = self.rt;
-        std::mem::forget(self);
+        drop(self);
         rt.destroy_client(raw)
     }
 
+    pub fn load_executable_file(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<PJRTLoadedExecutable<'a>, ExecutableFileError> {
+        self.load_executable_file_with(path, false)
+    }
+
+    // `allow_platform_mismatch` skips the platform check, e.g. for plugins that renamed
+    // their platform between releases.
+    pub fn load_executable_file_with(
+        &self,
+        path: impl AsRef<Path>,
+        allow_platform_mismatch: bool,
+    ) -> Result<PJRTLoadedExecutable<'a>, ExecutableFileError> {
+        self.load_executable_file_with_header(path, allow_platform_mismatch)
+            .map(|(_, executable)| executable)
+    }
+
+    pub(crate) fn load_executable_file_with_header(
+        &self,
+        path: impl AsRef<Path>,
+        allow_platform_mismatch: bool,
+    ) -> Result<(ExecutableFileHeader, PJRTLoadedExecutable<'a>), ExecutableFileError> {
+        let bytes = std::fs::read(path).map_err(ExecutableFileError::Io)?;
+        let (header, serialized) =
+            decode_executable_file(&bytes).map_err(ExecutableFileError::Format)?;
+
+        let platform = self
+            .platform_name()
+            .map_err(ExecutableFileError::Deserialize)?;
+        if header.platform_name != platform && !allow_platform_mismatch {
+            return Err(ExecutableFileError::PlatformMismatch {
+                expected: platform,
+                found: header.platform_name,
+            });
+        }
+        let version = self.rt.api().pjrt_api_version;
+        if header.api_minor != version.minor_version {
+            log::warn!(
+                "executable file was written with PJRT API minor {} but plugin is {}",
+                header.api_minor,
+                version.minor_version
+            );
+        }
+
+        let executable = self
+            .deserialize_and_load(serialized, None)
+            .map_err(ExecutableFileError::Deserialize)?;
+        Ok((header, executable))
+    }
+
+    // Restores an executable from `PJRTLoadedExecutable::serialize` output. Passing
+    // `overridden_compile_options` replaces the options stored in the blob.
+    pub fn deserialize_and_load(
+        &self,
+        serialized: &[u8],
+        overridden_compile_options: Option<&[u8]>,
+    ) -> Result<PJRTLoadedExecutable<'a>, String> {
+        let client = self.raw_checked()?;
+        deserialize_and_load_raw(self.rt, client, serialized, overridden_compile_options)
+    }
+
     pub fn platform_name(&self) -> Result<String, String> {
         let client = self.raw_checked()?;
 
@@ -869,6 +1641,42 @@ impl<'a> PJRTClient<'a> {
         };
         Ok(String::from_utf8_lossy(bytes).into_owned())
     }
+
+    pub fn platform(&self) -> Result<Platform, String> {
+        Ok(Platform::classify(
+            &self.platform_name()?,
+            &self.platform_version()?,
+        ))
+    }
+
+    pub fn is_cpu(&self) -> Result<bool, String> {
+        Ok(self.platform()?.is_cpu())
+    }
+
+    pub fn is_gpu(&self) -> Result<bool, String> {
+        Ok(self.platform()?.is_gpu())
+    }
+
+    pub fn is_tpu(&self) -> Result<bool, String> {
+        Ok(self.platform()?.is_tpu())
+    }
+}
+
+impl fmt::Debug for PJRTClient<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PJRTClient")
+            .field("platform", &DebugResult::debug(self.platform_name()))
+            .field("version", &DebugResult::debug(self.platform_version()))
+            .field(
+                "devices",
+                &DebugResult::debug(self.devices().map(|d| d.len())),
+            )
+            .field(
+                "addressable_devices",
+                &DebugResult::debug(self.addressable_devices().map(|d| d.len())),
+            )
+            .finish()
+    }
 }
 
 impl Drop for PJRTClient<'_> {
@@ -881,3 +1689,57 @@ impl Drop for PJRTClient<'_> {
         let _ = self.rt.destroy_client(self.raw_client);
     }
 }
+
+pub(crate) fn fulfill_alias_buffer_raw(
+    rt: &PjrtRuntime,
+    client: *mut PJRT_Client,
+    fulfill_alias_buffer_cb: *mut PJRT_FulfillAliasBufferCallback,
+    buffer: Option<*mut PJRT_Buffer>,
+    status_code: PjrtErrorCode,
+    error_message: Option<&str>,
+) -> Result<(), String> {
+    if client.is_null() {
+        return Err("PJRT_Client is null".to_string());
+    }
+    if fulfill_alias_buffer_cb.is_null() {
+        return Err("fulfill_alias_buffer_cb is null".to_string());
+    }
+
+    let f = rt
+        .api()
+        .PJRT_Client_FulfillAliasBuffer
+        .ok_or("PJRT_Client_FulfillAliasBuffer symbol not found")?;
+
+    let raw_buffer = buffer.unwrap_or(ptr::null_mut());
+    if status_code == PjrtErrorCode::Ok && raw_buffer.is_null() {
+        return Err("buffer must be non-null when status_code is OK".to_string());
+    }
+
+    let error_message_bytes = if status_code == PjrtErrorCode::Ok {
+        &[][..]
+    } else {
+        error_message.map(str::as_bytes).unwrap_or(&[])
+    };
+
+    let mut args = PJRT_Client_FulfillAliasBuffer_Args {
+        struct_size: PJRT_Client_FulfillAliasBuffer_Args_STRUCT_SIZE as usize,
+        extension_start: ptr::null_mut(),
+        client,
+        buffer: raw_buffer,
+        status_code: status_code.raw(),
+        error_message: if error_message_bytes.is_empty() {
+            ptr::null()
+        } else {
+            error_message_bytes.as_ptr() as *const libc::c_char
+        },
+        error_message_size: error_message_bytes.len(),
+        fulfill_alias_buffer_cb,
+    };
+
+    let err = unsafe { f(&mut args) };
+    if err.is_null() {
+        Ok(())
+    } else {
+        Err(error_to_string(rt.api(), err))
+    }
+}
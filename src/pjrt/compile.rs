@@ -1,8 +1,399 @@
-use std::ptr::null_mut;
+use crate::pjrt::client::PJRTClient;
 use crate::pjrt::device::PJRTDevice;
+use crate::pjrt::error::{
+    io_error_code, pjrt_check, ContextFrame, OwnedPjrtError, PJRTError, PjrtErrorCode,
+};
 use crate::pjrt::executable::PJRTLoadedExecutable;
 use crate::pjrt::loader::{error_to_string, PjrtRuntime};
 use crate::pjrt_sys::*;
+use std::fmt;
+use std::path::Path;
+use std::ptr::null_mut;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::thread::{Scope, ScopedJoinHandle};
+use std::time::Duration;
+
+const SNIPPET_MAX_CHARS: usize = 160;
+
+// A compile failure with the plugin's error code preserved, plus enough of the input to
+// tell which program failed.
+pub struct CompileError<'a> {
+    error: PJRTError<'a>,
+    format: String,
+    snippet: String,
+}
+
+impl<'a> CompileError<'a> {
+    pub(crate) fn new(error: PJRTError<'a>, format: &str, code: &[u8]) -> Self {
+        Self {
+            error,
+            format: format.to_string(),
+            snippet: program_snippet(code),
+        }
+    }
+
+    pub fn error(&self) -> &PJRTError<'a> {
+        &self.error
+    }
+
+    pub fn into_error(self) -> PJRTError<'a> {
+        self.error
+    }
+
+    pub fn code(&self) -> PjrtErrorCode {
+        self.error.code()
+    }
+
+    pub fn message(&self) -> &str {
+        self.error.message()
+    }
+
+    pub fn format(&self) -> &str {
+        &self.format
+    }
+
+    pub fn snippet(&self) -> &str {
+        &self.snippet
+    }
+
+    // MLIR diagnostic lines from the plugin message, e.g. `loc("-":3:5): error: ...`.
+    pub fn diagnostics(&self) -> Vec<&str> {
+        self.message()
+            .lines()
+            .map(str::trim)
+            .filter(|l| is_diagnostic_line(l))
+            .collect()
+    }
+}
+
+fn is_diagnostic_line(line: &str) -> bool {
+    line.contains("loc(")
+        || [": error:", ": warning:", ": note:", ": remark:"]
+            .iter()
+            .any(|kind| line.contains(kind))
+}
+
+fn program_snippet(code: &[u8]) -> String {
+    match std::str::from_utf8(code) {
+        Ok(text) => {
+            let mut snippet: String = text.chars().take(SNIPPET_MAX_CHARS).collect();
+            if snippet.len() < text.len() {
+                snippet.push_str("...");
+            }
+            snippet
+        }
+        Err(_) => format!("<{} bytes of binary program>", code.len()),
+    }
+}
+
+impl fmt::Debug for CompileError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompileError")
+            .field("error", &self.error)
+            .field("format", &self.format)
+            .field("snippet", &self.snippet)
+            .finish()
+    }
+}
+
+impl fmt::Display for CompileError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "compile failed (format {}): {}\nprogram: {}",
+            self.format, self.error, self.snippet
+        )
+    }
+}
+
+impl std::error::Error for CompileError<'_> {}
+
+impl From<CompileError<'_>> for String {
+    fn from(e: CompileError<'_>) -> Self {
+        e.to_string()
+    }
+}
+
+// Keeps the plugin's code; the message gains the format and program snippet.
+impl From<CompileError<'_>> for OwnedPjrtError {
+    fn from(e: CompileError<'_>) -> Self {
+        let message = e.to_string();
+        let owned = e.error.into_owned();
+        OwnedPjrtError { message, ..owned }
+    }
+}
+
+#[derive(Debug)]
+pub enum CompileFileError<'a> {
+    Io(std::io::Error),
+    Format(String),
+    Compile(CompileError<'a>),
+}
+
+impl fmt::Display for CompileFileError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileFileError::Io(e) => write!(f, "failed to read program file: {e}"),
+            CompileFileError::Format(e) => write!(f, "cannot determine program format: {e}"),
+            CompileFileError::Compile(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for CompileFileError<'_> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CompileFileError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<CompileFileError<'_>> for String {
+    fn from(e: CompileFileError<'_>) -> Self {
+        e.to_string()
+    }
+}
+
+impl From<CompileFileError<'_>> for OwnedPjrtError {
+    fn from(e: CompileFileError<'_>) -> Self {
+        match e {
+            CompileFileError::Io(ref io) => OwnedPjrtError::new(io_error_code(io), e.to_string()),
+            CompileFileError::Format(_) => {
+                OwnedPjrtError::new(PjrtErrorCode::InvalidArgument, e.to_string())
+            }
+            CompileFileError::Compile(e) => e.into(),
+        }
+    }
+}
+
+// Program formats PJRT_Client_Compile understands. `Other` passes a plugin-specific format
+// name through unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProgramFormat {
+    // MLIR/StableHLO, as text or bytecode.
+    Mlir,
+    // A serialized HloModuleProto.
+    HloProto,
+    Other(&'static str),
+}
+
+impl ProgramFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProgramFormat::Mlir => "mlir",
+            ProgramFormat::HloProto => "hlo",
+            ProgramFormat::Other(name) => name,
+        }
+    }
+
+    // Only the names PJRT defines are accepted, so a typo fails here rather than inside the
+    // plugin; use `Other` for anything else.
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "mlir" => Ok(ProgramFormat::Mlir),
+            "hlo" => Ok(ProgramFormat::HloProto),
+            _ => Err(format!(
+                "unknown program format {name:?}; expected \"mlir\" or \"hlo\""
+            )),
+        }
+    }
+
+    // Whether programs in this format can be passed as text. Serialized protos are binary
+    // and must go through compile_bytes.
+    pub fn accepts_text(&self) -> bool {
+        !matches!(self, ProgramFormat::HloProto)
+    }
+}
+
+impl fmt::Display for ProgramFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// Binary formats passed as `&str` would have gone through UTF-8 already, so they are
+// refused before reaching the plugin.
+pub(crate) fn check_text_format(format: ProgramFormat) -> Result<(), String> {
+    if format.accepts_text() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{format} programs are binary; pass the bytes to compile_bytes"
+        ))
+    }
+}
+
+// PJRT program format for a file name: MLIR for MLIR/StableHLO text or bytecode, HloProto
+// for a serialized HloModuleProto.
+pub fn program_format_for_path(path: &Path) -> Result<ProgramFormat, String> {
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| format!("{} has no usable file name", path.display()))?
+        .to_ascii_lowercase();
+
+    if name.ends_with(".mlir") || name.ends_with(".stablehlo") || name.ends_with(".mlirbc") {
+        Ok(ProgramFormat::Mlir)
+    } else if name.ends_with(".hlo.pb") || name.ends_with(".pb") {
+        Ok(ProgramFormat::HloProto)
+    } else {
+        Err(format!(
+            "unrecognized extension for {}; pass an explicit format",
+            path.display()
+        ))
+    }
+}
+
+type CompileOutcome<'a> = Result<PJRTLoadedExecutable<'a>, CompileError<'a>>;
+
+// The client handle a compile worker uses. PJRT_Client_Compile may be called from any
+// thread, so the handle itself may cross threads.
+struct CompileClient(*mut PJRT_Client);
+
+unsafe impl Send for CompileClient {}
+
+// A freshly compiled executable on its way back from the worker. It is only used by one
+// thread at a time, and is destroyed if nobody collects it.
+struct CompiledExecutable<'a> {
+    rt: &'a PjrtRuntime,
+    raw: *mut PJRT_LoadedExecutable,
+}
+
+unsafe impl Send for CompiledExecutable<'_> {}
+
+impl<'a> CompiledExecutable<'a> {
+    fn from_executable(mut executable: PJRTLoadedExecutable<'a>) -> Self {
+        let raw = std::mem::replace(&mut executable.raw, null_mut());
+        Self {
+            rt: executable.rt,
+            raw,
+        }
+    }
+
+    fn into_executable(mut self) -> PJRTLoadedExecutable<'a> {
+        PJRTLoadedExecutable::new(self.rt, std::mem::replace(&mut self.raw, null_mut()))
+    }
+}
+
+impl Drop for CompiledExecutable<'_> {
+    fn drop(&mut self) {
+        drop(PJRTLoadedExecutable::new(self.rt, self.raw));
+    }
+}
+
+type WorkerOutcome<'a> = Result<CompiledExecutable<'a>, CompileError<'a>>;
+
+pub enum CompileWait<'s, 'a> {
+    Ready(CompileOutcome<'a>),
+    Pending(CompileHandle<'s, 'a>),
+}
+
+// Starts compiles on scoped threads; see PJRTClient::compile_scope.
+pub struct CompileScope<'s, 'c, 'a> {
+    scope: &'s Scope<'s, 'c>,
+    client: &'c PJRTClient<'a>,
+}
+
+impl<'s, 'c, 'a> CompileScope<'s, 'c, 'a> {
+    pub(crate) fn run<R>(
+        client: &'c PJRTClient<'a>,
+        f: impl for<'t> FnOnce(&CompileScope<'t, 'c, 'a>) -> R,
+    ) -> R {
+        std::thread::scope(|scope| f(&CompileScope { scope, client }))
+    }
+
+    pub fn spawn(
+        &self,
+        program_code: &[u8],
+        format: ProgramFormat,
+        compile_options: &[u8],
+    ) -> CompileHandle<'s, 'a> {
+        let rt = self.client.rt;
+        let client = CompileClient(self.client.raw());
+        let code = program_code.to_vec();
+        let options = compile_options.to_vec();
+        let (tx, rx) = channel();
+        let worker_tx = tx.clone();
+        let thread = std::thread::Builder::new()
+            .name("rrad-compile".to_string())
+            .spawn_scoped(self.scope, move || {
+                let client = client;
+                let outcome = PJRTCompiler::new(rt, client.0)
+                    .compile_bytes(&code, format, &options)
+                    .map(CompiledExecutable::from_executable);
+                // If the handle is gone the executable is destroyed with the unsent message.
+                let _ = worker_tx.send(outcome);
+            });
+        let thread = match thread {
+            Ok(thread) => Some(thread),
+            Err(e) => {
+                let error = PJRTError::with_code(
+                    rt,
+                    PjrtErrorCode::ResourceExhausted,
+                    format!("failed to start a compile thread: {e}"),
+                );
+                let _ = tx.send(Err(CompileError::new(error, format.as_str(), program_code)));
+                None
+            }
+        };
+        CompileHandle {
+            rt,
+            format,
+            thread,
+            result: rx,
+        }
+    }
+}
+
+// A compile running on a thread of a CompileScope, which joins it before the scope ends.
+pub struct CompileHandle<'s, 'a> {
+    rt: &'a PjrtRuntime,
+    format: ProgramFormat,
+    thread: Option<ScopedJoinHandle<'s, ()>>,
+    result: Receiver<WorkerOutcome<'a>>,
+}
+
+impl<'s, 'a> CompileHandle<'s, 'a> {
+    pub fn is_done(&self) -> bool {
+        self.thread
+            .as_ref()
+            .is_none_or(ScopedJoinHandle::is_finished)
+    }
+
+    pub fn wait(mut self) -> CompileOutcome<'a> {
+        let received = self.result.recv().map_err(|_| ());
+        self.finish(received)
+    }
+
+    pub fn wait_timeout(mut self, timeout: Duration) -> CompileWait<'s, 'a> {
+        match self.result.recv_timeout(timeout) {
+            Ok(outcome) => CompileWait::Ready(self.finish(Ok(outcome))),
+            Err(RecvTimeoutError::Timeout) => CompileWait::Pending(self),
+            Err(RecvTimeoutError::Disconnected) => CompileWait::Ready(self.finish(Err(()))),
+        }
+    }
+
+    // A disconnected channel means the worker died before reporting a result.
+    fn finish(&mut self, received: Result<WorkerOutcome<'a>, ()>) -> CompileOutcome<'a> {
+        let panicked = self.thread.take().is_some_and(|t| t.join().is_err());
+        match received {
+            Ok(outcome) => outcome.map(CompiledExecutable::into_executable),
+            Err(()) => {
+                let message = if panicked {
+                    "background compile thread panicked"
+                } else {
+                    "background compile thread exited without a result"
+                };
+                Err(CompileError::new(
+                    PJRTError::with_code(self.rt, PjrtErrorCode::Internal, message),
+                    self.format.as_str(),
+                    &[],
+                ))
+            }
+        }
+    }
+}
 
 pub struct PJRTCompiler<'a> {
     rt: &'a PjrtRuntime,
@@ -26,25 +417,63 @@ impl<'a> PJRTCompiler<'a> {
         &self,
         program: &PJRT_Program,
         compile_options: &[u8],
-    ) -> Result<PJRTLoadedExecutable<'a>, String> {
-        let client = self.raw_checked()?;
+    ) -> Result<PJRTLoadedExecutable<'a>, CompileError<'a>> {
         let mut program_local = *program;
 
         if program_local.struct_size == 0 {
             program_local.struct_size = std::mem::size_of::<PJRT_Program>();
         }
+        let format = if program_local.format.is_null() || program_local.format_size == 0 {
+            String::new()
+        } else {
+            let bytes = unsafe {
+                std::slice::from_raw_parts(
+                    program_local.format as *const u8,
+                    program_local.format_size,
+                )
+            };
+            String::from_utf8_lossy(bytes).into_owned()
+        };
+        let fail = |code: PjrtErrorCode, msg: &str, program_code: &[u8]| {
+            CompileError::new(
+                PJRTError::with_code(self.rt, code, msg),
+                &format,
+                program_code,
+            )
+        };
+
         if program_local.code_size > 0 && program_local.code.is_null() {
-            return Err("PJRT_Program.code is null but code_size is nonzero".to_string());
+            return Err(fail(
+                PjrtErrorCode::InvalidArgument,
+                "PJRT_Program.code is null but code_size is nonzero",
+                &[],
+            ));
         }
+        let code_bytes: &[u8] = if program_local.code.is_null() {
+            &[]
+        } else {
+            unsafe {
+                std::slice::from_raw_parts(program_local.code as *const u8, program_local.code_size)
+            }
+        };
         if program_local.format_size > 0 && program_local.format.is_null() {
-            return Err("PJRT_Program.format is null but format_size is nonzero".to_string());
+            return Err(fail(
+                PjrtErrorCode::InvalidArgument,
+                "PJRT_Program.format is null but format_size is nonzero",
+                code_bytes,
+            ));
         }
+        let client = self
+            .raw_checked()
+            .map_err(|e| fail(PjrtErrorCode::InvalidArgument, &e, code_bytes))?;
 
-        let client_compile = self
-            .rt
-            .api()
-            .PJRT_Client_Compile
-            .ok_or("PJRT_Client_Compile symbol not found")?;
+        let client_compile = self.rt.api().PJRT_Client_Compile.ok_or_else(|| {
+            fail(
+                PjrtErrorCode::Unimplemented,
+                "PJRT_Client_Compile symbol not found",
+                code_bytes,
+            )
+        })?;
 
         let (compile_options_ptr, compile_options_size) = if compile_options.is_empty() {
             (std::ptr::null(), 0usize)
@@ -65,29 +494,87 @@ impl<'a> PJRTCompiler<'a> {
             executable: std::ptr::null_mut(),
         };
 
-        let err = unsafe { client_compile(&mut args) };
-
-        if !err.is_null() {
-            return Err(error_to_string(self.rt.api(), err));
-        }
+        pjrt_check!(
+            self.rt,
+            unsafe { client_compile(&mut args) },
+            ContextFrame::new("PJRT_Client_Compile")
+        )
+        .map_err(|e| CompileError::new(e, &format, code_bytes))?;
         if args.executable.is_null() {
-            return Err("PJRT_Client_Compile succeeded but returned null executable".to_string());
+            return Err(fail(
+                PjrtErrorCode::Internal,
+                "PJRT_Client_Compile succeeded but returned null executable",
+                code_bytes,
+            ));
         }
 
         Ok(PJRTLoadedExecutable::new(self.rt, args.executable))
     }
 
     pub fn compile(
+        &self,
+        program_code: &str,
+        format: ProgramFormat,
+        compile_options: &[u8],
+    ) -> Result<PJRTLoadedExecutable<'a>, CompileError<'a>> {
+        check_text_format(format).map_err(|e| {
+            CompileError::new(
+                PJRTError::with_code(self.rt, PjrtErrorCode::InvalidArgument, e),
+                format.as_str(),
+                program_code.as_bytes(),
+            )
+        })?;
+        self.compile_bytes(program_code.as_bytes(), format, compile_options)
+    }
+
+    #[deprecated(note = "use compile with a ProgramFormat")]
+    pub fn compile_with_format_name(
         &self,
         program_code: &str,
         format: &str,
         compile_options: &[u8],
-    ) -> Result<PJRTLoadedExecutable<'a>, String> {
+    ) -> Result<PJRTLoadedExecutable<'a>, CompileError<'a>> {
+        self.compile_bytes_as(program_code.as_bytes(), format, compile_options)
+    }
+
+    // Binary-safe variant for serialized protos and MLIR bytecode.
+    pub fn compile_bytes(
+        &self,
+        program_code: &[u8],
+        format: ProgramFormat,
+        compile_options: &[u8],
+    ) -> Result<PJRTLoadedExecutable<'a>, CompileError<'a>> {
+        self.compile_bytes_as(program_code, format.as_str(), compile_options)
+    }
+
+    #[deprecated(note = "use compile_bytes with a ProgramFormat")]
+    pub fn compile_bytes_with_format_name(
+        &self,
+        program_code: &[u8],
+        format: &str,
+        compile_options: &[u8],
+    ) -> Result<PJRTLoadedExecutable<'a>, CompileError<'a>> {
+        self.compile_bytes_as(program_code, format, compile_options)
+    }
+
+    fn compile_bytes_as(
+        &self,
+        program_code: &[u8],
+        format: &str,
+        compile_options: &[u8],
+    ) -> Result<PJRTLoadedExecutable<'a>, CompileError<'a>> {
+        let invalid = |msg: &str| {
+            CompileError::new(
+                PJRTError::with_code(self.rt, PjrtErrorCode::InvalidArgument, msg),
+                format,
+                program_code,
+            )
+        };
         if program_code.is_empty() {
-            return Err("program_code must not be empty".to_string());
+            return Err(invalid("program_code must not be empty"));
         }
         if format.is_empty() {
-            return Err("format must not be empty".to_string());
+            return Err(invalid("format must not be empty"));
         }
 
         let program = PJRT_Program {
@@ -103,26 +590,51 @@ impl<'a> PJRTCompiler<'a> {
         self.compile_program(&program, compile_options)
     }
 
+    pub fn compile_file(
+        &self,
+        path: &Path,
+        format: Option<ProgramFormat>,
+        compile_options: &[u8],
+    ) -> Result<PJRTLoadedExecutable<'a>, CompileFileError<'a>> {
+        let format = match format {
+            Some(f) => f,
+            None => program_format_for_path(path).map_err(CompileFileError::Format)?,
+        };
+        let code = std::fs::read(path).map_err(CompileFileError::Io)?;
+        self.compile_bytes(&code, format, compile_options)
+            .map_err(CompileFileError::Compile)
+    }
+
     pub fn compile_program_with_format(
         &self,
         program: &mut PJRT_Program,
-        format: &str,
+        format: ProgramFormat,
         compile_options: &[u8],
-    ) -> Result<PJRTLoadedExecutable<'a>, String> {
+    ) -> Result<PJRTLoadedExecutable<'a>, CompileError<'a>> {
+        let format = format.as_str();
         if format.is_empty() {
-            return Err("format must not be empty".to_string());
+            return Err(CompileError::new(
+                PJRTError::with_code(
+                    self.rt,
+                    PjrtErrorCode::InvalidArgument,
+                    "format must not be empty",
+                ),
+                format,
+                &[],
+            ));
         }
         program.format = format.as_ptr() as *const libc::c_char;
         program.format_size = format.len();
         self.compile_program(program, compile_options)
     }
 
-
     pub fn addressable_devices(&self) -> Result<Vec<PJRTDevice<'a>>, String> {
         let raw = self.raw_checked()?;
 
-        let f = self.rt
-            .api().PJRT_Client_AddressableDevices
+        let f = self
+            .rt
+            .api()
+            .PJRT_Client_AddressableDevices
             .ok_or("PJRT_Client_AddressableDevices symbol not found")?;
 
         let mut args = PJRT_Client_AddressableDevices_Args {
@@ -130,13 +642,10 @@ impl<'a> PJRTCompiler<'a> {
             extension_start: null_mut(),
             client: raw,
             addressable_devices: std::ptr::null(),
-            num_addressable_devices: 0
-        };
-
-        let err = unsafe {
-            f(&mut args)
+            num_addressable_devices: 0,
         };
 
+        let err = unsafe { f(&mut args) };
 
         if !err.is_null() {
             Err(error_to_string(self.rt.api(), err))
@@ -148,10 +657,89 @@ impl<'a> PJRTCompiler<'a> {
                     .to_string(),
             )
         } else {
-            let bytes = unsafe { std::slice::from_raw_parts(args.addressable_devices, args.num_addressable_devices) };
-            let devices = bytes.iter().map(|raw_device| PJRTDevice::new(self.rt, *raw_device)).collect();
-            Ok(devices)
+            let bytes = unsafe {
+                std::slice::from_raw_parts(args.addressable_devices, args.num_addressable_devices)
+            };
+            bytes
+                .iter()
+                .map(|raw_device| PJRTDevice::new(self.rt, *raw_device))
+                .collect()
         }
-        
+    }
+}
+
+#[cfg(test)]
+mod compile_tests {
+    use super::{
+        check_text_format, is_diagnostic_line, program_format_for_path, program_snippet,
+        ProgramFormat,
+    };
+    use std::path::Path;
+
+    #[test]
+    fn snippet_truncates_text_and_summarizes_binary() {
+        assert_eq!(program_snippet(b"module {}"), "module {}");
+        let long = "x".repeat(500);
+        let snippet = program_snippet(long.as_bytes());
+        assert!(snippet.ends_with("...") && snippet.len() < 200);
+        assert_eq!(
+            program_snippet(&[0xff, 0x00, 0x12]),
+            "<3 bytes of binary program>"
+        );
+    }
+
+    #[test]
+    fn recognizes_mlir_diagnostics() {
+        assert!(is_diagnostic_line("loc(\"-\":2:3): error: unknown op"));
+        assert!(is_diagnostic_line(
+            "<unknown>:1:1: error: expected operation name"
+        ));
+        assert!(!is_diagnostic_line(
+            "INVALID_ARGUMENT: failed to parse module"
+        ));
+    }
+
+    #[test]
+    fn detects_format_from_extension() {
+        let mlir = Ok(ProgramFormat::Mlir);
+        assert_eq!(program_format_for_path(Path::new("add.mlir")), mlir);
+        assert_eq!(
+            program_format_for_path(Path::new("dir/model.stablehlo")),
+            mlir
+        );
+        assert_eq!(program_format_for_path(Path::new("m.MLIRBC")), mlir);
+        assert_eq!(
+            program_format_for_path(Path::new("/tmp/m.hlo.pb")),
+            Ok(ProgramFormat::HloProto)
+        );
+        assert!(program_format_for_path(Path::new("model.txt")).is_err());
+        assert!(program_format_for_path(Path::new("/")).is_err());
+    }
+
+    #[test]
+    fn hlo_fixture_is_not_text() {
+        let fixture = include_bytes!("../../tests/fixtures/add_self_f32.hlo.pb");
+        assert!(fixture.contains(&0));
+        let snippet = program_snippet(fixture);
+        assert_eq!(
+            snippet,
+            format!("<{} bytes of binary program>", fixture.len())
+        );
+        assert!(check_text_format(ProgramFormat::HloProto).is_err());
+        assert!(check_text_format(ProgramFormat::Mlir).is_ok());
+    }
+
+    #[test]
+    fn program_formats_map_to_pjrt_names() {
+        for format in [ProgramFormat::Mlir, ProgramFormat::HloProto] {
+            assert_eq!(ProgramFormat::from_name(format.as_str()), Ok(format));
+        }
+        assert_eq!(ProgramFormat::HloProto.to_string(), "hlo");
+        assert_eq!(ProgramFormat::Other("tpu_custom").as_str(), "tpu_custom");
+
+        let err = ProgramFormat::from_name("mhlo").unwrap_err();
+        assert!(err.contains("\"mhlo\""), "{err}");
+        assert!(ProgramFormat::Mlir.accepts_text());
+        assert!(!ProgramFormat::HloProto.accepts_text());
     }
 }
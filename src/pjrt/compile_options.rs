@@ -0,0 +1,739 @@
+use std::collections::BTreeMap;
+
+use crate::pjrt::client::PJRTClient;
+use crate::pjrt::device::PJRTDevice;
+
+// Hand-rolled encoder/decoder for the subset of xla.CompileOptionsProto the builder exposes.
+// Field numbers follow xla/pjrt/proto/compile_options.proto and xla/xla_data.proto.
+const COMPILE_OPTIONS_EXECUTABLE_BUILD_OPTIONS: u32 = 3;
+const COMPILE_OPTIONS_ENV_OPTION_OVERRIDES: u32 = 7;
+
+const BUILD_OPTIONS_DEVICE_ORDINAL: u32 = 1;
+const BUILD_OPTIONS_NUM_REPLICAS: u32 = 4;
+const BUILD_OPTIONS_NUM_PARTITIONS: u32 = 5;
+const BUILD_OPTIONS_USE_SPMD_PARTITIONING: u32 = 6;
+const BUILD_OPTIONS_DEVICE_ASSIGNMENT: u32 = 9;
+
+const DEVICE_ASSIGNMENT_REPLICA_COUNT: u32 = 1;
+const DEVICE_ASSIGNMENT_COMPUTATION_COUNT: u32 = 2;
+const DEVICE_ASSIGNMENT_COMPUTATION_DEVICES: u32 = 3;
+const COMPUTATION_DEVICE_REPLICA_DEVICE_IDS: u32 = 1;
+
+const MAP_ENTRY_KEY: u32 = 1;
+const MAP_ENTRY_VALUE: u32 = 2;
+
+const OPTION_OVERRIDE_STRING: u32 = 1;
+const OPTION_OVERRIDE_BOOL: u32 = 2;
+const OPTION_OVERRIDE_INT: u32 = 3;
+const OPTION_OVERRIDE_DOUBLE: u32 = 4;
+
+const WIRE_VARINT: u8 = 0;
+const WIRE_FIXED64: u8 = 1;
+const WIRE_LEN: u8 = 2;
+const WIRE_FIXED32: u8 = 5;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OptionOverride {
+    String(String),
+    Bool(bool),
+    Int(i64),
+    Double(f64),
+}
+
+impl From<&str> for OptionOverride {
+    fn from(v: &str) -> Self {
+        OptionOverride::String(v.to_string())
+    }
+}
+
+impl From<String> for OptionOverride {
+    fn from(v: String) -> Self {
+        OptionOverride::String(v)
+    }
+}
+
+impl From<bool> for OptionOverride {
+    fn from(v: bool) -> Self {
+        OptionOverride::Bool(v)
+    }
+}
+
+impl From<i64> for OptionOverride {
+    fn from(v: i64) -> Self {
+        OptionOverride::Int(v)
+    }
+}
+
+impl From<f64> for OptionOverride {
+    fn from(v: f64) -> Self {
+        OptionOverride::Double(v)
+    }
+}
+
+// computation_devices[computation][replica] is the device id running that replica.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceAssignment {
+    computation_devices: Vec<Vec<i64>>,
+}
+
+impl DeviceAssignment {
+    pub fn new(computation_devices: Vec<Vec<i64>>) -> Result<Self, String> {
+        let replicas = computation_devices.first().map_or(0, Vec::len);
+        if computation_devices.iter().any(|c| c.len() != replicas) {
+            return Err("every computation must list the same number of replicas".to_string());
+        }
+        Ok(Self {
+            computation_devices,
+        })
+    }
+
+    pub fn replica_count(&self) -> usize {
+        self.computation_devices.first().map_or(0, Vec::len)
+    }
+
+    pub fn computation_count(&self) -> usize {
+        self.computation_devices.len()
+    }
+
+    pub fn device_id(&self, replica: usize, computation: usize) -> Option<i64> {
+        self.computation_devices
+            .get(computation)
+            .and_then(|c| c.get(replica))
+            .copied()
+    }
+
+    // Like device_id, but as the i32 PJRT uses for device ids, with an error naming the
+    // assignment's bounds when the slot doesn't exist.
+    pub fn device_for(&self, replica: usize, partition: usize) -> Result<i32, String> {
+        let id = self.device_id(replica, partition).ok_or_else(|| {
+            format!(
+                "replica {replica}, partition {partition} is outside the {}x{} device assignment",
+                self.replica_count(),
+                self.computation_count()
+            )
+        })?;
+        i32::try_from(id).map_err(|_| format!("device id {id} does not fit a PJRT device id"))
+    }
+
+    pub fn device_ref_for<'a>(
+        &self,
+        client: &PJRTClient<'a>,
+        replica: usize,
+        partition: usize,
+    ) -> Result<PJRTDevice<'a>, String> {
+        client.lookup_device_ref(self.device_for(replica, partition)?)
+    }
+
+    // device_ids()[partition][replica], matching the proto's computation-major layout.
+    pub fn device_ids(&self) -> &[Vec<i64>] {
+        &self.computation_devices
+    }
+
+    // Parses a serialized xla.DeviceAssignmentProto, e.g. from
+    // PJRTLoadedExecutable::device_assignment_serialized.
+    pub fn decode(bytes: &[u8]) -> Result<Self, String> {
+        decode_device_assignment(bytes).map_err(|e| {
+            format!(
+                "failed to decode DeviceAssignmentProto ({} bytes): {e}",
+                bytes.len()
+            )
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompileOptionsBuilder {
+    num_replicas: i64,
+    num_partitions: i64,
+    use_spmd_partitioning: bool,
+    device_assignment: Option<DeviceAssignment>,
+    debug_flags: BTreeMap<String, OptionOverride>,
+}
+
+impl Default for CompileOptionsBuilder {
+    fn default() -> Self {
+        Self {
+            num_replicas: 1,
+            num_partitions: 1,
+            use_spmd_partitioning: false,
+            device_assignment: None,
+            debug_flags: BTreeMap::new(),
+        }
+    }
+}
+
+impl CompileOptionsBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn num_replicas(mut self, n: i64) -> Self {
+        self.num_replicas = n;
+        self
+    }
+
+    pub fn num_partitions(mut self, n: i64) -> Self {
+        self.num_partitions = n;
+        self
+    }
+
+    pub fn use_spmd_partitioning(mut self, enabled: bool) -> Self {
+        self.use_spmd_partitioning = enabled;
+        self
+    }
+
+    pub fn device_assignment(mut self, assignment: DeviceAssignment) -> Self {
+        self.device_assignment = Some(assignment);
+        self
+    }
+
+    // XLA debug options such as "xla_cpu_enable_fast_math", applied by the plugin through
+    // env_option_overrides. Setting a flag again replaces its value.
+    pub fn override_flag(
+        mut self,
+        name: impl Into<String>,
+        value: impl Into<OptionOverride>,
+    ) -> Self {
+        self.debug_flags.insert(name.into(), value.into());
+        self
+    }
+
+    #[deprecated(note = "use override_flag")]
+    pub fn debug_flag(self, name: impl Into<String>, value: impl Into<OptionOverride>) -> Self {
+        self.override_flag(name, value)
+    }
+
+    pub fn get_num_replicas(&self) -> i64 {
+        self.num_replicas
+    }
+
+    pub fn get_num_partitions(&self) -> i64 {
+        self.num_partitions
+    }
+
+    pub fn get_use_spmd_partitioning(&self) -> bool {
+        self.use_spmd_partitioning
+    }
+
+    pub fn get_device_assignment(&self) -> Option<&DeviceAssignment> {
+        self.device_assignment.as_ref()
+    }
+
+    pub fn get_debug_flags(&self) -> &BTreeMap<String, OptionOverride> {
+        &self.debug_flags
+    }
+
+    pub fn build(&self) -> Result<Vec<u8>, String> {
+        if self.num_replicas < 1 || self.num_partitions < 1 {
+            return Err(format!(
+                "num_replicas ({}) and num_partitions ({}) must be at least 1",
+                self.num_replicas, self.num_partitions
+            ));
+        }
+        if let Some(da) = &self.device_assignment {
+            if da.replica_count() as i64 != self.num_replicas
+                || da.computation_count() as i64 != self.num_partitions
+            {
+                return Err(format!(
+                    "device assignment is {}x{} but options ask for {} replicas x {} partitions",
+                    da.replica_count(),
+                    da.computation_count(),
+                    self.num_replicas,
+                    self.num_partitions
+                ));
+            }
+        }
+
+        let mut build_options = Vec::new();
+        put_varint_field(
+            &mut build_options,
+            BUILD_OPTIONS_DEVICE_ORDINAL,
+            -1i64 as u64,
+        );
+        put_varint_field(
+            &mut build_options,
+            BUILD_OPTIONS_NUM_REPLICAS,
+            self.num_replicas as u64,
+        );
+        put_varint_field(
+            &mut build_options,
+            BUILD_OPTIONS_NUM_PARTITIONS,
+            self.num_partitions as u64,
+        );
+        if self.use_spmd_partitioning {
+            put_varint_field(&mut build_options, BUILD_OPTIONS_USE_SPMD_PARTITIONING, 1);
+        }
+        if let Some(da) = &self.device_assignment {
+            let encoded = encode_device_assignment(da);
+            put_len_field(
+                &mut build_options,
+                BUILD_OPTIONS_DEVICE_ASSIGNMENT,
+                &encoded,
+            );
+        }
+
+        let mut out = Vec::new();
+        put_len_field(
+            &mut out,
+            COMPILE_OPTIONS_EXECUTABLE_BUILD_OPTIONS,
+            &build_options,
+        );
+        for (name, value) in &self.debug_flags {
+            let mut entry = Vec::new();
+            put_len_field(&mut entry, MAP_ENTRY_KEY, name.as_bytes());
+            put_len_field(&mut entry, MAP_ENTRY_VALUE, &encode_option_override(value));
+            put_len_field(&mut out, COMPILE_OPTIONS_ENV_OPTION_OVERRIDES, &entry);
+        }
+        Ok(out)
+    }
+
+    // Reads back the fields this builder understands from serialized CompileOptionsProto
+    // bytes (e.g. from PJRTLoadedExecutable::get_compile_options); other fields are skipped.
+    pub fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let mut out = Self::default();
+        for field in Fields::new(bytes) {
+            let (number, value) = field?;
+            match (number, value) {
+                (COMPILE_OPTIONS_EXECUTABLE_BUILD_OPTIONS, Value::Len(b)) => {
+                    out.decode_build_options(b)?
+                }
+                (COMPILE_OPTIONS_ENV_OPTION_OVERRIDES, Value::Len(b)) => {
+                    let (name, value) = decode_override_entry(b)?;
+                    out.debug_flags.insert(name, value);
+                }
+                _ => {}
+            }
+        }
+        Ok(out)
+    }
+
+    fn decode_build_options(&mut self, bytes: &[u8]) -> Result<(), String> {
+        for field in Fields::new(bytes) {
+            match field? {
+                (BUILD_OPTIONS_NUM_REPLICAS, Value::Varint(v)) => self.num_replicas = v as i64,
+                (BUILD_OPTIONS_NUM_PARTITIONS, Value::Varint(v)) => self.num_partitions = v as i64,
+                (BUILD_OPTIONS_USE_SPMD_PARTITIONING, Value::Varint(v)) => {
+                    self.use_spmd_partitioning = v != 0
+                }
+                (BUILD_OPTIONS_DEVICE_ASSIGNMENT, Value::Len(b)) => {
+                    self.device_assignment = Some(decode_device_assignment(b)?)
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+// Read-only view of the options an executable was compiled with, decoded from the
+// serialized CompileOptionsProto. Fields this crate does not model are ignored.
+#[cfg(feature = "proto")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompileOptionsInfo {
+    pub num_replicas: i64,
+    pub num_partitions: i64,
+    pub use_spmd_partitioning: bool,
+    pub device_assignment: Option<DeviceAssignment>,
+    pub debug_flags: BTreeMap<String, OptionOverride>,
+}
+
+#[cfg(feature = "proto")]
+impl CompileOptionsInfo {
+    pub fn decode(bytes: &[u8]) -> Result<Self, String> {
+        Ok(CompileOptionsBuilder::decode(bytes)?.into())
+    }
+
+    // Builder carrying the same options, e.g. to recompile with one field changed.
+    pub fn to_builder(&self) -> CompileOptionsBuilder {
+        CompileOptionsBuilder {
+            num_replicas: self.num_replicas,
+            num_partitions: self.num_partitions,
+            use_spmd_partitioning: self.use_spmd_partitioning,
+            device_assignment: self.device_assignment.clone(),
+            debug_flags: self.debug_flags.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "proto")]
+impl From<CompileOptionsBuilder> for CompileOptionsInfo {
+    fn from(b: CompileOptionsBuilder) -> Self {
+        Self {
+            num_replicas: b.num_replicas,
+            num_partitions: b.num_partitions,
+            use_spmd_partitioning: b.use_spmd_partitioning,
+            device_assignment: b.device_assignment,
+            debug_flags: b.debug_flags,
+        }
+    }
+}
+
+fn encode_device_assignment(da: &DeviceAssignment) -> Vec<u8> {
+    let mut out = Vec::new();
+    put_varint_field(
+        &mut out,
+        DEVICE_ASSIGNMENT_REPLICA_COUNT,
+        da.replica_count() as u64,
+    );
+    put_varint_field(
+        &mut out,
+        DEVICE_ASSIGNMENT_COMPUTATION_COUNT,
+        da.computation_count() as u64,
+    );
+    for computation in &da.computation_devices {
+        let mut packed = Vec::new();
+        for &id in computation {
+            put_varint(&mut packed, id as u64);
+        }
+        let mut device = Vec::new();
+        put_len_field(&mut device, COMPUTATION_DEVICE_REPLICA_DEVICE_IDS, &packed);
+        put_len_field(&mut out, DEVICE_ASSIGNMENT_COMPUTATION_DEVICES, &device);
+    }
+    out
+}
+
+fn decode_device_assignment(bytes: &[u8]) -> Result<DeviceAssignment, String> {
+    let mut replica_count = None;
+    let mut computation_count = None;
+    let mut computations = Vec::new();
+    for field in Fields::new(bytes) {
+        match field? {
+            (DEVICE_ASSIGNMENT_REPLICA_COUNT, Value::Varint(v)) => replica_count = Some(v as usize),
+            (DEVICE_ASSIGNMENT_COMPUTATION_COUNT, Value::Varint(v)) => {
+                computation_count = Some(v as usize)
+            }
+            (DEVICE_ASSIGNMENT_COMPUTATION_DEVICES, Value::Len(b)) => {
+                let mut ids = Vec::new();
+                for inner in Fields::new(b) {
+                    match inner? {
+                        (COMPUTATION_DEVICE_REPLICA_DEVICE_IDS, Value::Varint(v)) => {
+                            ids.push(v as i64)
+                        }
+                        (COMPUTATION_DEVICE_REPLICA_DEVICE_IDS, Value::Len(mut packed)) => {
+                            while !packed.is_empty() {
+                                ids.push(take_varint(&mut packed)? as i64);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                computations.push(ids);
+            }
+            _ => {}
+        }
+    }
+    let assignment = DeviceAssignment::new(computations)?;
+    // The counts are redundant with the device lists; a disagreement means corrupt bytes.
+    if replica_count.is_some_and(|n| n != assignment.replica_count())
+        || computation_count.is_some_and(|n| n != assignment.computation_count())
+    {
+        return Err(format!(
+            "header says {}x{} but the device lists are {}x{}",
+            replica_count.unwrap_or(0),
+            computation_count.unwrap_or(0),
+            assignment.replica_count(),
+            assignment.computation_count()
+        ));
+    }
+    Ok(assignment)
+}
+
+fn encode_option_override(value: &OptionOverride) -> Vec<u8> {
+    let mut out = Vec::new();
+    match value {
+        OptionOverride::String(s) => put_len_field(&mut out, OPTION_OVERRIDE_STRING, s.as_bytes()),
+        OptionOverride::Bool(b) => put_varint_field(&mut out, OPTION_OVERRIDE_BOOL, *b as u64),
+        OptionOverride::Int(i) => put_varint_field(&mut out, OPTION_OVERRIDE_INT, *i as u64),
+        OptionOverride::Double(d) => {
+            put_tag(&mut out, OPTION_OVERRIDE_DOUBLE, WIRE_FIXED64);
+            out.extend_from_slice(&d.to_bits().to_le_bytes());
+        }
+    }
+    out
+}
+
+fn decode_override_entry(bytes: &[u8]) -> Result<(String, OptionOverride), String> {
+    let mut name = None;
+    let mut value = None;
+    for field in Fields::new(bytes) {
+        match field? {
+            (MAP_ENTRY_KEY, Value::Len(b)) => {
+                name = Some(String::from_utf8(b.to_vec()).map_err(|e| e.to_string())?)
+            }
+            (MAP_ENTRY_VALUE, Value::Len(b)) => {
+                for inner in Fields::new(b) {
+                    value = match inner? {
+                        (OPTION_OVERRIDE_STRING, Value::Len(s)) => Some(OptionOverride::String(
+                            String::from_utf8(s.to_vec()).map_err(|e| e.to_string())?,
+                        )),
+                        (OPTION_OVERRIDE_BOOL, Value::Varint(v)) => {
+                            Some(OptionOverride::Bool(v != 0))
+                        }
+                        (OPTION_OVERRIDE_INT, Value::Varint(v)) => {
+                            Some(OptionOverride::Int(v as i64))
+                        }
+                        (OPTION_OVERRIDE_DOUBLE, Value::Fixed64(v)) => {
+                            Some(OptionOverride::Double(f64::from_bits(v)))
+                        }
+                        _ => value,
+                    };
+                }
+            }
+            _ => {}
+        }
+    }
+    let name = name.ok_or("env_option_overrides entry without a key")?;
+    // A map entry with an unset oneof is an empty string override.
+    Ok((name, value.unwrap_or(OptionOverride::String(String::new()))))
+}
+
+fn put_varint(out: &mut Vec<u8>, mut v: u64) {
+    while v >= 0x80 {
+        out.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    out.push(v as u8);
+}
+
+fn put_tag(out: &mut Vec<u8>, field: u32, wire_type: u8) {
+    put_varint(out, ((field as u64) << 3) | wire_type as u64);
+}
+
+fn put_varint_field(out: &mut Vec<u8>, field: u32, v: u64) {
+    put_tag(out, field, WIRE_VARINT);
+    put_varint(out, v);
+}
+
+fn put_len_field(out: &mut Vec<u8>, field: u32, bytes: &[u8]) {
+    put_tag(out, field, WIRE_LEN);
+    put_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+fn take_varint(bytes: &mut &[u8]) -> Result<u64, String> {
+    let mut v = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&b, rest) = bytes.split_first().ok_or("truncated varint")?;
+        *bytes = rest;
+        v |= ((b & 0x7f) as u64) << shift;
+        if b & 0x80 == 0 {
+            return Ok(v);
+        }
+    }
+    Err("varint longer than 10 bytes".to_string())
+}
+
+fn take_bytes<'b>(bytes: &mut &'b [u8], n: usize) -> Result<&'b [u8], String> {
+    if bytes.len() < n {
+        return Err(format!(
+            "truncated field: need {n} bytes, have {}",
+            bytes.len()
+        ));
+    }
+    let (head, rest) = bytes.split_at(n);
+    *bytes = rest;
+    Ok(head)
+}
+
+enum Value<'b> {
+    Varint(u64),
+    Fixed64(u64),
+    Len(&'b [u8]),
+    Fixed32,
+}
+
+struct Fields<'b> {
+    rest: &'b [u8],
+    failed: bool,
+}
+
+impl<'b> Fields<'b> {
+    fn new(bytes: &'b [u8]) -> Self {
+        Self {
+            rest: bytes,
+            failed: false,
+        }
+    }
+
+    fn read(&mut self) -> Result<(u32, Value<'b>), String> {
+        let tag = take_varint(&mut self.rest)?;
+        let number = (tag >> 3) as u32;
+        let value = match (tag & 0x7) as u8 {
+            WIRE_VARINT => Value::Varint(take_varint(&mut self.rest)?),
+            WIRE_FIXED64 => {
+                let b = take_bytes(&mut self.rest, 8)?;
+                Value::Fixed64(u64::from_le_bytes(b.try_into().unwrap()))
+            }
+            WIRE_LEN => {
+                let n = take_varint(&mut self.rest)? as usize;
+                Value::Len(take_bytes(&mut self.rest, n)?)
+            }
+            WIRE_FIXED32 => {
+                take_bytes(&mut self.rest, 4)?;
+                Value::Fixed32
+            }
+            other => return Err(format!("unsupported wire type {other} for field {number}")),
+        };
+        Ok((number, value))
+    }
+}
+
+impl<'b> Iterator for Fields<'b> {
+    type Item = Result<(u32, Value<'b>), String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.rest.is_empty() {
+            return None;
+        }
+        let item = self.read();
+        self.failed = item.is_err();
+        Some(item)
+    }
+}
+
+#[cfg(test)]
+mod compile_options_tests {
+    use super::*;
+
+    #[test]
+    fn default_options_encode_replicas_and_partitions() {
+        let bytes = CompileOptionsBuilder::new().build().unwrap();
+        // field 3 (executable_build_options), then device_ordinal = -1, replicas = 1, partitions = 1.
+        assert_eq!(bytes[0], 0x1a);
+        let decoded = CompileOptionsBuilder::decode(&bytes).unwrap();
+        assert_eq!(decoded, CompileOptionsBuilder::new());
+    }
+
+    #[test]
+    fn round_trips_all_fields() {
+        let options = CompileOptionsBuilder::new()
+            .num_replicas(2)
+            .num_partitions(3)
+            .use_spmd_partitioning(true)
+            .device_assignment(
+                DeviceAssignment::new(vec![vec![0, 1], vec![2, 3], vec![4, 5]]).unwrap(),
+            )
+            .override_flag("xla_cpu_enable_fast_math", false)
+            .override_flag("xla_dump_to", "/tmp/dump")
+            .override_flag("xla_backend_optimization_level", 2i64)
+            .override_flag("xla_some_ratio", 0.5f64);
+        let decoded = CompileOptionsBuilder::decode(&options.build().unwrap()).unwrap();
+        assert_eq!(decoded, options);
+        assert_eq!(
+            decoded.get_device_assignment().unwrap().device_id(1, 2),
+            Some(5)
+        );
+    }
+
+    #[test]
+    fn override_flags_replace_earlier_values() {
+        let options = CompileOptionsBuilder::new()
+            .override_flag("xla_cpu_enable_fast_math", true)
+            .override_flag("xla_cpu_enable_fast_math", false);
+        let decoded = CompileOptionsBuilder::decode(&options.build().unwrap()).unwrap();
+        assert_eq!(decoded.get_debug_flags().len(), 1);
+        assert_eq!(
+            decoded.get_debug_flags()["xla_cpu_enable_fast_math"],
+            OptionOverride::Bool(false)
+        );
+    }
+
+    #[test]
+    fn decode_skips_unknown_fields_and_accepts_unpacked_ids() {
+        let mut computation = Vec::new();
+        put_varint_field(&mut computation, COMPUTATION_DEVICE_REPLICA_DEVICE_IDS, 7);
+        let mut da = Vec::new();
+        put_len_field(&mut da, DEVICE_ASSIGNMENT_COMPUTATION_DEVICES, &computation);
+        let mut build = Vec::new();
+        put_varint_field(&mut build, 8, 1);
+        put_len_field(&mut build, BUILD_OPTIONS_DEVICE_ASSIGNMENT, &da);
+        let mut bytes = Vec::new();
+        put_varint_field(&mut bytes, 4, 1);
+        put_len_field(&mut bytes, COMPILE_OPTIONS_EXECUTABLE_BUILD_OPTIONS, &build);
+        put_tag(&mut bytes, 99, WIRE_FIXED32);
+        bytes.extend_from_slice(&[0; 4]);
+
+        let decoded = CompileOptionsBuilder::decode(&bytes).unwrap();
+        assert_eq!(
+            decoded.get_device_assignment().unwrap().device_id(0, 0),
+            Some(7)
+        );
+    }
+
+    #[test]
+    fn rejects_inconsistent_options() {
+        assert!(CompileOptionsBuilder::new()
+            .num_replicas(0)
+            .build()
+            .is_err());
+        let da = DeviceAssignment::new(vec![vec![0, 1]]).unwrap();
+        assert!(CompileOptionsBuilder::new()
+            .device_assignment(da)
+            .build()
+            .is_err());
+        assert!(DeviceAssignment::new(vec![vec![0, 1], vec![2]]).is_err());
+    }
+
+    #[cfg(feature = "proto")]
+    #[test]
+    fn info_decodes_and_converts_back() {
+        let options = CompileOptionsBuilder::new()
+            .num_replicas(2)
+            .num_partitions(2)
+            .use_spmd_partitioning(true)
+            .device_assignment(DeviceAssignment::new(vec![vec![0, 1], vec![2, 3]]).unwrap());
+        let mut bytes = options.build().unwrap();
+        // An unknown trailing field (e.g. from a newer XLA) must not break decoding.
+        put_len_field(&mut bytes, 42, b"future");
+
+        let info = CompileOptionsInfo::decode(&bytes).unwrap();
+        assert_eq!(info.num_replicas, 2);
+        assert_eq!(info.num_partitions, 2);
+        assert!(info.use_spmd_partitioning);
+        assert_eq!(
+            info.device_assignment.as_ref().unwrap().device_id(1, 1),
+            Some(3)
+        );
+        assert_eq!(info.to_builder(), options);
+    }
+
+    #[test]
+    fn decodes_device_assignment_proto() {
+        let da = DeviceAssignment::new(vec![vec![0, 1], vec![2, 3], vec![4, 5]]).unwrap();
+        let decoded = DeviceAssignment::decode(&encode_device_assignment(&da)).unwrap();
+        assert_eq!(decoded, da);
+        assert_eq!(decoded.device_for(1, 2), Ok(5));
+        assert_eq!(decoded.device_ids()[1], vec![2, 3]);
+        let err = decoded.device_for(2, 0).unwrap_err();
+        assert!(err.contains("outside the 2x3"), "{err}");
+
+        let empty = DeviceAssignment::decode(&[]).unwrap();
+        assert_eq!((empty.replica_count(), empty.computation_count()), (0, 0));
+    }
+
+    #[test]
+    fn device_assignment_decode_errors_name_the_length() {
+        let bytes = encode_device_assignment(&DeviceAssignment::new(vec![vec![7, 8]]).unwrap());
+        let err = DeviceAssignment::decode(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert!(
+            err.contains(&format!("({} bytes)", bytes.len() - 1)),
+            "{err}"
+        );
+
+        // replica_count disagreeing with the device list.
+        let mut inconsistent = Vec::new();
+        put_varint_field(&mut inconsistent, DEVICE_ASSIGNMENT_REPLICA_COUNT, 3);
+        inconsistent.extend_from_slice(&bytes[2..]);
+        let err = DeviceAssignment::decode(&inconsistent).unwrap_err();
+        assert!(err.contains("header says 3x1"), "{err}");
+    }
+
+    #[test]
+    fn rejects_truncated_input() {
+        let bytes = CompileOptionsBuilder::new()
+            .num_replicas(300)
+            .build()
+            .unwrap();
+        assert!(CompileOptionsBuilder::decode(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
@@ -1,13 +1,48 @@
-use std::ptr;
+use crate::pjrt::event::PJRTEvent;
 use crate::pjrt::loader::{error_to_string, PjrtRuntime};
 use crate::pjrt_sys::*;
+use std::ffi::c_void;
+use std::ptr;
 
-pub struct PJRTCopyToDeviceStreamRef<'a> {
+// Owns the stream: dropping it calls PJRT_CopyToDeviceStream_Destroy.
+pub struct PjrtCopyToDeviceStream<'a> {
     rt: &'a PjrtRuntime,
     raw: *mut PJRT_CopyToDeviceStream,
 }
 
-impl<'a> PJRTCopyToDeviceStreamRef<'a> {
+// Back-compat with the original name in this crate.
+pub type PJRTCopyToDeviceStreamRef<'a> = PjrtCopyToDeviceStream<'a>;
+
+unsafe extern "C" fn drop_chunk_bytes(_data: *mut c_void, deleter_arg: *mut c_void) {
+    if !deleter_arg.is_null() {
+        drop(unsafe { Box::from_raw(deleter_arg.cast::<Vec<u8>>()) });
+    }
+}
+
+// PJRT rejects chunks that aren't whole granules or that run past the end of the buffer;
+// checking up front gives a readable error instead of the plugin's.
+pub(crate) fn check_chunk_size(
+    chunk_len: i64,
+    granule_size: i64,
+    current_bytes: i64,
+    total_bytes: i64,
+) -> Result<(), String> {
+    if granule_size > 0 && chunk_len % granule_size != 0 {
+        return Err(format!(
+            "chunk of {chunk_len} bytes is not a multiple of the stream granule size {granule_size}"
+        ));
+    }
+    let remaining = total_bytes - current_bytes;
+    if chunk_len > remaining {
+        return Err(format!(
+            "chunk of {chunk_len} bytes exceeds the {remaining} bytes remaining in the stream \
+             ({current_bytes} of {total_bytes} already added)"
+        ));
+    }
+    Ok(())
+}
+
+impl<'a> PjrtCopyToDeviceStream<'a> {
     pub fn new(rt: &'a PjrtRuntime, raw: *mut PJRT_CopyToDeviceStream) -> Self {
         Self { rt, raw }
     }
@@ -56,6 +91,55 @@ impl<'a> PJRTCopyToDeviceStreamRef<'a> {
         }
     }
 
+    // Copies `data` into the stream once validated. The returned event fires when the
+    // runtime has finished with the chunk.
+    pub fn add_chunk_bytes(&self, data: Vec<u8>) -> Result<PJRTEvent<'a>, String> {
+        let stream = self.raw_checked()?;
+        check_chunk_size(
+            data.len() as i64,
+            self.granule_size()?,
+            self.current_bytes()?,
+            self.total_bytes()?,
+        )?;
+
+        let func = self
+            .rt
+            .api()
+            .PJRT_CopyToDeviceStream_AddChunk
+            .ok_or("PJRT_CopyToDeviceStream_AddChunk symbol not found")?;
+
+        // The runtime owns the chunk from here on and frees it through the deleter.
+        let size = data.len();
+        let owner = Box::into_raw(Box::new(data));
+        let mut chunk = PJRT_Chunk {
+            data: unsafe { (*owner).as_mut_ptr() }.cast::<c_void>(),
+            size,
+            deleter: Some(drop_chunk_bytes),
+            deleter_arg: owner.cast::<c_void>(),
+        };
+
+        let mut args = PJRT_CopyToDeviceStream_AddChunk_Args {
+            struct_size: PJRT_CopyToDeviceStream_AddChunk_Args_STRUCT_SIZE as usize,
+            extension_start: ptr::null_mut(),
+            stream,
+            chunk: &mut chunk,
+            transfer_complete: ptr::null_mut(),
+        };
+
+        let err = unsafe { func(&mut args) };
+        if !err.is_null() {
+            return Err(error_to_string(self.rt.api(), err));
+        }
+        if args.transfer_complete.is_null() {
+            return Err("PJRT_CopyToDeviceStream_AddChunk returned null transfer_complete".into());
+        }
+        Ok(PJRTEvent::new(self.rt, args.transfer_complete))
+    }
+
+    pub fn remaining_bytes(&self) -> Result<i64, String> {
+        Ok(self.total_bytes()? - self.current_bytes()?)
+    }
+
     pub fn current_bytes(&self) -> Result<i64, String> {
         let stream = self.raw_checked()?;
         let func = self
@@ -95,9 +179,7 @@ impl<'a> PJRTCopyToDeviceStreamRef<'a> {
             total_bytes: 0,
         };
 
-        let err = unsafe {
-            func(&mut args)
-        };
+        let err = unsafe { func(&mut args) };
 
         if !err.is_null() {
             Err(error_to_string(self.rt.api(), err))
@@ -136,7 +218,7 @@ impl<'a> PJRTCopyToDeviceStreamRef<'a> {
         self.granule_size()
     }
 }
-impl Drop for PJRTCopyToDeviceStreamRef<'_> {
+impl Drop for PjrtCopyToDeviceStream<'_> {
     fn drop(&mut self) {
         if self.raw.is_null() {
             return;
@@ -157,3 +239,20 @@ impl Drop for PJRTCopyToDeviceStreamRef<'_> {
         }
     }
 }
+
+#[cfg(test)]
+mod copy_to_device_stream_tests {
+    use super::check_chunk_size;
+
+    #[test]
+    fn chunk_sizes_must_fit_granule_and_remaining_bytes() {
+        assert!(check_chunk_size(8, 4, 0, 16).is_ok());
+        assert!(check_chunk_size(8, 4, 8, 16).is_ok());
+        assert!(check_chunk_size(0, 4, 16, 16).is_ok());
+
+        let err = check_chunk_size(6, 4, 0, 16).unwrap_err();
+        assert!(err.contains("granule size 4"), "{err}");
+        let err = check_chunk_size(8, 4, 12, 16).unwrap_err();
+        assert!(err.contains("4 bytes remaining"), "{err}");
+    }
+}
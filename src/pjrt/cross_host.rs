@@ -0,0 +1,616 @@
+use std::cell::OnceCell;
+use std::ffi::{c_char, c_void};
+use std::mem;
+use std::ptr;
+use std::slice;
+use std::sync::mpsc;
+
+use crate::pjrt::buffer::PJRTBuffer;
+use crate::pjrt::client::PJRTClient;
+use crate::pjrt::device::PJRTDevice;
+use crate::pjrt::error::{
+    error_status, extension_missing, plugin_error, OwnedPjrtError, PjrtErrorCode, PjrtResult,
+};
+use crate::pjrt::event::PJRTEvent;
+use crate::pjrt::loader::{extension_has_field, find_extension, PjrtRuntime};
+use crate::pjrt_sys::*;
+
+// Layouts from xla/pjrt/c/pjrt_c_api_cross_host_transfers_extension.h, which the generated
+// bindings don't cover.
+type RecvNotifierFn = unsafe extern "C" fn(
+    error: *mut PJRT_Error,
+    serialized_descriptors: *const *const c_char,
+    descriptor_sizes: *const usize,
+    num_descriptors: usize,
+    user_arg: *mut c_void,
+);
+
+#[repr(C)]
+struct RecvNotifierInfo {
+    user_arg: *mut c_void,
+    notifier: Option<RecvNotifierFn>,
+}
+
+#[repr(C)]
+struct MakeReceiveBuffersArgs {
+    struct_size: usize,
+    extension_start: *mut PJRT_Extension_Base,
+    client: *mut PJRT_Client,
+    num_shapes: usize,
+    shape_num_dims: *const usize,
+    num_dims: *const *const i64,
+    element_types: *const PJRT_Buffer_Type,
+    layouts: *const *mut PJRT_Buffer_MemoryLayout,
+    device: *mut PJRT_Device,
+    notifier: RecvNotifierInfo,
+    // Caller storage for num_shapes handles, filled by the plugin.
+    buffers: *mut *mut PJRT_Buffer,
+    num_buffers: usize,
+}
+
+type MakeReceiveBuffersFn =
+    unsafe extern "C" fn(args: *mut MakeReceiveBuffersArgs) -> *mut PJRT_Error;
+
+type SendDoneFn =
+    unsafe extern "C" fn(error: *mut PJRT_Error, sends_were_enqueued: bool, user_arg: *mut c_void);
+
+#[repr(C)]
+struct SendDoneInfo {
+    user_arg: *mut c_void,
+    on_done: Option<SendDoneFn>,
+}
+
+#[repr(C)]
+struct CopyToRemoteDeviceArgs {
+    struct_size: usize,
+    extension_start: *mut PJRT_Extension_Base,
+    buffer: *mut PJRT_Buffer,
+    // Becomes ready once *serialized_descriptor holds the receiver's descriptor.
+    event: *mut PJRT_Event,
+    serialized_descriptor: *mut *mut c_char,
+    serialized_descriptor_size: *mut usize,
+    on_done: SendDoneInfo,
+}
+
+type CopyToRemoteDeviceFn =
+    unsafe extern "C" fn(args: *mut CopyToRemoteDeviceArgs) -> *mut PJRT_Error;
+
+#[repr(C)]
+struct CrossHostExtension {
+    base: PJRT_Extension_Base,
+    make_receive_buffers: Option<MakeReceiveBuffersFn>,
+    copy_to_remote_device: Option<CopyToRemoteDeviceFn>,
+}
+
+unsafe fn find_cross_host_extension<'e>(
+    start: *const PJRT_Extension_Base,
+) -> Option<&'e CrossHostExtension> {
+    let ext = unsafe {
+        find_extension(
+            start,
+            PJRT_Extension_Type_PJRT_Extension_Type_CrossHostTransfers,
+        )?
+    };
+    let ext = unsafe { &*ext.cast::<CrossHostExtension>() };
+    extension_has_field(
+        &ext.base,
+        mem::offset_of!(CrossHostExtension, copy_to_remote_device),
+    )
+    .then_some(ext)
+}
+
+fn cross_host_extension(rt: &PjrtRuntime) -> PjrtResult<&CrossHostExtension> {
+    unsafe { find_cross_host_extension(rt.api().extension_start) }
+        .ok_or_else(|| extension_missing("the PJRT cross-host transfers extension"))
+}
+
+pub fn is_available(rt: &PjrtRuntime) -> bool {
+    unsafe { find_cross_host_extension(rt.api().extension_start) }.is_some()
+}
+
+type TransferStatus<T> = Result<T, (PjrtErrorCode, String)>;
+
+type DescriptorReceiver = mpsc::Receiver<TransferStatus<Vec<CrossHostDescriptor>>>;
+
+fn transfer_result<T>(status: Option<TransferStatus<T>>, operation: &str) -> PjrtResult<T> {
+    match status {
+        Some(Ok(value)) => Ok(value),
+        Some(Err((code, message))) => Err(OwnedPjrtError::new(
+            code,
+            format!("{operation} failed: {message}"),
+        )),
+        None => Err(OwnedPjrtError::new(
+            PjrtErrorCode::Cancelled,
+            format!("{operation} was dropped without completing"),
+        )),
+    }
+}
+
+// Where a receive buffer expects its data. The bytes are plugin-defined; the application
+// ships them to the sending process over its own network layer.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CrossHostDescriptor(Vec<u8>);
+
+impl CrossHostDescriptor {
+    pub fn from_bytes(bytes: impl Into<Vec<u8>>) -> Self {
+        Self(bytes.into())
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+// The array a receive buffer will hold, in the default device layout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceiveShape {
+    pub element_type: PJRT_Buffer_Type,
+    pub dims: Vec<i64>,
+}
+
+impl ReceiveShape {
+    pub fn new(element_type: PJRT_Buffer_Type, dims: &[i64]) -> Self {
+        Self {
+            element_type,
+            dims: dims.to_vec(),
+        }
+    }
+}
+
+struct RecvState {
+    api: *const PJRT_Api,
+    tx: mpsc::SyncSender<TransferStatus<Vec<CrossHostDescriptor>>>,
+}
+
+unsafe extern "C" fn recv_notifier_trampoline(
+    error: *mut PJRT_Error,
+    serialized_descriptors: *const *const c_char,
+    descriptor_sizes: *const usize,
+    num_descriptors: usize,
+    user_arg: *mut c_void,
+) {
+    if user_arg.is_null() {
+        return;
+    }
+    let state = unsafe { Box::from_raw(user_arg.cast::<RecvState>()) };
+    let status = if !error.is_null() {
+        Err(error_status(unsafe { &*state.api }, error))
+    } else if num_descriptors > 0
+        && (serialized_descriptors.is_null() || descriptor_sizes.is_null())
+    {
+        Err((
+            PjrtErrorCode::Internal,
+            "receive notifier passed null descriptors with nonzero count".to_string(),
+        ))
+    } else {
+        // The descriptors are only valid during the callback, so they are copied out.
+        (0..num_descriptors)
+            .map(|i| {
+                let (data, size) =
+                    unsafe { (*serialized_descriptors.add(i), *descriptor_sizes.add(i)) };
+                if size == 0 {
+                    return Ok(CrossHostDescriptor(Vec::new()));
+                }
+                if data.is_null() {
+                    return Err((PjrtErrorCode::Internal, format!("descriptor {i} is null")));
+                }
+                let bytes = unsafe { slice::from_raw_parts(data.cast::<u8>(), size) };
+                Ok(CrossHostDescriptor(bytes.to_vec()))
+            })
+            .collect()
+    };
+    let _ = state.tx.send(status);
+}
+
+// Receive buffers and the descriptors a sender needs to fill them. Each buffer's ready event
+// completes when its data has arrived.
+pub struct CrossHostReceive<'a> {
+    pub buffers: Vec<PJRTBuffer<'a>>,
+    descriptors: DescriptorReceiver,
+    received: OnceCell<TransferStatus<Vec<CrossHostDescriptor>>>,
+}
+
+impl<'a> CrossHostReceive<'a> {
+    // Blocks until the plugin has produced the descriptors, one per buffer.
+    pub fn descriptors(&self) -> PjrtResult<Vec<CrossHostDescriptor>> {
+        if self.received.get().is_none() {
+            if let Ok(status) = self.descriptors.recv() {
+                let _ = self.received.set(status);
+            }
+        }
+        transfer_result(
+            self.received.get().cloned(),
+            "PJRT_Transfers_PJRT_Client_MakeCrossHostReceiveBuffers",
+        )
+    }
+
+    pub fn ready_events(&self) -> PjrtResult<Vec<PJRTEvent<'a>>> {
+        self.buffers
+            .iter()
+            .map(|buffer| Ok(buffer.ready_event()?))
+            .collect()
+    }
+}
+
+unsafe fn make_receive_buffers(
+    f: MakeReceiveBuffersFn,
+    api: *const PJRT_Api,
+    client: *mut PJRT_Client,
+    device: *mut PJRT_Device,
+    shapes: &[ReceiveShape],
+) -> Result<(Vec<*mut PJRT_Buffer>, DescriptorReceiver), *mut PJRT_Error> {
+    let shape_num_dims: Vec<usize> = shapes.iter().map(|shape| shape.dims.len()).collect();
+    let num_dims: Vec<*const i64> = shapes.iter().map(|shape| shape.dims.as_ptr()).collect();
+    let element_types: Vec<PJRT_Buffer_Type> =
+        shapes.iter().map(|shape| shape.element_type).collect();
+    let layouts = vec![ptr::null_mut::<PJRT_Buffer_MemoryLayout>(); shapes.len()];
+    let mut buffers = vec![ptr::null_mut::<PJRT_Buffer>(); shapes.len()];
+
+    let (tx, rx) = mpsc::sync_channel(1);
+    let state = Box::into_raw(Box::new(RecvState { api, tx }));
+    let mut args = MakeReceiveBuffersArgs {
+        struct_size: mem::size_of::<MakeReceiveBuffersArgs>(),
+        extension_start: ptr::null_mut(),
+        client,
+        num_shapes: shapes.len(),
+        shape_num_dims: shape_num_dims.as_ptr(),
+        num_dims: num_dims.as_ptr(),
+        element_types: element_types.as_ptr(),
+        layouts: layouts.as_ptr(),
+        device,
+        notifier: RecvNotifierInfo {
+            user_arg: state.cast(),
+            notifier: Some(recv_notifier_trampoline),
+        },
+        buffers: buffers.as_mut_ptr(),
+        num_buffers: 0,
+    };
+    let err = unsafe { f(&mut args) };
+    // On failure the plugin may or may not have kept the notifier, so its state is leaked
+    // rather than risking a use after free.
+    if !err.is_null() {
+        return Err(err);
+    }
+    buffers.truncate(args.num_buffers);
+    Ok((buffers, rx))
+}
+
+struct SendState {
+    api: *const PJRT_Api,
+    event: *mut PJRT_Event,
+    // Read by the plugin through the pointers in CopyToRemoteDeviceArgs.
+    descriptor: Vec<u8>,
+    descriptor_ptr: *mut c_char,
+    descriptor_size: usize,
+    tx: mpsc::SyncSender<TransferStatus<()>>,
+}
+
+impl Drop for SendState {
+    fn drop(&mut self) {
+        if self.event.is_null() || self.api.is_null() {
+            return;
+        }
+        if let Some(destroy) = unsafe { &*self.api }.PJRT_Event_Destroy {
+            let mut args = PJRT_Event_Destroy_Args {
+                struct_size: PJRT_Event_Destroy_Args_STRUCT_SIZE as usize,
+                extension_start: ptr::null_mut(),
+                event: self.event,
+            };
+            let err = unsafe { destroy(&mut args) };
+            if !err.is_null() {
+                let _ = error_status(unsafe { &*self.api }, err);
+            }
+        }
+    }
+}
+
+unsafe extern "C" fn send_done_trampoline(
+    error: *mut PJRT_Error,
+    sends_were_enqueued: bool,
+    user_arg: *mut c_void,
+) {
+    if user_arg.is_null() {
+        return;
+    }
+    let state = unsafe { Box::from_raw(user_arg.cast::<SendState>()) };
+    let status = if !error.is_null() {
+        Err(error_status(unsafe { &*state.api }, error))
+    } else if !sends_were_enqueued {
+        Err((
+            PjrtErrorCode::Aborted,
+            "the plugin did not enqueue the send".to_string(),
+        ))
+    } else {
+        Ok(())
+    };
+    let _ = state.tx.send(status);
+}
+
+// A send started with PJRTBuffer::copy_to_remote_device.
+pub struct CrossHostSend {
+    done: mpsc::Receiver<TransferStatus<()>>,
+}
+
+impl CrossHostSend {
+    // Blocks until the plugin reports the send as enqueued or failed.
+    pub fn wait(&self) -> PjrtResult<()> {
+        transfer_result(
+            self.done.recv().ok(),
+            "PJRT_Transfers_PJRT_Buffer_CopyToRemoteDevice",
+        )
+    }
+}
+
+// `event` must already be ready, since the descriptor is known before the call.
+unsafe fn copy_to_remote_device(
+    f: CopyToRemoteDeviceFn,
+    api: *const PJRT_Api,
+    buffer: *mut PJRT_Buffer,
+    event: *mut PJRT_Event,
+    descriptor: &CrossHostDescriptor,
+) -> Result<CrossHostSend, *mut PJRT_Error> {
+    let (tx, rx) = mpsc::sync_channel(1);
+    let mut state = Box::new(SendState {
+        api,
+        event,
+        descriptor: descriptor.0.clone(),
+        descriptor_ptr: ptr::null_mut(),
+        descriptor_size: descriptor.0.len(),
+        tx,
+    });
+    state.descriptor_ptr = state.descriptor.as_mut_ptr().cast();
+    let state = Box::into_raw(state);
+
+    let mut args = CopyToRemoteDeviceArgs {
+        struct_size: mem::size_of::<CopyToRemoteDeviceArgs>(),
+        extension_start: ptr::null_mut(),
+        buffer,
+        event,
+        serialized_descriptor: unsafe { ptr::addr_of_mut!((*state).descriptor_ptr) },
+        serialized_descriptor_size: unsafe { ptr::addr_of_mut!((*state).descriptor_size) },
+        on_done: SendDoneInfo {
+            user_arg: state.cast(),
+            on_done: Some(send_done_trampoline),
+        },
+    };
+    let err = unsafe { f(&mut args) };
+    // As above, a failed call leaks the callback state instead of racing a late callback.
+    if !err.is_null() {
+        return Err(err);
+    }
+    Ok(CrossHostSend { done: rx })
+}
+
+// An event that is already ready, for handing a known descriptor to the plugin.
+fn ready_event(rt: &PjrtRuntime) -> PjrtResult<*mut PJRT_Event> {
+    let event = PJRTEvent::create(rt)?.into_raw();
+    let set = rt
+        .api()
+        .PJRT_Event_Set
+        .ok_or("PJRT_Event_Set symbol not found")?;
+    let mut args = PJRT_Event_Set_Args {
+        struct_size: PJRT_Event_Set_Args_STRUCT_SIZE as usize,
+        extension_start: ptr::null_mut(),
+        event,
+        error_code: PJRT_Error_Code_PJRT_Error_Code_OK,
+        error_message: ptr::null(),
+        error_message_size: 0,
+    };
+    let err = unsafe { set(&mut args) };
+    if !err.is_null() {
+        drop(PJRTEvent::new(rt, event));
+        return Err(plugin_error(rt.api(), "PJRT_Event_Set", err));
+    }
+    Ok(event)
+}
+
+impl<'a> PJRTBuffer<'a> {
+    // Allocates buffers on `device` that a buffer in another process can be sent into. The
+    // descriptors identify them to the sender (see copy_to_remote_device).
+    pub fn make_cross_host_receive_descriptors(
+        client: &PJRTClient<'a>,
+        device: &PJRTDevice<'a>,
+        shapes: &[ReceiveShape],
+    ) -> PjrtResult<CrossHostReceive<'a>> {
+        let f = cross_host_extension(client.rt)?
+            .make_receive_buffers
+            .ok_or_else(|| {
+                extension_missing("PJRT_Transfers_PJRT_Client_MakeCrossHostReceiveBuffers")
+            })?;
+        let (raw_buffers, descriptors) =
+            unsafe { make_receive_buffers(f, client.rt.api(), client.raw(), device.raw(), shapes) }
+                .map_err(|err| {
+                    plugin_error(
+                        client.rt.api(),
+                        "PJRT_Transfers_PJRT_Client_MakeCrossHostReceiveBuffers",
+                        err,
+                    )
+                })?;
+        Ok(CrossHostReceive {
+            buffers: raw_buffers
+                .into_iter()
+                .map(|raw| PJRTBuffer::new(client.rt, raw))
+                .collect(),
+            descriptors,
+            received: OnceCell::new(),
+        })
+    }
+
+    // Sends this buffer to the receive buffer `descriptor` names, which may live in another
+    // process. The buffer must match the receiver's shape.
+    pub fn copy_to_remote_device(
+        &self,
+        descriptor: &CrossHostDescriptor,
+    ) -> PjrtResult<CrossHostSend> {
+        let f = cross_host_extension(self.rt)?
+            .copy_to_remote_device
+            .ok_or_else(|| extension_missing("PJRT_Transfers_PJRT_Buffer_CopyToRemoteDevice"))?;
+        if self.raw.is_null() {
+            return Err(OwnedPjrtError::new(
+                PjrtErrorCode::FailedPrecondition,
+                "buffer has been released",
+            ));
+        }
+        let event = ready_event(self.rt)?;
+        unsafe { copy_to_remote_device(f, self.rt.api(), self.raw, event, descriptor) }.map_err(
+            |err| {
+                plugin_error(
+                    self.rt.api(),
+                    "PJRT_Transfers_PJRT_Buffer_CopyToRemoteDevice",
+                    err,
+                )
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod cross_host_tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    // Stands in for a plugin that loops transfers back within the process: the receive
+    // descriptor is the index of a slot, and a send records the buffer against it.
+    static SHAPES: Mutex<Vec<(PJRT_Buffer_Type, Vec<i64>)>> = Mutex::new(Vec::new());
+    static SENT: Mutex<Vec<(Vec<u8>, usize)>> = Mutex::new(Vec::new());
+
+    unsafe extern "C" fn fake_make_receive_buffers(
+        args: *mut MakeReceiveBuffersArgs,
+    ) -> *mut PJRT_Error {
+        let args = unsafe { &mut *args };
+        assert_eq!(args.struct_size, mem::size_of::<MakeReceiveBuffersArgs>());
+        let mut descriptors = Vec::new();
+        for i in 0..args.num_shapes {
+            let dims = unsafe {
+                slice::from_raw_parts(*args.num_dims.add(i), *args.shape_num_dims.add(i))
+            };
+            SHAPES
+                .lock()
+                .unwrap()
+                .push((unsafe { *args.element_types.add(i) }, dims.to_vec()));
+            unsafe { *args.buffers.add(i) = ptr::NonNull::dangling().as_ptr() };
+            descriptors.push(format!("slot:{i}"));
+        }
+        args.num_buffers = args.num_shapes;
+
+        let ptrs: Vec<*const c_char> = descriptors.iter().map(|d| d.as_ptr().cast()).collect();
+        let sizes: Vec<usize> = descriptors.iter().map(String::len).collect();
+        let notifier = args.notifier.notifier.unwrap();
+        unsafe {
+            notifier(
+                ptr::null_mut(),
+                ptrs.as_ptr(),
+                sizes.as_ptr(),
+                ptrs.len(),
+                args.notifier.user_arg,
+            )
+        };
+        ptr::null_mut()
+    }
+
+    unsafe extern "C" fn fake_copy_to_remote_device(
+        args: *mut CopyToRemoteDeviceArgs,
+    ) -> *mut PJRT_Error {
+        let args = unsafe { &*args };
+        let descriptor = unsafe {
+            slice::from_raw_parts(
+                (*args.serialized_descriptor).cast::<u8>(),
+                *args.serialized_descriptor_size,
+            )
+        };
+        SENT.lock()
+            .unwrap()
+            .push((descriptor.to_vec(), args.buffer as usize));
+        unsafe { (args.on_done.on_done.unwrap())(ptr::null_mut(), true, args.on_done.user_arg) };
+        ptr::null_mut()
+    }
+
+    fn extension(size: usize) -> CrossHostExtension {
+        CrossHostExtension {
+            base: PJRT_Extension_Base {
+                struct_size: size,
+                type_: PJRT_Extension_Type_PJRT_Extension_Type_CrossHostTransfers,
+                next: ptr::null_mut(),
+            },
+            make_receive_buffers: Some(fake_make_receive_buffers),
+            copy_to_remote_device: Some(fake_copy_to_remote_device),
+        }
+    }
+
+    #[test]
+    fn loopback_transfer_goes_through_the_extension() {
+        let ext = extension(mem::size_of::<CrossHostExtension>());
+        let found = unsafe { find_cross_host_extension(ptr::addr_of!(ext).cast()) }.unwrap();
+
+        let shapes = [
+            ReceiveShape::new(PJRT_Buffer_Type_PJRT_Buffer_Type_F32, &[2, 3]),
+            ReceiveShape::new(PJRT_Buffer_Type_PJRT_Buffer_Type_S32, &[]),
+        ];
+        let (buffers, rx) = unsafe {
+            make_receive_buffers(
+                found.make_receive_buffers.unwrap(),
+                ptr::null(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                &shapes,
+            )
+        }
+        .unwrap();
+        assert_eq!(buffers.len(), 2);
+        assert_eq!(
+            *SHAPES.lock().unwrap(),
+            [
+                (PJRT_Buffer_Type_PJRT_Buffer_Type_F32, vec![2, 3]),
+                (PJRT_Buffer_Type_PJRT_Buffer_Type_S32, vec![]),
+            ]
+        );
+        let descriptors = transfer_result(rx.recv().ok(), "receive").unwrap();
+        assert_eq!(descriptors[1].as_bytes(), b"slot:1");
+
+        let source = ptr::NonNull::<PJRT_Buffer>::dangling().as_ptr();
+        let send = unsafe {
+            copy_to_remote_device(
+                found.copy_to_remote_device.unwrap(),
+                ptr::null(),
+                source,
+                ptr::null_mut(),
+                &descriptors[1],
+            )
+        }
+        .unwrap();
+        send.wait().unwrap();
+        assert_eq!(
+            *SENT.lock().unwrap(),
+            [(b"slot:1".to_vec(), source as usize)]
+        );
+    }
+
+    #[test]
+    fn transfer_statuses_keep_their_codes() {
+        let err = transfer_result::<()>(
+            Some(Err((PjrtErrorCode::Aborted, "peer went away".to_string()))),
+            "send",
+        )
+        .unwrap_err();
+        assert!(err.is_code(PjrtErrorCode::Aborted));
+        assert_eq!(err.message(), "send failed: peer went away");
+        let err = transfer_result::<()>(None, "send").unwrap_err();
+        assert!(err.is_code(PjrtErrorCode::Cancelled));
+    }
+
+    #[test]
+    fn missing_or_old_extension_is_not_found() {
+        assert!(unsafe { find_cross_host_extension(ptr::null()) }.is_none());
+
+        let old = extension(mem::offset_of!(CrossHostExtension, copy_to_remote_device));
+        assert!(unsafe { find_cross_host_extension(ptr::addr_of!(old).cast()) }.is_none());
+        assert_eq!(
+            CrossHostDescriptor::from_bytes(b"slot:0".to_vec()).into_bytes(),
+            b"slot:0"
+        );
+    }
+}
@@ -1,11 +1,16 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::ptr;
 
+use crate::pjrt::error::PjrtErrorCode;
 use crate::pjrt::loader::{error_to_string, PjrtRuntime};
 use crate::pjrt::memory::PJRTMemory;
-use crate::pjrt::topology_desc::{PJRTDeviceDescriptionRef, PJRTNamedAttribute};
+use crate::pjrt::topology_desc::{DeviceAttributes, PJRTDeviceDescriptionRef, PJRTNamedAttribute};
+use crate::pjrt::utils::DebugResult;
 use crate::pjrt_sys::*;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct PJRTDeviceMemoryStats {
     pub bytes_in_use: i64,
     pub peak_bytes_in_use: Option<i64>,
@@ -20,6 +25,117 @@ pub struct PJRTDeviceMemoryStats {
     pub peak_pool_bytes: Option<i64>,
 }
 
+impl PJRTDeviceMemoryStats {
+    // Fraction of `bytes_limit` in use; None when the plugin reports no (or a zero) limit.
+    pub fn utilization(&self) -> Option<f64> {
+        self.bytes_limit
+            .filter(|&limit| limit > 0)
+            .map(|limit| self.bytes_in_use as f64 / limit as f64)
+    }
+
+    // Bytes left before `bytes_limit`, never negative.
+    pub fn headroom_bytes(&self) -> Option<i64> {
+        self.bytes_limit
+            .map(|limit| limit.saturating_sub(self.bytes_in_use).max(0))
+    }
+}
+
+// Signed change between two stats snapshots. Optional fields are None unless both
+// snapshots report them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct MemoryStatsDelta {
+    pub bytes_in_use: i64,
+    pub num_allocs: Option<i64>,
+    pub pool_bytes: Option<i64>,
+}
+
+impl PJRTDeviceMemoryStats {
+    pub fn delta(&self, earlier: &Self) -> MemoryStatsDelta {
+        let diff = |now: Option<i64>, then: Option<i64>| Some(now?.saturating_sub(then?));
+        MemoryStatsDelta {
+            bytes_in_use: self.bytes_in_use.saturating_sub(earlier.bytes_in_use),
+            num_allocs: diff(self.num_allocs, earlier.num_allocs),
+            pool_bytes: diff(self.pool_bytes, earlier.pool_bytes),
+        }
+    }
+}
+
+// Tracks a device's memory against the baseline taken when the watch started.
+pub struct MemoryWatch<'d, 'a> {
+    device: &'d PJRTDevice<'a>,
+    baseline: PJRTDeviceMemoryStats,
+    peak_bytes_in_use: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct MemoryWatchReport {
+    pub delta: MemoryStatsDelta,
+    // Highest bytes_in_use seen by the watch's own samples, including the baseline.
+    pub peak_bytes_in_use: i64,
+}
+
+impl MemoryWatch<'_, '_> {
+    pub fn baseline(&self) -> &PJRTDeviceMemoryStats {
+        &self.baseline
+    }
+
+    // Samples the device, folding the sample into the observed peak.
+    pub fn snapshot(&mut self) -> Result<PJRTDeviceMemoryStats, String> {
+        let stats = self.device.memory_stats()?;
+        self.peak_bytes_in_use = self.peak_bytes_in_use.max(stats.bytes_in_use);
+        Ok(stats)
+    }
+
+    // Samples the device and reports the change since the baseline.
+    pub fn report(&mut self) -> Result<MemoryWatchReport, String> {
+        let stats = self.snapshot()?;
+        Ok(MemoryWatchReport {
+            delta: stats.delta(&self.baseline),
+            peak_bytes_in_use: self.peak_bytes_in_use,
+        })
+    }
+}
+
+// e.g. "in use 1.0 GiB / 4.0 GiB (25.0%), peak 2.0 GiB, reserved 1.5 GiB"; fields the plugin
+// didn't report are left out.
+impl fmt::Display for PJRTDeviceMemoryStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "in use {}", format_bytes(self.bytes_in_use))?;
+        if let Some(limit) = self.bytes_limit {
+            write!(f, " / {}", format_bytes(limit))?;
+        }
+        if let Some(utilization) = self.utilization() {
+            write!(f, " ({:.1}%)", utilization * 100.0)?;
+        }
+        if let Some(peak) = self.peak_bytes_in_use {
+            write!(f, ", peak {}", format_bytes(peak))?;
+        }
+        if let Some(reserved) = self.bytes_reserved {
+            write!(f, ", reserved {}", format_bytes(reserved))?;
+            if let Some(limit) = self.bytes_reservable_limit {
+                write!(f, " / {}", format_bytes(limit))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn format_bytes(bytes: i64) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+    if bytes.unsigned_abs() < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value.abs() >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
 pub struct PJRTAsyncTrackingEvent<'a> {
     rt: &'a PjrtRuntime,
     raw: *mut PJRT_AsyncTrackingEvent,
@@ -60,11 +176,60 @@ impl Drop for PJRTAsyncTrackingEvent<'_> {
 pub struct PJRTDevice<'a> {
     pub rt: &'a PjrtRuntime,
     pub raw: *mut PJRT_Device,
+    // The global id never changes for a device, so it is fetched once, up front, and is
+    // also the device's identity for Eq and Hash.
+    id: i32,
+}
+
+impl fmt::Debug for PJRTDevice<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PJRTDevice")
+            .field("id", &self.id)
+            .field("kind", &DebugResult::debug(self.kind()))
+            .field(
+                "local_hardware_id",
+                &DebugResult::debug(self.local_hardware_id()),
+            )
+            .field("process_index", &DebugResult::debug(self.process_index()))
+            .finish()
+    }
+}
+
+// Devices compare by global id, so wrappers from separate lookups of the same device are
+// equal.
+impl PartialEq for PJRTDevice<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for PJRTDevice<'_> {}
+
+impl Hash for PJRTDevice<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
 }
 
 impl<'a> PJRTDevice<'a> {
-    pub fn new(rt: &'a PjrtRuntime, raw_device: *mut PJRT_Device) -> Self {
-        Self { rt, raw: raw_device }
+    pub fn new(rt: &'a PjrtRuntime, raw_device: *mut PJRT_Device) -> Result<Self, String> {
+        let mut device = Self {
+            rt,
+            raw: raw_device,
+            id: 0,
+        };
+        device.id = device.description()?.id()?;
+        Ok(device)
+    }
+
+    // Like ==, but also requires the same process index, for multi-host setups where ids
+    // are only meaningful together with the owning process.
+    pub fn same_device(&self, other: &PJRTDevice<'_>) -> bool {
+        self.id == other.id
+            && matches!(
+                (self.process_index(), other.process_index()),
+                (Ok(p), Ok(q)) if p == q
+            )
     }
 
     pub fn raw(&self) -> *mut PJRT_Device {
@@ -198,10 +363,20 @@ impl<'a> PJRTDevice<'a> {
         })
     }
 
+    // Fails like memory_stats when the plugin doesn't report stats.
+    pub fn memory_watch(&self) -> Result<MemoryWatch<'_, 'a>, String> {
+        let baseline = self.memory_stats()?;
+        Ok(MemoryWatch {
+            device: self,
+            peak_bytes_in_use: baseline.bytes_in_use,
+            baseline,
+        })
+    }
+
     pub fn poison_execution(
         &self,
         launch_id: i32,
-        error_code: PJRT_Error_Code,
+        error_code: PjrtErrorCode,
         error_message: &str,
     ) -> Result<bool, String> {
         let raw = self.raw_checked()?;
@@ -218,7 +393,7 @@ impl<'a> PJRTDevice<'a> {
             extension_start: ptr::null_mut(),
             device: raw,
             launch_id,
-            error_code,
+            error_code: error_code.raw(),
             error_message: if error_message_bytes.is_empty() {
                 ptr::null()
             } else {
@@ -340,7 +515,34 @@ impl<'a> PJRTDevice<'a> {
             .collect())
     }
 
-    pub fn default_memory(&self) -> Result<*mut PJRT_Memory, String> {
+    // Each addressable memory with its kind, e.g. "device" or "pinned_host".
+    pub fn memories_with_kinds(&self) -> Result<Vec<(PJRTMemory<'a>, String)>, String> {
+        self.addressable_memory_refs()?
+            .into_iter()
+            .map(|memory| {
+                let kind = memory.kind()?;
+                Ok((memory, kind))
+            })
+            .collect()
+    }
+
+    // The first addressable memory of `kind`, given as a MemoryKind or a kind string.
+    pub fn memory_by_kind(&self, kind: impl AsRef<str>) -> Result<PJRTMemory<'a>, String> {
+        let kind = kind.as_ref();
+        let mut available = Vec::new();
+        for (memory, memory_kind) in self.memories_with_kinds()? {
+            if memory_kind == kind {
+                return Ok(memory);
+            }
+            available.push(memory_kind);
+        }
+        Err(format!(
+            "device has no memory of kind {kind:?}; available kinds: [{}]",
+            available.join(", ")
+        ))
+    }
+
+    pub fn default_memory_raw(&self) -> Result<*mut PJRT_Memory, String> {
         let raw = self.raw_checked()?;
 
         let f = self
@@ -366,12 +568,21 @@ impl<'a> PJRTDevice<'a> {
         Ok(args.memory)
     }
 
+    pub fn default_memory(&self) -> Result<PJRTMemory<'a>, String> {
+        Ok(PJRTMemory::new(self.rt, self.default_memory_raw()?))
+    }
+
+    #[deprecated(note = "use default_memory, which now returns a PJRTMemory")]
     pub fn default_memory_ref(&self) -> Result<PJRTMemory<'a>, String> {
-        Ok(PJRTMemory::new(self.rt, self.default_memory()?))
+        self.default_memory()
+    }
+
+    pub fn memory_kind_of_default(&self) -> Result<String, String> {
+        Ok(self.default_memory()?.kind()?)
     }
 
-    pub fn id(&self) -> Result<i32, String> {
-        self.description()?.id()
+    pub fn id(&self) -> i32 {
+        self.id
     }
 
     pub fn kind(&self) -> Result<String, String> {
@@ -394,8 +605,95 @@ impl<'a> PJRTDevice<'a> {
         self.description()?.attributes()
     }
 
+    pub fn attributes_typed(&self) -> Result<DeviceAttributes, String> {
+        self.attributes().map(DeviceAttributes::new)
+    }
+
     // Backward compatibility with existing call sites.
     pub fn debug_error(&self) -> Result<String, String> {
         self.debug_string()
     }
 }
+
+#[cfg(test)]
+mod device_tests {
+    use super::*;
+
+    fn stats(bytes_in_use: i64, bytes_limit: Option<i64>) -> PJRTDeviceMemoryStats {
+        PJRTDeviceMemoryStats {
+            bytes_in_use,
+            peak_bytes_in_use: None,
+            num_allocs: None,
+            largest_alloc_size: None,
+            bytes_limit,
+            bytes_reserved: None,
+            peak_bytes_reserved: None,
+            bytes_reservable_limit: None,
+            largest_free_block_bytes: None,
+            pool_bytes: None,
+            peak_pool_bytes: None,
+        }
+    }
+
+    #[test]
+    fn utilization_and_headroom_need_a_limit() {
+        let limited = stats(256 << 20, Some(1 << 30));
+        assert_eq!(limited.utilization(), Some(0.25));
+        assert_eq!(limited.headroom_bytes(), Some(768 << 20));
+
+        let unlimited = stats(4096, None);
+        assert_eq!(unlimited.utilization(), None);
+        assert_eq!(unlimited.headroom_bytes(), None);
+
+        let zero_limit = stats(0, Some(0));
+        assert_eq!(zero_limit.utilization(), None);
+        assert_eq!(zero_limit.headroom_bytes(), Some(0));
+
+        let over = stats(3000, Some(2000));
+        assert_eq!(over.utilization(), Some(1.5));
+        assert_eq!(over.headroom_bytes(), Some(0));
+    }
+
+    #[test]
+    fn display_summarizes_reported_fields() {
+        assert_eq!(stats(512, None).to_string(), "in use 512 B");
+
+        let mut full = stats(256 << 20, Some(1 << 30));
+        full.peak_bytes_in_use = Some(300 << 20);
+        full.bytes_reserved = Some(1536);
+        full.bytes_reservable_limit = Some(2 << 30);
+        assert_eq!(
+            full.to_string(),
+            "in use 256.0 MiB / 1.0 GiB (25.0%), peak 300.0 MiB, reserved 1.5 KiB / 2.0 GiB"
+        );
+    }
+
+    #[test]
+    fn deltas_are_signed_and_need_both_sides() {
+        let mut before = stats(1000, None);
+        before.num_allocs = Some(4);
+        before.pool_bytes = Some(64);
+        let mut after = stats(600, None);
+        after.num_allocs = Some(7);
+
+        assert_eq!(
+            after.delta(&before),
+            MemoryStatsDelta {
+                bytes_in_use: -400,
+                num_allocs: Some(3),
+                pool_bytes: None,
+            }
+        );
+        assert_eq!(before.delta(&before).bytes_in_use, 0);
+        assert_eq!(before.delta(&after).num_allocs, Some(-3));
+    }
+
+    #[test]
+    fn byte_counts_use_binary_units() {
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(1023), "1023 B");
+        assert_eq!(format_bytes(1024), "1.0 KiB");
+        assert_eq!(format_bytes(5 << 40), "5.0 TiB");
+        assert_eq!(format_bytes(-2048), "-2.0 KiB");
+    }
+}
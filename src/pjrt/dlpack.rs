@@ -0,0 +1,461 @@
+use std::any::Any;
+use std::ffi::c_void;
+use std::ptr;
+
+use crate::pjrt::layout::MemoryLayout;
+use crate::pjrt::loader::error_to_string;
+use crate::pjrt::platform::Platform;
+use crate::pjrt::utils::BufferType;
+use crate::pjrt_sys::*;
+
+// C layout of the DLPack structs (dlpack.h, ABI version 0.8 / 1.0 unversioned tensor).
+
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DLDeviceType(pub i32);
+
+impl DLDeviceType {
+    pub const CPU: Self = Self(1);
+    pub const CUDA: Self = Self(2);
+    pub const CUDA_HOST: Self = Self(3);
+    pub const ROCM: Self = Self(10);
+    pub const ROCM_HOST: Self = Self(11);
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DLDevice {
+    pub device_type: DLDeviceType,
+    pub device_id: i32,
+}
+
+pub const DL_INT: u8 = 0;
+pub const DL_UINT: u8 = 1;
+pub const DL_FLOAT: u8 = 2;
+pub const DL_BFLOAT: u8 = 4;
+pub const DL_COMPLEX: u8 = 5;
+pub const DL_BOOL: u8 = 6;
+pub const DL_FLOAT8_E3M4: u8 = 7;
+pub const DL_FLOAT8_E4M3: u8 = 8;
+pub const DL_FLOAT8_E4M3B11FNUZ: u8 = 9;
+pub const DL_FLOAT8_E4M3FN: u8 = 10;
+pub const DL_FLOAT8_E4M3FNUZ: u8 = 11;
+pub const DL_FLOAT8_E5M2: u8 = 12;
+pub const DL_FLOAT8_E5M2FNUZ: u8 = 13;
+pub const DL_FLOAT8_E8M0FNU: u8 = 14;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DLDataType {
+    pub code: u8,
+    pub bits: u8,
+    pub lanes: u16,
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct DLTensor {
+    pub data: *mut c_void,
+    pub device: DLDevice,
+    pub ndim: i32,
+    pub dtype: DLDataType,
+    pub shape: *mut i64,
+    // In elements, not bytes; null means compact row-major.
+    pub strides: *mut i64,
+    pub byte_offset: u64,
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct DLManagedTensor {
+    pub dl_tensor: DLTensor,
+    pub manager_ctx: *mut c_void,
+    pub deleter: Option<unsafe extern "C" fn(*mut DLManagedTensor)>,
+}
+
+// DLPack has no 2-bit or 4-bit integer codes, so sub-byte types are not exchanged.
+const DTYPES: [(BufferType, u8, u8); 23] = [
+    (BufferType::Pred, DL_BOOL, 8),
+    (BufferType::S8, DL_INT, 8),
+    (BufferType::S16, DL_INT, 16),
+    (BufferType::S32, DL_INT, 32),
+    (BufferType::S64, DL_INT, 64),
+    (BufferType::U8, DL_UINT, 8),
+    (BufferType::U16, DL_UINT, 16),
+    (BufferType::U32, DL_UINT, 32),
+    (BufferType::U64, DL_UINT, 64),
+    (BufferType::F16, DL_FLOAT, 16),
+    (BufferType::F32, DL_FLOAT, 32),
+    (BufferType::F64, DL_FLOAT, 64),
+    (BufferType::BF16, DL_BFLOAT, 16),
+    (BufferType::C64, DL_COMPLEX, 64),
+    (BufferType::C128, DL_COMPLEX, 128),
+    (BufferType::F8E3M4, DL_FLOAT8_E3M4, 8),
+    (BufferType::F8E4M3, DL_FLOAT8_E4M3, 8),
+    (BufferType::F8E4M3B11FNUZ, DL_FLOAT8_E4M3B11FNUZ, 8),
+    (BufferType::F8E4M3FN, DL_FLOAT8_E4M3FN, 8),
+    (BufferType::F8E4M3FNUZ, DL_FLOAT8_E4M3FNUZ, 8),
+    (BufferType::F8E5M2, DL_FLOAT8_E5M2, 8),
+    (BufferType::F8E5M2FNUZ, DL_FLOAT8_E5M2FNUZ, 8),
+    (BufferType::F8E8M0FNU, DL_FLOAT8_E8M0FNU, 8),
+];
+
+pub fn dl_data_type(ty: BufferType) -> Option<DLDataType> {
+    DTYPES
+        .iter()
+        .find(|(t, _, _)| *t == ty)
+        .map(|&(_, code, bits)| DLDataType {
+            code,
+            bits,
+            lanes: 1,
+        })
+}
+
+pub fn buffer_type_from_dl(dtype: DLDataType) -> Option<BufferType> {
+    if dtype.lanes != 1 {
+        return None;
+    }
+    DTYPES
+        .iter()
+        .find(|(_, code, bits)| *code == dtype.code && *bits == dtype.bits)
+        .map(|&(t, _, _)| t)
+}
+
+pub fn dl_device_type(platform: &Platform) -> Option<DLDeviceType> {
+    match platform {
+        Platform::Cpu => Some(DLDeviceType::CPU),
+        Platform::Cuda => Some(DLDeviceType::CUDA),
+        Platform::Rocm => Some(DLDeviceType::ROCM),
+        Platform::Tpu | Platform::Other(_) => None,
+    }
+}
+
+pub fn platform_from_dl(device_type: DLDeviceType) -> Option<Platform> {
+    match device_type {
+        DLDeviceType::CPU => Some(Platform::Cpu),
+        DLDeviceType::CUDA => Some(Platform::Cuda),
+        DLDeviceType::ROCM => Some(Platform::Rocm),
+        _ => None,
+    }
+}
+
+// Element strides for a dense layout; tiled layouts have no DLPack equivalent.
+pub(crate) fn dl_strides(
+    layout: &MemoryLayout,
+    dims: &[i64],
+    element_bytes: usize,
+) -> Result<Vec<i64>, String> {
+    match layout {
+        MemoryLayout::Tiled {
+            minor_to_major,
+            tile_dims,
+        } => {
+            if !tile_dims.is_empty() {
+                return Err("tiled layouts cannot be exported to DLPack".to_string());
+            }
+            if minor_to_major.len() != dims.len() {
+                return Err(format!(
+                    "layout rank {} does not match buffer rank {}",
+                    minor_to_major.len(),
+                    dims.len()
+                ));
+            }
+            let mut strides = vec![0i64; dims.len()];
+            let mut stride = 1i64;
+            for &dim in minor_to_major {
+                let dim = usize::try_from(dim)
+                    .ok()
+                    .filter(|&d| d < dims.len())
+                    .ok_or_else(|| format!("invalid minor_to_major entry {dim}"))?;
+                strides[dim] = stride;
+                stride *= dims[dim].max(1);
+            }
+            Ok(strides)
+        }
+        MemoryLayout::Strides { byte_strides } => {
+            let element_bytes = element_bytes as i64;
+            byte_strides
+                .iter()
+                .map(|&s| {
+                    if s % element_bytes == 0 {
+                        Ok(s / element_bytes)
+                    } else {
+                        Err(format!(
+                            "byte stride {s} is not a multiple of the element size {element_bytes}"
+                        ))
+                    }
+                })
+                .collect()
+        }
+    }
+}
+
+// Recovers a dense minor-to-major layout from element strides. Strides that skip elements
+// or overlap cannot be viewed by PJRT and are rejected.
+pub(crate) fn layout_from_dl_strides(
+    dims: &[i64],
+    strides: &[i64],
+) -> Result<MemoryLayout, String> {
+    if dims.len() != strides.len() {
+        return Err(format!(
+            "DLPack tensor has {} dims but {} strides",
+            dims.len(),
+            strides.len()
+        ));
+    }
+    let mut order: Vec<usize> = (0..dims.len()).collect();
+    // Size-1 dimensions can carry any stride; order them as row-major would.
+    order.sort_by_key(|&d| {
+        (
+            if dims[d] == 1 { i64::MAX } else { strides[d] },
+            usize::MAX - d,
+        )
+    });
+
+    let mut expected = 1i64;
+    for &d in &order {
+        if dims[d] != 1 && strides[d] != expected {
+            return Err(format!(
+                "DLPack strides {strides:?} for shape {dims:?} are not compact"
+            ));
+        }
+        expected *= dims[d].max(1);
+    }
+    Ok(MemoryLayout::Tiled {
+        minor_to_major: order.into_iter().map(|d| d as i64).collect(),
+        tile_dims: Vec::new(),
+    })
+}
+
+// Owns everything a DLManagedTensor exported from a PJRTBuffer points at, including the
+// buffer itself. The tensor is the first field so the DLManagedTensor pointer handed out
+// is also the context pointer.
+#[repr(C)]
+struct ExportContext {
+    tensor: DLManagedTensor,
+    shape: Vec<i64>,
+    strides: Vec<i64>,
+    api: *const PJRT_Api,
+    buffer: *mut PJRT_Buffer,
+    // Host memory the buffer may alias; dropped with the context, after the destroy.
+    keepalive: Option<Box<dyn Any + Send>>,
+}
+
+unsafe extern "C" fn export_deleter(tensor: *mut DLManagedTensor) {
+    if tensor.is_null() {
+        return;
+    }
+    let ctx = unsafe { Box::from_raw((*tensor).manager_ctx.cast::<ExportContext>()) };
+    let api = unsafe { &*ctx.api };
+    if let Some(f) = api.PJRT_Buffer_DecreaseExternalReferenceCount {
+        let mut args = PJRT_Buffer_DecreaseExternalReferenceCount_Args {
+            struct_size: PJRT_Buffer_DecreaseExternalReferenceCount_Args_STRUCT_SIZE as usize,
+            extension_start: ptr::null_mut(),
+            buffer: ctx.buffer,
+        };
+        let err = unsafe { f(&mut args) };
+        if !err.is_null() {
+            log::warn!(
+                "failed to release PJRT buffer external reference from DLPack deleter: {}",
+                error_to_string(api, err)
+            );
+        }
+    }
+
+    let Some(destroy) = api.PJRT_Buffer_Destroy else {
+        return;
+    };
+    let mut args = PJRT_Buffer_Destroy_Args {
+        struct_size: PJRT_Buffer_Destroy_Args_STRUCT_SIZE as usize,
+        extension_start: ptr::null_mut(),
+        buffer: ctx.buffer,
+    };
+    let err = unsafe { destroy(&mut args) };
+    if !err.is_null() {
+        log::warn!(
+            "failed to destroy exported PJRT buffer from DLPack deleter: {}",
+            error_to_string(api, err)
+        );
+    }
+}
+
+// Takes ownership of `buffer`, on which the caller must already hold one external
+// reference; the deleter returns the reference and then destroys the buffer.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn export_managed_tensor(
+    api: *const PJRT_Api,
+    buffer: *mut PJRT_Buffer,
+    keepalive: Option<Box<dyn Any + Send>>,
+    data: *mut c_void,
+    device: DLDevice,
+    dtype: DLDataType,
+    shape: Vec<i64>,
+    strides: Vec<i64>,
+) -> *mut DLManagedTensor {
+    let ctx = Box::into_raw(Box::new(ExportContext {
+        tensor: DLManagedTensor {
+            dl_tensor: DLTensor {
+                data,
+                device,
+                ndim: shape.len() as i32,
+                dtype,
+                shape: ptr::null_mut(),
+                strides: ptr::null_mut(),
+                byte_offset: 0,
+            },
+            manager_ctx: ptr::null_mut(),
+            deleter: Some(export_deleter),
+        },
+        shape,
+        strides,
+        api,
+        buffer,
+        keepalive,
+    }));
+
+    // The Vec heap allocations stay put now that the context is boxed.
+    unsafe {
+        (*ctx).tensor.dl_tensor.shape = (*ctx).shape.as_mut_ptr();
+        (*ctx).tensor.dl_tensor.strides = (*ctx).strides.as_mut_ptr();
+        (*ctx).tensor.manager_ctx = ctx.cast::<c_void>();
+        ptr::addr_of_mut!((*ctx).tensor)
+    }
+}
+
+// A consumed DLManagedTensor whose deleter runs once PJRT drops the view over it.
+pub(crate) struct ImportedTensor(*mut DLManagedTensor);
+
+unsafe impl Send for ImportedTensor {}
+
+impl ImportedTensor {
+    /// # Safety
+    ///
+    /// `tensor` must be a valid DLManagedTensor that nothing else will delete.
+    pub(crate) unsafe fn new(tensor: *mut DLManagedTensor) -> Self {
+        Self(tensor)
+    }
+
+    pub(crate) fn delete(self) {
+        if let Some(deleter) = unsafe { (*self.0).deleter } {
+            unsafe { deleter(self.0) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod dlpack_tests {
+    use super::*;
+
+    #[test]
+    fn dtype_table_round_trips() {
+        for ty in BufferType::ALL {
+            if let Some(dtype) = dl_data_type(ty) {
+                assert_eq!(dtype.lanes, 1);
+                assert_eq!(buffer_type_from_dl(dtype), Some(ty), "{ty}");
+                assert_eq!(dtype.bits as usize, ty.size_in_bits(), "{ty}");
+            }
+        }
+        assert_eq!(
+            dl_data_type(BufferType::F32),
+            Some(DLDataType {
+                code: DL_FLOAT,
+                bits: 32,
+                lanes: 1
+            })
+        );
+        assert_eq!(
+            dl_data_type(BufferType::Pred).map(|d| d.code),
+            Some(DL_BOOL)
+        );
+        assert_eq!(dl_data_type(BufferType::S4), None);
+        assert_eq!(dl_data_type(BufferType::Token), None);
+        assert_eq!(dl_data_type(BufferType::Invalid), None);
+    }
+
+    #[test]
+    fn dtype_rejects_vector_lanes_and_unknown_codes() {
+        let f32x4 = DLDataType {
+            code: DL_FLOAT,
+            bits: 32,
+            lanes: 4,
+        };
+        assert_eq!(buffer_type_from_dl(f32x4), None);
+        let opaque = DLDataType {
+            code: 3,
+            bits: 64,
+            lanes: 1,
+        };
+        assert_eq!(buffer_type_from_dl(opaque), None);
+        let token_like = DLDataType {
+            code: u8::MAX,
+            bits: 0,
+            lanes: 1,
+        };
+        assert_eq!(buffer_type_from_dl(token_like), None);
+    }
+
+    #[test]
+    fn device_type_table_round_trips() {
+        for platform in [Platform::Cpu, Platform::Cuda, Platform::Rocm] {
+            let dl = dl_device_type(&platform).unwrap();
+            assert_eq!(platform_from_dl(dl), Some(platform));
+        }
+        assert_eq!(dl_device_type(&Platform::Tpu), None);
+        assert_eq!(platform_from_dl(DLDeviceType::CUDA_HOST), None);
+        assert_eq!(DLDeviceType::CPU.0, 1);
+        assert_eq!(DLDeviceType::CUDA.0, 2);
+        assert_eq!(DLDeviceType::ROCM.0, 10);
+    }
+
+    #[test]
+    fn strides_round_trip_through_layouts() {
+        let dims = [2, 3, 4];
+        let row = dl_strides(&MemoryLayout::row_major(3), &dims, 4).unwrap();
+        assert_eq!(row, vec![12, 4, 1]);
+        assert_eq!(
+            layout_from_dl_strides(&dims, &row).unwrap(),
+            MemoryLayout::row_major(3)
+        );
+
+        let col = dl_strides(&MemoryLayout::column_major(3), &dims, 4).unwrap();
+        assert_eq!(col, vec![1, 2, 6]);
+        assert_eq!(
+            layout_from_dl_strides(&dims, &col).unwrap(),
+            MemoryLayout::column_major(3)
+        );
+
+        let bytes = dl_strides(
+            &MemoryLayout::Strides {
+                byte_strides: vec![48, 16, 4],
+            },
+            &dims,
+            4,
+        )
+        .unwrap();
+        assert_eq!(bytes, vec![12, 4, 1]);
+    }
+
+    #[test]
+    fn strides_reject_non_compact_views() {
+        assert!(layout_from_dl_strides(&[2, 3], &[6, 2]).is_err());
+        assert!(layout_from_dl_strides(&[2, 3], &[3]).is_err());
+        assert!(dl_strides(
+            &MemoryLayout::Strides {
+                byte_strides: vec![6, 3]
+            },
+            &[2, 2],
+            4
+        )
+        .is_err());
+        let tiled = MemoryLayout::Tiled {
+            minor_to_major: vec![1, 0],
+            tile_dims: vec![vec![8, 128]],
+        };
+        assert!(dl_strides(&tiled, &[8, 128], 4).is_err());
+        // Unit dimensions may carry arbitrary strides.
+        assert_eq!(
+            layout_from_dl_strides(&[1, 3], &[99, 1]).unwrap(),
+            MemoryLayout::row_major(2)
+        );
+    }
+}
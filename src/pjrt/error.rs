@@ -1,51 +1,585 @@
+use std::fmt;
 use std::ptr;
 
+use crate::pjrt::event::PjrtEventError;
 use crate::pjrt::loader::{error_to_string, PjrtRuntime};
 use crate::pjrt_sys::*;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PjrtErrorCode {
+    Ok,
+    Cancelled,
+    Unknown,
+    InvalidArgument,
+    DeadlineExceeded,
+    NotFound,
+    AlreadyExists,
+    PermissionDenied,
+    ResourceExhausted,
+    FailedPrecondition,
+    Aborted,
+    OutOfRange,
+    Unimplemented,
+    Internal,
+    Unavailable,
+    DataLoss,
+    Unauthenticated,
+}
+
+impl PjrtErrorCode {
+    pub const ALL: [PjrtErrorCode; 17] = [
+        PjrtErrorCode::Ok,
+        PjrtErrorCode::Cancelled,
+        PjrtErrorCode::Unknown,
+        PjrtErrorCode::InvalidArgument,
+        PjrtErrorCode::DeadlineExceeded,
+        PjrtErrorCode::NotFound,
+        PjrtErrorCode::AlreadyExists,
+        PjrtErrorCode::PermissionDenied,
+        PjrtErrorCode::ResourceExhausted,
+        PjrtErrorCode::FailedPrecondition,
+        PjrtErrorCode::Aborted,
+        PjrtErrorCode::OutOfRange,
+        PjrtErrorCode::Unimplemented,
+        PjrtErrorCode::Internal,
+        PjrtErrorCode::Unavailable,
+        PjrtErrorCode::DataLoss,
+        PjrtErrorCode::Unauthenticated,
+    ];
+
+    pub fn raw(self) -> PJRT_Error_Code {
+        match self {
+            PjrtErrorCode::Ok => PJRT_Error_Code_PJRT_Error_Code_OK,
+            PjrtErrorCode::Cancelled => PJRT_Error_Code_PJRT_Error_Code_CANCELLED,
+            PjrtErrorCode::Unknown => PJRT_Error_Code_PJRT_Error_Code_UNKNOWN,
+            PjrtErrorCode::InvalidArgument => PJRT_Error_Code_PJRT_Error_Code_INVALID_ARGUMENT,
+            PjrtErrorCode::DeadlineExceeded => PJRT_Error_Code_PJRT_Error_Code_DEADLINE_EXCEEDED,
+            PjrtErrorCode::NotFound => PJRT_Error_Code_PJRT_Error_Code_NOT_FOUND,
+            PjrtErrorCode::AlreadyExists => PJRT_Error_Code_PJRT_Error_Code_ALREADY_EXISTS,
+            PjrtErrorCode::PermissionDenied => PJRT_Error_Code_PJRT_Error_Code_PERMISSION_DENIED,
+            PjrtErrorCode::ResourceExhausted => PJRT_Error_Code_PJRT_Error_Code_RESOURCE_EXHAUSTED,
+            PjrtErrorCode::FailedPrecondition => {
+                PJRT_Error_Code_PJRT_Error_Code_FAILED_PRECONDITION
+            }
+            PjrtErrorCode::Aborted => PJRT_Error_Code_PJRT_Error_Code_ABORTED,
+            PjrtErrorCode::OutOfRange => PJRT_Error_Code_PJRT_Error_Code_OUT_OF_RANGE,
+            PjrtErrorCode::Unimplemented => PJRT_Error_Code_PJRT_Error_Code_UNIMPLEMENTED,
+            PjrtErrorCode::Internal => PJRT_Error_Code_PJRT_Error_Code_INTERNAL,
+            PjrtErrorCode::Unavailable => PJRT_Error_Code_PJRT_Error_Code_UNAVAILABLE,
+            PjrtErrorCode::DataLoss => PJRT_Error_Code_PJRT_Error_Code_DATA_LOSS,
+            PjrtErrorCode::Unauthenticated => PJRT_Error_Code_PJRT_Error_Code_UNAUTHENTICATED,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PjrtErrorCode::Ok => "OK",
+            PjrtErrorCode::Cancelled => "CANCELLED",
+            PjrtErrorCode::Unknown => "UNKNOWN",
+            PjrtErrorCode::InvalidArgument => "INVALID_ARGUMENT",
+            PjrtErrorCode::DeadlineExceeded => "DEADLINE_EXCEEDED",
+            PjrtErrorCode::NotFound => "NOT_FOUND",
+            PjrtErrorCode::AlreadyExists => "ALREADY_EXISTS",
+            PjrtErrorCode::PermissionDenied => "PERMISSION_DENIED",
+            PjrtErrorCode::ResourceExhausted => "RESOURCE_EXHAUSTED",
+            PjrtErrorCode::FailedPrecondition => "FAILED_PRECONDITION",
+            PjrtErrorCode::Aborted => "ABORTED",
+            PjrtErrorCode::OutOfRange => "OUT_OF_RANGE",
+            PjrtErrorCode::Unimplemented => "UNIMPLEMENTED",
+            PjrtErrorCode::Internal => "INTERNAL",
+            PjrtErrorCode::Unavailable => "UNAVAILABLE",
+            PjrtErrorCode::DataLoss => "DATA_LOSS",
+            PjrtErrorCode::Unauthenticated => "UNAUTHENTICATED",
+        }
+    }
+}
+
+impl TryFrom<PJRT_Error_Code> for PjrtErrorCode {
+    type Error = String;
 
+    fn try_from(code: PJRT_Error_Code) -> Result<Self, Self::Error> {
+        PjrtErrorCode::ALL
+            .iter()
+            .copied()
+            .find(|c| c.raw() == code)
+            .ok_or_else(|| format!("unknown PJRT_Error_Code {code}"))
+    }
+}
+
+impl From<PjrtErrorCode> for PJRT_Error_Code {
+    fn from(code: PjrtErrorCode) -> Self {
+        code.raw()
+    }
+}
+
+impl fmt::Display for PjrtErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// A PJRT error with its code and message captured eagerly, so the plugin-side
+// PJRT_Error can be destroyed as soon as it is received.
 pub struct PJRTError<'a> {
     pub rt: &'a PjrtRuntime,
-    pub raw: *mut PJRT_Error,
+    code: PjrtErrorCode,
+    message: String,
+    context: Vec<ContextFrame>,
+}
+
+// Where an error surfaced: the PJRT entry point that returned it and, when known, the
+// executable and device involved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextFrame {
+    pub operation: &'static str,
+    pub executable: Option<String>,
+    pub device_id: Option<i32>,
+}
+
+impl ContextFrame {
+    pub fn new(operation: &'static str) -> Self {
+        Self {
+            operation,
+            executable: None,
+            device_id: None,
+        }
+    }
+
+    pub fn executable(mut self, name: Option<String>) -> Self {
+        self.executable = name;
+        self
+    }
+
+    pub fn device(mut self, id: Option<i32>) -> Self {
+        self.device_id = id;
+        self
+    }
+}
+
+impl fmt::Display for ContextFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.operation)?;
+        if let Some(name) = &self.executable {
+            write!(f, " for executable {name:?}")?;
+        }
+        if let Some(id) = self.device_id {
+            write!(f, " on device {id}")?;
+        }
+        Ok(())
+    }
+}
+
+fn write_context(f: &mut fmt::Formatter<'_>, context: &[ContextFrame]) -> fmt::Result {
+    for frame in context {
+        write!(f, " (in {frame})")?;
+    }
+    Ok(())
 }
 
+// Checks the PJRT_Error returned by a C API call, naming the call in the resulting
+// PJRTError. `$frame` is only evaluated on failure, so it may do further FFI lookups.
+macro_rules! pjrt_check {
+    ($rt:expr, $err:expr, $frame:expr) => {{
+        let err = $err;
+        if err.is_null() {
+            Ok(())
+        } else {
+            Err($crate::pjrt::error::PJRTError::new($rt, err).with_context($frame))
+        }
+    }};
+}
+pub(crate) use pjrt_check;
 
 impl<'a> PJRTError<'a> {
+    // Takes ownership of a plugin error: reads its code and message, then destroys it.
     pub fn new(rt: &'a PjrtRuntime, raw: *mut PJRT_Error) -> Self {
-        Self { rt, raw }
+        if raw.is_null() {
+            return Self::with_code(rt, PjrtErrorCode::Unknown, "PJRT_Error is null");
+        }
+        let code = raw_error_code(rt.api(), raw).unwrap_or(PjrtErrorCode::Unknown);
+        // error_to_string reads the message and destroys the error.
+        let message = error_to_string(rt.api(), raw);
+        Self {
+            rt,
+            code,
+            message,
+            context: Vec::new(),
+        }
     }
-    
-    pub fn raw_checked(&self) -> Result<*mut PJRT_Error, String> {
-        if self.raw.is_null() {
-            Err("PJRT_Error is null".to_string())
-        } else {
-            Ok(self.raw)
-        }
-    }
-    
-    pub fn get_code(&self) -> Result<PJRT_Error_Code, String> {
-        let raw = self.raw_checked()?;
-        
-        let func = self.rt
-            .api().PJRT_Error_GetCode
-            .ok_or("PJRT_Error_GetCode symbol not found")?;
-        
-        let mut args = PJRT_Error_GetCode_Args {
-            struct_size: PJRT_Error_GetCode_Args_STRUCT_SIZE as usize,
-            extension_start: ptr::null_mut(),
-            error: raw,
-            code: 0,
-        };
-        
-        let err = unsafe {
-            func(&mut args)
-        };
-        
-        
-        if !err.is_null() {
-            Err(error_to_string(self.rt.api(), err).to_string())
-        } else {
-            Ok(args.code)
+
+    pub fn with_code(rt: &'a PjrtRuntime, code: PjrtErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            rt,
+            code,
+            message: message.into(),
+            context: Vec::new(),
+        }
+    }
+
+    pub fn invalid_argument(rt: &'a PjrtRuntime, message: impl Into<String>) -> Self {
+        Self::with_code(rt, PjrtErrorCode::InvalidArgument, message)
+    }
+
+    pub fn unimplemented(rt: &'a PjrtRuntime, message: impl Into<String>) -> Self {
+        Self::with_code(rt, PjrtErrorCode::Unimplemented, message)
+    }
+
+    pub fn internal(rt: &'a PjrtRuntime, message: impl Into<String>) -> Self {
+        Self::with_code(rt, PjrtErrorCode::Internal, message)
+    }
+
+    pub fn not_found(rt: &'a PjrtRuntime, message: impl Into<String>) -> Self {
+        Self::with_code(rt, PjrtErrorCode::NotFound, message)
+    }
+
+    pub fn resource_exhausted(rt: &'a PjrtRuntime, message: impl Into<String>) -> Self {
+        Self::with_code(rt, PjrtErrorCode::ResourceExhausted, message)
+    }
+
+    // The plugin's API table has no entry for `symbol`, e.g. "PJRT_Event_IsReady".
+    pub fn missing_symbol(rt: &'a PjrtRuntime, symbol: &str) -> Self {
+        Self::unimplemented(rt, format!("{symbol} symbol not found"))
+    }
+
+    // Wraps a message from one of the String-returning wrappers, which lose the code.
+    // Missing symbols are recognized as UNIMPLEMENTED; anything else gets `fallback`.
+    pub fn from_wrapper_message(
+        rt: &'a PjrtRuntime,
+        fallback: PjrtErrorCode,
+        message: impl Into<String>,
+    ) -> Self {
+        let message = message.into();
+        Self::with_code(rt, code_for_wrapper_message(&message, fallback), message)
+    }
+
+    pub fn get_code(&self) -> PJRT_Error_Code {
+        self.code.raw()
+    }
+
+    pub fn code(&self) -> PjrtErrorCode {
+        self.code
+    }
+
+    pub fn is_code(&self, code: PjrtErrorCode) -> bool {
+        self.code == code
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    // Innermost frame first.
+    pub fn context(&self) -> &[ContextFrame] {
+        &self.context
+    }
+
+    pub fn with_context(mut self, frame: ContextFrame) -> Self {
+        self.context.push(frame);
+        self
+    }
+}
+
+fn code_for_wrapper_message(message: &str, fallback: PjrtErrorCode) -> PjrtErrorCode {
+    if message.ends_with(" symbol not found") {
+        PjrtErrorCode::Unimplemented
+    } else {
+        fallback
+    }
+}
+
+impl fmt::Debug for PJRTError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PJRTError")
+            .field("code", &self.code)
+            .field("message", &self.message)
+            .field("context", &self.context)
+            .finish()
+    }
+}
+
+impl fmt::Display for PJRTError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)?;
+        write_context(f, &self.context)
+    }
+}
+
+impl From<PJRTError<'_>> for String {
+    fn from(e: PJRTError<'_>) -> Self {
+        e.to_string()
+    }
+}
+
+impl std::error::Error for PJRTError<'_> {}
+
+impl<'a> PJRTError<'a> {
+    // Drops the runtime reference so the error can leave the runtime's scope or thread.
+    pub fn into_owned(self) -> OwnedPjrtError {
+        OwnedPjrtError {
+            code: self.code,
+            message: self.message,
+            context: self.context,
         }
     }
 }
+
+// Results for APIs that hand errors back to application code, and for wrappers that keep the
+// runtime borrow.
+pub type PjrtResult<T> = Result<T, OwnedPjrtError>;
+pub type PjrtResultRef<'a, T> = Result<T, PJRTError<'a>>;
+
+// A PJRTError detached from its runtime. PJRTError itself is Send + Sync but borrows the
+// runtime; this is also 'static, as `Box<dyn Error + Send + Sync>` and anyhow require.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedPjrtError {
+    pub code: PjrtErrorCode,
+    pub message: String,
+    pub context: Vec<ContextFrame>,
+}
+
+impl OwnedPjrtError {
+    pub fn new(code: PjrtErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            context: Vec::new(),
+        }
+    }
+
+    pub fn code(&self) -> PjrtErrorCode {
+        self.code
+    }
+
+    pub fn is_code(&self, code: PjrtErrorCode) -> bool {
+        self.code == code
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    // Innermost frame first.
+    pub fn context(&self) -> &[ContextFrame] {
+        &self.context
+    }
+
+    pub fn with_context(mut self, frame: ContextFrame) -> Self {
+        self.context.push(frame);
+        self
+    }
+}
+
+impl fmt::Display for OwnedPjrtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)?;
+        write_context(f, &self.context)
+    }
+}
+
+impl std::error::Error for OwnedPjrtError {}
+
+impl From<PJRTError<'_>> for OwnedPjrtError {
+    fn from(e: PJRTError<'_>) -> Self {
+        e.into_owned()
+    }
+}
+
+// For the wrappers that still return `Result<_, String>`; the code is recovered where the
+// message allows it.
+impl From<String> for OwnedPjrtError {
+    fn from(message: String) -> Self {
+        let code = code_for_wrapper_message(&message, PjrtErrorCode::Unknown);
+        Self::new(code, message)
+    }
+}
+
+impl From<&str> for OwnedPjrtError {
+    fn from(message: &str) -> Self {
+        message.to_string().into()
+    }
+}
+
+impl From<PjrtEventError> for OwnedPjrtError {
+    fn from(e: PjrtEventError) -> Self {
+        Self::new(e.code, e.message)
+    }
+}
+
+pub(crate) fn io_error_code(e: &std::io::Error) -> PjrtErrorCode {
+    match e.kind() {
+        std::io::ErrorKind::NotFound => PjrtErrorCode::NotFound,
+        std::io::ErrorKind::PermissionDenied => PjrtErrorCode::PermissionDenied,
+        _ => PjrtErrorCode::Unknown,
+    }
+}
+
+impl From<OwnedPjrtError> for String {
+    fn from(e: OwnedPjrtError) -> Self {
+        e.to_string()
+    }
+}
+
+// Reads the code and message of an owned PJRT_Error, destroying it.
+pub(crate) fn error_status(api: &PJRT_Api, raw: *mut PJRT_Error) -> (PjrtErrorCode, String) {
+    let code = raw_error_code(api, raw).unwrap_or(PjrtErrorCode::Unknown);
+    (code, error_to_string(api, raw))
+}
+
+// An owned PJRT_Error returned by an extension entry point, tagged with that entry point.
+// Extension wrappers return PjrtResult, so they share this instead of each wrapping the
+// status in an error type of their own.
+pub(crate) fn plugin_error(
+    api: &PJRT_Api,
+    operation: &'static str,
+    raw: *mut PJRT_Error,
+) -> OwnedPjrtError {
+    let (code, message) = error_status(api, raw);
+    OwnedPjrtError::new(code, message).with_context(ContextFrame::new(operation))
+}
+
+// For an extension, or an entry point of one, that the loaded plugin doesn't provide.
+pub(crate) fn extension_missing(what: &str) -> OwnedPjrtError {
+    OwnedPjrtError::new(
+        PjrtErrorCode::Unimplemented,
+        format!("plugin does not provide {what}"),
+    )
+}
+
+fn raw_error_code(api: &PJRT_Api, raw: *mut PJRT_Error) -> Result<PjrtErrorCode, String> {
+    let func = api
+        .PJRT_Error_GetCode
+        .ok_or("PJRT_Error_GetCode symbol not found")?;
+
+    let mut args = PJRT_Error_GetCode_Args {
+        struct_size: PJRT_Error_GetCode_Args_STRUCT_SIZE as usize,
+        extension_start: ptr::null_mut(),
+        error: raw,
+        code: 0,
+    };
+
+    let err = unsafe { func(&mut args) };
+    if !err.is_null() {
+        return Err(error_to_string(api, err));
+    }
+    PjrtErrorCode::try_from(args.code)
+}
+
+#[cfg(test)]
+mod pjrt_error_code_tests {
+    use super::{
+        code_for_wrapper_message, extension_missing, ContextFrame, OwnedPjrtError, PjrtErrorCode,
+        PjrtResult,
+    };
+    use crate::pjrt_sys::*;
+
+    #[test]
+    fn error_code_round_trips_every_variant() {
+        for code in PjrtErrorCode::ALL {
+            let raw: PJRT_Error_Code = code.into();
+            assert_eq!(PjrtErrorCode::try_from(raw), Ok(code));
+        }
+        assert_eq!(
+            PjrtErrorCode::Internal.raw(),
+            PJRT_Error_Code_PJRT_Error_Code_INTERNAL
+        );
+        assert_eq!(
+            PjrtErrorCode::Unauthenticated.raw(),
+            PJRT_Error_Code_PJRT_Error_Code_UNAUTHENTICATED
+        );
+    }
+
+    #[test]
+    fn error_code_rejects_unknown_constant() {
+        assert!(PjrtErrorCode::try_from(99).is_err());
+    }
+
+    #[test]
+    fn missing_symbols_are_unimplemented() {
+        assert_eq!(
+            code_for_wrapper_message("PJRT_Event_Await symbol not found", PjrtErrorCode::Unknown),
+            PjrtErrorCode::Unimplemented
+        );
+        assert_eq!(
+            code_for_wrapper_message("buffer was deleted", PjrtErrorCode::FailedPrecondition),
+            PjrtErrorCode::FailedPrecondition
+        );
+    }
+
+    #[test]
+    fn owned_errors_cross_threads_and_box() {
+        let err = OwnedPjrtError::from("PJRT_Client_Compile symbol not found");
+        assert!(err.is_code(PjrtErrorCode::Unimplemented));
+
+        let moved = std::thread::spawn(move || err).join().unwrap();
+        let boxed: Box<dyn std::error::Error + Send + Sync> = Box::new(moved.clone());
+        assert_eq!(boxed.to_string(), moved.to_string());
+        assert_eq!(
+            boxed.downcast_ref::<OwnedPjrtError>().map(|e| e.code()),
+            Some(PjrtErrorCode::Unimplemented)
+        );
+        assert_eq!(
+            OwnedPjrtError::from("device busy".to_string()).to_string(),
+            "UNKNOWN: device busy"
+        );
+    }
+
+    #[test]
+    fn owned_errors_collect_across_threads() {
+        let workers: Vec<_> = (0..4)
+            .map(|i| {
+                std::thread::spawn(move || -> PjrtResult<()> {
+                    if i % 2 == 0 {
+                        return Err(OwnedPjrtError::new(
+                            PjrtErrorCode::Internal,
+                            format!("worker {i}"),
+                        ));
+                    }
+                    Err(format!("PJRT_Worker_{i} symbol not found").into())
+                })
+            })
+            .collect();
+        let errors: Vec<OwnedPjrtError> = workers
+            .into_iter()
+            .filter_map(|worker| worker.join().unwrap().err())
+            .collect();
+
+        let codes: Vec<_> = errors.iter().map(OwnedPjrtError::code).collect();
+        assert_eq!(
+            codes,
+            [
+                PjrtErrorCode::Internal,
+                PjrtErrorCode::Unimplemented,
+                PjrtErrorCode::Internal,
+                PjrtErrorCode::Unimplemented,
+            ]
+        );
+        assert_eq!(errors[2].message(), "worker 2");
+    }
+
+    #[test]
+    fn context_frames_follow_the_message() {
+        let err = OwnedPjrtError::new(PjrtErrorCode::InvalidArgument, "shape mismatch")
+            .with_context(
+                ContextFrame::new("PJRT_LoadedExecutable_Execute")
+                    .executable(Some("add_one".to_string()))
+                    .device(Some(0)),
+            )
+            .with_context(ContextFrame::new("PJRT_Client_Compile"));
+        assert_eq!(err.context()[1].operation, "PJRT_Client_Compile");
+        assert_eq!(
+            err.to_string(),
+            "INVALID_ARGUMENT: shape mismatch (in PJRT_LoadedExecutable_Execute for executable \
+             \"add_one\" on device 0) (in PJRT_Client_Compile)"
+        );
+    }
+
+    #[test]
+    fn missing_extensions_are_unimplemented() {
+        let err = extension_missing("the PJRT layouts extension");
+        assert!(err.is_code(PjrtErrorCode::Unimplemented));
+        assert_eq!(
+            err.message(),
+            "plugin does not provide the PJRT layouts extension"
+        );
+    }
+}
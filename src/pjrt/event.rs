@@ -1,30 +1,264 @@
+use crate::pjrt::error::{error_status, PJRTError, PjrtErrorCode};
+use crate::pjrt::loader::{error_to_string, PjrtRuntime};
+use crate::pjrt_sys::*;
+use std::any::Any;
+use std::ffi::c_void;
+use std::fmt;
+#[cfg(feature = "tokio")]
+use std::future::{Future, IntoFuture};
 use std::mem;
+#[cfg(feature = "tokio")]
+use std::pin::Pin;
 use std::ptr;
 use std::ptr::null_mut;
-use crate::pjrt::loader::{error_to_string, PjrtRuntime};
-use crate::pjrt_sys::*;
-use crate::pjrt::error::PJRTError;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+#[cfg(feature = "tokio")]
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+#[cfg(feature = "tokio")]
+use tokio::sync::oneshot;
+
+// Final status of an event, keeping the error code alongside the message.
+pub(crate) type EventStatus = Result<(), (PjrtErrorCode, String)>;
+
+pub(crate) type OnReadyFn = Box<dyn FnOnce(EventStatus) + Send>;
+
+struct OnReadyState {
+    api: *const PJRT_Api,
+    callback: OnReadyFn,
+}
+
+unsafe extern "C" fn on_ready_trampoline(error: *mut PJRT_Error, user_arg: *mut c_void) {
+    if user_arg.is_null() {
+        return;
+    }
+    let state = unsafe { Box::from_raw(user_arg.cast::<OnReadyState>()) };
+    // The callback owns `error`; error_status destroys it.
+    let status = if error.is_null() {
+        Ok(())
+    } else {
+        Err(error_status(unsafe { &*state.api }, error))
+    };
+    (state.callback)(status);
+}
+
+// Owned by both PJRTEvent::on_ready and the plugin callback, since the plugin may run the
+// callback before PJRT_Event_OnReady returns. Whichever side finishes second frees it, so the
+// event is never destroyed while OnReady is still using it.
+struct OnReadyRegistration<'a, F> {
+    event: PJRTEvent<'a>,
+    callback: Option<F>,
+    handed_off: AtomicBool,
+}
+
+unsafe fn release_registration<F>(registration: *mut OnReadyRegistration<'_, F>) {
+    if unsafe { &(*registration).handed_off }.swap(true, Ordering::AcqRel) {
+        drop(unsafe { Box::from_raw(registration) });
+    }
+}
+
+unsafe extern "C" fn registration_trampoline<'a, F>(error: *mut PJRT_Error, user_arg: *mut c_void)
+where
+    F: FnOnce(Result<(), PJRTError<'a>>) + Send + 'static,
+{
+    if user_arg.is_null() {
+        return;
+    }
+    let registration = user_arg.cast::<OnReadyRegistration<'a, F>>();
+    let rt = unsafe { (*registration).event.rt };
+    // The callback owns `error`; PJRTError::new destroys it.
+    let status = if error.is_null() {
+        Ok(())
+    } else {
+        Err(PJRTError::new(rt, error))
+    };
+    if let Some(callback) = unsafe { (*registration).callback.take() } {
+        callback(status);
+    }
+    unsafe { release_registration(registration) };
+}
+
+// How a failed event completed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PjrtEventError {
+    pub code: PjrtErrorCode,
+    pub message: String,
+}
+
+impl fmt::Display for PjrtEventError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for PjrtEventError {}
+
+impl From<(PjrtErrorCode, String)> for PjrtEventError {
+    fn from((code, message): (PjrtErrorCode, String)) -> Self {
+        Self { code, message }
+    }
+}
+
+impl From<PjrtEventError> for String {
+    fn from(e: PjrtEventError) -> Self {
+        e.to_string()
+    }
+}
+
+// Calls `notify` with each event's index and status as it completes. Events whose OnReady
+// registration fails are awaited in place.
+fn notify_all<N>(events: Vec<PJRTEvent<'_>>, notify: N)
+where
+    N: Fn(usize, EventStatus) + Clone + Send + 'static,
+{
+    for (i, event) in events.into_iter().enumerate() {
+        let on_ready = notify.clone();
+        let registered = event.try_on_ready(move |status| {
+            on_ready(i, status.map_err(|e| (e.code(), e.message().to_string())))
+        });
+        if let Err((event, e)) = registered {
+            log::warn!("PJRT_Event_OnReady failed, waiting for event {i} synchronously: {e}");
+            notify(i, event.await_status());
+        }
+    }
+}
+
+// Failures sorted by index. An event that never reported is counted as cancelled.
+fn failures_by_index(
+    count: usize,
+    statuses: impl IntoIterator<Item = (usize, EventStatus)>,
+) -> Vec<(usize, (PjrtErrorCode, String))> {
+    let mut seen: Vec<Option<EventStatus>> = vec![None; count];
+    for (i, status) in statuses {
+        if let Some(slot) = seen.get_mut(i) {
+            *slot = Some(status);
+        }
+    }
+    seen.into_iter()
+        .enumerate()
+        .filter_map(|(i, status)| match status {
+            Some(Ok(())) => None,
+            Some(Err(e)) => Some((i, e)),
+            None => Some((
+                i,
+                (
+                    PjrtErrorCode::Cancelled,
+                    "event completion was dropped without running".to_string(),
+                ),
+            )),
+        })
+        .collect()
+}
+
+fn errors_with_index<'a>(
+    rt: &'a PjrtRuntime,
+    failures: Vec<(usize, (PjrtErrorCode, String))>,
+) -> Result<(), Vec<(usize, PJRTError<'a>)>> {
+    if failures.is_empty() {
+        return Ok(());
+    }
+    Err(failures
+        .into_iter()
+        .map(|(i, (code, message))| (i, PJRTError::with_code(rt, code, message)))
+        .collect())
+}
+
+const MIN_POLL_INTERVAL: Duration = Duration::from_micros(50);
+const MAX_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Debug)]
+pub enum WaitOutcome<'a> {
+    Ready(Result<(), PJRTError<'a>>),
+    TimedOut,
+}
 
 pub struct PJRTEvent<'a> {
     rt: &'a PjrtRuntime,
     raw: *mut PJRT_Event,
+    // Declared after `raw` and only dropped once Drop has destroyed the event.
+    keepalive: Vec<Box<dyn Any + Send>>,
 }
 
 impl<'a> PJRTEvent<'a> {
     pub(crate) fn new(rt: &'a PjrtRuntime, raw: *mut PJRT_Event) -> Self {
-        Self { rt, raw }
+        Self {
+            rt,
+            raw,
+            keepalive: Vec::new(),
+        }
     }
 
     pub fn raw(&self) -> *mut PJRT_Event {
         self.raw
     }
 
+    // Keeps `data` alive for as long as this event: it is dropped after PJRT_Event_Destroy
+    // returns, in attach order. Use it for memory the work behind the event still reads,
+    // such as a pinned host buffer. Attached data moves with the event into on_ready.
+    pub fn attach_keepalive(&mut self, data: Box<dyn Any + Send>) {
+        self.keepalive.push(data);
+    }
+
+    /// Takes over an event from other PJRT code; the wrapper destroys it on drop.
+    ///
+    /// # Safety
+    ///
+    /// `raw` must be a live event from `rt`'s plugin that nothing else will destroy.
+    pub unsafe fn from_raw(rt: &'a PjrtRuntime, raw: *mut PJRT_Event) -> Self {
+        Self::new(rt, raw)
+    }
+
+    // Attached keepalive data is leaked, since whoever takes the raw event may rely on it.
+    // Use into_raw_parts to keep hold of it instead.
     pub fn into_raw(self) -> *mut PJRT_Event {
-        let raw = self.raw;
-        mem::forget(self);
+        let (raw, keepalive) = self.into_raw_parts();
+        mem::forget(keepalive);
         raw
     }
 
+    // Gives up ownership of the raw event; the caller must destroy it, and should keep the
+    // returned keepalive data until it has.
+    pub fn into_raw_parts(mut self) -> (*mut PJRT_Event, Vec<Box<dyn Any + Send>>) {
+        let keepalive = mem::take(&mut self.keepalive);
+        let raw = mem::replace(&mut self.raw, ptr::null_mut());
+        (raw, keepalive)
+    }
+
+    // Destroys the event now, reporting the error Drop would discard. Keepalive data is
+    // dropped afterwards, as on drop.
+    pub fn destroy(mut self) -> Result<(), PJRTError<'a>> {
+        self.destroy_raw()
+    }
+
+    // Nulls `raw` first, so the event is destroyed at most once.
+    fn destroy_raw(&mut self) -> Result<(), PJRTError<'a>> {
+        let raw = mem::replace(&mut self.raw, ptr::null_mut());
+        if raw.is_null() {
+            return Ok(());
+        }
+
+        let f = self
+            .rt
+            .api()
+            .PJRT_Event_Destroy
+            .ok_or_else(|| PJRTError::missing_symbol(self.rt, "PJRT_Event_Destroy"))?;
+
+        let mut args = PJRT_Event_Destroy_Args {
+            struct_size: PJRT_Event_Destroy_Args_STRUCT_SIZE as usize,
+            extension_start: ptr::null_mut(),
+            event: raw,
+        };
+
+        let err = unsafe { f(&mut args) };
+        if err.is_null() {
+            Ok(())
+        } else {
+            Err(PJRTError::new(self.rt, err))
+        }
+    }
+
     pub fn create(rt: &'a PjrtRuntime) -> Result<PJRTEvent<'a>, String> {
         let f = rt
             .api()
@@ -46,10 +280,7 @@ impl<'a> PJRTEvent<'a> {
             return Err("PJRT_Event_Create returned null event".to_string());
         }
 
-        Ok(PJRTEvent {
-            rt,
-            raw: args.event,
-        })
+        Ok(PJRTEvent::new(rt, args.event))
     }
 
     fn raw_checked(&self) -> Result<*mut PJRT_Event, String> {
@@ -60,14 +291,16 @@ impl<'a> PJRTEvent<'a> {
         }
     }
 
-    pub fn is_ready(&self) -> Result<bool, String> {
-        let raw = self.raw_checked()?;
+    pub fn is_ready(&self) -> Result<bool, PJRTError<'a>> {
+        let raw = self
+            .raw_checked()
+            .map_err(|e| PJRTError::with_code(self.rt, PjrtErrorCode::FailedPrecondition, e))?;
 
         let f = self
             .rt
             .api()
             .PJRT_Event_IsReady
-            .ok_or("PJRT_Event_IsReady symbol not found")?;
+            .ok_or_else(|| PJRTError::missing_symbol(self.rt, "PJRT_Event_IsReady"))?;
 
         let mut args = PJRT_Event_IsReady_Args {
             struct_size: PJRT_Event_IsReady_Args_STRUCT_SIZE as usize,
@@ -80,11 +313,23 @@ impl<'a> PJRTEvent<'a> {
         if err.is_null() {
             Ok(args.is_ready)
         } else {
-            Err(error_to_string(self.rt.api(), err))
+            Err(PJRTError::new(self.rt, err))
+        }
+    }
+
+    // None while the event is pending, otherwise how it completed. Never blocks, and the
+    // event stays usable either way.
+    pub fn try_status(&self) -> Result<Option<Result<(), PJRTError<'a>>>, PJRTError<'a>> {
+        if !self.is_ready()? {
+            return Ok(None);
         }
+        let status = self
+            .status()
+            .map_err(|e| PJRTError::with_code(self.rt, e.code, e.message));
+        Ok(Some(status))
     }
 
-    pub fn on_ready(
+    pub fn on_ready_raw(
         &self,
         callback: PJRT_Event_OnReadyCallback,
         user_arg: *mut libc::c_void,
@@ -94,8 +339,10 @@ impl<'a> PJRTEvent<'a> {
             return Err("PJRT_Event_OnReady callback must be provided".to_string());
         }
 
-        let func = self.rt
-            .api().PJRT_Event_OnReady
+        let func = self
+            .rt
+            .api()
+            .PJRT_Event_OnReady
             .ok_or("PJRT_Event_OnReady symbol not found")?;
 
         let mut args = PJRT_Event_OnReady_Args {
@@ -105,9 +352,7 @@ impl<'a> PJRTEvent<'a> {
             callback,
             user_arg,
         };
-        let err = unsafe {
-            func(&mut args)
-        };
+        let err = unsafe { func(&mut args) };
 
         if !err.is_null() {
             Err(error_to_string(self.rt.api(), err).to_string())
@@ -116,25 +361,121 @@ impl<'a> PJRTEvent<'a> {
         }
     }
 
+    // Registers a Rust closure with PJRT_Event_OnReady. The closure is owned by the
+    // registration and runs exactly once, possibly on a plugin thread; if registration
+    // fails it is dropped without being called.
+    pub(crate) fn on_ready_boxed(&self, callback: OnReadyFn) -> Result<(), String> {
+        let state = Box::into_raw(Box::new(OnReadyState {
+            api: self.rt.api() as *const PJRT_Api,
+            callback,
+        }));
+
+        let result = self.on_ready_raw(Some(on_ready_trampoline), state.cast::<c_void>());
+        if result.is_err() {
+            drop(unsafe { Box::from_raw(state) });
+        }
+        result
+    }
+
+    // Runs `callback` once the event completes, possibly on a plugin thread. The event moves
+    // into the registration and is destroyed after the callback; if registration fails the
+    // callback is dropped without being called.
+    pub fn on_ready<F>(self, callback: F) -> Result<(), String>
+    where
+        F: FnOnce(Result<(), PJRTError<'a>>) + Send + 'static,
+    {
+        self.try_on_ready(callback).map_err(|(_, e)| e)
+    }
+
+    // Like on_ready, but hands the event back when registration fails.
+    pub(crate) fn try_on_ready<F>(self, callback: F) -> Result<(), (Self, String)>
+    where
+        F: FnOnce(Result<(), PJRTError<'a>>) + Send + 'static,
+    {
+        let raw = match self.raw_checked() {
+            Ok(raw) => raw,
+            Err(e) => return Err((self, e)),
+        };
+        let Some(func) = self.rt.api().PJRT_Event_OnReady else {
+            return Err((self, "PJRT_Event_OnReady symbol not found".to_string()));
+        };
+
+        let rt = self.rt;
+        let registration = Box::into_raw(Box::new(OnReadyRegistration {
+            event: self,
+            callback: Some(callback),
+            handed_off: AtomicBool::new(false),
+        }));
+        let mut args = PJRT_Event_OnReady_Args {
+            struct_size: PJRT_Event_OnReady_Args_STRUCT_SIZE as usize,
+            extension_start: null_mut(),
+            event: raw,
+            callback: Some(registration_trampoline::<F>),
+            user_arg: registration.cast::<c_void>(),
+        };
+        let err = unsafe { func(&mut args) };
+
+        if !err.is_null() {
+            let registration = unsafe { Box::from_raw(registration) };
+            return Err((registration.event, error_to_string(rt.api(), err)));
+        }
+        unsafe { release_registration(registration) };
+        Ok(())
+    }
+
+    // Waits for every event, reporting each failure with its index instead of stopping at
+    // the first. All events are waited on at once through OnReady callbacks.
+    pub fn join_all(events: Vec<PJRTEvent<'a>>) -> Result<(), Vec<(usize, PJRTError<'a>)>> {
+        let Some(rt) = events.first().map(|event| event.rt) else {
+            return Ok(());
+        };
+        let count = events.len();
+        let (tx, rx) = mpsc::channel();
+        notify_all(events, move |i, status| {
+            let _ = tx.send((i, status));
+        });
+        // The iterator ends once every callback has run (or been dropped).
+        errors_with_index(rt, failures_by_index(count, rx.iter()))
+    }
+
+    #[cfg(feature = "tokio")]
+    pub async fn join_all_async(
+        events: Vec<PJRTEvent<'a>>,
+    ) -> Result<(), Vec<(usize, PJRTError<'a>)>> {
+        let Some(rt) = events.first().map(|event| event.rt) else {
+            return Ok(());
+        };
+        let count = events.len();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        notify_all(events, move |i, status| {
+            let _ = tx.send((i, status));
+        });
+        let mut statuses = Vec::with_capacity(count);
+        while let Some(status) = rx.recv().await {
+            statuses.push(status);
+        }
+        errors_with_index(rt, failures_by_index(count, statuses))
+    }
+
     pub fn set(&self, error: &PJRTError) -> Result<(), String> {
         let raw = self.raw_checked()?;
 
-        let func = self.rt
-            .api().PJRT_Event_Set
+        let func = self
+            .rt
+            .api()
+            .PJRT_Event_Set
             .ok_or("PJRT_Event_Set symbol not found")?;
 
-        let mut args =  PJRT_Event_Set_Args {
+        let mut args = PJRT_Event_Set_Args {
             struct_size: PJRT_Event_Set_Args_STRUCT_SIZE as usize,
             extension_start: null_mut(),
             event: raw,
-            error_code: error.get_code()?,
-            error_message: ptr::null(),
-            error_message_size: 0,
+            error_code: error.get_code(),
+            error_message: error.message().as_ptr() as *const libc::c_char,
+            error_message_size: error.message().len(),
         };
 
-        let err = unsafe {
-            func(&mut args)
-        };
+        let err = unsafe { func(&mut args) };
 
         if !err.is_null() {
             Err(error_to_string(self.rt.api(), err).to_string())
@@ -143,8 +484,6 @@ impl<'a> PJRTEvent<'a> {
         }
     }
 
-
-
     pub fn await_ready(&self) -> Result<(), String> {
         let raw = self.raw_checked()?;
 
@@ -168,15 +507,62 @@ impl<'a> PJRTEvent<'a> {
         }
     }
 
-    pub fn ok(&self) -> Result<(), String> {
-        self.await_ready()?;
+    // Polls PJRT_Event_IsReady with exponential backoff, so timing out leaves nothing
+    // registered on the event and it can still be awaited later.
+    pub fn await_timeout(&self, timeout: Duration) -> Result<WaitOutcome<'a>, PJRTError<'a>> {
+        let deadline = Instant::now() + timeout;
+        let mut backoff = MIN_POLL_INTERVAL;
+        loop {
+            if let Some(status) = self.try_status()? {
+                return Ok(WaitOutcome::Ready(status));
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(WaitOutcome::TimedOut);
+            }
+            thread::sleep(backoff.min(deadline - now));
+            backoff = (backoff * 2).min(MAX_POLL_INTERVAL);
+        }
+    }
 
-        let raw = self.raw_checked()?;
-        let f = self
-            .rt
-            .api()
-            .PJRT_Event_Error
-            .ok_or("PJRT_Event_Error symbol not found")?;
+    // Like await_ready, but keeps the error code of a failed event.
+    pub(crate) fn await_status(&self) -> EventStatus {
+        let raw = self
+            .raw_checked()
+            .map_err(|e| (PjrtErrorCode::FailedPrecondition, e))?;
+
+        let f = self.rt.api().PJRT_Event_Await.ok_or((
+            PjrtErrorCode::Unimplemented,
+            "PJRT_Event_Await symbol not found".to_string(),
+        ))?;
+
+        let mut args = PJRT_Event_Await_Args {
+            struct_size: PJRT_Event_Await_Args_STRUCT_SIZE as usize,
+            extension_start: ptr::null_mut(),
+            event: raw,
+        };
+
+        let err = unsafe { f(&mut args) };
+        if err.is_null() {
+            Ok(())
+        } else {
+            Err(error_status(self.rt.api(), err))
+        }
+    }
+
+    // Waits for the event, then reports how it completed. The code lets callers tell e.g. a
+    // retryable RESOURCE_EXHAUSTED from an INVALID_ARGUMENT.
+    pub fn status(&self) -> Result<(), PjrtEventError> {
+        self.await_status().map_err(PjrtEventError::from)?;
+
+        let raw = self.raw_checked().map_err(|message| PjrtEventError {
+            code: PjrtErrorCode::FailedPrecondition,
+            message,
+        })?;
+        let f = self.rt.api().PJRT_Event_Error.ok_or(PjrtEventError {
+            code: PjrtErrorCode::Unimplemented,
+            message: "PJRT_Event_Error symbol not found".to_string(),
+        })?;
 
         let mut args = PJRT_Event_Error_Args {
             struct_size: PJRT_Event_Error_Args_STRUCT_SIZE as usize,
@@ -188,30 +574,96 @@ impl<'a> PJRTEvent<'a> {
         if err.is_null() {
             Ok(())
         } else {
-            Err(error_to_string(self.rt.api(), err))
+            Err(error_status(self.rt.api(), err).into())
         }
     }
+
+    pub fn ok(&self) -> Result<(), String> {
+        self.status().map_err(|e| e.message)
+    }
 }
 
 impl Drop for PJRTEvent<'_> {
     fn drop(&mut self) {
-        if self.raw.is_null() {
-            return;
-        }
-
-        let Some(f) = self.rt.api().PJRT_Event_Destroy else {
-            return;
-        };
+        let _ = self.destroy_raw();
+    }
+}
 
-        let mut args = PJRT_Event_Destroy_Args {
-            struct_size: PJRT_Event_Destroy_Args_STRUCT_SIZE as usize,
-            extension_start: ptr::null_mut(),
-            event: self.raw,
+// `event.await` resolves when the event completes. The event is owned by its OnReady
+// registration, so dropping the future early frees nothing the plugin still uses.
+#[cfg(feature = "tokio")]
+impl<'a> IntoFuture for PJRTEvent<'a> {
+    type Output = Result<(), PJRTError<'a>>;
+    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + Send + 'a>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        let rt = self.rt;
+        let (tx, rx) = oneshot::channel::<EventStatus>();
+        let tx = Arc::new(Mutex::new(Some(tx)));
+        let complete = |tx: &Mutex<Option<oneshot::Sender<EventStatus>>>, status: EventStatus| {
+            if let Some(tx) = tx.lock().ok().and_then(|mut tx| tx.take()) {
+                let _ = tx.send(status);
+            }
         };
 
-        let err = unsafe { f(&mut args) };
-        if !err.is_null() {
-            let _ = error_to_string(self.rt.api(), err);
+        let from_event = Arc::clone(&tx);
+        let registered = self.try_on_ready(move |status| {
+            complete(
+                &from_event,
+                status.map_err(|e| (e.code(), e.message().to_string())),
+            )
+        });
+        if let Err((event, e)) = registered {
+            log::warn!("PJRT_Event_OnReady failed, waiting for the event synchronously: {e}");
+            complete(&tx, event.await_status());
         }
+
+        Box::pin(async move {
+            match rx.await {
+                Ok(status) => {
+                    status.map_err(|(code, message)| PJRTError::with_code(rt, code, message))
+                }
+                Err(_) => Err(PJRTError::with_code(
+                    rt,
+                    PjrtErrorCode::Cancelled,
+                    "event completion was dropped without running",
+                )),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod event_tests {
+    use super::*;
+
+    #[test]
+    fn join_reports_every_failure_in_index_order() {
+        let statuses = vec![
+            (3, Err((PjrtErrorCode::Aborted, "three".to_string()))),
+            (0, Ok(())),
+            (1, Err((PjrtErrorCode::Internal, "one".to_string()))),
+        ];
+        let failures = failures_by_index(5, statuses);
+        let indices: Vec<usize> = failures.iter().map(|(i, _)| *i).collect();
+        assert_eq!(indices, vec![1, 2, 3, 4]);
+        assert_eq!(failures[0].1, (PjrtErrorCode::Internal, "one".to_string()));
+        assert_eq!(failures[1].1 .0, PjrtErrorCode::Cancelled);
+        assert_eq!(failures[2].1, (PjrtErrorCode::Aborted, "three".to_string()));
+
+        assert!(failures_by_index(2, vec![(1, Ok(())), (0, Ok(()))]).is_empty());
+        assert!(failures_by_index(0, Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn event_errors_keep_code_and_message() {
+        let err = PjrtEventError::from((
+            PjrtErrorCode::ResourceExhausted,
+            "out of memory".to_string(),
+        ));
+        assert_eq!(err.code, PjrtErrorCode::ResourceExhausted);
+        assert_eq!(err.message, "out of memory");
+        assert_eq!(String::from(err.clone()), err.to_string());
+        assert!(err.to_string().ends_with(": out of memory"), "{err}");
     }
 }
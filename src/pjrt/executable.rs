@@ -1,23 +1,796 @@
 use crate::pjrt::buffer::PJRTBuffer;
+use crate::pjrt::client::PJRTClient;
+#[cfg(feature = "proto")]
+use crate::pjrt::compile_options::{CompileOptionsInfo, DeviceAssignment};
 use crate::pjrt::device::PJRTDevice;
+use crate::pjrt::error::{pjrt_check, ContextFrame, PjrtErrorCode};
+#[cfg(feature = "tokio")]
+use crate::pjrt::error::{OwnedPjrtError, PjrtResult};
+#[cfg(feature = "tokio")]
+use crate::pjrt::event::EventStatus;
 use crate::pjrt::event::PJRTEvent;
+use crate::pjrt::executable_file::{
+    encode_executable_file, ExecutableFileError, ExecutableFileHeader,
+};
+use crate::pjrt::execute_callbacks::{
+    CallbackErrors, ExecuteCallbackKeepalive, PJRTRecvCallbackFn, PJRTRecvCallbackInvocation,
+    PJRTSendCallbackFn, PJRTSendCallbackInvocation, RecvRegistration, SendRegistration,
+};
+use crate::pjrt::execute_context::{ContextHandle, PJRTExecuteContext};
 use crate::pjrt::loader::{error_to_string, PjrtRuntime};
+use crate::pjrt::topology_desc::{decode_named_values, PJRTNamedAttribute, PJRTNamedValue};
+use crate::pjrt::utils::{host_byte_size, BufferType, DebugResult};
 use crate::pjrt_sys::*;
+use std::cell::OnceCell;
+use std::ffi::CString;
+use std::fmt;
+use std::mem;
+use std::path::Path;
 use std::ptr;
 use std::ptr::{null, null_mut};
 use std::slice::from_raw_parts;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+#[cfg(feature = "tokio")]
+use tokio::sync::oneshot;
 
 pub struct PJRTLoadedExecutable<'a> {
     pub rt: &'a PjrtRuntime,
     pub raw: *mut PJRT_LoadedExecutable,
+    // Both are fixed for the lifetime of a loaded executable, so they are fetched once
+    // rather than on every launch.
+    executable: OnceCell<PJRTExecutableRef<'a>>,
+    num_outputs: OnceCell<usize>,
+    // Declared (element type, dims) per output, used by output validation.
+    declared_outputs: OnceCell<Vec<(PJRT_Buffer_Type, Vec<i64>)>>,
 }
 
 // Back-compat with the original name in this crate.
 pub type PJRTExecutable<'a> = PJRTLoadedExecutable<'a>;
 
+// Per-launch settings forwarded to PJRT_ExecuteOptions. Cloning shares the registered
+// send/recv callbacks rather than duplicating them.
+#[derive(Debug, Clone)]
+pub struct PJRTExecuteRunOptions {
+    launch_id: Option<i32>,
+    non_donatable_input_indices: Vec<i64>,
+    copy_inputs_to_device: bool,
+    send_callbacks: Vec<SendRegistration>,
+    recv_callbacks: Vec<RecvRegistration>,
+    callback_errors: CallbackErrors,
+    call_location: Option<String>,
+    tasks: Vec<TaskInfo>,
+    validate_outputs: bool,
+    execute_context: Option<Arc<ContextHandle>>,
+}
+
+impl Default for PJRTExecuteRunOptions {
+    fn default() -> Self {
+        Self {
+            launch_id: None,
+            non_donatable_input_indices: Vec::new(),
+            copy_inputs_to_device: false,
+            send_callbacks: Vec::new(),
+            recv_callbacks: Vec::new(),
+            callback_errors: CallbackErrors::default(),
+            call_location: None,
+            tasks: Vec::new(),
+            validate_outputs: cfg!(debug_assertions),
+            execute_context: None,
+        }
+    }
+}
+
+// One participating task of a multi-task launch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TaskInfo {
+    pub task_id: i64,
+    pub incarnation_id: i64,
+}
+
+impl TaskInfo {
+    pub fn zip(task_ids: &[i64], incarnation_ids: &[i64]) -> Result<Vec<TaskInfo>, String> {
+        if task_ids.len() != incarnation_ids.len() {
+            return Err(format!(
+                "got {} task ids but {} incarnation ids",
+                task_ids.len(),
+                incarnation_ids.len()
+            ));
+        }
+        Ok(task_ids
+            .iter()
+            .zip(incarnation_ids)
+            .map(|(&task_id, &incarnation_id)| TaskInfo {
+                task_id,
+                incarnation_id,
+            })
+            .collect())
+    }
+}
+
+// Static description of one executable output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputShape {
+    pub element_type: BufferType,
+    pub dims: Vec<i64>,
+    // None when the plugin doesn't report memory kinds.
+    pub memory_kind: Option<String>,
+}
+
+impl OutputShape {
+    // Bytes needed for a dense host copy of this output.
+    pub fn host_byte_size(&self) -> Result<usize, String> {
+        host_byte_size(&self.dims, self.element_type)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptimizedProgram {
+    pub format: String,
+    pub code: Vec<u8>,
+}
+
+impl OptimizedProgram {
+    // Lossy text view; only meaningful for textual formats.
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.code).into_owned()
+    }
+}
+
+// Outputs of a single-device launch together with its completion event.
+pub struct ExecuteResult<'a> {
+    pub outputs: Vec<PJRTBuffer<'a>>,
+    pub done: PJRTEvent<'a>,
+    pub launch_id: i32,
+    pub handle: LaunchHandle<'a>,
+}
+
+impl<'a> ExecuteResult<'a> {
+    // Waits for the launch to finish and returns its outputs.
+    pub fn wait(self) -> Result<Vec<PJRTBuffer<'a>>, String> {
+        self.done.ok()?;
+        Ok(self.outputs)
+    }
+
+    // Like wait, but also waits on every output's own ready event, for plugins that
+    // signal launch completion before the outputs are materialized.
+    pub fn outputs_when_ready(self) -> Result<Vec<PJRTBuffer<'a>>, String> {
+        let outputs = self.wait()?;
+        for output in &outputs {
+            output.ready_event()?.ok()?;
+        }
+        Ok(outputs)
+    }
+
+    pub fn into_parts(self) -> (Vec<PJRTBuffer<'a>>, PJRTEvent<'a>) {
+        (self.outputs, self.done)
+    }
+}
+
+// The launch id and device of one launch, for poisoning it while it is still in flight.
+pub struct LaunchHandle<'a> {
+    launch_id: i32,
+    device: PJRTDevice<'a>,
+}
+
+impl<'a> LaunchHandle<'a> {
+    pub fn launch_id(&self) -> i32 {
+        self.launch_id
+    }
+
+    pub fn device(&self) -> &PJRTDevice<'a> {
+        &self.device
+    }
+
+    // Fails the launch with `code` and `message` if it has not finished yet. Returns false
+    // when there was nothing left to poison.
+    pub fn poison(&self, code: PjrtErrorCode, message: &str) -> Result<bool, String> {
+        self.device.poison_execution(self.launch_id, code, message)
+    }
+}
+
+// Launch ids handed out when the run options don't carry one. They cycle through
+// 1..=i32::MAX, leaving 0 to callers that never set an id.
+static LAUNCH_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+fn next_launch_id() -> i32 {
+    let n = LAUNCH_COUNTER.fetch_add(1, Ordering::Relaxed);
+    (n % i32::MAX as u32) as i32 + 1
+}
+
+// Outputs and completion events of a launch across every addressable device.
+pub struct ReplicatedExecuteResult<'a> {
+    // outputs[i] and done[i] belong to the i-th addressable device.
+    pub outputs: Vec<Vec<PJRTBuffer<'a>>>,
+    pub done: Vec<PJRTEvent<'a>>,
+    inputs: Vec<Vec<PJRTBuffer<'a>>>,
+    callback_errors: CallbackErrors,
+}
+
+impl<'a> ReplicatedExecuteResult<'a> {
+    // Waits for every device, then returns the per-device outputs.
+    pub fn wait(self) -> Result<Vec<Vec<PJRTBuffer<'a>>>, String> {
+        join_device_events("execute_replicated", &self.done, &self.callback_errors)?;
+        drop(self.inputs);
+        Ok(self.outputs)
+    }
+}
+
+// Waits on every device before reporting, so no launch is still running on return.
+fn join_device_events(
+    what: &str,
+    events: &[PJRTEvent<'_>],
+    callback_errors: &CallbackErrors,
+) -> Result<(), String> {
+    let failures: Vec<String> = events
+        .iter()
+        .enumerate()
+        .filter_map(|(i, event)| event.ok().err().map(|e| format!("device {i}: {e}")))
+        .collect();
+    if !failures.is_empty() {
+        return Err(format!("{what} failed on {}", failures.join("; ")));
+    }
+    if let Some(callback_error) = callback_errors.take() {
+        return Err(callback_error);
+    }
+    Ok(())
+}
+
+fn check_shard_arities(arities: &[usize]) -> Result<(), String> {
+    if let Some((i, &arity)) = arities.iter().enumerate().find(|&(_, &a)| a != arities[0]) {
+        return Err(format!(
+            "execute_replicated shards differ in arity: shard 0 has {} but shard {i} has {arity}",
+            arities[0]
+        ));
+    }
+    Ok(())
+}
+
+fn single_device_result<'a>(
+    mut outputs: Vec<Vec<PJRTBuffer<'a>>>,
+    mut events: Vec<PJRTEvent<'a>>,
+    handle: LaunchHandle<'a>,
+) -> Result<ExecuteResult<'a>, String> {
+    match (outputs.pop(), events.pop()) {
+        (Some(outputs), Some(done)) => Ok(ExecuteResult {
+            outputs,
+            done,
+            launch_id: handle.launch_id,
+            handle,
+        }),
+        _ => Err("PJRT_LoadedExecutable_Execute returned no per-device results".to_string()),
+    }
+}
+
+// Identity of a compiled program as reported by the plugin's fingerprint. Two executables
+// with equal ids run the same compiled code.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ExecutableId(String);
+
+impl ExecutableId {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for ExecutableId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+// Static facts about a compiled executable. Fields are None when the plugin doesn't
+// implement the corresponding query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ExecutableMetadata {
+    pub name: Option<String>,
+    pub fingerprint: Option<String>,
+    pub identity: Option<ExecutableId>,
+    pub num_replicas: Option<usize>,
+    pub num_partitions: Option<usize>,
+    pub num_outputs: Option<usize>,
+    pub generated_code_size_in_bytes: Option<i64>,
+    pub output_element_types: Option<Vec<BufferType>>,
+    pub output_memory_kinds: Option<Vec<String>>,
+    // In get_compiled_memory_stats order.
+    pub compiled_memory_stats: Option<Vec<i64>>,
+}
+
+// Wall-clock breakdown of one execute_timed call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExecuteTimings {
+    // Time spent inside PJRT_LoadedExecutable_Execute enqueueing the work.
+    pub enqueue: Duration,
+    // From the enqueue returning until the completion event fired.
+    pub completion: Duration,
+    pub total: Duration,
+}
+
+fn numeric_property(properties: &[PJRTNamedAttribute], name: &str) -> Option<f64> {
+    properties
+        .iter()
+        .find(|property| property.name == name)
+        .and_then(|property| match property.value {
+            PJRTNamedValue::Float(v) => Some(v as f64),
+            PJRTNamedValue::Int64(v) => Some(v as f64),
+            _ => None,
+        })
+}
+
+fn named_value_text(value: &PJRTNamedValue) -> String {
+    match value {
+        PJRTNamedValue::String(v) => v.clone(),
+        PJRTNamedValue::Int64(v) => v.to_string(),
+        PJRTNamedValue::Int64List(v) => format!("{v:?}"),
+        PJRTNamedValue::Float(v) => v.to_string(),
+        PJRTNamedValue::Bool(v) => v.to_string(),
+    }
+}
+
+// PJRT takes task ids as C ints alongside a parallel incarnation id array.
+fn encode_tasks(tasks: &[TaskInfo]) -> Result<(Vec<libc::c_int>, Vec<i64>), String> {
+    let task_ids = tasks
+        .iter()
+        .map(|task| {
+            libc::c_int::try_from(task.task_id)
+                .map_err(|_| format!("task id {} does not fit in a C int", task.task_id))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let incarnation_ids = tasks.iter().map(|task| task.incarnation_id).collect();
+    Ok((task_ids, incarnation_ids))
+}
+
+pub(crate) fn deserialize_and_load_raw<'a>(
+    rt: &'a PjrtRuntime,
+    client: *mut PJRT_Client,
+    serialized_executable: &[u8],
+    overridden_compile_options: Option<&[u8]>,
+) -> Result<PJRTLoadedExecutable<'a>, String> {
+    if client.is_null() {
+        return Err("PJRT_Client is null".to_string());
+    }
+    if serialized_executable.is_empty() {
+        return Err("serialized_executable must not be empty".to_string());
+    }
+
+    let f = rt
+        .api()
+        .PJRT_Executable_DeserializeAndLoad
+        .ok_or("PJRT_Executable_DeserializeAndLoad symbol not found")?;
+
+    let serialized_ptr = serialized_executable.as_ptr() as *const libc::c_char;
+    let serialized_size = serialized_executable.len();
+
+    let (override_ptr, override_size) = match overridden_compile_options {
+        None => (ptr::null(), 0usize),
+        Some([]) => (ptr::null(), 0usize),
+        Some(opts) => (opts.as_ptr() as *const libc::c_char, opts.len()),
+    };
+
+    let mut args = PJRT_Executable_DeserializeAndLoad_Args {
+        struct_size: PJRT_Executable_DeserializeAndLoad_Args_STRUCT_SIZE as usize,
+        extension_start: ptr::null_mut(),
+        client,
+        serialized_executable: serialized_ptr,
+        serialized_executable_size: serialized_size,
+        loaded_executable: ptr::null_mut(),
+        overridden_serialized_compile_options: override_ptr,
+        overridden_serialized_compile_options_size: override_size,
+    };
+
+    let err = unsafe { f(&mut args) };
+    if !err.is_null() {
+        return Err(error_to_string(rt.api(), err));
+    }
+    if args.loaded_executable.is_null() {
+        return Err(
+            "PJRT_Executable_DeserializeAndLoad succeeded but returned null loaded_executable"
+                .to_string(),
+        );
+    }
+
+    Ok(PJRTLoadedExecutable::new(rt, args.loaded_executable))
+}
+
+// A PJRT_Executable from PJRT_LoadedExecutable_GetExecutable. The C API gives the caller
+// ownership of each handle it returns, so this destroys it exactly once.
+pub struct PJRTExecutableRef<'a> {
+    rt: &'a PjrtRuntime,
+    raw: *mut PJRT_Executable,
+}
+
+impl<'a> PJRTExecutableRef<'a> {
+    pub(crate) fn new(rt: &'a PjrtRuntime, raw: *mut PJRT_Executable) -> Self {
+        Self { rt, raw }
+    }
+
+    pub fn raw(&self) -> *mut PJRT_Executable {
+        self.raw
+    }
+
+    pub fn serialize(&self) -> Result<Vec<u8>, String> {
+        if self.raw.is_null() {
+            return Err("PJRT_Executable is null".to_string());
+        }
+        serialize_executable(self.rt, self.raw)
+    }
+
+    // Destroys the handle now, reporting the plugin's error instead of dropping it.
+    pub fn destroy(mut self) -> Result<(), String> {
+        let raw = mem::replace(&mut self.raw, ptr::null_mut());
+        destroy_executable(self.rt, raw)
+    }
+}
+
+impl Drop for PJRTExecutableRef<'_> {
+    fn drop(&mut self) {
+        if !self.raw.is_null() {
+            // Drop must not panic; best-effort cleanup.
+            let _ = destroy_executable(self.rt, self.raw);
+        }
+    }
+}
+
+fn serialize_executable(
+    rt: &PjrtRuntime,
+    executable: *mut PJRT_Executable,
+) -> Result<Vec<u8>, String> {
+    let func = rt
+        .api()
+        .PJRT_Executable_Serialize
+        .ok_or("PJRT_Executable_Serialize symbol not found")?;
+
+    let mut args = PJRT_Executable_Serialize_Args {
+        struct_size: PJRT_Executable_Serialize_Args_STRUCT_SIZE as usize,
+        extension_start: ptr::null_mut(),
+        executable: executable as *const PJRT_Executable,
+        serialized_bytes: ptr::null(),
+        serialized_bytes_size: 0,
+        serialized_executable: ptr::null_mut(),
+        serialized_executable_deleter: None,
+    };
+
+    let err = unsafe { func(&mut args) };
+    if !err.is_null() {
+        return Err(error_to_string(rt.api(), err));
+    }
+
+    if !args.serialized_executable.is_null() && args.serialized_executable_deleter.is_none() {
+        return Err(
+            "PJRT_Executable_Serialize returned serialized_executable without deleter".to_string(),
+        );
+    }
+
+    let result = if args.serialized_bytes_size == 0 {
+        Ok(Vec::new())
+    } else if args.serialized_bytes.is_null() {
+        Err(
+            "PJRT_Executable_Serialize returned null serialized_bytes with nonzero size"
+                .to_string(),
+        )
+    } else {
+        let bytes = unsafe {
+            from_raw_parts(
+                args.serialized_bytes as *const u8,
+                args.serialized_bytes_size,
+            )
+        };
+        Ok(bytes.to_vec())
+    };
+
+    if !args.serialized_executable.is_null() {
+        if let Some(deleter) = args.serialized_executable_deleter {
+            unsafe { deleter(args.serialized_executable) };
+        }
+    }
+
+    result
+}
+
+fn destroy_executable(rt: &PjrtRuntime, executable: *mut PJRT_Executable) -> Result<(), String> {
+    let f = rt
+        .api()
+        .PJRT_Executable_Destroy
+        .ok_or("PJRT_Executable_Destroy symbol not found")?;
+
+    let mut args = PJRT_Executable_Destroy_Args {
+        struct_size: PJRT_Executable_Destroy_Args_STRUCT_SIZE as usize,
+        extension_start: ptr::null_mut(),
+        executable,
+    };
+
+    let err = unsafe { f(&mut args) };
+    if err.is_null() {
+        Ok(())
+    } else {
+        Err(error_to_string(rt.api(), err))
+    }
+}
+
+fn split_output_dims(dims: &[i64], dim_sizes: &[usize]) -> Result<Vec<Vec<i64>>, String> {
+    let mut rest = dims;
+    let mut out = Vec::with_capacity(dim_sizes.len());
+    for (i, &rank) in dim_sizes.iter().enumerate() {
+        if rank > rest.len() {
+            return Err(format!(
+                "output {i} claims rank {rank} but only {} dims remain",
+                rest.len()
+            ));
+        }
+        let (output, tail) = rest.split_at(rank);
+        out.push(output.to_vec());
+        rest = tail;
+    }
+    if !rest.is_empty() {
+        return Err(format!(
+            "{} output dims left over after splitting by dim_sizes",
+            rest.len()
+        ));
+    }
+    Ok(out)
+}
+
+// What output_dimension always returned: the first entry of PJRT's flat dims array, which
+// skips over any leading scalar outputs.
+fn first_flat_output_dim(dims: &[Vec<i64>]) -> Result<i64, String> {
+    if dims.is_empty() {
+        return Err("PJRT_Executable_OutputDimensions returned no outputs".to_string());
+    }
+    dims.iter()
+        .flatten()
+        .next()
+        .copied()
+        .ok_or_else(|| "PJRT_Executable_OutputDimensions returned null dims".to_string())
+}
+
+fn encode_call_location(call_location: Option<&str>) -> Result<Option<CString>, String> {
+    call_location
+        .map(|location| {
+            CString::new(location)
+                .map_err(|_| format!("call_location {location:?} contains a NUL byte"))
+        })
+        .transpose()
+}
+
+impl PJRTExecuteRunOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Without an explicit id every launch gets a fresh one from a process-wide counter.
+    // Multi-host launches must set the same id on every participating process.
+    pub fn launch_id(mut self, launch_id: i32) -> Self {
+        self.launch_id = Some(launch_id);
+        self
+    }
+
+    // Argument positions the runtime must not donate even if the executable aliases them.
+    pub fn non_donatable_input_indices(mut self, indices: Vec<i64>) -> Self {
+        self.non_donatable_input_indices = indices;
+        self
+    }
+
+    pub fn non_donatable_inputs(mut self, indices: &[usize]) -> Self {
+        self.non_donatable_input_indices = indices.iter().map(|&i| i as i64).collect();
+        self
+    }
+
+    // Check every output's element type and dimensions against the executable's declared
+    // outputs after launching, and reject donated or deleted arguments before it. On by default
+    // in debug builds.
+    pub fn validate_outputs(mut self, enabled: bool) -> Self {
+        self.validate_outputs = enabled;
+        self
+    }
+
+    // For `execute_on`: copy inputs that live on another device instead of rejecting them.
+    pub fn copy_inputs_to_device(mut self, enabled: bool) -> Self {
+        self.copy_inputs_to_device = enabled;
+        self
+    }
+
+    pub fn send_callback<F>(mut self, channel_id: i64, callback: F) -> Self
+    where
+        F: FnMut(PJRTSendCallbackInvocation) -> Result<(), String> + Send + 'static,
+    {
+        let callback: PJRTSendCallbackFn = Box::new(callback);
+        self.send_callbacks.push(SendRegistration {
+            channel_id,
+            callback: Arc::new(Mutex::new(callback)),
+        });
+        self
+    }
+
+    pub fn recv_callback<F>(mut self, channel_id: i64, callback: F) -> Self
+    where
+        F: FnMut(PJRTRecvCallbackInvocation) -> Result<(), String> + Send + 'static,
+    {
+        let callback: PJRTRecvCallbackFn = Box::new(callback);
+        self.recv_callbacks.push(RecvRegistration {
+            channel_id,
+            callback: Arc::new(Mutex::new(callback)),
+        });
+        self
+    }
+
+    // Source location reported to profilers for this launch.
+    pub fn call_location(mut self, location: impl Into<String>) -> Self {
+        self.call_location = Some(location.into());
+        self
+    }
+
+    // Passes `context` to every launch made with these options. The options and each
+    // unfinished launch keep the context alive, so it can be dropped after building them.
+    pub fn execute_context(mut self, context: &PJRTExecuteContext<'_>) -> Self {
+        self.execute_context = Some(context.share());
+        self
+    }
+
+    pub fn tasks(mut self, tasks: Vec<TaskInfo>) -> Self {
+        self.tasks = tasks;
+        self
+    }
+
+    pub fn get_call_location(&self) -> Option<&str> {
+        self.call_location.as_deref()
+    }
+
+    pub fn get_tasks(&self) -> &[TaskInfo] {
+        &self.tasks
+    }
+
+    pub fn has_execute_context(&self) -> bool {
+        self.execute_context.is_some()
+    }
+
+    pub fn get_launch_id(&self) -> Option<i32> {
+        self.launch_id
+    }
+
+    fn resolve_launch_id(&self) -> i32 {
+        self.launch_id.unwrap_or_else(next_launch_id)
+    }
+
+    pub fn get_validate_outputs(&self) -> bool {
+        self.validate_outputs
+    }
+
+    pub fn get_non_donatable_input_indices(&self) -> &[i64] {
+        &self.non_donatable_input_indices
+    }
+
+    pub fn get_copy_inputs_to_device(&self) -> bool {
+        self.copy_inputs_to_device
+    }
+
+    pub fn send_channel_ids(&self) -> Vec<i64> {
+        self.send_callbacks.iter().map(|r| r.channel_id).collect()
+    }
+
+    pub fn recv_channel_ids(&self) -> Vec<i64> {
+        self.recv_callbacks.iter().map(|r| r.channel_id).collect()
+    }
+
+    // First error reported by a send or recv callback since the last call. Recv callbacks
+    // cannot fail the execution themselves, so this is the only place their errors surface.
+    pub fn take_callback_error(&self) -> Option<String> {
+        self.callback_errors.take()
+    }
+}
+
+fn argument_ptrs(
+    arguments: &[&PJRTBuffer<'_>],
+    options: &PJRTExecuteRunOptions,
+) -> Result<Vec<*mut PJRT_Buffer>, String> {
+    let ptrs: Vec<*mut PJRT_Buffer> = arguments.iter().map(|b| b.raw()).collect();
+    if ptrs.iter().any(|p| p.is_null()) {
+        return Err("execute arguments contain null PJRT_Buffer".to_string());
+    }
+    // Plugins may crash on a donated input rather than report an error, so catch it here. That
+    // costs an FFI call per argument, so it rides on validate_outputs like the output checks.
+    if !options.get_validate_outputs() {
+        return Ok(ptrs);
+    }
+    for (i, argument) in arguments.iter().enumerate() {
+        if argument.is_deleted()? {
+            return Err(format!(
+                "execute argument {i} was donated to an earlier execution or deleted"
+            ));
+        }
+    }
+    Ok(ptrs)
+}
+
+// Checks the element type first and only fetches the dimensions when the type matches and
+// the output is declared with a shape, so a scalar or wrong-typed output costs one FFI call
+// and a shaped output two.
+fn check_output(
+    index: usize,
+    declared: (PJRT_Buffer_Type, &[i64]),
+    actual_type: PJRT_Buffer_Type,
+    actual_dims: impl FnOnce() -> Result<Vec<i64>, String>,
+) -> Result<(), String> {
+    let type_name = |ty: PJRT_Buffer_Type| {
+        BufferType::try_from(ty)
+            .map(|t| t.to_string())
+            .unwrap_or_else(|_| format!("type {ty}"))
+    };
+    let declared_shape = format!("{}{:?}", type_name(declared.0), declared.1);
+    if actual_type != declared.0 {
+        return Err(format!(
+            "output {index} is {} but the executable declares {declared_shape}",
+            type_name(actual_type)
+        ));
+    }
+    if declared.1.is_empty() {
+        return Ok(());
+    }
+    let dims = actual_dims()?;
+    if dims != declared.1 {
+        return Err(format!(
+            "output {index} is {}{dims:?} but the executable declares {declared_shape}",
+            type_name(actual_type)
+        ));
+    }
+    Ok(())
+}
+
+// Plugins answer out-of-range or repeated indices with an opaque INTERNAL error.
+fn validate_non_donatable(indices: &[i64], num_args: usize) -> Result<(), String> {
+    let mut seen = Vec::with_capacity(indices.len());
+    for &index in indices {
+        if index < 0 || index as u64 >= num_args as u64 {
+            return Err(format!(
+                "non_donatable_input_indices contains {index}, but the execution has {num_args} arguments"
+            ));
+        }
+        if seen.contains(&index) {
+            return Err(format!(
+                "non_donatable_input_indices lists {index} more than once"
+            ));
+        }
+        seen.push(index);
+    }
+    Ok(())
+}
+
+// execute_consuming passes donated buffers first and borrowed ones after them, so every
+// borrowed position is marked non-donatable on top of what the caller already listed.
+fn consuming_non_donatable(
+    num_donated: usize,
+    num_borrowed: usize,
+    listed: &[i64],
+) -> Result<Vec<i64>, String> {
+    if let Some(index) = listed
+        .iter()
+        .find(|&&i| i >= 0 && (i as usize) < num_donated)
+    {
+        return Err(format!(
+            "non_donatable_input_indices lists {index}, which is a donated argument"
+        ));
+    }
+    let mut indices: Vec<i64> = listed
+        .iter()
+        .copied()
+        .chain((num_donated..num_donated + num_borrowed).map(|i| i as i64))
+        .collect();
+    indices.sort_unstable();
+    indices.dedup();
+    Ok(indices)
+}
+
 impl<'a> PJRTLoadedExecutable<'a> {
     pub(crate) fn new(rt: &'a PjrtRuntime, raw: *mut PJRT_LoadedExecutable) -> Self {
-        Self { rt, raw }
+        Self {
+            rt,
+            raw,
+            executable: OnceCell::new(),
+            num_outputs: OnceCell::new(),
+            declared_outputs: OnceCell::new(),
+        }
     }
 
     fn raw_checked(&self) -> Result<*mut PJRT_LoadedExecutable, String> {
@@ -29,6 +802,9 @@ impl<'a> PJRTLoadedExecutable<'a> {
     }
 
     fn executable(&self) -> Result<*mut PJRT_Executable, String> {
+        if let Some(executable) = self.executable.get() {
+            return Ok(executable.raw());
+        }
         let raw = self.raw_checked()?;
 
         let f = self
@@ -51,46 +827,103 @@ impl<'a> PJRTLoadedExecutable<'a> {
         if args.executable.is_null() {
             return Err("PJRT_LoadedExecutable_GetExecutable returned null executable".into());
         }
+        let _ = self
+            .executable
+            .set(PJRTExecutableRef::new(self.rt, args.executable));
         Ok(args.executable)
     }
 
     pub fn serialize(&self) -> Result<Vec<u8>, String> {
-        let executable = self.executable()?;
-        let func = self
+        serialize_executable(self.rt, self.executable()?)
+    }
+
+    #[deprecated(note = "use PJRTClient::deserialize_and_load, which needs no loaded executable")]
+    pub fn deserialize_and_load(
+        &self,
+        client: *mut PJRT_Client,
+        serialized_executable: &[u8],
+        overridden_compile_options: Option<&[u8]>,
+    ) -> Result<PJRTLoadedExecutable<'a>, String> {
+        deserialize_and_load_raw(
+            self.rt,
+            client,
+            serialized_executable,
+            overridden_compile_options,
+        )
+    }
+
+    // Writes `serialize()` output behind an ExecutableFileHeader describing `client`, which
+    // must be the client this executable was compiled on.
+    pub fn save_to_file(
+        &self,
+        client: &PJRTClient<'_>,
+        path: impl AsRef<Path>,
+    ) -> Result<(), ExecutableFileError> {
+        let version = self.rt.api().pjrt_api_version;
+        let header = ExecutableFileHeader {
+            platform_name: client
+                .platform_name()
+                .map_err(ExecutableFileError::Serialize)?,
+            api_major: version.major_version,
+            api_minor: version.minor_version,
+            fingerprint: self
+                .identity()
+                .map(|id| id.to_string())
+                .map_err(ExecutableFileError::Serialize)?,
+        };
+        let serialized = self.serialize().map_err(ExecutableFileError::Serialize)?;
+        let file =
+            encode_executable_file(&header, &serialized).map_err(ExecutableFileError::Format)?;
+        std::fs::write(path, file).map_err(ExecutableFileError::Io)
+    }
+
+    #[cfg(feature = "proto")]
+    pub fn compile_options(&self) -> Result<CompileOptionsInfo, String> {
+        CompileOptionsInfo::decode(&self.get_compile_options()?)
+    }
+
+    pub fn get_compile_options(&self) -> Result<Vec<u8>, String> {
+        let exec = self.executable()?;
+
+        let f = self
             .rt
             .api()
-            .PJRT_Executable_Serialize
-            .ok_or("PJRT_Executable_Serialize symbol not found")?;
+            .PJRT_Executable_GetCompileOptions
+            .ok_or("PJRT_Executable_GetCompileOptions symbol not found")?;
 
-        let mut args = PJRT_Executable_Serialize_Args {
-            struct_size: PJRT_Executable_Serialize_Args_STRUCT_SIZE as usize,
+        let mut args = PJRT_Executable_GetCompileOptions_Args {
+            struct_size: PJRT_Executable_GetCompileOptions_Args_STRUCT_SIZE as usize,
             extension_start: ptr::null_mut(),
-            executable: executable as *const PJRT_Executable,
+            executable: exec,
             serialized_bytes: ptr::null(),
             serialized_bytes_size: 0,
-            serialized_executable: ptr::null_mut(),
-            serialized_executable_deleter: None,
+            serialized_compile_options: ptr::null_mut(),
+            serialized_compile_options_deleter: None,
         };
 
-        let err = unsafe { func(&mut args) };
+        let err = unsafe { f(&mut args) };
         if !err.is_null() {
             return Err(error_to_string(self.rt.api(), err));
         }
 
-        if !args.serialized_executable.is_null() && args.serialized_executable_deleter.is_none() {
+        if args.serialized_bytes_size > 0 && args.serialized_bytes.is_null() {
             return Err(
-                "PJRT_Executable_Serialize returned serialized_executable without deleter"
+                "PJRT_Executable_GetCompileOptions returned null serialized_bytes with nonzero size"
+                    .to_string(),
+            );
+        }
+
+        if !args.serialized_compile_options.is_null()
+            && args.serialized_compile_options_deleter.is_none()
+        {
+            return Err(
+                "PJRT_Executable_GetCompileOptions returned serialized_compile_options without deleter"
                     .to_string(),
             );
         }
 
         let result = if args.serialized_bytes_size == 0 {
             Ok(Vec::new())
-        } else if args.serialized_bytes.is_null() {
-            Err(
-                "PJRT_Executable_Serialize returned null serialized_bytes with nonzero size"
-                    .to_string(),
-            )
         } else {
             let bytes = unsafe {
                 from_raw_parts(
@@ -101,159 +934,373 @@ impl<'a> PJRTLoadedExecutable<'a> {
             Ok(bytes.to_vec())
         };
 
-        if !args.serialized_executable.is_null() {
-            if let Some(deleter) = args.serialized_executable_deleter {
-                unsafe { deleter(args.serialized_executable) };
+        if !args.serialized_compile_options.is_null() {
+            if let Some(deleter) = args.serialized_compile_options_deleter {
+                unsafe { deleter(args.serialized_compile_options) };
             }
         }
 
         result
     }
 
-    pub fn deserialize_and_load(
-        &self,
-        client: *mut PJRT_Client,
-        serialized_executable: &[u8],
-        overridden_compile_options: Option<&[u8]>,
-    ) -> Result<PJRTLoadedExecutable<'a>, String> {
-        if client.is_null() {
-            return Err("PJRT_Client is null".to_string());
-        }
-        if serialized_executable.is_empty() {
-            return Err("serialized_executable must not be empty".to_string());
+    fn num_outputs(&self) -> Result<usize, String> {
+        if let Some(&n) = self.num_outputs.get() {
+            return Ok(n);
         }
+        let exec = self.executable()?;
 
         let f = self
             .rt
             .api()
-            .PJRT_Executable_DeserializeAndLoad
-            .ok_or("PJRT_Executable_DeserializeAndLoad symbol not found")?;
-
-        let serialized_ptr = serialized_executable.as_ptr() as *const libc::c_char;
-        let serialized_size = serialized_executable.len();
-
-        let (override_ptr, override_size) = match overridden_compile_options {
-            None => (ptr::null(), 0usize),
-            Some(opts) if opts.is_empty() => (ptr::null(), 0usize),
-            Some(opts) => (opts.as_ptr() as *const libc::c_char, opts.len()),
-        };
+            .PJRT_Executable_NumOutputs
+            .ok_or("PJRT_Executable_NumOutputs symbol not found")?;
 
-        let mut args = PJRT_Executable_DeserializeAndLoad_Args {
-            struct_size: PJRT_Executable_DeserializeAndLoad_Args_STRUCT_SIZE as usize,
+        let mut args = PJRT_Executable_NumOutputs_Args {
+            struct_size: PJRT_Executable_NumOutputs_Args_STRUCT_SIZE as usize,
             extension_start: ptr::null_mut(),
-            client,
-            serialized_executable: serialized_ptr,
-            serialized_executable_size: serialized_size,
-            loaded_executable: ptr::null_mut(),
-            overridden_serialized_compile_options: override_ptr,
-            overridden_serialized_compile_options_size: override_size,
+            executable: exec,
+            num_outputs: 0,
         };
 
         let err = unsafe { f(&mut args) };
-        if !err.is_null() {
-            return Err(error_to_string(self.rt.api(), err));
-        }
-        if args.loaded_executable.is_null() {
-            return Err(
-                "PJRT_Executable_DeserializeAndLoad succeeded but returned null loaded_executable"
-                    .to_string(),
-            );
+        if err.is_null() {
+            let _ = self.num_outputs.set(args.num_outputs);
+            Ok(args.num_outputs)
+        } else {
+            Err(error_to_string(self.rt.api(), err))
         }
+    }
 
-        Ok(PJRTLoadedExecutable::new(self.rt, args.loaded_executable))
+    pub fn run(&self, arguments: &[&PJRTBuffer<'a>]) -> Result<ExecuteResult<'a>, String> {
+        self.run_with_options(arguments, &PJRTExecuteRunOptions::default())
     }
 
-    pub fn get_compile_options(&self) -> Result<Vec<u8>, String> {
-        let exec = self.executable()?;
+    pub fn run_with_options(
+        &self,
+        arguments: &[&PJRTBuffer<'a>],
+        options: &PJRTExecuteRunOptions,
+    ) -> Result<ExecuteResult<'a>, String> {
+        let argument_lists = vec![argument_ptrs(arguments, options)?];
+        // Without an execute device the launch runs on the executable's first device.
+        let device = self
+            .addressable_device_refs()?
+            .into_iter()
+            .next()
+            .ok_or("executable has no addressable devices")?;
+        let launch_id = options.resolve_launch_id();
+        let (outputs, events) =
+            self.launch(&argument_lists, options, launch_id, ptr::null_mut())?;
+        single_device_result(outputs, events, LaunchHandle { launch_id, device })
+    }
 
-        let f = self
-            .rt
-            .api()
-            .PJRT_Executable_GetCompileOptions
-            .ok_or("PJRT_Executable_GetCompileOptions symbol not found")?;
+    #[deprecated(note = "use run, which returns an ExecuteResult")]
+    pub fn execute(
+        &self,
+        arguments: &[&PJRTBuffer<'a>],
+    ) -> Result<(Vec<PJRTBuffer<'a>>, PJRTEvent<'a>), String> {
+        self.run(arguments).map(ExecuteResult::into_parts)
+    }
 
-        let mut args = PJRT_Executable_GetCompileOptions_Args {
-            struct_size: PJRT_Executable_GetCompileOptions_Args_STRUCT_SIZE as usize,
-            extension_start: ptr::null_mut(),
-            executable: exec,
-            serialized_bytes: ptr::null(),
-            serialized_bytes_size: 0,
-            serialized_compile_options: ptr::null_mut(),
-            serialized_compile_options_deleter: None,
-        };
+    #[deprecated(note = "use run_with_options, which returns an ExecuteResult")]
+    pub fn execute_with_options(
+        &self,
+        arguments: &[&PJRTBuffer<'a>],
+        options: &PJRTExecuteRunOptions,
+    ) -> Result<(Vec<PJRTBuffer<'a>>, PJRTEvent<'a>), String> {
+        self.run_with_options(arguments, options)
+            .map(ExecuteResult::into_parts)
+    }
 
-        let err = unsafe { f(&mut args) };
-        if !err.is_null() {
-            return Err(error_to_string(self.rt.api(), err));
-        }
+    // Awaitable launch for async callers: the enqueue happens immediately and the future
+    // resolves once the completion event fires. Dropping the future early is safe; the
+    // completion callback only holds the sending half of the channel. Errors are owned so
+    // they can cross task boundaries; device failures keep the event's code.
+    #[cfg(feature = "tokio")]
+    pub fn execute_async(
+        &self,
+        arguments: &[&PJRTBuffer<'a>],
+        options: &PJRTExecuteRunOptions,
+    ) -> impl std::future::Future<Output = PjrtResult<ExecuteResult<'a>>> + 'a {
+        let callback_errors = options.callback_errors.clone();
+        let started = self.run_with_options(arguments, options).map(|result| {
+            let (tx, rx) = oneshot::channel();
+            let pending = Arc::new(Mutex::new(Some(tx)));
+            let complete = |pending: &Mutex<Option<oneshot::Sender<EventStatus>>>,
+                            status: EventStatus| {
+                if let Ok(Some(tx)) = pending.lock().map(|mut p| p.take()) {
+                    let _ = tx.send(status);
+                }
+            };
 
-        if args.serialized_bytes_size > 0 && args.serialized_bytes.is_null() {
-            return Err(
-                "PJRT_Executable_GetCompileOptions returned null serialized_bytes with nonzero size"
-                    .to_string(),
-            );
+            let from_event = Arc::clone(&pending);
+            let registered = result
+                .done
+                .on_ready_boxed(Box::new(move |status| complete(&from_event, status)));
+            if let Err(e) = registered {
+                log::warn!("PJRT_Event_OnReady failed, waiting for execution synchronously: {e}");
+                complete(&pending, result.done.await_status());
+            }
+            (result, rx)
+        });
+
+        async move {
+            let (result, rx) = started?;
+            match rx.await {
+                Ok(Ok(())) => {}
+                Ok(Err((code, message))) => return Err(OwnedPjrtError::new(code, message)),
+                Err(_) => {
+                    return Err(OwnedPjrtError::new(
+                        PjrtErrorCode::Cancelled,
+                        "execute completion was dropped without running",
+                    ))
+                }
+            }
+            if let Some(error) = callback_errors.take() {
+                return Err(error.into());
+            }
+            Ok(result)
         }
+    }
 
-        if !args.serialized_compile_options.is_null()
-            && args.serialized_compile_options_deleter.is_none()
-        {
-            return Err(
-                "PJRT_Executable_GetCompileOptions returned serialized_compile_options without deleter"
-                    .to_string(),
-            );
+    // Like run_with_options, but blocks until the device finishes and reports how long
+    // the enqueue and the device work took.
+    pub fn execute_timed(
+        &self,
+        arguments: &[&PJRTBuffer<'a>],
+        options: &PJRTExecuteRunOptions,
+    ) -> Result<(Vec<PJRTBuffer<'a>>, ExecuteTimings), String> {
+        let start = Instant::now();
+        let result = self.run_with_options(arguments, options)?;
+        let enqueued = Instant::now();
+
+        // The OnReady hook stamps completion on whichever thread the plugin fires it from.
+        let (tx, rx) = mpsc::channel();
+        result.done.on_ready_boxed(Box::new(move |status| {
+            let _ = tx.send((Instant::now(), status));
+        }))?;
+        let (finished, status) = rx
+            .recv()
+            .map_err(|_| "execute completion event was dropped without firing".to_string())?;
+        status.map_err(|(_, message)| message)?;
+        if let Some(error) = options.take_callback_error() {
+            return Err(error);
         }
 
-        let result = if args.serialized_bytes_size == 0 {
-            Ok(Vec::new())
-        } else {
-            let bytes = unsafe {
-                from_raw_parts(
-                    args.serialized_bytes as *const u8,
-                    args.serialized_bytes_size,
-                )
-            };
-            Ok(bytes.to_vec())
-        };
+        let timings = ExecuteTimings {
+            enqueue: enqueued - start,
+            completion: finished.saturating_duration_since(enqueued),
+            total: finished.saturating_duration_since(start),
+        };
+        Ok((result.outputs, timings))
+    }
+
+    // Runs with `donated` as the leading arguments and `borrowed` after them. Donated
+    // buffers are consumed: the runtime may reuse their memory for outputs, so they are
+    // released here whether or not the launch succeeds. Borrowed buffers stay valid.
+    pub fn run_consuming(
+        &self,
+        donated: Vec<PJRTBuffer<'a>>,
+        borrowed: &[&PJRTBuffer<'a>],
+        options: &PJRTExecuteRunOptions,
+    ) -> Result<ExecuteResult<'a>, String> {
+        let non_donatable = consuming_non_donatable(
+            donated.len(),
+            borrowed.len(),
+            options.get_non_donatable_input_indices(),
+        )?;
+        let options = options.clone().non_donatable_input_indices(non_donatable);
+        let arguments: Vec<&PJRTBuffer<'a>> =
+            donated.iter().chain(borrowed.iter().copied()).collect();
+        self.run_with_options(&arguments, &options)
+    }
+
+    #[deprecated(note = "use run_consuming, which returns an ExecuteResult")]
+    pub fn execute_consuming(
+        &self,
+        donated: Vec<PJRTBuffer<'a>>,
+        borrowed: &[&PJRTBuffer<'a>],
+        options: &PJRTExecuteRunOptions,
+    ) -> Result<(Vec<PJRTBuffer<'a>>, PJRTEvent<'a>), String> {
+        self.run_consuming(donated, borrowed, options)
+            .map(ExecuteResult::into_parts)
+    }
+
+    pub fn run_on(
+        &self,
+        device: &PJRTDevice<'_>,
+        arguments: &[&PJRTBuffer<'a>],
+    ) -> Result<ExecuteResult<'a>, String> {
+        self.run_on_with_options(device, arguments, &PJRTExecuteRunOptions::default())
+    }
+
+    // Runs a portable (single replica, single partition) executable on `device`.
+    pub fn run_on_with_options(
+        &self,
+        device: &PJRTDevice<'_>,
+        arguments: &[&PJRTBuffer<'a>],
+        options: &PJRTExecuteRunOptions,
+    ) -> Result<ExecuteResult<'a>, String> {
+        let target = device.raw();
+        if target.is_null() {
+            return Err("execute_on device is null".to_string());
+        }
+        if !device.is_addressable()? {
+            return Err(format!(
+                "execute_on device {} is not addressable by this process",
+                device.id()
+            ));
+        }
 
-        if !args.serialized_compile_options.is_null() {
-            if let Some(deleter) = args.serialized_compile_options_deleter {
-                unsafe { deleter(args.serialized_compile_options) };
+        argument_ptrs(arguments, options)?;
+        let mut copies = Vec::new();
+        let mut argument_list = Vec::with_capacity(arguments.len());
+        for (i, argument) in arguments.iter().enumerate() {
+            let on_device = argument.device()?;
+            if on_device == target {
+                argument_list.push(argument.raw());
+            } else if options.get_copy_inputs_to_device() {
+                let copy = PJRTBuffer::new(self.rt, argument.copy_to_device(device)?);
+                argument_list.push(copy.raw());
+                copies.push(copy);
+            } else {
+                return Err(format!(
+                    "execute_on argument {i} lives on device {} but execution targets device {}",
+                    PJRTDevice::new(self.rt, on_device)?.id(),
+                    device.id()
+                ));
             }
         }
+        let launch_id = options.resolve_launch_id();
+        let (outputs, events) = self.launch(&[argument_list], options, launch_id, target)?;
+        let device = PJRTDevice::new(self.rt, target)?;
+        single_device_result(outputs, events, LaunchHandle { launch_id, device })
+    }
 
-        result
+    #[deprecated(note = "use run_on, which returns an ExecuteResult")]
+    pub fn execute_on(
+        &self,
+        device: &PJRTDevice<'_>,
+        arguments: &[&PJRTBuffer<'a>],
+    ) -> Result<(Vec<PJRTBuffer<'a>>, PJRTEvent<'a>), String> {
+        self.run_on(device, arguments)
+            .map(ExecuteResult::into_parts)
     }
 
-    fn num_outputs(&self) -> Result<usize, String> {
-        let exec = self.executable()?;
+    #[deprecated(note = "use run_on_with_options, which returns an ExecuteResult")]
+    pub fn execute_on_with_options(
+        &self,
+        device: &PJRTDevice<'_>,
+        arguments: &[&PJRTBuffer<'a>],
+        options: &PJRTExecuteRunOptions,
+    ) -> Result<(Vec<PJRTBuffer<'a>>, PJRTEvent<'a>), String> {
+        self.run_on_with_options(device, arguments, options)
+            .map(ExecuteResult::into_parts)
+    }
 
-        let f = self
-            .rt
-            .api()
-            .PJRT_Executable_NumOutputs
-            .ok_or("PJRT_Executable_NumOutputs symbol not found")?;
+    // Runs one launch across every addressable device (replicas and/or SPMD partitions).
+    // `per_device_args[i]` feeds the i-th entry of `addressable_devices()`. Blocks until
+    // every device has finished.
+    pub fn execute_sharded(
+        &self,
+        per_device_args: &[Vec<&PJRTBuffer<'a>>],
+        options: &PJRTExecuteRunOptions,
+    ) -> Result<Vec<Vec<PJRTBuffer<'a>>>, String> {
+        let num_devices = self.addressable_devices()?.len();
+        if per_device_args.len() != num_devices {
+            return Err(format!(
+                "execute_sharded expected argument lists for {num_devices} addressable devices, got {}",
+                per_device_args.len()
+            ));
+        }
+        if let Some(first) = per_device_args.first() {
+            if let Some((i, args)) = per_device_args
+                .iter()
+                .enumerate()
+                .find(|(_, args)| args.len() != first.len())
+            {
+                return Err(format!(
+                    "execute_sharded argument lists differ in arity: device 0 has {} but device {i} has {}",
+                    first.len(),
+                    args.len()
+                ));
+            }
+        }
 
-        let mut args = PJRT_Executable_NumOutputs_Args {
-            struct_size: PJRT_Executable_NumOutputs_Args_STRUCT_SIZE as usize,
-            extension_start: ptr::null_mut(),
-            executable: exec,
-            num_outputs: 0,
-        };
+        let argument_lists = per_device_args
+            .iter()
+            .map(|args| argument_ptrs(args, options))
+            .collect::<Result<Vec<_>, _>>()?;
+        let launch_id = options.resolve_launch_id();
+        let (outputs, events) =
+            self.launch(&argument_lists, options, launch_id, ptr::null_mut())?;
+        join_device_events("execute_sharded", &events, &options.callback_errors)?;
+        Ok(outputs)
+    }
 
-        let err = unsafe { f(&mut args) };
-        if err.is_null() {
-            Ok(args.num_outputs)
-        } else {
-            Err(error_to_string(self.rt.api(), err))
+    // Data-parallel launch: `shards[i]` feeds the i-th addressable device. Buffers on the
+    // wrong device are copied to it first. The shards stay alive until the result is waited
+    // on or dropped.
+    pub fn execute_replicated(
+        &self,
+        shards: Vec<Vec<PJRTBuffer<'a>>>,
+        options: &PJRTExecuteRunOptions,
+    ) -> Result<ReplicatedExecuteResult<'a>, String> {
+        let arities: Vec<usize> = shards.iter().map(Vec::len).collect();
+        check_shard_arities(&arities)?;
+        let devices = self.addressable_device_refs()?;
+        if shards.len() != devices.len() {
+            return Err(format!(
+                "execute_replicated got {} shards for {} addressable devices",
+                shards.len(),
+                devices.len()
+            ));
+        }
+
+        let mut inputs = Vec::with_capacity(shards.len());
+        for (shard, device) in shards.into_iter().zip(&devices) {
+            let target = device.raw();
+            let mut on_device = Vec::with_capacity(shard.len());
+            for buffer in shard {
+                if buffer.device()? == target {
+                    on_device.push(buffer);
+                } else {
+                    on_device.push(PJRTBuffer::new(self.rt, buffer.copy_to_device(device)?));
+                }
+            }
+            inputs.push(on_device);
         }
+
+        let argument_lists = inputs
+            .iter()
+            .map(|shard| argument_ptrs(&shard.iter().collect::<Vec<_>>(), options))
+            .collect::<Result<Vec<_>, _>>()?;
+        let launch_id = options.resolve_launch_id();
+        let (outputs, done) = self.launch(&argument_lists, options, launch_id, ptr::null_mut())?;
+        Ok(ReplicatedExecuteResult {
+            outputs,
+            done,
+            inputs,
+            callback_errors: options.callback_errors.clone(),
+        })
     }
 
-    pub fn execute(
+    // One PJRT_LoadedExecutable_Execute call; returns each device's outputs and completion
+    // event in the order of `argument_lists`.
+    fn launch(
         &self,
-        arguments: &[&PJRTBuffer<'a>],
-    ) -> Result<(Vec<PJRTBuffer<'a>>, PJRTEvent<'a>), String> {
+        argument_lists: &[Vec<*mut PJRT_Buffer>],
+        run_options: &PJRTExecuteRunOptions,
+        launch_id: i32,
+        execute_device: *mut PJRT_Device,
+    ) -> Result<(Vec<Vec<PJRTBuffer<'a>>>, Vec<PJRTEvent<'a>>), String> {
         let raw_executable = self.raw_checked()?;
         let num_outputs = self.num_outputs()?;
+        let num_devices = argument_lists.len();
+        if num_devices == 0 {
+            return Err("execute requires at least one device argument list".to_string());
+        }
+        let num_args = argument_lists[0].len();
+        validate_non_donatable(&run_options.non_donatable_input_indices, num_args)?;
 
         let f = self
             .rt
@@ -261,43 +1308,77 @@ impl<'a> PJRTLoadedExecutable<'a> {
             .PJRT_LoadedExecutable_Execute
             .ok_or("PJRT_LoadedExecutable_Execute symbol not found")?;
 
-        let argument_ptrs: Vec<*mut PJRT_Buffer> = arguments.iter().map(|b| b.raw()).collect();
-        if argument_ptrs.iter().any(|p| p.is_null()) {
-            return Err("execute arguments contain null PJRT_Buffer".to_string());
-        }
-
-        let per_device_argument_lists: Vec<*const *mut PJRT_Buffer> =
-            vec![if arguments.is_empty() {
-                ptr::null()
-            } else {
-                argument_ptrs.as_ptr()
-            }];
+        let per_device_argument_lists: Vec<*const *mut PJRT_Buffer> = argument_lists
+            .iter()
+            .map(|args| {
+                if args.is_empty() {
+                    ptr::null()
+                } else {
+                    args.as_ptr()
+                }
+            })
+            .collect();
 
-        let mut output_ptrs: Vec<*mut PJRT_Buffer> = vec![ptr::null_mut(); num_outputs];
-        let per_device_output_lists: Vec<*mut *mut PJRT_Buffer> = vec![if num_outputs == 0 {
-            ptr::null_mut()
-        } else {
-            output_ptrs.as_mut_ptr()
-        }];
+        let mut output_ptrs: Vec<Vec<*mut PJRT_Buffer>> =
+            vec![vec![ptr::null_mut(); num_outputs]; num_devices];
+        let per_device_output_lists: Vec<*mut *mut PJRT_Buffer> = output_ptrs
+            .iter_mut()
+            .map(|outputs| {
+                if num_outputs == 0 {
+                    ptr::null_mut()
+                } else {
+                    outputs.as_mut_ptr()
+                }
+            })
+            .collect();
 
+        let keepalive = Arc::new(ExecuteCallbackKeepalive::new(
+            self.rt,
+            &run_options.send_callbacks,
+            &run_options.recv_callbacks,
+            &run_options.callback_errors,
+            num_devices,
+        ));
+        let call_location = encode_call_location(run_options.call_location.as_deref())?;
+        let (mut task_ids, mut incarnation_ids) = encode_tasks(&run_options.tasks)?;
+        let non_donatable = &run_options.non_donatable_input_indices;
+        let context = match &run_options.execute_context {
+            Some(context) if context.raw().is_null() => {
+                return Err("execute context was destroyed before the launch".to_string())
+            }
+            Some(context) => context.raw(),
+            None => ptr::null_mut(),
+        };
         let mut options = PJRT_ExecuteOptions {
             struct_size: PJRT_ExecuteOptions_STRUCT_SIZE as usize,
             extension_start: ptr::null_mut(),
-            send_callbacks: ptr::null_mut(),
-            recv_callbacks: ptr::null_mut(),
-            num_send_ops: 0,
-            num_recv_ops: 0,
-            launch_id: 0,
-            non_donatable_input_indices: ptr::null(),
-            num_non_donatable_input_indices: 0,
-            context: ptr::null_mut(),
-            call_location: ptr::null(),
-            num_tasks: 0,
-            task_ids: ptr::null_mut(),
-            incarnation_ids: ptr::null_mut(),
-        };
-
-        let mut device_complete_event: *mut PJRT_Event = ptr::null_mut();
+            send_callbacks: keepalive.send_callbacks(),
+            recv_callbacks: keepalive.recv_callbacks(),
+            num_send_ops: keepalive.num_send_ops(),
+            num_recv_ops: keepalive.num_recv_ops(),
+            launch_id,
+            non_donatable_input_indices: if non_donatable.is_empty() {
+                ptr::null()
+            } else {
+                non_donatable.as_ptr()
+            },
+            num_non_donatable_input_indices: non_donatable.len(),
+            context,
+            call_location: call_location.as_ref().map_or(ptr::null(), |c| c.as_ptr()),
+            num_tasks: task_ids.len(),
+            task_ids: if task_ids.is_empty() {
+                ptr::null_mut()
+            } else {
+                task_ids.as_mut_ptr()
+            },
+            incarnation_ids: if incarnation_ids.is_empty() {
+                ptr::null_mut()
+            } else {
+                incarnation_ids.as_mut_ptr()
+            },
+        };
+
+        let mut device_complete_events: Vec<*mut PJRT_Event> = vec![ptr::null_mut(); num_devices];
 
         let mut args = PJRT_LoadedExecutable_Execute_Args {
             struct_size: PJRT_LoadedExecutable_Execute_Args_STRUCT_SIZE as usize,
@@ -305,53 +1386,109 @@ impl<'a> PJRTLoadedExecutable<'a> {
             executable: raw_executable,
             options: &mut options,
             argument_lists: per_device_argument_lists.as_ptr(),
-            num_devices: 1,
-            num_args: argument_ptrs.len(),
+            num_devices,
+            num_args,
             output_lists: per_device_output_lists.as_ptr(),
-            device_complete_events: &mut device_complete_event,
-            execute_device: ptr::null_mut(),
+            device_complete_events: device_complete_events.as_mut_ptr(),
+            execute_device,
         };
 
-        let err = unsafe { f(&mut args) };
-        if !err.is_null() {
-            return Err(error_to_string(self.rt.api(), err));
-        }
+        pjrt_check!(
+            self.rt,
+            unsafe { f(&mut args) },
+            ContextFrame::new("PJRT_LoadedExecutable_Execute")
+                .executable(self.name().ok())
+                .device(if execute_device.is_null() {
+                    None
+                } else {
+                    PJRTDevice::new(self.rt, execute_device)
+                        .ok()
+                        .map(|d| d.id())
+                })
+        )?;
 
-        if args.num_args != argument_ptrs.len() {
+        if args.num_args != num_args {
             return Err(format!(
                 "execute argument count mismatch: requested {} but runtime used {}",
-                argument_ptrs.len(),
-                args.num_args
+                num_args, args.num_args
             ));
         }
 
-        let output_list_ptr = per_device_output_lists[0];
-        if num_outputs > 0 && output_list_ptr.is_null() {
-            return Err(
-                "PJRT_LoadedExecutable_Execute returned null output list with nonzero num_outputs"
-                    .to_string(),
-            );
-        }
+        // Take ownership of everything the runtime handed back before validating, so a bad
+        // entry on one device doesn't leak the buffers and events of the others.
+        let outputs: Vec<Vec<PJRTBuffer<'a>>> = output_ptrs
+            .into_iter()
+            .map(|raws| {
+                raws.into_iter()
+                    .filter(|raw| !raw.is_null())
+                    .map(|raw| PJRTBuffer::new(self.rt, raw))
+                    .collect()
+            })
+            .collect();
+        let events: Vec<PJRTEvent<'a>> = device_complete_events
+            .into_iter()
+            .filter(|raw| !raw.is_null())
+            .map(|raw| PJRTEvent::new(self.rt, raw))
+            .collect();
 
-        let output_raws: Vec<*mut PJRT_Buffer> = if num_outputs == 0 {
-            Vec::new()
-        } else {
-            unsafe { from_raw_parts(output_list_ptr, num_outputs).to_vec() }
-        };
-        if output_raws.iter().any(|p| p.is_null()) {
+        if events.len() != num_devices {
+            // Without every completion event there's no safe point to free callback state.
+            mem::forget(keepalive);
+            mem::forget(run_options.execute_context.clone());
+            return Err("PJRT_LoadedExecutable_Execute returned null completion event".to_string());
+        }
+        if keepalive.num_send_ops() + keepalive.num_recv_ops() > 0 {
+            for event in &events {
+                let held = Arc::clone(&keepalive);
+                if event.on_ready_boxed(Box::new(move |_| drop(held))).is_err() {
+                    mem::forget(Arc::clone(&keepalive));
+                }
+            }
+        }
+        if let Some(context) = &run_options.execute_context {
+            for event in &events {
+                let held = Arc::clone(context);
+                if event.on_ready_boxed(Box::new(move |_| drop(held))).is_err() {
+                    mem::forget(Arc::clone(context));
+                }
+            }
+        }
+        if outputs.iter().any(|device| device.len() != num_outputs) {
             return Err("PJRT_LoadedExecutable_Execute produced null output buffer".to_string());
         }
+        if run_options.validate_outputs {
+            self.validate_outputs(&outputs)?;
+        }
+        Ok((outputs, events))
+    }
 
-        if device_complete_event.is_null() {
-            return Err("PJRT_LoadedExecutable_Execute returned null completion event".to_string());
+    fn declared_outputs(&self) -> Result<&[(PJRT_Buffer_Type, Vec<i64>)], String> {
+        if let Some(declared) = self.declared_outputs.get() {
+            return Ok(declared);
+        }
+        let element_types = self.output_element_types()?;
+        let dims = self.output_dimensions()?;
+        if element_types.len() != dims.len() {
+            return Err(format!(
+                "executable reports {} output element types but {} output dimensions",
+                element_types.len(),
+                dims.len()
+            ));
         }
+        let declared = element_types.into_iter().zip(dims).collect();
+        Ok(self.declared_outputs.get_or_init(|| declared))
+    }
 
-        let output_buffers = output_raws
-            .into_iter()
-            .map(|raw| PJRTBuffer::new(self.rt, raw))
-            .collect();
-        let event = PJRTEvent::new(self.rt, device_complete_event);
-        Ok((output_buffers, event))
+    fn validate_outputs(&self, outputs: &[Vec<PJRTBuffer<'a>>]) -> Result<(), String> {
+        let declared = self.declared_outputs()?;
+        for device_outputs in outputs {
+            for (i, (output, (ty, dims))) in device_outputs.iter().zip(declared).enumerate() {
+                check_output(i, (*ty, dims), output.element_type_raw()?, || {
+                    output.dimensions()
+                })?;
+            }
+        }
+        Ok(())
     }
 
     pub fn num_replicas(&self) -> Result<usize, String> {
@@ -402,27 +1539,12 @@ impl<'a> PJRTLoadedExecutable<'a> {
         }
     }
 
-    pub fn destroy_executable_handle(&self) -> Result<(), String> {
-        let executable = self.executable()?;
-
-        let f = self
-            .rt
-            .api()
-            .PJRT_Executable_Destroy
-            .ok_or("PJRT_Executable_Destroy symbol not found")?;
-
-        let mut args = PJRT_Executable_Destroy_Args {
-            struct_size: PJRT_Executable_Destroy_Args_STRUCT_SIZE as usize,
-            extension_start: ptr::null_mut(),
-            executable,
-        };
-
-        let err = unsafe { f(&mut args) };
-        if err.is_null() {
-            Ok(())
-        } else {
-            Err(error_to_string(self.rt.api(), err))
-        }
+    // Releases the cached PJRT_Executable early. Metadata getters fetch a fresh handle if
+    // they are called afterwards.
+    pub fn destroy_executable_handle(&mut self) -> Result<(), String> {
+        self.executable
+            .take()
+            .map_or(Ok(()), PJRTExecutableRef::destroy)
     }
 
     pub fn delete(&self) -> Result<(), String> {
@@ -545,18 +1667,18 @@ impl<'a> PJRTLoadedExecutable<'a> {
     }
 
     pub fn addressable_device_refs(&self) -> Result<Vec<PJRTDevice<'a>>, String> {
-        Ok(self
-            .addressable_devices()?
+        self.addressable_devices()?
             .into_iter()
             .map(|raw| PJRTDevice::new(self.rt, raw))
-            .collect())
+            .collect()
     }
 
     pub fn addressable_device_ids(&self) -> Result<Vec<i32>, String> {
-        self.addressable_device_refs()?
+        Ok(self
+            .addressable_device_refs()?
             .iter()
             .map(PJRTDevice::id)
-            .collect()
+            .collect())
     }
 
     pub fn addressable_device_kinds(&self) -> Result<Vec<String>, String> {
@@ -616,6 +1738,19 @@ impl<'a> PJRTLoadedExecutable<'a> {
         Ok(String::from_utf8_lossy(bytes).into_owned())
     }
 
+    // executable_fingerprint, or the loaded executable's fingerprint for plugins that leave
+    // the former empty or unimplemented.
+    pub fn identity(&self) -> Result<ExecutableId, String> {
+        if let Some(fingerprint) = self.executable_fingerprint().ok().filter(|f| !f.is_empty()) {
+            return Ok(ExecutableId(fingerprint));
+        }
+        let fingerprint = self.fingerprint()?;
+        if fingerprint.is_empty() {
+            return Err("plugin reports an empty executable fingerprint".to_string());
+        }
+        Ok(ExecutableId(fingerprint))
+    }
+
     pub fn executable_fingerprint(&self) -> Result<String, String> {
         let exec = self.executable()?;
 
@@ -736,6 +1871,11 @@ impl<'a> PJRTLoadedExecutable<'a> {
         Ok(out)
     }
 
+    #[cfg(feature = "proto")]
+    pub fn device_assignment(&self) -> Result<DeviceAssignment, String> {
+        DeviceAssignment::decode(&self.device_assignment_serialized()?)
+    }
+
     pub fn device_assignment_serialized(&self) -> Result<Vec<u8>, String> {
         let raw = self.raw_checked()?;
 
@@ -826,6 +1966,29 @@ impl<'a> PJRTLoadedExecutable<'a> {
         Ok(String::from_utf8_lossy(bytes).into_owned())
     }
 
+    pub fn metadata(&self) -> Result<ExecutableMetadata, String> {
+        // Every query below goes through this handle, so failing to get it is a hard error.
+        self.executable()?;
+        Ok(ExecutableMetadata {
+            name: self.name().ok(),
+            fingerprint: self.executable_fingerprint().ok(),
+            identity: self.identity().ok(),
+            num_replicas: self.num_replicas().ok(),
+            num_partitions: self.num_partitions().ok(),
+            num_outputs: self.num_outputs().ok(),
+            generated_code_size_in_bytes: self.size_of_generated_code_in_bytes().ok(),
+            output_element_types: self.output_element_types().ok().and_then(|types| {
+                types
+                    .into_iter()
+                    .map(BufferType::try_from)
+                    .collect::<Result<_, _>>()
+                    .ok()
+            }),
+            output_memory_kinds: self.output_memory_kinds().ok(),
+            compiled_memory_stats: self.get_compiled_memory_stats().ok(),
+        })
+    }
+
     pub fn get_compiled_memory_stats(&self) -> Result<Vec<i64>, String> {
         let exec = self.executable()?;
 
@@ -877,7 +2040,7 @@ impl<'a> PJRTLoadedExecutable<'a> {
         }
     }
 
-    pub fn get_cost_analysis(&self) -> Result<String, String> {
+    pub fn get_cost_analysis(&self) -> Result<Vec<PJRTNamedAttribute>, String> {
         let exec = self.executable()?;
 
         let func = self
@@ -895,69 +2058,107 @@ impl<'a> PJRTLoadedExecutable<'a> {
         };
 
         let err = unsafe { func(&mut args) };
-
         if !err.is_null() {
-            Err(error_to_string(self.rt.api(), err))
-        } else if args.num_properties == 0 {
-            Ok(String::new())
-        } else if args.properties.is_null() {
-            Err(
-                "PJRT_Executable_GetCostAnalysis returned null properties with nonzero count"
-                    .to_string(),
-            )
-        } else {
-            let properties = unsafe { from_raw_parts(args.properties, args.num_properties) };
-            let names = properties
-                .iter()
-                .map(|property| {
-                    if property.name.is_null() {
-                        "<null>".to_string()
-                    } else {
-                        let bytes = unsafe {
-                            from_raw_parts(property.name as *const u8, property.name_size)
-                        };
-                        String::from_utf8_lossy(bytes).into_owned()
-                    }
-                })
-                .collect::<Vec<_>>()
-                .join(",");
-            Ok(names)
+            return Err(error_to_string(self.rt.api(), err));
         }
+        decode_named_values(args.properties, args.num_properties)
+    }
+
+    // Estimated floating point operations, if the plugin reports them.
+    pub fn flops(&self) -> Result<Option<f64>, String> {
+        Ok(numeric_property(&self.get_cost_analysis()?, "flops"))
+    }
+
+    pub fn bytes_accessed(&self) -> Result<Option<f64>, String> {
+        Ok(numeric_property(
+            &self.get_cost_analysis()?,
+            "bytes accessed",
+        ))
+    }
+
+    // "name=value" pairs, one property per entry, for logging.
+    pub fn cost_analysis_summary(&self) -> Result<String, String> {
+        Ok(self
+            .get_cost_analysis()?
+            .iter()
+            .map(|property| format!("{}={}", property.name, named_value_text(&property.value)))
+            .collect::<Vec<_>>()
+            .join(", "))
     }
 
-    pub fn optimized_program(&self) -> Result<(), String> {
+    // The program after XLA optimization, typically serialized HLO ("hlo" format).
+    pub fn optimized_program(&self) -> Result<OptimizedProgram, String> {
         let exec = self.executable()?;
 
         let func = self
             .rt
             .api()
             .PJRT_Executable_OptimizedProgram
-            .ok_or("PJRT_Exectuable_Optimized not found.")?;
+            .ok_or("PJRT_Executable_OptimizedProgram symbol not found")?;
 
+        let mut program = PJRT_Program {
+            struct_size: PJRT_Program_STRUCT_SIZE as usize,
+            extension_start: null_mut(),
+            code: null_mut(),
+            code_size: 0,
+            format: null(),
+            format_size: 0,
+        };
         let mut args = PJRT_Executable_OptimizedProgram_Args {
             struct_size: PJRT_Executable_OptimizedProgram_Args_STRUCT_SIZE as usize,
             extension_start: null_mut(),
             executable: exec,
-            program: null_mut(),
+            program: &mut program,
         };
 
+        // First pass: with a null code buffer the plugin only reports code_size.
         let err = unsafe { func(&mut args) };
-
         if !err.is_null() {
-            Err(error_to_string(self.rt.api(), err))
-        } else {
-            Ok(())
+            return Err(error_to_string(self.rt.api(), err));
+        }
+
+        let mut code = vec![0u8; program.code_size];
+        if !code.is_empty() {
+            program.code = code.as_mut_ptr().cast::<libc::c_char>();
+            args.program = &mut program;
+            let err = unsafe { func(&mut args) };
+            if !err.is_null() {
+                return Err(error_to_string(self.rt.api(), err));
+            }
+            if program.code_size != code.len() {
+                return Err(format!(
+                    "PJRT_Executable_OptimizedProgram reported {} bytes but wrote {}",
+                    code.len(),
+                    program.code_size
+                ));
+            }
         }
+
+        let format = if program.format_size == 0 {
+            String::new()
+        } else if program.format.is_null() {
+            return Err(
+                "PJRT_Executable_OptimizedProgram returned null format with nonzero size"
+                    .to_string(),
+            );
+        } else {
+            let bytes = unsafe { from_raw_parts(program.format as *const u8, program.format_size) };
+            String::from_utf8_lossy(bytes).into_owned()
+        };
+
+        Ok(OptimizedProgram { format, code })
     }
 
-    pub fn output_dimension(&self) -> Result<i64, String> {
+    // Dimensions of each output, in output order. PJRT returns one flat array plus the rank
+    // of every output.
+    pub fn output_dimensions(&self) -> Result<Vec<Vec<i64>>, String> {
         let exec = self.executable()?;
 
         let func = self
             .rt
             .api()
             .PJRT_Executable_OutputDimensions
-            .ok_or("PJRT_Executable_OutputDimensions not found.")?;
+            .ok_or("PJRT_Executable_OutputDimensions symbol not found")?;
 
         let mut args = PJRT_Executable_OutputDimensions_Args {
             struct_size: PJRT_Executable_OutputDimensions_Args_STRUCT_SIZE as usize,
@@ -968,22 +2169,95 @@ impl<'a> PJRTLoadedExecutable<'a> {
             dim_sizes: null(),
         };
         let err = unsafe { func(&mut args) };
-
         if !err.is_null() {
-            Err(error_to_string(self.rt.api(), err))
-        } else if args.num_outputs == 0 {
-            Err("PJRT_Executable_OutputDimensions returned no outputs".to_string())
+            return Err(error_to_string(self.rt.api(), err));
+        }
+        if args.num_outputs == 0 {
+            return Ok(Vec::new());
+        }
+        if args.dim_sizes.is_null() {
+            return Err(
+                "PJRT_Executable_OutputDimensions returned null dim_sizes with nonzero num_outputs"
+                    .to_string(),
+            );
+        }
+
+        let dim_sizes = unsafe { from_raw_parts(args.dim_sizes, args.num_outputs) };
+        let total: usize = dim_sizes.iter().sum();
+        let dims = if total == 0 {
+            &[][..]
         } else if args.dims.is_null() {
-            Err("PJRT_Executable_OutputDimensions returned null dims".to_string())
+            return Err(
+                "PJRT_Executable_OutputDimensions returned null dims with nonzero total rank"
+                    .to_string(),
+            );
         } else {
-            let dims = unsafe { from_raw_parts(args.dims as *const i64, args.num_outputs) };
-            Ok(dims[0])
+            unsafe { from_raw_parts(args.dims, total) }
+        };
+        split_output_dims(dims, dim_sizes)
+    }
+
+    pub fn output_shapes(&self) -> Result<Vec<OutputShape>, String> {
+        let element_types = self.output_element_types()?;
+        let dims = self.output_dimensions()?;
+        // Memory kinds are optional on older plugins.
+        let memory_kinds = self.output_memory_kinds().ok();
+
+        if dims.len() != element_types.len() {
+            return Err(format!(
+                "executable reports {} output element types but {} output dimensions",
+                element_types.len(),
+                dims.len()
+            ));
+        }
+        if let Some(kinds) = &memory_kinds {
+            if kinds.len() != element_types.len() {
+                return Err(format!(
+                    "executable reports {} output element types but {} output memory kinds",
+                    element_types.len(),
+                    kinds.len()
+                ));
+            }
         }
+
+        element_types
+            .into_iter()
+            .zip(dims)
+            .enumerate()
+            .map(|(i, (element_type, dims))| {
+                let memory_kind = memory_kinds
+                    .as_ref()
+                    .map(|kinds| kinds[i].clone())
+                    .filter(|kind| !kind.is_empty());
+                Ok(OutputShape {
+                    element_type: BufferType::try_from(element_type)?,
+                    dims,
+                    memory_kind,
+                })
+            })
+            .collect()
+    }
+
+    #[deprecated(note = "use output_dimensions, which covers every output")]
+    pub fn output_dimension(&self) -> Result<i64, String> {
+        first_flat_output_dim(&self.output_dimensions()?)
+    }
+}
+
+impl fmt::Debug for PJRTLoadedExecutable<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let num_outputs = self.output_element_types().map(|types| types.len());
+        f.debug_struct("PJRTLoadedExecutable")
+            .field("name", &DebugResult::debug(self.name()))
+            .field("fingerprint", &DebugResult::debug(self.fingerprint()))
+            .field("num_outputs", &DebugResult::debug(num_outputs))
+            .finish()
     }
 }
 
 impl Drop for PJRTLoadedExecutable<'_> {
     fn drop(&mut self) {
+        drop(self.executable.take());
         if self.raw.is_null() {
             return;
         }
@@ -1005,3 +2279,171 @@ impl Drop for PJRTLoadedExecutable<'_> {
         }
     }
 }
+
+#[cfg(test)]
+mod executable_tests {
+    use super::*;
+
+    #[test]
+    fn generated_launch_ids_are_positive_and_distinct() {
+        let ids: Vec<i32> = (0..64)
+            .map(|_| PJRTExecuteRunOptions::new().resolve_launch_id())
+            .collect();
+        assert!(ids.iter().all(|&id| id > 0));
+        assert!(ids.windows(2).all(|w| w[0] != w[1]));
+        assert_eq!(
+            PJRTExecuteRunOptions::new()
+                .launch_id(7)
+                .resolve_launch_id(),
+            7
+        );
+        assert_eq!(PJRTExecuteRunOptions::new().get_launch_id(), None);
+    }
+
+    #[test]
+    fn non_donatable_indices_are_checked_against_arguments() {
+        assert!(validate_non_donatable(&[], 0).is_ok());
+        assert!(validate_non_donatable(&[2, 0], 3).is_ok());
+
+        let err = validate_non_donatable(&[0, 3], 3).unwrap_err();
+        assert!(
+            err.contains("contains 3") && err.contains("3 arguments"),
+            "{err}"
+        );
+        let err = validate_non_donatable(&[-1], 3).unwrap_err();
+        assert!(err.contains("contains -1"), "{err}");
+        let err = validate_non_donatable(&[1, 0, 1], 3).unwrap_err();
+        assert!(err.contains("lists 1 more than once"), "{err}");
+
+        let options = PJRTExecuteRunOptions::new().non_donatable_inputs(&[4, 1]);
+        assert_eq!(options.get_non_donatable_input_indices(), &[4, 1]);
+    }
+
+    #[test]
+    fn output_check_names_both_shapes() {
+        let f32_ty = PJRT_Buffer_Type_PJRT_Buffer_Type_F32;
+        let s32_ty = PJRT_Buffer_Type_PJRT_Buffer_Type_S32;
+        let unused = || -> Result<Vec<i64>, String> { panic!("dimensions should not be read") };
+        assert!(check_output(0, (f32_ty, &[2, 3]), f32_ty, || Ok(vec![2, 3])).is_ok());
+        assert!(check_output(0, (f32_ty, &[]), f32_ty, unused).is_ok());
+
+        let err = check_output(1, (f32_ty, &[2, 3]), s32_ty, unused).unwrap_err();
+        assert_eq!(err, "output 1 is s32 but the executable declares f32[2, 3]");
+        let err = check_output(0, (f32_ty, &[4]), f32_ty, || Ok(vec![2, 2])).unwrap_err();
+        assert_eq!(
+            err,
+            "output 0 is f32[2, 2] but the executable declares f32[4]"
+        );
+    }
+
+    #[test]
+    fn output_validation_defaults_to_debug_builds() {
+        assert_eq!(
+            PJRTExecuteRunOptions::default().get_validate_outputs(),
+            cfg!(debug_assertions)
+        );
+        assert!(!PJRTExecuteRunOptions::new()
+            .validate_outputs(false)
+            .get_validate_outputs());
+    }
+
+    #[test]
+    fn shard_arities_must_agree() {
+        assert!(check_shard_arities(&[]).is_ok());
+        assert!(check_shard_arities(&[2, 2, 2]).is_ok());
+        let err = check_shard_arities(&[2, 2, 1]).unwrap_err();
+        assert!(err.contains("shard 0 has 2 but shard 2 has 1"), "{err}");
+    }
+
+    #[test]
+    fn consuming_marks_borrowed_positions_non_donatable() {
+        assert_eq!(consuming_non_donatable(2, 3, &[]).unwrap(), vec![2, 3, 4]);
+        assert_eq!(consuming_non_donatable(0, 2, &[]).unwrap(), vec![0, 1]);
+        assert_eq!(
+            consuming_non_donatable(2, 0, &[]).unwrap(),
+            Vec::<i64>::new()
+        );
+        // Caller-listed indices past the donated prefix are kept and deduplicated.
+        assert_eq!(
+            consuming_non_donatable(1, 2, &[5, 2]).unwrap(),
+            vec![1, 2, 5]
+        );
+        let err = consuming_non_donatable(2, 1, &[1]).unwrap_err();
+        assert!(err.contains("donated argument"), "{err}");
+    }
+
+    #[test]
+    fn task_lists_encode_as_parallel_arrays() {
+        let tasks = TaskInfo::zip(&[0, 3], &[11, 12]).unwrap();
+        assert_eq!(
+            tasks[1],
+            TaskInfo {
+                task_id: 3,
+                incarnation_id: 12
+            }
+        );
+        assert_eq!(encode_tasks(&tasks).unwrap(), (vec![0, 3], vec![11, 12]));
+        assert_eq!(encode_tasks(&[]).unwrap(), (vec![], vec![]));
+
+        let err = TaskInfo::zip(&[0, 1], &[5]).unwrap_err();
+        assert!(err.contains("2 task ids but 1 incarnation ids"), "{err}");
+        let too_big = TaskInfo {
+            task_id: i64::MAX,
+            incarnation_id: 0,
+        };
+        assert!(encode_tasks(&[too_big]).is_err());
+    }
+
+    #[test]
+    fn output_dims_split_by_rank() {
+        assert_eq!(
+            split_output_dims(&[2, 3, 4], &[0, 2, 0, 1]).unwrap(),
+            vec![vec![], vec![2, 3], vec![], vec![4]]
+        );
+        assert_eq!(split_output_dims(&[], &[]).unwrap(), Vec::<Vec<i64>>::new());
+        assert!(split_output_dims(&[2], &[2]).is_err());
+        assert!(split_output_dims(&[2, 3], &[1]).is_err());
+    }
+
+    #[test]
+    fn output_dimension_reads_the_flat_dims_array() {
+        assert_eq!(first_flat_output_dim(&[vec![5, 6]]).unwrap(), 5);
+        assert_eq!(first_flat_output_dim(&[vec![], vec![2, 3]]).unwrap(), 2);
+        assert!(first_flat_output_dim(&[]).is_err());
+        assert!(first_flat_output_dim(&[vec![], vec![]]).is_err());
+    }
+
+    #[test]
+    fn cost_properties_read_numeric_values() {
+        let properties = vec![
+            PJRTNamedAttribute {
+                name: "flops".to_string(),
+                value: PJRTNamedValue::Float(12.0),
+            },
+            PJRTNamedAttribute {
+                name: "bytes accessed".to_string(),
+                value: PJRTNamedValue::Int64(64),
+            },
+            PJRTNamedAttribute {
+                name: "note".to_string(),
+                value: PJRTNamedValue::String("x".to_string()),
+            },
+        ];
+        assert_eq!(numeric_property(&properties, "flops"), Some(12.0));
+        assert_eq!(numeric_property(&properties, "bytes accessed"), Some(64.0));
+        assert_eq!(numeric_property(&properties, "note"), None);
+        assert_eq!(numeric_property(&properties, "missing"), None);
+        assert_eq!(
+            named_value_text(&PJRTNamedValue::Int64List(vec![1, 2])),
+            "[1, 2]"
+        );
+    }
+
+    #[test]
+    fn call_location_is_nul_terminated() {
+        let encoded = encode_call_location(Some("model.py:42")).unwrap().unwrap();
+        assert_eq!(encoded.as_bytes_with_nul(), b"model.py:42\0");
+        assert_eq!(encode_call_location(None).unwrap(), None);
+        assert!(encode_call_location(Some("a\0b")).is_err());
+    }
+}
@@ -0,0 +1,144 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::pjrt::client::PJRTClient;
+use crate::pjrt::compile::ProgramFormat;
+use crate::pjrt::error::{io_error_code, OwnedPjrtError, PjrtResult};
+use crate::pjrt::executable::PJRTLoadedExecutable;
+
+// Directory of serialized executables, one file per (program, options, plugin) key. Entries
+// are written in the executable file format, so they carry their own platform header.
+pub struct ExecutableCache {
+    dir: PathBuf,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl ExecutableCache {
+    pub fn new(dir: impl Into<PathBuf>) -> PjrtResult<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir).map_err(|e| {
+            OwnedPjrtError::new(
+                io_error_code(&e),
+                format!("failed to create cache directory {}: {e}", dir.display()),
+            )
+        })?;
+        Ok(Self {
+            dir,
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn misses(&self) -> usize {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    pub fn entry_path(
+        &self,
+        client: &PJRTClient<'_>,
+        code: &str,
+        format: ProgramFormat,
+        options: &[u8],
+    ) -> PjrtResult<PathBuf> {
+        let platform = client.platform_name()?;
+        let version = client.platform_version()?;
+        let api_minor = client.rt.api().pjrt_api_version.minor_version.to_string();
+        let key = cache_key(&[
+            code.as_bytes(),
+            format.as_str().as_bytes(),
+            options,
+            platform.as_bytes(),
+            version.as_bytes(),
+            api_minor.as_bytes(),
+        ]);
+        Ok(self.dir.join(format!("{key:016x}.rradexe")))
+    }
+
+    // Unreadable or stale entries are recompiled and overwritten; failing to write the
+    // cache only costs the next process a compile.
+    pub fn get_or_compile<'a>(
+        &self,
+        client: &PJRTClient<'a>,
+        code: &str,
+        format: ProgramFormat,
+        options: &[u8],
+    ) -> PjrtResult<PJRTLoadedExecutable<'a>> {
+        let path = self.entry_path(client, code, format, options)?;
+        if path.is_file() {
+            match client.load_executable_file_with_header(&path, false) {
+                Ok((header, executable)) => match executable.identity() {
+                    // Entries are written with the executable's identity; anything else was
+                    // produced by a different compiler and is recompiled.
+                    Ok(id) if id.as_str() == header.fingerprint => {
+                        self.hits.fetch_add(1, Ordering::Relaxed);
+                        return Ok(executable);
+                    }
+                    Ok(id) => log::warn!(
+                        "ignoring cache entry {}: restored executable is {id}, entry records {}",
+                        path.display(),
+                        header.fingerprint
+                    ),
+                    Err(e) => log::warn!("ignoring cache entry {}: {e}", path.display()),
+                },
+                Err(e) => log::warn!("ignoring cache entry {}: {e}", path.display()),
+            }
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let executable = client.compile(code, format, options)?;
+        // Write to a temporary name first so concurrent readers never see a partial file.
+        let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+        let written = executable
+            .save_to_file(client, &tmp)
+            .map_err(|e| e.to_string())
+            .and_then(|_| std::fs::rename(&tmp, &path).map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            log::warn!("failed to write cache entry {}: {e}", path.display());
+            let _ = std::fs::remove_file(&tmp);
+        }
+        Ok(executable)
+    }
+}
+
+// FNV-1a over length-prefixed parts, so ("ab", "c") and ("a", "bc") hash differently. It
+// is stable across Rust releases, unlike std's DefaultHasher.
+fn cache_key(parts: &[&[u8]]) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let mut hash = OFFSET;
+    for part in parts {
+        let len = (part.len() as u64).to_le_bytes();
+        for &byte in len.iter().chain(part.iter()) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(PRIME);
+        }
+    }
+    hash
+}
+
+#[cfg(test)]
+mod executable_cache_tests {
+    use super::cache_key;
+
+    #[test]
+    fn cache_key_separates_fields() {
+        assert_eq!(cache_key(&[b"abc", b""]), cache_key(&[b"abc", b""]));
+        assert_ne!(cache_key(&[b"ab", b"c"]), cache_key(&[b"a", b"bc"]));
+        assert_ne!(
+            cache_key(&[b"code", b"mlir"]),
+            cache_key(&[b"code", b"hlo"])
+        );
+        // Pinned so cache directories stay valid across toolchain upgrades.
+        assert_eq!(cache_key(&[]), 0xcbf2_9ce4_8422_2325);
+    }
+}
@@ -0,0 +1,221 @@
+use std::fmt;
+
+use crate::pjrt::error::{io_error_code, OwnedPjrtError, PjrtErrorCode};
+
+const MAGIC: &[u8; 8] = b"RRADXEXE";
+const FORMAT_VERSION: u32 = 1;
+
+// Recorded next to the serialized executable so a file is only loaded into a matching
+// runtime.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutableFileHeader {
+    pub platform_name: String,
+    pub api_major: i32,
+    pub api_minor: i32,
+    pub fingerprint: String,
+}
+
+#[derive(Debug)]
+pub enum ExecutableFileError {
+    Io(std::io::Error),
+    Format(String),
+    PlatformMismatch { expected: String, found: String },
+    Serialize(String),
+    Deserialize(String),
+}
+
+impl fmt::Display for ExecutableFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecutableFileError::Io(e) => write!(f, "executable file I/O failed: {e}"),
+            ExecutableFileError::Format(e) => write!(f, "malformed executable file: {e}"),
+            ExecutableFileError::PlatformMismatch { expected, found } => write!(
+                f,
+                "executable file was built for platform {found:?} but the client is {expected:?}"
+            ),
+            ExecutableFileError::Serialize(e) => write!(f, "failed to serialize executable: {e}"),
+            ExecutableFileError::Deserialize(e) => {
+                write!(f, "failed to deserialize executable: {e}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExecutableFileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ExecutableFileError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ExecutableFileError> for String {
+    fn from(e: ExecutableFileError) -> Self {
+        e.to_string()
+    }
+}
+
+impl From<ExecutableFileError> for OwnedPjrtError {
+    fn from(e: ExecutableFileError) -> Self {
+        let code = match &e {
+            ExecutableFileError::Io(io) => io_error_code(io),
+            ExecutableFileError::Format(_) => PjrtErrorCode::InvalidArgument,
+            ExecutableFileError::PlatformMismatch { .. } => PjrtErrorCode::FailedPrecondition,
+            ExecutableFileError::Serialize(_) | ExecutableFileError::Deserialize(_) => {
+                return OwnedPjrtError::from(e.to_string())
+            }
+        };
+        OwnedPjrtError::new(code, e.to_string())
+    }
+}
+
+// Layout: magic, u32 format version, length-prefixed platform name, i32 API major and
+// minor, length-prefixed fingerprint, then the serialized executable. Integers are
+// little-endian; lengths are u32.
+pub fn encode_executable_file(
+    header: &ExecutableFileHeader,
+    serialized: &[u8],
+) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(
+        MAGIC.len() + 24 + header.platform_name.len() + header.fingerprint.len() + serialized.len(),
+    );
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    push_str(&mut out, "platform name", &header.platform_name)?;
+    out.extend_from_slice(&header.api_major.to_le_bytes());
+    out.extend_from_slice(&header.api_minor.to_le_bytes());
+    push_str(&mut out, "fingerprint", &header.fingerprint)?;
+    out.extend_from_slice(serialized);
+    Ok(out)
+}
+
+// Shared with the topology file format.
+pub(crate) fn push_str(out: &mut Vec<u8>, field: &str, value: &str) -> Result<(), String> {
+    let len =
+        u32::try_from(value.len()).map_err(|_| format!("{field} is too long for a file header"))?;
+    out.extend_from_slice(&len.to_le_bytes());
+    out.extend_from_slice(value.as_bytes());
+    Ok(())
+}
+
+pub(crate) struct Reader<'b> {
+    pub(crate) rest: &'b [u8],
+}
+
+impl<'b> Reader<'b> {
+    fn take(&mut self, n: usize, what: &str) -> Result<&'b [u8], String> {
+        if self.rest.len() < n {
+            return Err(format!("truncated file while reading {what}"));
+        }
+        let (head, tail) = self.rest.split_at(n);
+        self.rest = tail;
+        Ok(head)
+    }
+
+    pub(crate) fn u32(&mut self, what: &str) -> Result<u32, String> {
+        let b = self.take(4, what)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    pub(crate) fn string(&mut self, what: &str) -> Result<String, String> {
+        let len = self.u32(what)? as usize;
+        Ok(String::from_utf8_lossy(self.take(len, what)?).into_owned())
+    }
+}
+
+pub fn decode_executable_file(bytes: &[u8]) -> Result<(ExecutableFileHeader, &[u8]), String> {
+    let rest = bytes
+        .strip_prefix(MAGIC.as_slice())
+        .ok_or("missing executable file magic")?;
+    let mut reader = Reader { rest };
+
+    let version = reader.u32("format version")?;
+    if version != FORMAT_VERSION {
+        return Err(format!("unsupported executable file version {version}"));
+    }
+    let header = ExecutableFileHeader {
+        platform_name: reader.string("platform name")?,
+        api_major: reader.u32("API major version")? as i32,
+        api_minor: reader.u32("API minor version")? as i32,
+        fingerprint: reader.string("fingerprint")?,
+    };
+    Ok((header, reader.rest))
+}
+
+#[cfg(test)]
+mod executable_file_tests {
+    use super::*;
+
+    fn header() -> ExecutableFileHeader {
+        ExecutableFileHeader {
+            platform_name: "cpu".to_string(),
+            api_major: 0,
+            api_minor: 75,
+            fingerprint: "abc123".to_string(),
+        }
+    }
+
+    #[test]
+    fn header_round_trips() {
+        let file = encode_executable_file(&header(), b"payload").unwrap();
+        let (decoded, payload) = decode_executable_file(&file).unwrap();
+        assert_eq!(decoded, header());
+        assert_eq!(payload, b"payload");
+
+        let empty = ExecutableFileHeader {
+            platform_name: String::new(),
+            fingerprint: String::new(),
+            ..header()
+        };
+        let file = encode_executable_file(&empty, &[]).unwrap();
+        assert_eq!(decode_executable_file(&file).unwrap(), (empty, &[][..]));
+    }
+
+    #[test]
+    fn file_errors_map_to_codes() {
+        let missing = std::io::Error::from(std::io::ErrorKind::NotFound);
+        let cases = [
+            (ExecutableFileError::Io(missing), PjrtErrorCode::NotFound),
+            (
+                ExecutableFileError::Format("bad magic".into()),
+                PjrtErrorCode::InvalidArgument,
+            ),
+            (
+                ExecutableFileError::PlatformMismatch {
+                    expected: "cpu".into(),
+                    found: "tpu".into(),
+                },
+                PjrtErrorCode::FailedPrecondition,
+            ),
+            (
+                ExecutableFileError::Deserialize(
+                    "PJRT_Executable_DeserializeAndLoad symbol not found".into(),
+                ),
+                PjrtErrorCode::Unimplemented,
+            ),
+        ];
+        for (err, code) in cases {
+            let message = err.to_string();
+            let owned = OwnedPjrtError::from(err);
+            assert_eq!(owned.code(), code, "{message}");
+            assert_eq!(owned.message(), message);
+        }
+    }
+
+    #[test]
+    fn rejects_foreign_and_truncated_files() {
+        assert!(decode_executable_file(b"not an executable")
+            .unwrap_err()
+            .contains("magic"));
+
+        let file = encode_executable_file(&header(), b"payload").unwrap();
+        let err = decode_executable_file(&file[..MAGIC.len() + 10]).unwrap_err();
+        assert!(err.contains("truncated"), "{err}");
+
+        let mut future = file.clone();
+        future[MAGIC.len()] = 9;
+        let err = decode_executable_file(&future).unwrap_err();
+        assert!(err.contains("version 9"), "{err}");
+    }
+}
@@ -0,0 +1,413 @@
+use std::ffi::c_void;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ptr;
+use std::slice::from_raw_parts;
+use std::sync::{Arc, Mutex};
+
+use crate::pjrt::copy_to_device_stream::PjrtCopyToDeviceStream;
+use crate::pjrt::loader::PjrtRuntime;
+use crate::pjrt_sys::*;
+
+// A chunk handed to a send callback. The callback owns it, so the chunk's deleter runs
+// exactly once: when this wrapper is dropped, unless `into_raw` takes the chunk over.
+pub struct PjrtChunk<'cb> {
+    raw: PJRT_Chunk,
+    _callback: PhantomData<&'cb mut PJRT_Chunk>,
+}
+
+impl PjrtChunk<'_> {
+    // Takes ownership of `*chunk`; a null pointer yields an empty chunk.
+    unsafe fn from_raw(chunk: *mut PJRT_Chunk) -> Self {
+        let raw = match unsafe { chunk.as_ref() } {
+            Some(chunk) => *chunk,
+            None => PJRT_Chunk {
+                data: ptr::null_mut(),
+                size: 0,
+                deleter: None,
+                deleter_arg: ptr::null_mut(),
+            },
+        };
+        Self {
+            raw,
+            _callback: PhantomData,
+        }
+    }
+
+    pub fn data(&self) -> &[u8] {
+        if self.raw.data.is_null() || self.raw.size == 0 {
+            &[]
+        } else {
+            unsafe { from_raw_parts(self.raw.data as *const u8, self.raw.size) }
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.raw.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.raw.size == 0
+    }
+
+    // Hands the chunk back without running its deleter; the caller must call it later.
+    pub fn into_raw(self) -> PJRT_Chunk {
+        let raw = self.raw;
+        std::mem::forget(self);
+        raw
+    }
+}
+
+impl Drop for PjrtChunk<'_> {
+    fn drop(&mut self) {
+        if let Some(deleter) = self.raw.deleter {
+            unsafe { deleter(self.raw.data, self.raw.deleter_arg) };
+        }
+    }
+}
+
+pub struct PJRTSendCallbackInvocation<'cb> {
+    pub channel_id: i64,
+    pub chunk: PjrtChunk<'cb>,
+    pub total_size_in_bytes: usize,
+    pub done: bool,
+}
+
+// The callback owns `stream`; it is destroyed when the invocation is dropped, so all chunks
+// must be added before the callback returns.
+pub struct PJRTRecvCallbackInvocation<'cb> {
+    pub channel_id: i64,
+    pub stream: PjrtCopyToDeviceStream<'cb>,
+}
+
+pub type PJRTSendCallbackFn =
+    Box<dyn FnMut(PJRTSendCallbackInvocation<'_>) -> Result<(), String> + Send>;
+pub type PJRTRecvCallbackFn =
+    Box<dyn FnMut(PJRTRecvCallbackInvocation<'_>) -> Result<(), String> + Send>;
+
+// The runtime may call back from several threads (one per device), so each closure sits
+// behind a mutex. Registrations are shared by every launch made with the same options.
+#[derive(Clone)]
+pub(crate) struct SendRegistration {
+    pub(crate) channel_id: i64,
+    pub(crate) callback: Arc<Mutex<PJRTSendCallbackFn>>,
+}
+
+#[derive(Clone)]
+pub(crate) struct RecvRegistration {
+    pub(crate) channel_id: i64,
+    pub(crate) callback: Arc<Mutex<PJRTRecvCallbackFn>>,
+}
+
+impl fmt::Debug for SendRegistration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SendRegistration")
+            .field("channel_id", &self.channel_id)
+            .finish()
+    }
+}
+
+impl fmt::Debug for RecvRegistration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RecvRegistration")
+            .field("channel_id", &self.channel_id)
+            .finish()
+    }
+}
+
+// Keeps the first error any callback reported; later ones are usually fallout from it.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CallbackErrors(Arc<Mutex<Option<String>>>);
+
+impl CallbackErrors {
+    pub(crate) fn record(&self, message: String) {
+        if let Ok(mut first) = self.0.lock() {
+            first.get_or_insert(message);
+        }
+    }
+
+    pub(crate) fn take(&self) -> Option<String> {
+        self.0.lock().ok().and_then(|mut first| first.take())
+    }
+}
+
+struct SendEntry {
+    registration: SendRegistration,
+    errors: CallbackErrors,
+}
+
+struct RecvEntry {
+    rt: *const PjrtRuntime,
+    registration: RecvRegistration,
+    errors: CallbackErrors,
+}
+
+unsafe extern "C" fn send_trampoline(
+    chunk: *mut PJRT_Chunk,
+    callback_error: *mut PJRT_CallbackError,
+    total_size_in_bytes: usize,
+    done: bool,
+    user_arg: *mut c_void,
+) -> *mut PJRT_Error {
+    if user_arg.is_null() {
+        return ptr::null_mut();
+    }
+    let entry = unsafe { &*user_arg.cast::<SendEntry>() };
+    let channel_id = entry.registration.channel_id;
+    let result = match entry.registration.callback.lock() {
+        Ok(mut callback) => callback(PJRTSendCallbackInvocation {
+            channel_id,
+            chunk: unsafe { PjrtChunk::from_raw(chunk) },
+            total_size_in_bytes,
+            done,
+        }),
+        Err(_) => Err("callback mutex poisoned by an earlier panic".to_string()),
+    };
+    let Err(message) = result else {
+        return ptr::null_mut();
+    };
+
+    let message = format!("send callback for channel {channel_id} failed: {message}");
+    entry.errors.record(message.clone());
+    match unsafe { callback_error.as_ref() }.and_then(|f| *f) {
+        Some(make_error) => unsafe {
+            make_error(
+                PJRT_Error_Code_PJRT_Error_Code_INTERNAL,
+                message.as_ptr() as *const libc::c_char,
+                message.len(),
+            )
+        },
+        None => ptr::null_mut(),
+    }
+}
+
+unsafe extern "C" fn recv_trampoline(stream: *mut PJRT_CopyToDeviceStream, user_arg: *mut c_void) {
+    if user_arg.is_null() {
+        return;
+    }
+    let entry = unsafe { &*user_arg.cast::<RecvEntry>() };
+    let channel_id = entry.registration.channel_id;
+    let result = match (
+        unsafe { entry.rt.as_ref() },
+        entry.registration.callback.lock(),
+    ) {
+        (Some(rt), Ok(mut callback)) => callback(PJRTRecvCallbackInvocation {
+            channel_id,
+            stream: PjrtCopyToDeviceStream::new(rt, stream),
+        }),
+        (None, _) => Err("no runtime registered for the recv callback".to_string()),
+        (_, Err(_)) => Err("callback mutex poisoned by an earlier panic".to_string()),
+    };
+    // Recv callbacks have no error channel back into the runtime.
+    if let Err(message) = result {
+        entry.errors.record(format!(
+            "recv callback for channel {channel_id} failed: {message}"
+        ));
+    }
+}
+
+// Owns everything PJRT_ExecuteOptions points at for send/recv ops. The runtime can invoke
+// callbacks until every device's completion event fires, so this must outlive the launch.
+pub(crate) struct ExecuteCallbackKeepalive {
+    _send_entries: Vec<SendEntry>,
+    _recv_entries: Vec<RecvEntry>,
+    send_infos: Vec<PJRT_SendCallbackInfo>,
+    recv_infos: Vec<PJRT_RecvCallbackInfo>,
+    send_lists: Vec<*mut PJRT_SendCallbackInfo>,
+    recv_lists: Vec<*mut PJRT_RecvCallbackInfo>,
+}
+
+// The raw pointers reference the vectors owned here, which are never resized, and the
+// runtime, which outlives every launch made through it.
+unsafe impl Send for ExecuteCallbackKeepalive {}
+unsafe impl Sync for ExecuteCallbackKeepalive {}
+
+impl ExecuteCallbackKeepalive {
+    pub(crate) fn new(
+        rt: *const PjrtRuntime,
+        sends: &[SendRegistration],
+        recvs: &[RecvRegistration],
+        errors: &CallbackErrors,
+        num_devices: usize,
+    ) -> Self {
+        let send_entries: Vec<SendEntry> = sends
+            .iter()
+            .map(|registration| SendEntry {
+                registration: registration.clone(),
+                errors: errors.clone(),
+            })
+            .collect();
+        let recv_entries: Vec<RecvEntry> = recvs
+            .iter()
+            .map(|registration| RecvEntry {
+                rt,
+                registration: registration.clone(),
+                errors: errors.clone(),
+            })
+            .collect();
+
+        let mut send_infos: Vec<PJRT_SendCallbackInfo> = send_entries
+            .iter()
+            .map(|entry| PJRT_SendCallbackInfo {
+                channel_id: entry.registration.channel_id,
+                user_arg: entry as *const SendEntry as *mut c_void,
+                send_callback: Some(send_trampoline),
+            })
+            .collect();
+        let mut recv_infos: Vec<PJRT_RecvCallbackInfo> = recv_entries
+            .iter()
+            .map(|entry| PJRT_RecvCallbackInfo {
+                channel_id: entry.registration.channel_id,
+                user_arg: entry as *const RecvEntry as *mut c_void,
+                recv_callback: Some(recv_trampoline),
+            })
+            .collect();
+
+        // Every device runs the same callbacks, so all per-device lists share one array.
+        let send_lists = vec![send_infos.as_mut_ptr(); num_devices];
+        let recv_lists = vec![recv_infos.as_mut_ptr(); num_devices];
+
+        Self {
+            _send_entries: send_entries,
+            _recv_entries: recv_entries,
+            send_infos,
+            recv_infos,
+            send_lists,
+            recv_lists,
+        }
+    }
+
+    pub(crate) fn num_send_ops(&self) -> usize {
+        self.send_infos.len()
+    }
+
+    pub(crate) fn num_recv_ops(&self) -> usize {
+        self.recv_infos.len()
+    }
+
+    pub(crate) fn send_callbacks(&self) -> *mut *mut PJRT_SendCallbackInfo {
+        if self.send_infos.is_empty() {
+            ptr::null_mut()
+        } else {
+            self.send_lists.as_ptr() as *mut _
+        }
+    }
+
+    pub(crate) fn recv_callbacks(&self) -> *mut *mut PJRT_RecvCallbackInfo {
+        if self.recv_infos.is_empty() {
+            ptr::null_mut()
+        } else {
+            self.recv_lists.as_ptr() as *mut _
+        }
+    }
+}
+
+#[cfg(test)]
+mod execute_callbacks_tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn send_trampoline_runs_closure_and_keeps_first_error() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        let callback: PJRTSendCallbackFn = Box::new(move |invocation| {
+            sink.lock().unwrap().push(invocation.total_size_in_bytes);
+            if invocation.done {
+                Err(format!("rejected {} bytes", invocation.total_size_in_bytes))
+            } else {
+                Ok(())
+            }
+        });
+        let errors = CallbackErrors::default();
+        let keepalive = ExecuteCallbackKeepalive::new(
+            ptr::null(),
+            &[SendRegistration {
+                channel_id: 7,
+                callback: Arc::new(Mutex::new(callback)),
+            }],
+            &[],
+            &errors,
+            2,
+        );
+        assert_eq!(keepalive.num_send_ops(), 1);
+        assert_eq!(keepalive.num_recv_ops(), 0);
+        assert!(keepalive.recv_callbacks().is_null());
+
+        let lists = keepalive.send_callbacks();
+        let (first, second) = unsafe { (**lists, **lists.add(1)) };
+        assert_eq!(first.channel_id, 7);
+        assert_eq!(first.user_arg, second.user_arg);
+
+        let send = first.send_callback.unwrap();
+        let ok = unsafe { send(ptr::null_mut(), ptr::null_mut(), 4, false, first.user_arg) };
+        assert!(ok.is_null());
+        let failed = unsafe { send(ptr::null_mut(), ptr::null_mut(), 8, true, first.user_arg) };
+        assert!(failed.is_null());
+        unsafe { send(ptr::null_mut(), ptr::null_mut(), 16, true, first.user_arg) };
+
+        assert_eq!(*seen.lock().unwrap(), vec![4, 8, 16]);
+        assert_eq!(
+            errors.take().as_deref(),
+            Some("send callback for channel 7 failed: rejected 8 bytes")
+        );
+        assert_eq!(errors.take(), None);
+    }
+
+    static DELETED: AtomicUsize = AtomicUsize::new(0);
+
+    unsafe extern "C" fn count_deleter(data: *mut c_void, deleter_arg: *mut c_void) {
+        assert_eq!(data, deleter_arg);
+        DELETED.fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn send_chunks_expose_bytes_and_run_deleter_once() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&received);
+        let callback: PJRTSendCallbackFn = Box::new(move |invocation| {
+            assert_eq!(invocation.chunk.size(), invocation.chunk.data().len());
+            sink.lock().unwrap().push(invocation.chunk.data().to_vec());
+            Ok(())
+        });
+        let keepalive = ExecuteCallbackKeepalive::new(
+            ptr::null(),
+            &[SendRegistration {
+                channel_id: 3,
+                callback: Arc::new(Mutex::new(callback)),
+            }],
+            &[],
+            &CallbackErrors::default(),
+            1,
+        );
+        let info = unsafe { **keepalive.send_callbacks() };
+        let send = info.send_callback.unwrap();
+
+        let mut payloads = [vec![1u8, 2, 3], vec![4u8]];
+        for payload in payloads.iter_mut() {
+            let data = payload.as_mut_ptr().cast::<c_void>();
+            let mut chunk = PJRT_Chunk {
+                data,
+                size: payload.len(),
+                deleter: Some(count_deleter),
+                deleter_arg: data,
+            };
+            let err = unsafe { send(&mut chunk, ptr::null_mut(), 4, false, info.user_arg) };
+            assert!(err.is_null());
+        }
+
+        assert_eq!(*received.lock().unwrap(), vec![vec![1, 2, 3], vec![4]]);
+        assert_eq!(DELETED.load(Ordering::SeqCst), 2);
+
+        let data = payloads[0].as_mut_ptr().cast::<c_void>();
+        let mut chunk = PJRT_Chunk {
+            data,
+            size: 3,
+            deleter: Some(count_deleter),
+            deleter_arg: data,
+        };
+        let raw = unsafe { PjrtChunk::from_raw(&mut chunk) }.into_raw();
+        assert_eq!(DELETED.load(Ordering::SeqCst), 2);
+        assert_eq!(raw.size, 3);
+    }
+}
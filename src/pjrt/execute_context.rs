@@ -1,13 +1,94 @@
+use std::ffi::c_void;
+use std::mem;
 use std::ptr;
+use std::sync::Arc;
 
-use crate::pjrt::loader::{error_to_string, PjrtRuntime};
+use crate::pjrt::error::{
+    extension_missing, plugin_error, OwnedPjrtError, PjrtErrorCode, PjrtResult,
+};
+use crate::pjrt::ffi::{
+    find_ffi_extension, FfiExtension, FfiUserData, FfiUserDataAddArgs, FfiUserDataAddFn,
+    FfiUserDataDeleter,
+};
+use crate::pjrt::loader::{error_to_string, extension_has_field, PjrtRuntime};
 use crate::pjrt_sys::*;
 
+// PJRT_FFI_UserData_Add from the extension chain starting at `start`, if present and new
+// enough to carry it.
+unsafe fn user_data_add_fn(start: *const PJRT_Extension_Base) -> Option<FfiUserDataAddFn> {
+    let ext = unsafe { find_ffi_extension(start)? };
+    if !extension_has_field(&ext.base, mem::offset_of!(FfiExtension, user_data_add)) {
+        return None;
+    }
+    ext.user_data_add
+}
+
+unsafe extern "C" fn drop_boxed<T>(data: *mut c_void) {
+    if !data.is_null() {
+        drop(unsafe { Box::from_raw(data.cast::<T>()) });
+    }
+}
+
+// The PJRT context itself, shared by the owning PJRTExecuteContext, run options that name
+// it, and launches that have not completed. The last reference destroys it.
+#[derive(Debug)]
+pub(crate) struct ContextHandle {
+    api: *const PJRT_Api,
+    raw: *mut PJRT_ExecuteContext,
+}
+
+// Launches only read the context, and it is only mutated through a unique reference (see
+// PJRTExecuteContext::add_user_data). The API table outlives every context made from it.
+unsafe impl Send for ContextHandle {}
+unsafe impl Sync for ContextHandle {}
+
+impl ContextHandle {
+    pub(crate) fn raw(&self) -> *mut PJRT_ExecuteContext {
+        self.raw
+    }
+
+    fn destroy(&mut self) -> Result<(), String> {
+        let raw = mem::replace(&mut self.raw, ptr::null_mut());
+        if raw.is_null() {
+            return Ok(());
+        }
+        let api = unsafe { &*self.api };
+        let f = api
+            .PJRT_ExecuteContext_Destroy
+            .ok_or("PJRT_ExecuteContext_Destroy symbol not found")?;
+
+        let mut args = PJRT_ExecuteContext_Destroy_Args {
+            struct_size: PJRT_ExecuteContext_Destroy_Args_STRUCT_SIZE as usize,
+            extension_start: ptr::null_mut(),
+            context: raw,
+        };
+
+        let err = unsafe { f(&mut args) };
+        if err.is_null() {
+            Ok(())
+        } else {
+            Err(error_to_string(api, err))
+        }
+    }
+}
+
+impl Drop for ContextHandle {
+    fn drop(&mut self) {
+        let _ = self.destroy();
+    }
+}
+
+// Safe to share between threads and between overlapping launches: launches hold their own
+// reference until they complete, so the context outlives them even if this value is dropped
+// or destroyed first. User data can only be added while nothing else holds the context.
 pub struct PJRTExecuteContext<'a> {
     rt: &'a PjrtRuntime,
-    raw: *mut PJRT_ExecuteContext,
+    handle: Option<Arc<ContextHandle>>,
 }
 
+unsafe impl Send for PJRTExecuteContext<'_> {}
+unsafe impl Sync for PJRTExecuteContext<'_> {}
+
 impl<'a> PJRTExecuteContext<'a> {
     pub fn create(rt: &'a PjrtRuntime) -> Result<Self, String> {
         let f = rt
@@ -31,40 +112,224 @@ impl<'a> PJRTExecuteContext<'a> {
 
         Ok(Self {
             rt,
-            raw: args.context,
+            handle: Some(Arc::new(ContextHandle {
+                api: rt.api(),
+                raw: args.context,
+            })),
         })
     }
 
     pub fn raw(&self) -> *mut PJRT_ExecuteContext {
-        self.raw
+        self.handle.as_ref().map_or(ptr::null_mut(), |h| h.raw)
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.handle.is_some()
     }
 
-    pub fn into_raw(self) -> *mut PJRT_ExecuteContext {
-        let raw = self.raw;
-        std::mem::forget(self);
+    // Another reference to the context for run options. Once destroyed this is a null
+    // handle, which the launch rejects.
+    pub(crate) fn share(&self) -> Arc<ContextHandle> {
+        self.handle.clone().unwrap_or_else(|| {
+            Arc::new(ContextHandle {
+                api: ptr::null(),
+                raw: ptr::null_mut(),
+            })
+        })
+    }
+
+    // Gives up ownership without destroying the context; the caller must destroy it with
+    // PJRT_ExecuteContext_Destroy once no launch uses it.
+    pub fn into_raw(mut self) -> *mut PJRT_ExecuteContext {
+        let raw = self.raw();
+        if let Some(handle) = self.handle.take() {
+            mem::forget(handle);
+        }
         raw
     }
+
+    // Destroys the context now if nothing else references it. Otherwise this releases only
+    // this reference and the last run options or launch destroys it.
+    pub fn destroy(&mut self) -> Result<(), String> {
+        match self.handle.take().map(Arc::try_unwrap) {
+            Some(Ok(mut handle)) => handle.destroy(),
+            Some(Err(_)) | None => Ok(()),
+        }
+    }
+
+    // Makes `data` visible to FFI custom calls that look up `type_id` in this context. The
+    // context does not own `data`; it must stay valid for every execution using the context.
+    pub fn add_user_data(&mut self, type_id: i64, data: *mut c_void) -> PjrtResult<()> {
+        self.add_user_data_with_deleter(type_id, data, None)
+    }
+
+    // Moves `value` into the context; it is dropped when the context is destroyed. Concurrent
+    // launches sharing the context hand it to custom calls on several threads at once, hence
+    // Sync as well as Send.
+    pub fn add_user_value<T: Send + Sync + 'static>(
+        &mut self,
+        type_id: i64,
+        value: T,
+    ) -> PjrtResult<()> {
+        let data = Box::into_raw(Box::new(value)).cast::<c_void>();
+        let result = self.add_user_data_with_deleter(type_id, data, Some(drop_boxed::<T>));
+        if result.is_err() {
+            unsafe { drop_boxed::<T>(data) };
+        }
+        result
+    }
+
+    fn add_user_data_with_deleter(
+        &mut self,
+        type_id: i64,
+        data: *mut c_void,
+        deleter: Option<FfiUserDataDeleter>,
+    ) -> PjrtResult<()> {
+        let handle = self.handle.as_mut().ok_or_else(|| {
+            OwnedPjrtError::new(
+                PjrtErrorCode::FailedPrecondition,
+                "execute context was destroyed",
+            )
+        })?;
+        // A launch could be reading the context right now.
+        let handle = Arc::get_mut(handle).ok_or_else(|| {
+            OwnedPjrtError::new(
+                PjrtErrorCode::FailedPrecondition,
+                "execute context is shared with run options or a running launch",
+            )
+        })?;
+
+        let start = self.rt.api().extension_start;
+        let add = unsafe { user_data_add_fn(start) }
+            .ok_or_else(|| extension_missing("the PJRT FFI user data extension"))?;
+
+        let mut args = FfiUserDataAddArgs {
+            struct_size: mem::size_of::<FfiUserDataAddArgs>(),
+            extension_start: ptr::null_mut(),
+            context: handle.raw,
+            user_data: FfiUserData {
+                type_id,
+                data,
+                deleter,
+            },
+        };
+
+        let err = unsafe { add(&mut args) };
+        if err.is_null() {
+            Ok(())
+        } else {
+            Err(plugin_error(self.rt.api(), "PJRT_FFI_UserData_Add", err))
+        }
+    }
 }
 
-impl Drop for PJRTExecuteContext<'_> {
-    fn drop(&mut self) {
-        if self.raw.is_null() {
-            return;
+#[cfg(test)]
+mod execute_context_tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    // Stands in for a plugin's FFI extension: remembers what was registered.
+    static REGISTERED: Mutex<Vec<(i64, usize)>> = Mutex::new(Vec::new());
+
+    unsafe extern "C" fn record_user_data(args: *mut FfiUserDataAddArgs) -> *mut PJRT_Error {
+        let args = unsafe { &*args };
+        assert_eq!(args.struct_size, mem::size_of::<FfiUserDataAddArgs>());
+        REGISTERED
+            .lock()
+            .unwrap()
+            .push((args.user_data.type_id, args.user_data.data as usize));
+        ptr::null_mut()
+    }
+
+    fn base(ty: PJRT_Extension_Type, size: usize) -> PJRT_Extension_Base {
+        PJRT_Extension_Base {
+            struct_size: size,
+            type_: ty,
+            next: ptr::null_mut(),
         }
+    }
 
-        let Some(f) = self.rt.api().PJRT_ExecuteContext_Destroy else {
-            return;
+    #[test]
+    fn finds_user_data_add_behind_other_extensions() {
+        let mut ffi = FfiExtension {
+            base: base(
+                PJRT_Extension_Type_PJRT_Extension_Type_FFI,
+                mem::size_of::<FfiExtension>(),
+            ),
+            type_id_register: None,
+            user_data_add: Some(record_user_data),
+            register_handler: None,
         };
+        let mut other = base(1, mem::size_of::<PJRT_Extension_Base>());
+        other.next = ptr::addr_of_mut!(ffi).cast();
 
-        let mut args = PJRT_ExecuteContext_Destroy_Args {
-            struct_size: PJRT_ExecuteContext_Destroy_Args_STRUCT_SIZE as usize,
+        let add = unsafe { user_data_add_fn(ptr::addr_of!(other)) }.unwrap();
+        let mut marker = 7u32;
+        let mut args = FfiUserDataAddArgs {
+            struct_size: mem::size_of::<FfiUserDataAddArgs>(),
             extension_start: ptr::null_mut(),
-            context: self.raw,
+            context: ptr::null_mut(),
+            user_data: FfiUserData {
+                type_id: 42,
+                data: ptr::addr_of_mut!(marker).cast(),
+                deleter: None,
+            },
         };
+        assert!(unsafe { add(&mut args) }.is_null());
+        assert!(REGISTERED
+            .lock()
+            .unwrap()
+            .contains(&(42, ptr::addr_of!(marker) as usize)));
+    }
 
-        let err = unsafe { f(&mut args) };
-        if !err.is_null() {
-            let _ = error_to_string(self.rt.api(), err);
+    #[test]
+    fn missing_or_old_extension_is_not_found() {
+        assert!(unsafe { user_data_add_fn(ptr::null()) }.is_none());
+
+        let other = base(1, mem::size_of::<PJRT_Extension_Base>());
+        assert!(unsafe { user_data_add_fn(ptr::addr_of!(other)) }.is_none());
+
+        // An extension struct that ends before user_data_add must not be read past its end.
+        let old = FfiExtension {
+            base: base(
+                PJRT_Extension_Type_PJRT_Extension_Type_FFI,
+                mem::offset_of!(FfiExtension, user_data_add),
+            ),
+            type_id_register: None,
+            user_data_add: Some(record_user_data),
+            register_handler: None,
+        };
+        assert!(unsafe { user_data_add_fn(ptr::addr_of!(old).cast()) }.is_none());
+    }
+
+    #[test]
+    fn boxed_values_drop_through_the_deleter() {
+        struct Flag(Arc<AtomicBool>);
+        impl Drop for Flag {
+            fn drop(&mut self) {
+                self.0.store(true, Ordering::SeqCst);
+            }
         }
+
+        let dropped = Arc::new(AtomicBool::new(false));
+        let data = Box::into_raw(Box::new(Flag(Arc::clone(&dropped)))).cast::<c_void>();
+        unsafe { drop_boxed::<Flag>(data) };
+        assert!(dropped.load(Ordering::SeqCst));
+        unsafe { drop_boxed::<Flag>(ptr::null_mut()) };
+    }
+
+    #[test]
+    fn contexts_and_run_options_cross_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<PJRTExecuteContext<'static>>();
+        assert_send_sync::<crate::pjrt::executable::PJRTExecuteRunOptions>();
+
+        // The placeholder handed out after destroy() never calls into the plugin.
+        let mut detached = ContextHandle {
+            api: ptr::null(),
+            raw: ptr::null_mut(),
+        };
+        assert_eq!(detached.destroy(), Ok(()));
     }
 }
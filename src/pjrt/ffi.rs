@@ -0,0 +1,780 @@
+use std::ffi::{c_char, c_void, CString};
+use std::mem;
+use std::ops::BitOr;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+use crate::pjrt::client::PJRTClient;
+use crate::pjrt::error::{
+    extension_missing, plugin_error, OwnedPjrtError, PjrtErrorCode, PjrtResult,
+};
+use crate::pjrt::loader::{extension_has_field, find_extension, PjrtRuntime};
+use crate::pjrt::utils::{BufferType, PjrtScalar};
+use crate::pjrt_sys::*;
+
+// Layouts from xla/pjrt/c/pjrt_c_api_ffi_extension.h, which the generated bindings don't
+// cover.
+pub(crate) type FfiUserDataDeleter = unsafe extern "C" fn(data: *mut c_void);
+
+#[repr(C)]
+pub(crate) struct FfiUserData {
+    pub(crate) type_id: i64,
+    pub(crate) data: *mut c_void,
+    pub(crate) deleter: Option<FfiUserDataDeleter>,
+}
+
+#[repr(C)]
+pub(crate) struct FfiUserDataAddArgs {
+    pub(crate) struct_size: usize,
+    pub(crate) extension_start: *mut PJRT_Extension_Base,
+    pub(crate) context: *mut PJRT_ExecuteContext,
+    pub(crate) user_data: FfiUserData,
+}
+
+pub(crate) type FfiUserDataAddFn =
+    unsafe extern "C" fn(args: *mut FfiUserDataAddArgs) -> *mut PJRT_Error;
+
+#[repr(C)]
+pub(crate) struct FfiTypeIdRegisterArgs {
+    struct_size: usize,
+    extension_start: *mut PJRT_Extension_Base,
+    type_name: *const c_char,
+    type_name_size: usize,
+    // In-out: zero asks the plugin to assign an id.
+    type_id: i64,
+}
+
+pub(crate) type FfiTypeIdRegisterFn =
+    unsafe extern "C" fn(args: *mut FfiTypeIdRegisterArgs) -> *mut PJRT_Error;
+
+#[repr(C)]
+pub(crate) struct FfiRegisterHandlerArgs {
+    struct_size: usize,
+    target_name: *const c_char,
+    target_name_size: usize,
+    // 0 for the untyped custom call ABI, 1 for XLA FFI handlers.
+    api_version: i32,
+    handler: *mut c_void,
+    platform_name: *const c_char,
+    platform_name_size: usize,
+    traits: u32,
+}
+
+pub(crate) type FfiRegisterHandlerFn =
+    unsafe extern "C" fn(args: *mut FfiRegisterHandlerArgs) -> *mut PJRT_Error;
+
+#[repr(C)]
+pub(crate) struct FfiExtension {
+    pub(crate) base: PJRT_Extension_Base,
+    pub(crate) type_id_register: Option<FfiTypeIdRegisterFn>,
+    pub(crate) user_data_add: Option<FfiUserDataAddFn>,
+    pub(crate) register_handler: Option<FfiRegisterHandlerFn>,
+}
+
+pub(crate) unsafe fn find_ffi_extension<'e>(
+    start: *const PJRT_Extension_Base,
+) -> Option<&'e FfiExtension> {
+    let ext = unsafe { find_extension(start, PJRT_Extension_Type_PJRT_Extension_Type_FFI)? };
+    Some(unsafe { &*ext.cast::<FfiExtension>() })
+}
+
+unsafe fn register_handler_fn(start: *const PJRT_Extension_Base) -> Option<FfiRegisterHandlerFn> {
+    let ext = unsafe { find_ffi_extension(start)? };
+    if !extension_has_field(&ext.base, mem::offset_of!(FfiExtension, register_handler)) {
+        return None;
+    }
+    ext.register_handler
+}
+
+unsafe fn type_id_register_fn(start: *const PJRT_Extension_Base) -> Option<FfiTypeIdRegisterFn> {
+    let ext = unsafe { find_ffi_extension(start)? };
+    if !extension_has_field(&ext.base, mem::offset_of!(FfiExtension, type_id_register)) {
+        return None;
+    }
+    ext.type_id_register
+}
+
+// PJRT_FFI_Handler_TraitsBits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct HandlerTraits(u32);
+
+impl HandlerTraits {
+    pub const NONE: HandlerTraits = HandlerTraits(0);
+    // The handler may be captured into command buffers (CUDA graphs) on GPU backends.
+    pub const COMMAND_BUFFER_COMPATIBLE: HandlerTraits = HandlerTraits(1);
+
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+}
+
+impl BitOr for HandlerTraits {
+    type Output = HandlerTraits;
+
+    fn bitor(self, rhs: HandlerTraits) -> HandlerTraits {
+        HandlerTraits(self.0 | rhs.0)
+    }
+}
+
+fn invalid_name(message: impl Into<String>) -> OwnedPjrtError {
+    OwnedPjrtError::new(PjrtErrorCode::InvalidArgument, message)
+}
+
+fn register_handler_args(
+    name: &str,
+    platform: &str,
+    handler: *mut c_void,
+    traits: HandlerTraits,
+) -> PjrtResult<FfiRegisterHandlerArgs> {
+    if name.is_empty() {
+        return Err(invalid_name("custom call target is empty"));
+    }
+    if platform.is_empty() {
+        return Err(invalid_name("platform name is empty"));
+    }
+    if handler.is_null() {
+        return Err(invalid_name(format!("handler for {name} is null")));
+    }
+    Ok(FfiRegisterHandlerArgs {
+        struct_size: mem::size_of::<FfiRegisterHandlerArgs>(),
+        target_name: name.as_ptr().cast(),
+        target_name_size: name.len(),
+        api_version: 1,
+        handler,
+        platform_name: platform.as_ptr().cast(),
+        platform_name_size: platform.len(),
+        traits: traits.bits(),
+    })
+}
+
+// Registers an XLA FFI handler as the `stablehlo.custom_call` target `name` for `platform`
+// (XLA's platform name, e.g. "Host" for CPU or "CUDA"). `handler` must point at an
+// `XlaFfiHandler`, such as one generated by `xla_ffi_buffer_handler!`, and stay valid for
+// as long as the plugin is loaded.
+pub fn register_custom_call(
+    rt: &PjrtRuntime,
+    name: &str,
+    platform: &str,
+    handler: *mut c_void,
+    traits: HandlerTraits,
+) -> PjrtResult<()> {
+    let register = unsafe { register_handler_fn(rt.api().extension_start) }
+        .ok_or_else(|| extension_missing("the PJRT FFI handler extension"))?;
+    let mut args = register_handler_args(name, platform, handler, traits)?;
+
+    let err = unsafe { register(&mut args) };
+    if err.is_null() {
+        Ok(())
+    } else {
+        Err(plugin_error(rt.api(), "PJRT_FFI_Register_Handler", err))
+    }
+}
+
+// Registers a user data type name and returns its id. Pass 0 to let the plugin assign one.
+pub fn register_type_id(rt: &PjrtRuntime, type_name: &str, type_id: i64) -> PjrtResult<i64> {
+    let register = unsafe { type_id_register_fn(rt.api().extension_start) }
+        .ok_or_else(|| extension_missing("the PJRT FFI handler extension"))?;
+    if type_name.is_empty() {
+        return Err(invalid_name("type name is empty"));
+    }
+
+    let mut args = FfiTypeIdRegisterArgs {
+        struct_size: mem::size_of::<FfiTypeIdRegisterArgs>(),
+        extension_start: ptr::null_mut(),
+        type_name: type_name.as_ptr().cast(),
+        type_name_size: type_name.len(),
+        type_id,
+    };
+
+    let err = unsafe { register(&mut args) };
+    if err.is_null() {
+        Ok(args.type_id)
+    } else {
+        Err(plugin_error(rt.api(), "PJRT_FFI_TypeID_Register", err))
+    }
+}
+
+impl PJRTClient<'_> {
+    pub fn register_custom_call(
+        &self,
+        name: &str,
+        platform: &str,
+        handler: *mut c_void,
+        traits: HandlerTraits,
+    ) -> PjrtResult<()> {
+        register_custom_call(self.rt, name, platform, handler, traits)
+    }
+}
+
+// Layouts from xla/ffi/api/c_api.h: the calling convention of the handlers registered above.
+#[repr(C)]
+struct XlaFfiExtensionBase {
+    struct_size: usize,
+    type_: i32,
+    next: *mut XlaFfiExtensionBase,
+}
+
+const XLA_FFI_EXTENSION_METADATA: i32 = 1;
+
+#[repr(C)]
+struct XlaFfiApiVersion {
+    struct_size: usize,
+    extension_start: *mut XlaFfiExtensionBase,
+    major_version: i32,
+    minor_version: i32,
+}
+
+// The oldest revision with typed buffers; XLA accepts handlers built against older
+// minor versions.
+const XLA_FFI_API_MAJOR: i32 = 0;
+const XLA_FFI_API_MINOR: i32 = 1;
+
+#[repr(C)]
+struct XlaFfiMetadata {
+    struct_size: usize,
+    extension_start: *mut XlaFfiExtensionBase,
+    api_version: XlaFfiApiVersion,
+    traits: u32,
+}
+
+#[repr(C)]
+struct XlaFfiMetadataExtension {
+    extension_base: XlaFfiExtensionBase,
+    metadata: *mut XlaFfiMetadata,
+}
+
+// Returned by handlers; created through the XLA_FFI_Api table and owned by XLA.
+#[repr(C)]
+pub struct XlaFfiError {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct XlaFfiErrorCreateArgs {
+    struct_size: usize,
+    extension_start: *mut XlaFfiExtensionBase,
+    message: *const c_char,
+    errc: i32,
+}
+
+type XlaFfiErrorCreateFn =
+    unsafe extern "C" fn(args: *mut XlaFfiErrorCreateArgs) -> *mut XlaFfiError;
+
+// Only the prefix of XLA_FFI_Api that handlers here use.
+#[repr(C)]
+struct XlaFfiApi {
+    struct_size: usize,
+    extension_start: *mut XlaFfiExtensionBase,
+    api_version: XlaFfiApiVersion,
+    internal_api: *mut c_void,
+    error_create: Option<XlaFfiErrorCreateFn>,
+}
+
+const XLA_FFI_EXECUTION_STAGE_EXECUTE: i32 = 3;
+const XLA_FFI_BUFFER: i32 = 1;
+
+#[repr(C)]
+struct XlaFfiBuffer {
+    struct_size: usize,
+    extension_start: *mut XlaFfiExtensionBase,
+    dtype: i32,
+    data: *mut c_void,
+    rank: i64,
+    dims: *mut i64,
+}
+
+#[repr(C)]
+struct XlaFfiValues {
+    struct_size: usize,
+    extension_start: *mut XlaFfiExtensionBase,
+    size: i64,
+    types: *mut i32,
+    values: *mut *mut c_void,
+}
+
+#[repr(C)]
+struct XlaFfiAttrs {
+    struct_size: usize,
+    extension_start: *mut XlaFfiExtensionBase,
+    size: i64,
+    types: *mut i32,
+    names: *mut *mut c_void,
+    attrs: *mut *mut c_void,
+}
+
+// XLA_FFI_CallFrame, up to the fields read here.
+#[repr(C)]
+pub struct XlaFfiCallFrame {
+    struct_size: usize,
+    extension_start: *mut XlaFfiExtensionBase,
+    api: *const XlaFfiApi,
+    ctx: *mut c_void,
+    stage: i32,
+    args: XlaFfiValues,
+    rets: XlaFfiValues,
+    attrs: XlaFfiAttrs,
+}
+
+pub type XlaFfiHandler = unsafe extern "C" fn(call_frame: *mut XlaFfiCallFrame) -> *mut XlaFfiError;
+
+// The pointer to pass to register_custom_call.
+pub fn handler_ptr(handler: XlaFfiHandler) -> *mut c_void {
+    handler as *mut c_void
+}
+
+// XLA's PrimitiveType numbering, which XLA_FFI_DataType follows.
+fn xla_primitive_type(ty: BufferType) -> Option<i32> {
+    Some(match ty {
+        BufferType::Pred => 1,
+        BufferType::S8 => 2,
+        BufferType::S16 => 3,
+        BufferType::S32 => 4,
+        BufferType::S64 => 5,
+        BufferType::U8 => 6,
+        BufferType::U16 => 7,
+        BufferType::U32 => 8,
+        BufferType::U64 => 9,
+        BufferType::F16 => 10,
+        BufferType::F32 => 11,
+        BufferType::F64 => 12,
+        BufferType::C64 => 15,
+        BufferType::BF16 => 16,
+        BufferType::C128 => 18,
+        _ => return None,
+    })
+}
+
+// A device buffer passed to a handler. Only valid for the duration of the call.
+pub struct FfiBuffer<'f> {
+    raw: &'f XlaFfiBuffer,
+}
+
+impl<'f> FfiBuffer<'f> {
+    pub fn dims(&self) -> &'f [i64] {
+        if self.raw.rank <= 0 || self.raw.dims.is_null() {
+            return &[];
+        }
+        unsafe { slice::from_raw_parts(self.raw.dims, self.raw.rank as usize) }
+    }
+
+    pub fn element_count(&self) -> usize {
+        self.dims().iter().map(|&d| d.max(0) as usize).product()
+    }
+
+    fn check<T: PjrtScalar>(&self) -> Result<(), String> {
+        if xla_primitive_type(T::TYPE) != Some(self.raw.dtype) {
+            return Err(format!(
+                "buffer holds XLA type {}, not {:?}",
+                self.raw.dtype,
+                T::TYPE
+            ));
+        }
+        if self.raw.data.is_null() && self.element_count() > 0 {
+            return Err("buffer data is null".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn data<T: PjrtScalar>(&self) -> Result<&'f [T], String> {
+        self.check::<T>()?;
+        if self.element_count() == 0 {
+            return Ok(&[]);
+        }
+        Ok(unsafe { slice::from_raw_parts(self.raw.data.cast::<T>(), self.element_count()) })
+    }
+}
+
+// A result buffer a handler writes into.
+pub struct FfiBufferMut<'f> {
+    inner: FfiBuffer<'f>,
+}
+
+impl<'f> FfiBufferMut<'f> {
+    pub fn dims(&self) -> &'f [i64] {
+        self.inner.dims()
+    }
+
+    pub fn element_count(&self) -> usize {
+        self.inner.element_count()
+    }
+
+    pub fn data_mut<T: PjrtScalar>(&mut self) -> Result<&mut [T], String> {
+        self.inner.check::<T>()?;
+        let len = self.element_count();
+        if len == 0 {
+            return Ok(&mut []);
+        }
+        Ok(unsafe { slice::from_raw_parts_mut(self.inner.raw.data.cast::<T>(), len) })
+    }
+}
+
+unsafe fn buffers<'f>(values: &'f XlaFfiValues, what: &str) -> Result<Vec<FfiBuffer<'f>>, String> {
+    if values.size <= 0 {
+        return Ok(Vec::new());
+    }
+    let n = values.size as usize;
+    let types = unsafe { slice::from_raw_parts(values.types, n) };
+    let ptrs = unsafe { slice::from_raw_parts(values.values, n) };
+    types
+        .iter()
+        .zip(ptrs)
+        .enumerate()
+        .map(|(i, (&ty, &ptr))| {
+            if ty != XLA_FFI_BUFFER || ptr.is_null() {
+                return Err(format!("{what} {i} is not a buffer"));
+            }
+            Ok(FfiBuffer {
+                raw: unsafe { &*ptr.cast::<XlaFfiBuffer>() },
+            })
+        })
+        .collect()
+}
+
+// Answers XLA's registration-time query, which calls the handler with only a metadata
+// extension set. Returns false if the frame is not such a query.
+unsafe fn fill_metadata(frame: &XlaFfiCallFrame) -> bool {
+    let mut next = frame.extension_start;
+    while !next.is_null() {
+        let ext = unsafe { &*next };
+        if ext.type_ == XLA_FFI_EXTENSION_METADATA {
+            let ext = unsafe { &*next.cast::<XlaFfiMetadataExtension>() };
+            if let Some(metadata) = unsafe { ext.metadata.as_mut() } {
+                metadata.api_version.major_version = XLA_FFI_API_MAJOR;
+                metadata.api_version.minor_version = XLA_FFI_API_MINOR;
+                metadata.traits = 0;
+            }
+            return true;
+        }
+        next = ext.next;
+    }
+    false
+}
+
+unsafe fn ffi_error(
+    frame: &XlaFfiCallFrame,
+    code: PjrtErrorCode,
+    message: &str,
+) -> *mut XlaFfiError {
+    let create = unsafe { frame.api.as_ref() }.and_then(|api| api.error_create);
+    let Some(create) = create else {
+        log::error!("XLA FFI handler failed without an error API: {message}");
+        return ptr::null_mut();
+    };
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    let mut args = XlaFfiErrorCreateArgs {
+        struct_size: mem::size_of::<XlaFfiErrorCreateArgs>(),
+        extension_start: ptr::null_mut(),
+        message: message.as_ptr(),
+        errc: code.raw() as i32,
+    };
+    unsafe { create(&mut args) }
+}
+
+/// Runs `handler` for the execute stage of an XLA FFI call whose arguments and results are
+/// all buffers. Other stages succeed without calling it. Errors and panics are reported back
+/// to XLA rather than unwinding across the FFI boundary.
+///
+/// # Safety
+///
+/// `frame` must be null or the call frame XLA passed to the handler.
+pub unsafe fn call_buffer_handler<F>(frame: *mut XlaFfiCallFrame, handler: F) -> *mut XlaFfiError
+where
+    F: FnOnce(&[FfiBuffer<'_>], &mut [FfiBufferMut<'_>]) -> Result<(), String>,
+{
+    let Some(frame) = (unsafe { frame.as_ref() }) else {
+        return ptr::null_mut();
+    };
+    if unsafe { fill_metadata(frame) } || frame.stage != XLA_FFI_EXECUTION_STAGE_EXECUTE {
+        return ptr::null_mut();
+    }
+
+    let buffers = unsafe { buffers(&frame.args, "argument") }.and_then(|args| {
+        let rets = unsafe { buffers(&frame.rets, "result") }?;
+        Ok((args, rets))
+    });
+    let (args, rets) = match buffers {
+        Ok(buffers) => buffers,
+        Err(e) => return unsafe { ffi_error(frame, PjrtErrorCode::InvalidArgument, &e) },
+    };
+    let mut rets: Vec<FfiBufferMut<'_>> = rets
+        .into_iter()
+        .map(|inner| FfiBufferMut { inner })
+        .collect();
+
+    match catch_unwind(AssertUnwindSafe(|| handler(&args, &mut rets))) {
+        Ok(Ok(())) => ptr::null_mut(),
+        Ok(Err(e)) => unsafe { ffi_error(frame, PjrtErrorCode::Internal, &e) },
+        Err(_) => unsafe { ffi_error(frame, PjrtErrorCode::Internal, "XLA FFI handler panicked") },
+    }
+}
+
+// Defines an `XlaFfiHandler` that forwards buffer-only calls to a Rust function of type
+// `fn(&[FfiBuffer], &mut [FfiBufferMut]) -> Result<(), String>`:
+//
+//     fn double(args: &[FfiBuffer], rets: &mut [FfiBufferMut]) -> Result<(), String> { .. }
+//     xla_ffi_buffer_handler!(double_handler = double);
+//     register_custom_call(&rt, "double", "Host", handler_ptr(double_handler), HandlerTraits::NONE)?;
+#[macro_export]
+macro_rules! xla_ffi_buffer_handler {
+    ($vis:vis $name:ident = $body:path) => {
+        $vis unsafe extern "C" fn $name(
+            call_frame: *mut $crate::pjrt::ffi::XlaFfiCallFrame,
+        ) -> *mut $crate::pjrt::ffi::XlaFfiError {
+            unsafe { $crate::pjrt::ffi::call_buffer_handler(call_frame, $body) }
+        }
+    };
+}
+
+#[cfg(test)]
+mod ffi_tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    // Stands in for a plugin's handler registry.
+    struct Registration {
+        target: String,
+        platform: String,
+        api_version: i32,
+        traits: u32,
+        handler: usize,
+    }
+
+    static REGISTERED: Mutex<Vec<Registration>> = Mutex::new(Vec::new());
+    static ERRORS: Mutex<Vec<(i32, String)>> = Mutex::new(Vec::new());
+    static DOUBLE_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    unsafe extern "C" fn record_handler(args: *mut FfiRegisterHandlerArgs) -> *mut PJRT_Error {
+        let args = unsafe { &*args };
+        let text = |ptr: *const c_char, len| unsafe {
+            String::from_utf8_lossy(slice::from_raw_parts(ptr.cast::<u8>(), len)).into_owned()
+        };
+        REGISTERED.lock().unwrap().push(Registration {
+            target: text(args.target_name, args.target_name_size),
+            platform: text(args.platform_name, args.platform_name_size),
+            api_version: args.api_version,
+            traits: args.traits,
+            handler: args.handler as usize,
+        });
+        ptr::null_mut()
+    }
+
+    unsafe extern "C" fn record_error(args: *mut XlaFfiErrorCreateArgs) -> *mut XlaFfiError {
+        let args = unsafe { &*args };
+        let message = unsafe { std::ffi::CStr::from_ptr(args.message) };
+        ERRORS
+            .lock()
+            .unwrap()
+            .push((args.errc, message.to_string_lossy().into_owned()));
+        ptr::NonNull::dangling().as_ptr()
+    }
+
+    fn double(args: &[FfiBuffer<'_>], rets: &mut [FfiBufferMut<'_>]) -> Result<(), String> {
+        DOUBLE_CALLS.fetch_add(1, Ordering::SeqCst);
+        let input = args[0].data::<f32>()?;
+        for (out, x) in rets[0].data_mut::<f32>()?.iter_mut().zip(input) {
+            *out = x * 2.0;
+        }
+        Ok(())
+    }
+
+    fn as_ints(args: &[FfiBuffer<'_>], _: &mut [FfiBufferMut<'_>]) -> Result<(), String> {
+        args[0].data::<i32>().map(|_| ())
+    }
+
+    crate::xla_ffi_buffer_handler!(double_handler = double);
+    crate::xla_ffi_buffer_handler!(as_ints_handler = as_ints);
+
+    fn base(ty: PJRT_Extension_Type, size: usize) -> PJRT_Extension_Base {
+        PJRT_Extension_Base {
+            struct_size: size,
+            type_: ty,
+            next: ptr::null_mut(),
+        }
+    }
+
+    fn ffi_extension(size: usize) -> FfiExtension {
+        FfiExtension {
+            base: base(PJRT_Extension_Type_PJRT_Extension_Type_FFI, size),
+            type_id_register: None,
+            user_data_add: None,
+            register_handler: Some(record_handler),
+        }
+    }
+
+    fn values(types: &mut [i32], ptrs: &mut [*mut c_void]) -> XlaFfiValues {
+        XlaFfiValues {
+            struct_size: mem::size_of::<XlaFfiValues>(),
+            extension_start: ptr::null_mut(),
+            size: types.len() as i64,
+            types: types.as_mut_ptr(),
+            values: ptrs.as_mut_ptr(),
+        }
+    }
+
+    fn f32_buffer(data: &mut [f32], dims: &mut [i64; 1]) -> XlaFfiBuffer {
+        XlaFfiBuffer {
+            struct_size: mem::size_of::<XlaFfiBuffer>(),
+            extension_start: ptr::null_mut(),
+            dtype: 11,
+            data: data.as_mut_ptr().cast(),
+            rank: 1,
+            dims: dims.as_mut_ptr(),
+        }
+    }
+
+    // What an executing plugin does at a custom call: one f32 buffer in, one out.
+    fn execute(
+        handler: XlaFfiHandler,
+        input: &mut [f32],
+        output: &mut [f32],
+        arg_type: i32,
+    ) -> *mut XlaFfiError {
+        let api = XlaFfiApi {
+            struct_size: mem::size_of::<XlaFfiApi>(),
+            extension_start: ptr::null_mut(),
+            api_version: XlaFfiApiVersion {
+                struct_size: mem::size_of::<XlaFfiApiVersion>(),
+                extension_start: ptr::null_mut(),
+                major_version: XLA_FFI_API_MAJOR,
+                minor_version: XLA_FFI_API_MINOR,
+            },
+            internal_api: ptr::null_mut(),
+            error_create: Some(record_error),
+        };
+        let (mut in_dims, mut out_dims) = ([input.len() as i64], [output.len() as i64]);
+        let mut arg = f32_buffer(input, &mut in_dims);
+        let mut ret = f32_buffer(output, &mut out_dims);
+        let (mut arg_types, mut ret_types) = ([arg_type], [XLA_FFI_BUFFER]);
+        let mut arg_ptrs = [ptr::addr_of_mut!(arg).cast::<c_void>()];
+        let mut ret_ptrs = [ptr::addr_of_mut!(ret).cast::<c_void>()];
+        let mut frame = XlaFfiCallFrame {
+            struct_size: mem::size_of::<XlaFfiCallFrame>(),
+            extension_start: ptr::null_mut(),
+            api: &api,
+            ctx: ptr::null_mut(),
+            stage: XLA_FFI_EXECUTION_STAGE_EXECUTE,
+            args: values(&mut arg_types, &mut arg_ptrs),
+            rets: values(&mut ret_types, &mut ret_ptrs),
+            attrs: XlaFfiAttrs {
+                struct_size: mem::size_of::<XlaFfiAttrs>(),
+                extension_start: ptr::null_mut(),
+                size: 0,
+                types: ptr::null_mut(),
+                names: ptr::null_mut(),
+                attrs: ptr::null_mut(),
+            },
+        };
+        unsafe { handler(&mut frame) }
+    }
+
+    #[test]
+    fn registered_handler_runs_once_per_execute() {
+        let mut ffi = ffi_extension(mem::size_of::<FfiExtension>());
+        let mut other = base(1, mem::size_of::<PJRT_Extension_Base>());
+        other.next = ptr::addr_of_mut!(ffi).cast();
+
+        let register = unsafe { register_handler_fn(ptr::addr_of!(other)) }.unwrap();
+        let traits = HandlerTraits::NONE | HandlerTraits::COMMAND_BUFFER_COMPATIBLE;
+        let mut args =
+            register_handler_args("rrad_double", "Host", handler_ptr(double_handler), traits)
+                .unwrap();
+        assert!(unsafe { register(&mut args) }.is_null());
+
+        let handler = {
+            let registered = REGISTERED.lock().unwrap();
+            let entry = registered
+                .iter()
+                .find(|e| e.target == "rrad_double")
+                .unwrap();
+            assert_eq!(
+                (entry.platform.as_str(), entry.api_version, entry.traits),
+                ("Host", 1, 1)
+            );
+            unsafe { mem::transmute::<usize, XlaFfiHandler>(entry.handler) }
+        };
+
+        let mut input = [1.0f32, 2.0, 3.0, 4.0];
+        let mut output = [0.0f32; 4];
+        assert!(execute(handler, &mut input, &mut output, XLA_FFI_BUFFER).is_null());
+        assert_eq!(output, [2.0, 4.0, 6.0, 8.0]);
+        assert_eq!(DOUBLE_CALLS.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn metadata_queries_do_not_run_the_handler() {
+        let mut metadata = XlaFfiMetadata {
+            struct_size: mem::size_of::<XlaFfiMetadata>(),
+            extension_start: ptr::null_mut(),
+            api_version: XlaFfiApiVersion {
+                struct_size: mem::size_of::<XlaFfiApiVersion>(),
+                extension_start: ptr::null_mut(),
+                major_version: -1,
+                minor_version: -1,
+            },
+            traits: 7,
+        };
+        let mut ext = XlaFfiMetadataExtension {
+            extension_base: XlaFfiExtensionBase {
+                struct_size: mem::size_of::<XlaFfiMetadataExtension>(),
+                type_: XLA_FFI_EXTENSION_METADATA,
+                next: ptr::null_mut(),
+            },
+            metadata: &mut metadata,
+        };
+        let mut frame: XlaFfiCallFrame = unsafe { mem::zeroed() };
+        frame.extension_start = ptr::addr_of_mut!(ext).cast();
+
+        // Any call to the handler would fail: there are no buffers to read.
+        assert!(unsafe { as_ints_handler(&mut frame) }.is_null());
+        assert_eq!(metadata.api_version.major_version, XLA_FFI_API_MAJOR);
+        assert_eq!(metadata.api_version.minor_version, XLA_FFI_API_MINOR);
+        assert_eq!(metadata.traits, 0);
+        assert!(unsafe { as_ints_handler(ptr::null_mut()) }.is_null());
+    }
+
+    #[test]
+    fn handler_errors_go_through_the_ffi_api() {
+        let mut input = [1.0f32];
+        let mut output = [0.0f32];
+        assert!(!execute(as_ints_handler, &mut input, &mut output, XLA_FFI_BUFFER).is_null());
+        assert!(!execute(as_ints_handler, &mut input, &mut output, 2).is_null());
+
+        let errors = ERRORS.lock().unwrap();
+        assert!(errors.iter().any(|(code, message)| {
+            *code == PjrtErrorCode::Internal.raw() as i32 && message.contains("not S32")
+        }));
+        assert!(errors.iter().any(|(code, message)| {
+            *code == PjrtErrorCode::InvalidArgument.raw() as i32
+                && message == "argument 0 is not a buffer"
+        }));
+    }
+
+    #[test]
+    fn missing_or_old_extension_is_not_found() {
+        assert!(unsafe { register_handler_fn(ptr::null()) }.is_none());
+
+        let other = base(1, mem::size_of::<PJRT_Extension_Base>());
+        assert!(unsafe { register_handler_fn(ptr::addr_of!(other)) }.is_none());
+
+        // An extension from before handler registration ends at user_data_add.
+        let old = ffi_extension(mem::offset_of!(FfiExtension, register_handler));
+        assert!(unsafe { register_handler_fn(ptr::addr_of!(old).cast()) }.is_none());
+    }
+
+    #[test]
+    fn registration_arguments_are_validated() {
+        let handler = handler_ptr(double_handler);
+        for (name, platform, handler) in [
+            ("", "Host", handler),
+            ("rrad_double", "", handler),
+            ("rrad_double", "Host", ptr::null_mut()),
+        ] {
+            let err = register_handler_args(name, platform, handler, HandlerTraits::NONE)
+                .err()
+                .unwrap();
+            assert!(err.is_code(PjrtErrorCode::InvalidArgument), "{err}");
+        }
+    }
+}
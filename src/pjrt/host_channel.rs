@@ -0,0 +1,265 @@
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::Range;
+use std::slice::from_raw_parts;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use crate::pjrt::executable::PJRTExecuteRunOptions;
+use crate::pjrt::execute_callbacks::{PJRTRecvCallbackFn, PJRTSendCallbackFn};
+use crate::pjrt::utils::PjrtScalar;
+
+// Recv payloads go to the device in chunks of about this size, rounded to the granule.
+const INFEED_CHUNK_BYTES: usize = 1 << 16;
+
+// Typed host end of a `stablehlo.send` / `stablehlo.recv` channel. Each transfer is one
+// whole tensor, flattened to a `Vec<T>` in row-major order.
+pub struct HostChannel<T: PjrtScalar> {
+    channel_id: i64,
+    _element: PhantomData<fn() -> T>,
+}
+
+impl<T: PjrtScalar> HostChannel<T> {
+    pub fn new(channel_id: i64) -> Self {
+        Self {
+            channel_id,
+            _element: PhantomData,
+        }
+    }
+
+    pub fn channel_id(&self) -> i64 {
+        self.channel_id
+    }
+
+    // Registers a send callback for this channel. Every completed device-to-host transfer
+    // arrives on the receiver as one value; the receiver may be dropped to discard them.
+    pub fn outfeed(
+        &self,
+        options: PJRTExecuteRunOptions,
+    ) -> (PJRTExecuteRunOptions, Receiver<Vec<T>>) {
+        let (tx, rx) = channel();
+        let options = options.send_callback(self.channel_id, outfeed_callback::<T>(tx));
+        (options, rx)
+    }
+
+    // Registers a recv callback for this channel. Each recv op takes the next value sent
+    // on the returned sender, blocking until the host provides one.
+    pub fn infeed(
+        &self,
+        options: PJRTExecuteRunOptions,
+    ) -> (PJRTExecuteRunOptions, Sender<Vec<T>>) {
+        let (tx, rx) = channel();
+        let options = options.recv_callback(self.channel_id, infeed_callback::<T>(rx));
+        (options, tx)
+    }
+}
+
+pub(crate) fn outfeed_callback<T: PjrtScalar>(values: Sender<Vec<T>>) -> PJRTSendCallbackFn {
+    let mut assembler = OutfeedAssembler::<T>::default();
+    Box::new(move |invocation| {
+        let value = assembler.push(
+            invocation.chunk.data(),
+            invocation.total_size_in_bytes,
+            invocation.done,
+        )?;
+        if let Some(value) = value {
+            // A dropped receiver means the host no longer wants the values.
+            let _ = values.send(value);
+        }
+        Ok(())
+    })
+}
+
+pub(crate) fn infeed_callback<T: PjrtScalar>(values: Receiver<Vec<T>>) -> PJRTRecvCallbackFn {
+    Box::new(move |invocation| {
+        let value = values
+            .recv()
+            .map_err(|_| "the host closed the channel before sending a value".to_string())?;
+        let bytes = host_bytes(&value);
+        let stream = &invocation.stream;
+        for range in infeed_chunks(
+            bytes.len(),
+            stream.granule_size()?,
+            stream.remaining_bytes()?,
+        )? {
+            stream.add_chunk_bytes(bytes[range].to_vec())?;
+        }
+        Ok(())
+    })
+}
+
+fn host_bytes<T: PjrtScalar>(values: &[T]) -> &[u8] {
+    unsafe { from_raw_parts(values.as_ptr().cast::<u8>(), mem::size_of_val(values)) }
+}
+
+// Collects the chunks of one send op. The runtime may split a transfer anywhere, including
+// mid-element, and marks the last chunk with `done`.
+struct OutfeedAssembler<T> {
+    bytes: Vec<u8>,
+    _element: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for OutfeedAssembler<T> {
+    fn default() -> Self {
+        Self {
+            bytes: Vec::new(),
+            _element: PhantomData,
+        }
+    }
+}
+
+impl<T: PjrtScalar> OutfeedAssembler<T> {
+    fn push(
+        &mut self,
+        chunk: &[u8],
+        total_size_in_bytes: usize,
+        done: bool,
+    ) -> Result<Option<Vec<T>>, String> {
+        self.bytes.extend_from_slice(chunk);
+        if self.bytes.len() > total_size_in_bytes {
+            let received = mem::take(&mut self.bytes).len();
+            return Err(format!(
+                "send op delivered {received} bytes but announced {total_size_in_bytes}"
+            ));
+        }
+        if !done {
+            return Ok(None);
+        }
+
+        let bytes = mem::take(&mut self.bytes);
+        if bytes.len() != total_size_in_bytes {
+            return Err(format!(
+                "send op finished after {} of {total_size_in_bytes} bytes",
+                bytes.len()
+            ));
+        }
+        if !bytes.len().is_multiple_of(T::BYTES) {
+            return Err(format!(
+                "send op delivered {} bytes, which is not a whole number of {} elements",
+                bytes.len(),
+                T::TYPE
+            ));
+        }
+        Ok(Some(
+            bytes.chunks_exact(T::BYTES).map(T::from_le_slice).collect(),
+        ))
+    }
+}
+
+// Byte ranges of `len` bytes split into granule-aligned chunks for a recv stream that
+// expects exactly `remaining` more bytes.
+fn infeed_chunks(len: usize, granule: i64, remaining: i64) -> Result<Vec<Range<usize>>, String> {
+    if len as i64 != remaining {
+        return Err(format!(
+            "host value is {len} bytes but the recv op expects {remaining}"
+        ));
+    }
+    let granule = granule.max(1) as usize;
+    let step = (INFEED_CHUNK_BYTES / granule).max(1) * granule;
+    Ok((0..len)
+        .step_by(step)
+        .map(|start| start..len.min(start + step))
+        .collect())
+}
+
+#[cfg(test)]
+mod host_channel_tests {
+    use super::*;
+    use crate::pjrt::execute_callbacks::{
+        CallbackErrors, ExecuteCallbackKeepalive, SendRegistration,
+    };
+    use crate::pjrt_sys::PJRT_Chunk;
+    use std::ffi::c_void;
+    use std::ptr;
+    use std::sync::{Arc, Mutex};
+
+    fn f32_bytes(values: &[f32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_ne_bytes()).collect()
+    }
+
+    #[test]
+    fn outfeed_reassembles_chunks_split_mid_element() {
+        let (tx, rx) = channel::<Vec<f32>>();
+        let errors = CallbackErrors::default();
+        let keepalive = ExecuteCallbackKeepalive::new(
+            ptr::null(),
+            &[SendRegistration {
+                channel_id: 1,
+                callback: Arc::new(Mutex::new(outfeed_callback(tx))),
+            }],
+            &[],
+            &errors,
+            1,
+        );
+        let info = unsafe { **keepalive.send_callbacks() };
+        let send = info.send_callback.unwrap();
+
+        let mut payload = f32_bytes(&[1.5, -2.0, 8.25]);
+        let total = payload.len();
+        for (range, done) in [(0..3, false), (3..10, false), (10..12, true)] {
+            let mut chunk = PJRT_Chunk {
+                data: payload[range.clone()].as_mut_ptr().cast::<c_void>(),
+                size: range.len(),
+                deleter: None,
+                deleter_arg: ptr::null_mut(),
+            };
+            let err = unsafe { send(&mut chunk, ptr::null_mut(), total, done, info.user_arg) };
+            assert!(err.is_null());
+            if !done {
+                assert!(rx.try_recv().is_err());
+            }
+        }
+        assert_eq!(rx.try_recv().unwrap(), vec![1.5, -2.0, 8.25]);
+        assert_eq!(errors.take(), None);
+
+        // The next transfer starts from an empty buffer.
+        let mut second = f32_bytes(&[4.0]);
+        let mut chunk = PJRT_Chunk {
+            data: second.as_mut_ptr().cast::<c_void>(),
+            size: second.len(),
+            deleter: None,
+            deleter_arg: ptr::null_mut(),
+        };
+        unsafe { send(&mut chunk, ptr::null_mut(), 4, true, info.user_arg) };
+        assert_eq!(rx.try_recv().unwrap(), vec![4.0]);
+    }
+
+    #[test]
+    fn outfeed_rejects_short_long_and_ragged_transfers() {
+        let mut assembler = OutfeedAssembler::<f32>::default();
+        let err = assembler.push(&[0; 4], 8, true).unwrap_err();
+        assert!(err.contains("after 4 of 8 bytes"), "{err}");
+
+        let err = assembler.push(&[0; 12], 8, false).unwrap_err();
+        assert!(err.contains("delivered 12 bytes but announced 8"), "{err}");
+
+        let err = assembler.push(&[0; 6], 6, true).unwrap_err();
+        assert!(err.contains("not a whole number of f32 elements"), "{err}");
+
+        assert_eq!(assembler.push(&[], 0, true).unwrap(), Some(vec![]));
+    }
+
+    #[test]
+    fn infeed_chunks_follow_granule_and_size() {
+        assert_eq!(infeed_chunks(8, 4, 8).unwrap(), vec![0..8]);
+        assert_eq!(infeed_chunks(0, 4, 0).unwrap(), vec![]);
+
+        let len = 3 * INFEED_CHUNK_BYTES;
+        let chunks = infeed_chunks(len, 3, len as i64).unwrap();
+        assert!(chunks.iter().all(|r| r.len() % 3 == 0));
+        assert_eq!(chunks.first().unwrap().start, 0);
+        assert_eq!(chunks.last().unwrap().end, len);
+        assert!(chunks.windows(2).all(|w| w[0].end == w[1].start));
+
+        let err = infeed_chunks(8, 4, 12).unwrap_err();
+        assert!(err.contains("8 bytes but the recv op expects 12"), "{err}");
+    }
+
+    #[test]
+    fn host_bytes_match_native_encoding() {
+        assert_eq!(
+            host_bytes(&[1.5f32, -2.0]),
+            f32_bytes(&[1.5, -2.0]).as_slice()
+        );
+        assert_eq!(host_bytes::<i64>(&[]), &[] as &[u8]);
+    }
+}
@@ -3,8 +3,10 @@ use std::ptr;
 
 use crate::pjrt::buffer::PJRTBuffer;
 use crate::pjrt::device::PJRTDevice;
+use crate::pjrt::error::PjrtErrorCode;
 use crate::pjrt::event::PJRTEvent;
 use crate::pjrt::loader::{error_to_string, PjrtRuntime};
+use crate::pjrt::topology_desc::{encode_named_values, PJRTNamedValue};
 use crate::pjrt_sys::*;
 
 pub struct PjrtHtoDeviceManager<'a> {
@@ -13,7 +15,10 @@ pub struct PjrtHtoDeviceManager<'a> {
 }
 
 impl<'a> PjrtHtoDeviceManager<'a> {
-    pub(crate) fn new(rt: &'a PjrtRuntime, raw: *mut PJRT_AsyncHostToDeviceTransferManager) -> Self {
+    pub(crate) fn new(
+        rt: &'a PjrtRuntime,
+        raw: *mut PJRT_AsyncHostToDeviceTransferManager,
+    ) -> Self {
         Self { rt, raw }
     }
 
@@ -29,8 +34,9 @@ impl<'a> PjrtHtoDeviceManager<'a> {
         }
     }
 
-    pub fn add_metadata(&self, metadata: &[PJRT_NamedValue]) -> Result<(), String> {
+    pub fn add_metadata(&self, metadata: &[(&str, PJRTNamedValue)]) -> Result<(), String> {
         let raw = self.raw_checked()?;
+        let metadata = encode_named_values(metadata);
 
         let f = self
             .rt
@@ -39,14 +45,11 @@ impl<'a> PjrtHtoDeviceManager<'a> {
             .ok_or("PJRT_AsyncHostToDeviceTransferManager_AddMetadata symbol not found")?;
 
         let mut args = PJRT_AsyncHostToDeviceTransferManager_AddMetadata_Args {
-            struct_size: PJRT_AsyncHostToDeviceTransferManager_AddMetadata_Args_STRUCT_SIZE as usize,
+            struct_size: PJRT_AsyncHostToDeviceTransferManager_AddMetadata_Args_STRUCT_SIZE
+                as usize,
             extension_start: ptr::null_mut(),
             transfer_manager: raw,
-            transfer_metadata: if metadata.is_empty() {
-                ptr::null()
-            } else {
-                metadata.as_ptr()
-            },
+            transfer_metadata: metadata.as_ptr(),
             num_metadata: metadata.len(),
         };
 
@@ -68,7 +71,8 @@ impl<'a> PjrtHtoDeviceManager<'a> {
             .ok_or("PJRT_AsyncHostToDeviceTransferManager_BufferCount symbol not found")?;
 
         let mut args = PJRT_AsyncHostToDeviceTransferManager_BufferCount_Args {
-            struct_size: PJRT_AsyncHostToDeviceTransferManager_BufferCount_Args_STRUCT_SIZE as usize,
+            struct_size: PJRT_AsyncHostToDeviceTransferManager_BufferCount_Args_STRUCT_SIZE
+                as usize,
             extension_start: ptr::null_mut(),
             transfer_manager: raw,
             buffer_count: 0,
@@ -128,13 +132,15 @@ impl<'a> PjrtHtoDeviceManager<'a> {
             return Err(error_to_string(self.rt.api(), err));
         }
         if args.device_out.is_null() {
-            return Err("PJRT_AsyncHostToDeviceTransferManager_Device returned null device".to_string());
+            return Err(
+                "PJRT_AsyncHostToDeviceTransferManager_Device returned null device".to_string(),
+            );
         }
         Ok(args.device_out)
     }
 
     pub fn device_ref(&self) -> Result<PJRTDevice<'a>, String> {
-        Ok(PJRTDevice::new(self.rt, self.device()?))
+        PJRTDevice::new(self.rt, self.device()?)
     }
 
     pub fn retrieve_buffer(&self, buffer_index: i32) -> Result<*mut PJRT_Buffer, String> {
@@ -147,7 +153,8 @@ impl<'a> PjrtHtoDeviceManager<'a> {
             .ok_or("PJRT_AsyncHostToDeviceTransferManager_RetrieveBuffer symbol not found")?;
 
         let mut args = PJRT_AsyncHostToDeviceTransferManager_RetrieveBuffer_Args {
-            struct_size: PJRT_AsyncHostToDeviceTransferManager_RetrieveBuffer_Args_STRUCT_SIZE as usize,
+            struct_size: PJRT_AsyncHostToDeviceTransferManager_RetrieveBuffer_Args_STRUCT_SIZE
+                as usize,
             extension_start: ptr::null_mut(),
             transfer_manager: raw,
             buffer_index,
@@ -168,13 +175,16 @@ impl<'a> PjrtHtoDeviceManager<'a> {
     }
 
     pub fn retrieve_buffer_ref(&self, buffer_index: i32) -> Result<PJRTBuffer<'a>, String> {
-        Ok(PJRTBuffer::new(self.rt, self.retrieve_buffer(buffer_index)?))
+        Ok(PJRTBuffer::new(
+            self.rt,
+            self.retrieve_buffer(buffer_index)?,
+        ))
     }
 
     pub fn set_buffer_error(
         &self,
         buffer_index: i32,
-        error_code: PJRT_Error_Code,
+        error_code: PjrtErrorCode,
         error_message: &str,
     ) -> Result<(), String> {
         let raw = self.raw_checked()?;
@@ -187,11 +197,12 @@ impl<'a> PjrtHtoDeviceManager<'a> {
 
         let error_message_bytes = error_message.as_bytes();
         let mut args = PJRT_AsyncHostToDeviceTransferManager_SetBufferError_Args {
-            struct_size: PJRT_AsyncHostToDeviceTransferManager_SetBufferError_Args_STRUCT_SIZE as usize,
+            struct_size: PJRT_AsyncHostToDeviceTransferManager_SetBufferError_Args_STRUCT_SIZE
+                as usize,
             extension_start: ptr::null_mut(),
             transfer_manager: raw,
             buffer_index,
-            error_code,
+            error_code: error_code.raw(),
             error_message: if error_message_bytes.is_empty() {
                 ptr::null()
             } else {
@@ -229,7 +240,8 @@ impl<'a> PjrtHtoDeviceManager<'a> {
             .ok_or("PJRT_AsyncHostToDeviceTransferManager_TransferData symbol not found")?;
 
         let mut args = PJRT_AsyncHostToDeviceTransferManager_TransferData_Args {
-            struct_size: PJRT_AsyncHostToDeviceTransferManager_TransferData_Args_STRUCT_SIZE as usize,
+            struct_size: PJRT_AsyncHostToDeviceTransferManager_TransferData_Args_STRUCT_SIZE
+                as usize,
             extension_start: ptr::null_mut(),
             transfer_manager: raw,
             buffer_index,
@@ -276,7 +288,8 @@ impl<'a> PjrtHtoDeviceManager<'a> {
             .ok_or("PJRT_AsyncHostToDeviceTransferManager_TransferLiteral symbol not found")?;
 
         let mut args = PJRT_AsyncHostToDeviceTransferManager_TransferLiteral_Args {
-            struct_size: PJRT_AsyncHostToDeviceTransferManager_TransferLiteral_Args_STRUCT_SIZE as usize,
+            struct_size: PJRT_AsyncHostToDeviceTransferManager_TransferLiteral_Args_STRUCT_SIZE
+                as usize,
             extension_start: ptr::null_mut(),
             transfer_manager: raw,
             buffer_index,
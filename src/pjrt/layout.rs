@@ -0,0 +1,274 @@
+use std::marker::PhantomData;
+use std::ptr;
+use std::slice::from_raw_parts;
+
+use crate::pjrt_sys::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MemoryLayout {
+    // XLA tiled layout: dimension order from minor to major, plus optional tiles
+    // (each entry of `tile_dims` is one tile's dimension sizes).
+    Tiled {
+        minor_to_major: Vec<i64>,
+        tile_dims: Vec<Vec<i64>>,
+    },
+    // Explicit byte stride per dimension.
+    Strides {
+        byte_strides: Vec<i64>,
+    },
+}
+
+impl MemoryLayout {
+    pub fn row_major(rank: usize) -> Self {
+        MemoryLayout::Tiled {
+            minor_to_major: (0..rank as i64).rev().collect(),
+            tile_dims: Vec::new(),
+        }
+    }
+
+    pub fn column_major(rank: usize) -> Self {
+        MemoryLayout::Tiled {
+            minor_to_major: (0..rank as i64).collect(),
+            tile_dims: Vec::new(),
+        }
+    }
+
+    // Untiled layouts store every element exactly once, so the dense host size applies.
+    pub fn is_dense(&self) -> bool {
+        matches!(self, MemoryLayout::Tiled { tile_dims, .. } if tile_dims.is_empty())
+    }
+
+    pub fn to_raw(&self) -> RawMemoryLayout<'_> {
+        RawMemoryLayout::new(self)
+    }
+
+    /// # Safety
+    ///
+    /// Every array pointer in `raw` must be valid for its stated length.
+    pub unsafe fn from_raw(raw: &PJRT_Buffer_MemoryLayout) -> Result<Self, String> {
+        unsafe fn slice<'s, T>(p: *const T, len: usize) -> Result<&'s [T], String> {
+            match (p.is_null(), len) {
+                (_, 0) => Ok(&[]),
+                (true, _) => {
+                    Err("PJRT_Buffer_MemoryLayout has a null array with nonzero size".into())
+                }
+                (false, _) => Ok(unsafe { from_raw_parts(p, len) }),
+            }
+        }
+
+        let type_ = raw.type_;
+        if type_ == PJRT_Buffer_MemoryLayout_Type_PJRT_Buffer_MemoryLayout_Type_Tiled {
+            let tiled = unsafe { raw.__bindgen_anon_1.tiled };
+            let minor_to_major =
+                unsafe { slice(tiled.minor_to_major, tiled.minor_to_major_size)? }.to_vec();
+            let sizes = unsafe { slice(tiled.tile_dim_sizes, tiled.num_tiles)? };
+            let total: usize = sizes.iter().sum();
+            let mut dims = unsafe { slice(tiled.tile_dims, total)? };
+            let tile_dims = sizes
+                .iter()
+                .map(|&n| {
+                    let (tile, rest) = dims.split_at(n);
+                    dims = rest;
+                    tile.to_vec()
+                })
+                .collect();
+            Ok(MemoryLayout::Tiled {
+                minor_to_major,
+                tile_dims,
+            })
+        } else if type_ == PJRT_Buffer_MemoryLayout_Type_PJRT_Buffer_MemoryLayout_Type_Strides {
+            let strides = unsafe { raw.__bindgen_anon_1.strides };
+            let byte_strides = unsafe { slice(strides.byte_strides, strides.num_byte_strides)? };
+            Ok(MemoryLayout::Strides {
+                byte_strides: byte_strides.to_vec(),
+            })
+        } else {
+            Err(format!("unknown PJRT_Buffer_MemoryLayout_Type {type_}"))
+        }
+    }
+}
+
+// A PJRT_Buffer_MemoryLayout whose arrays point into the borrowed MemoryLayout (or into
+// buffers owned here), so it stays valid for as long as this value is alive.
+pub struct RawMemoryLayout<'l> {
+    raw: PJRT_Buffer_MemoryLayout,
+    _tile_dims: Vec<i64>,
+    _tile_dim_sizes: Vec<usize>,
+    _layout: PhantomData<&'l MemoryLayout>,
+}
+
+impl<'l> RawMemoryLayout<'l> {
+    fn new(layout: &'l MemoryLayout) -> Self {
+        let mut flat_tile_dims = Vec::new();
+        let mut tile_dim_sizes = Vec::new();
+
+        let (union, type_) = match layout {
+            MemoryLayout::Tiled {
+                minor_to_major,
+                tile_dims,
+            } => {
+                for tile in tile_dims {
+                    flat_tile_dims.extend_from_slice(tile);
+                    tile_dim_sizes.push(tile.len());
+                }
+                (
+                    PJRT_Buffer_MemoryLayout__bindgen_ty_1 {
+                        tiled: PJRT_Buffer_MemoryLayout_Tiled {
+                            struct_size: PJRT_Buffer_MemoryLayout_Tiled_STRUCT_SIZE as usize,
+                            extension_start: ptr::null_mut(),
+                            minor_to_major: minor_to_major.as_ptr(),
+                            minor_to_major_size: minor_to_major.len(),
+                            tile_dims: flat_tile_dims.as_ptr(),
+                            tile_dim_sizes: tile_dim_sizes.as_ptr(),
+                            num_tiles: tile_dim_sizes.len(),
+                        },
+                    },
+                    PJRT_Buffer_MemoryLayout_Type_PJRT_Buffer_MemoryLayout_Type_Tiled,
+                )
+            }
+            MemoryLayout::Strides { byte_strides } => (
+                PJRT_Buffer_MemoryLayout__bindgen_ty_1 {
+                    strides: PJRT_Buffer_MemoryLayout_Strides {
+                        struct_size: PJRT_Buffer_MemoryLayout_Strides_STRUCT_SIZE as usize,
+                        extension_start: ptr::null_mut(),
+                        byte_strides: byte_strides.as_ptr(),
+                        num_byte_strides: byte_strides.len(),
+                    },
+                },
+                PJRT_Buffer_MemoryLayout_Type_PJRT_Buffer_MemoryLayout_Type_Strides,
+            ),
+        };
+
+        Self {
+            raw: PJRT_Buffer_MemoryLayout {
+                struct_size: PJRT_Buffer_MemoryLayout_STRUCT_SIZE as usize,
+                extension_start: ptr::null_mut(),
+                __bindgen_anon_1: union,
+                type_,
+            },
+            _tile_dims: flat_tile_dims,
+            _tile_dim_sizes: tile_dim_sizes,
+            _layout: PhantomData,
+        }
+    }
+
+    pub fn as_raw(&self) -> &PJRT_Buffer_MemoryLayout {
+        &self.raw
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut PJRT_Buffer_MemoryLayout {
+        &mut self.raw
+    }
+}
+
+#[cfg(test)]
+mod layout_tests {
+    use super::MemoryLayout;
+    use crate::pjrt_sys::*;
+    use std::ptr;
+
+    fn round_trip(layout: &MemoryLayout) -> MemoryLayout {
+        let raw = layout.to_raw();
+        unsafe { MemoryLayout::from_raw(raw.as_raw()) }.unwrap()
+    }
+
+    #[test]
+    fn major_order_helpers() {
+        assert_eq!(
+            MemoryLayout::row_major(3),
+            MemoryLayout::Tiled {
+                minor_to_major: vec![2, 1, 0],
+                tile_dims: vec![]
+            }
+        );
+        assert_eq!(
+            MemoryLayout::column_major(2),
+            MemoryLayout::Tiled {
+                minor_to_major: vec![0, 1],
+                tile_dims: vec![]
+            }
+        );
+        assert!(MemoryLayout::row_major(2).is_dense());
+        assert!(!MemoryLayout::Strides {
+            byte_strides: vec![8, 4]
+        }
+        .is_dense());
+    }
+
+    #[test]
+    fn raw_round_trips() {
+        let layouts = [
+            MemoryLayout::row_major(0),
+            MemoryLayout::column_major(3),
+            MemoryLayout::Tiled {
+                minor_to_major: vec![1, 0],
+                tile_dims: vec![vec![8, 128], vec![2]],
+            },
+            MemoryLayout::Strides {
+                byte_strides: vec![24, 8, 4],
+            },
+        ];
+        for layout in &layouts {
+            assert_eq!(&round_trip(layout), layout);
+        }
+    }
+
+    #[test]
+    fn decodes_hand_built_tiled_layout() {
+        let minor_to_major = [0i64, 1];
+        let tile_dims = [8i64, 128, 2];
+        let tile_dim_sizes = [2usize, 1];
+        let raw = PJRT_Buffer_MemoryLayout {
+            struct_size: PJRT_Buffer_MemoryLayout_STRUCT_SIZE as usize,
+            extension_start: ptr::null_mut(),
+            __bindgen_anon_1: PJRT_Buffer_MemoryLayout__bindgen_ty_1 {
+                tiled: PJRT_Buffer_MemoryLayout_Tiled {
+                    struct_size: PJRT_Buffer_MemoryLayout_Tiled_STRUCT_SIZE as usize,
+                    extension_start: ptr::null_mut(),
+                    minor_to_major: minor_to_major.as_ptr(),
+                    minor_to_major_size: minor_to_major.len(),
+                    tile_dims: tile_dims.as_ptr(),
+                    tile_dim_sizes: tile_dim_sizes.as_ptr(),
+                    num_tiles: tile_dim_sizes.len(),
+                },
+            },
+            type_: PJRT_Buffer_MemoryLayout_Type_PJRT_Buffer_MemoryLayout_Type_Tiled,
+        };
+        assert_eq!(
+            unsafe { MemoryLayout::from_raw(&raw) }.unwrap(),
+            MemoryLayout::Tiled {
+                minor_to_major: vec![0, 1],
+                tile_dims: vec![vec![8, 128], vec![2]],
+            }
+        );
+    }
+
+    #[test]
+    fn decoder_rejects_null_arrays_and_unknown_types() {
+        let mut raw = PJRT_Buffer_MemoryLayout {
+            struct_size: PJRT_Buffer_MemoryLayout_STRUCT_SIZE as usize,
+            extension_start: ptr::null_mut(),
+            __bindgen_anon_1: PJRT_Buffer_MemoryLayout__bindgen_ty_1 {
+                strides: PJRT_Buffer_MemoryLayout_Strides {
+                    struct_size: PJRT_Buffer_MemoryLayout_Strides_STRUCT_SIZE as usize,
+                    extension_start: ptr::null_mut(),
+                    byte_strides: ptr::null(),
+                    num_byte_strides: 3,
+                },
+            },
+            type_: PJRT_Buffer_MemoryLayout_Type_PJRT_Buffer_MemoryLayout_Type_Strides,
+        };
+        assert!(unsafe { MemoryLayout::from_raw(&raw) }.is_err());
+
+        raw.__bindgen_anon_1.strides.num_byte_strides = 0;
+        assert_eq!(
+            unsafe { MemoryLayout::from_raw(&raw) }.unwrap(),
+            MemoryLayout::Strides {
+                byte_strides: vec![]
+            }
+        );
+
+        raw.type_ = 0x7f;
+        assert!(unsafe { MemoryLayout::from_raw(&raw) }.is_err());
+    }
+}
@@ -0,0 +1,405 @@
+use std::ffi::c_char;
+use std::mem;
+use std::ptr;
+use std::slice;
+
+use crate::pjrt::client::PJRTClient;
+use crate::pjrt::error::{
+    extension_missing, plugin_error, ContextFrame, OwnedPjrtError, PjrtErrorCode, PjrtResult,
+};
+use crate::pjrt::layout::MemoryLayout;
+use crate::pjrt::loader::{extension_has_field, find_extension, PjrtRuntime};
+use crate::pjrt::utils::BufferType;
+use crate::pjrt_sys::*;
+
+// Layouts from xla/pjrt/c/pjrt_c_api_layouts_extension.h, which the generated bindings
+// don't cover. Layouts are opaque there; the only way to read one is its serialized form,
+// XLA's layout text such as "{1,0:T(8,128)}".
+#[repr(C)]
+struct OpaqueLayout {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct SerializedLayout {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct MemoryLayoutDestroyArgs {
+    struct_size: usize,
+    extension_start: *mut PJRT_Extension_Base,
+    layout: *mut OpaqueLayout,
+}
+
+type MemoryLayoutDestroyFn =
+    unsafe extern "C" fn(args: *mut MemoryLayoutDestroyArgs) -> *mut PJRT_Error;
+
+#[repr(C)]
+struct MemoryLayoutSerializeArgs {
+    struct_size: usize,
+    extension_start: *mut PJRT_Extension_Base,
+    layout: *mut OpaqueLayout,
+    // Out: the bytes are owned by `serialized_layout`, released through the deleter.
+    serialized_bytes: *const c_char,
+    serialized_bytes_size: usize,
+    serialized_layout: *mut SerializedLayout,
+    serialized_layout_deleter: Option<unsafe extern "C" fn(s: *mut SerializedLayout)>,
+}
+
+type MemoryLayoutSerializeFn =
+    unsafe extern "C" fn(args: *mut MemoryLayoutSerializeArgs) -> *mut PJRT_Error;
+
+#[repr(C)]
+struct ClientGetDefaultLayoutArgs {
+    struct_size: usize,
+    extension_start: *mut PJRT_Extension_Base,
+    client: *mut PJRT_Client,
+    type_: PJRT_Buffer_Type,
+    dims: *const i64,
+    num_dims: usize,
+    layout: *mut OpaqueLayout,
+}
+
+type ClientGetDefaultLayoutFn =
+    unsafe extern "C" fn(args: *mut ClientGetDefaultLayoutArgs) -> *mut PJRT_Error;
+
+// Only the prefix of PJRT_Layouts_Extension used here.
+#[repr(C)]
+struct LayoutsExtension {
+    base: PJRT_Extension_Base,
+    memory_layout_destroy: Option<MemoryLayoutDestroyFn>,
+    memory_layout_serialize: Option<MemoryLayoutSerializeFn>,
+    client_get_default_layout: Option<ClientGetDefaultLayoutFn>,
+}
+
+unsafe fn find_layouts_extension<'e>(
+    start: *const PJRT_Extension_Base,
+) -> Option<&'e LayoutsExtension> {
+    let ext = unsafe { find_extension(start, PJRT_Extension_Type_PJRT_Extension_Type_Layouts)? };
+    let ext = unsafe { &*ext.cast::<LayoutsExtension>() };
+    extension_has_field(
+        &ext.base,
+        mem::offset_of!(LayoutsExtension, client_get_default_layout),
+    )
+    .then_some(ext)
+}
+
+const LAYOUTS_EXTENSION: &str = "the PJRT layouts extension";
+
+pub fn is_available(rt: &PjrtRuntime) -> bool {
+    unsafe { find_layouts_extension(rt.api().extension_start) }.is_some()
+}
+
+// The layout the plugin picks for a new `element_type[dims]` buffer on the client's
+// default memory. Pass it as the device layout of an upload to skip a relayout.
+pub fn default_layout_for(
+    client: &PJRTClient<'_>,
+    element_type: BufferType,
+    dims: &[i64],
+) -> PjrtResult<MemoryLayout> {
+    let ext = unsafe { find_layouts_extension(client.rt.api().extension_start) }
+        .ok_or_else(|| extension_missing(LAYOUTS_EXTENSION))?;
+    let raw = client.raw_checked()?;
+    let text = unsafe { default_layout_text(client.rt.api(), ext, raw, element_type.raw(), dims)? };
+    // The plugin's layout text isn't something MemoryLayout can represent.
+    deserialize_layout(&text).map_err(|e| {
+        OwnedPjrtError::new(
+            PjrtErrorCode::Unimplemented,
+            format!("unsupported device layout: {e}"),
+        )
+    })
+}
+
+unsafe fn default_layout_text(
+    api: &PJRT_Api,
+    ext: &LayoutsExtension,
+    client: *mut PJRT_Client,
+    element_type: PJRT_Buffer_Type,
+    dims: &[i64],
+) -> PjrtResult<String> {
+    let (Some(get), Some(serialize), Some(destroy)) = (
+        ext.client_get_default_layout,
+        ext.memory_layout_serialize,
+        ext.memory_layout_destroy,
+    ) else {
+        return Err(extension_missing(LAYOUTS_EXTENSION));
+    };
+
+    let mut args = ClientGetDefaultLayoutArgs {
+        struct_size: mem::size_of::<ClientGetDefaultLayoutArgs>(),
+        extension_start: ptr::null_mut(),
+        client,
+        type_: element_type,
+        dims: dims.as_ptr(),
+        num_dims: dims.len(),
+        layout: ptr::null_mut(),
+    };
+    let err = unsafe { get(&mut args) };
+    if !err.is_null() {
+        return Err(plugin_error(
+            api,
+            "PJRT_Layouts_PJRT_Client_GetDefaultLayout",
+            err,
+        ));
+    }
+    let layout = args.layout;
+    if layout.is_null() {
+        return Err(
+            OwnedPjrtError::new(PjrtErrorCode::Internal, "plugin returned no layout").with_context(
+                ContextFrame::new("PJRT_Layouts_PJRT_Client_GetDefaultLayout"),
+            ),
+        );
+    }
+
+    let mut args = MemoryLayoutSerializeArgs {
+        struct_size: mem::size_of::<MemoryLayoutSerializeArgs>(),
+        extension_start: ptr::null_mut(),
+        layout,
+        serialized_bytes: ptr::null(),
+        serialized_bytes_size: 0,
+        serialized_layout: ptr::null_mut(),
+        serialized_layout_deleter: None,
+    };
+    let err = unsafe { serialize(&mut args) };
+    let text = if !err.is_null() {
+        Err(plugin_error(
+            api,
+            "PJRT_Layouts_MemoryLayout_Serialize",
+            err,
+        ))
+    } else {
+        let bytes = match (args.serialized_bytes.is_null(), args.serialized_bytes_size) {
+            (true, _) | (_, 0) => &[][..],
+            (false, n) => unsafe { slice::from_raw_parts(args.serialized_bytes.cast::<u8>(), n) },
+        };
+        let text = String::from_utf8_lossy(bytes).into_owned();
+        if let Some(deleter) = args.serialized_layout_deleter {
+            unsafe { deleter(args.serialized_layout) };
+        }
+        Ok(text)
+    };
+
+    let mut args = MemoryLayoutDestroyArgs {
+        struct_size: mem::size_of::<MemoryLayoutDestroyArgs>(),
+        extension_start: ptr::null_mut(),
+        layout,
+    };
+    let err = unsafe { destroy(&mut args) };
+    if !err.is_null() {
+        log::warn!(
+            "{}",
+            plugin_error(api, "PJRT_Layouts_MemoryLayout_Destroy", err)
+        );
+    }
+    text
+}
+
+// XLA's layout text for `layout`: minor-to-major order, then tiles, e.g. "{1,0:T(8,128)}".
+// Byte-stride layouts have no XLA form.
+pub fn serialize_layout(layout: &MemoryLayout) -> Result<String, String> {
+    let (minor_to_major, tile_dims) = match layout {
+        MemoryLayout::Tiled {
+            minor_to_major,
+            tile_dims,
+        } => (minor_to_major, tile_dims),
+        MemoryLayout::Strides { .. } => {
+            return Err("byte-stride layouts have no XLA layout form".to_string())
+        }
+    };
+
+    let join = |dims: &[i64]| {
+        dims.iter()
+            .map(|&d| match d {
+                COMBINE_DIMENSION => "*".to_string(),
+                d => d.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+    let mut text = format!("{{{}", join(minor_to_major));
+    if !tile_dims.is_empty() {
+        text.push_str(":T");
+        for tile in tile_dims {
+            text.push_str(&format!("({})", join(tile)));
+        }
+    }
+    text.push('}');
+    Ok(text)
+}
+
+// Tile dimension printed as "*": folds the corresponding array dimensions together.
+const COMBINE_DIMENSION: i64 = i64::MIN;
+
+// Parses XLA's layout text back into a MemoryLayout. Only minor-to-major order and tiles
+// are supported; attributes such as element size or memory space are rejected rather than
+// silently dropped.
+pub fn deserialize_layout(text: &str) -> Result<MemoryLayout, String> {
+    let inner = text
+        .trim()
+        .strip_prefix('{')
+        .and_then(|t| t.strip_suffix('}'))
+        .ok_or_else(|| format!("layout {text:?} is not enclosed in braces"))?;
+    let (order, attributes) = inner.split_once(':').unwrap_or((inner, ""));
+
+    let parse_dims = |list: &str| -> Result<Vec<i64>, String> {
+        if list.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        list.split(',')
+            .map(|d| match d.trim() {
+                "*" => Ok(COMBINE_DIMENSION),
+                d => d
+                    .parse()
+                    .map_err(|_| format!("bad dimension {d:?} in layout {text:?}")),
+            })
+            .collect()
+    };
+    let minor_to_major = parse_dims(order)?;
+
+    let mut tile_dims = Vec::new();
+    let mut rest = attributes.trim();
+    if let Some(tiles) = rest.strip_prefix('T') {
+        rest = tiles;
+        while let Some(tile) = rest.strip_prefix('(') {
+            let (dims, after) = tile
+                .split_once(')')
+                .ok_or_else(|| format!("unterminated tile in layout {text:?}"))?;
+            tile_dims.push(parse_dims(dims)?);
+            rest = after;
+        }
+        if tile_dims.is_empty() {
+            return Err(format!("tile attribute without tiles in layout {text:?}"));
+        }
+    }
+    if !rest.trim().is_empty() {
+        return Err(format!("unsupported attribute {rest:?} in layout {text:?}"));
+    }
+
+    Ok(MemoryLayout::Tiled {
+        minor_to_major,
+        tile_dims,
+    })
+}
+
+#[cfg(test)]
+mod layouts_tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static SERIALIZED_FREED: AtomicUsize = AtomicUsize::new(0);
+    static LAYOUTS_DESTROYED: AtomicUsize = AtomicUsize::new(0);
+    static DEFAULT_TEXT: &str = "{0,1:T(8,128)}";
+
+    unsafe extern "C" fn fake_default(args: *mut ClientGetDefaultLayoutArgs) -> *mut PJRT_Error {
+        let args = unsafe { &mut *args };
+        let dims = unsafe { slice::from_raw_parts(args.dims, args.num_dims) };
+        assert_eq!(
+            (args.type_, dims),
+            (PJRT_Buffer_Type_PJRT_Buffer_Type_F32, &[16, 16][..])
+        );
+        args.layout = ptr::NonNull::dangling().as_ptr();
+        ptr::null_mut()
+    }
+
+    unsafe extern "C" fn free_serialized(_: *mut SerializedLayout) {
+        SERIALIZED_FREED.fetch_add(1, Ordering::SeqCst);
+    }
+
+    unsafe extern "C" fn fake_serialize(args: *mut MemoryLayoutSerializeArgs) -> *mut PJRT_Error {
+        let args = unsafe { &mut *args };
+        args.serialized_bytes = DEFAULT_TEXT.as_ptr().cast();
+        args.serialized_bytes_size = DEFAULT_TEXT.len();
+        args.serialized_layout = ptr::NonNull::dangling().as_ptr();
+        args.serialized_layout_deleter = Some(free_serialized);
+        ptr::null_mut()
+    }
+
+    unsafe extern "C" fn fake_destroy(args: *mut MemoryLayoutDestroyArgs) -> *mut PJRT_Error {
+        assert!(!unsafe { &*args }.layout.is_null());
+        LAYOUTS_DESTROYED.fetch_add(1, Ordering::SeqCst);
+        ptr::null_mut()
+    }
+
+    fn extension(size: usize) -> LayoutsExtension {
+        LayoutsExtension {
+            base: PJRT_Extension_Base {
+                struct_size: size,
+                type_: PJRT_Extension_Type_PJRT_Extension_Type_Layouts,
+                next: ptr::null_mut(),
+            },
+            memory_layout_destroy: Some(fake_destroy),
+            memory_layout_serialize: Some(fake_serialize),
+            client_get_default_layout: Some(fake_default),
+        }
+    }
+
+    #[test]
+    fn default_layout_is_read_through_its_text() {
+        let ext = extension(mem::size_of::<LayoutsExtension>());
+        let found = unsafe { find_layouts_extension(ptr::addr_of!(ext).cast()) }.unwrap();
+        // Only read on failure, which the fake extension never reports.
+        let api: PJRT_Api = unsafe { mem::zeroed() };
+        let text = unsafe {
+            default_layout_text(
+                &api,
+                found,
+                ptr::null_mut(),
+                PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+                &[16, 16],
+            )
+        }
+        .unwrap();
+        assert_eq!(text, DEFAULT_TEXT);
+        assert_eq!(SERIALIZED_FREED.load(Ordering::SeqCst), 1);
+        assert_eq!(LAYOUTS_DESTROYED.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            deserialize_layout(&text).unwrap(),
+            MemoryLayout::Tiled {
+                minor_to_major: vec![0, 1],
+                tile_dims: vec![vec![8, 128]],
+            }
+        );
+    }
+
+    #[test]
+    fn missing_or_old_extension_is_not_found() {
+        assert!(unsafe { find_layouts_extension(ptr::null()) }.is_none());
+        let old = extension(mem::offset_of!(LayoutsExtension, client_get_default_layout));
+        assert!(unsafe { find_layouts_extension(ptr::addr_of!(old).cast()) }.is_none());
+    }
+
+    #[test]
+    fn layout_text_round_trips() {
+        for text in ["{}", "{1,0}", "{0,1,2:T(8,128)(2,1)}", "{0:T(*,128)}"] {
+            let layout = deserialize_layout(text).unwrap();
+            assert_eq!(serialize_layout(&layout).unwrap(), text);
+        }
+        assert_eq!(
+            deserialize_layout(" { 1, 0 } ").unwrap(),
+            MemoryLayout::row_major(2)
+        );
+        assert_eq!(
+            serialize_layout(&MemoryLayout::column_major(3)).unwrap(),
+            "{0,1,2}"
+        );
+    }
+
+    #[test]
+    fn unsupported_layout_text_is_rejected() {
+        for text in [
+            "1,0",
+            "{1,x}",
+            "{1,0:T}",
+            "{1,0:T(8,128}",
+            "{0:T(1024)E(4)}",
+            "{0:S(1)}",
+        ] {
+            assert!(deserialize_layout(text).is_err(), "{text}");
+        }
+        let strides = MemoryLayout::Strides {
+            byte_strides: vec![4],
+        };
+        assert!(serialize_layout(&strides).is_err());
+    }
+}
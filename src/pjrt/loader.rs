@@ -5,16 +5,24 @@ use std::slice::from_raw_parts;
 use std::vec::Vec;
 
 use crate::pjrt::client::PJRTClient;
-use crate::pjrt::topology_desc::{PJRTNamedAttribute, PJRTNamedValue};
+use crate::pjrt::topology_desc::{
+    decode_named_values, encode_named_values, PJRTNamedAttribute, PJRTNamedValue, PluginAttributes,
+};
 use crate::pjrt_sys::*;
 
 type GetPjrtApiFn = unsafe extern "C" fn() -> *const PJRT_Api;
 
 pub struct PjrtRuntime {
-    _lib: Library,
+    // None only for the fake runtimes unit tests build around a hand-made API table.
+    _lib: Option<Library>,
     api: *const PJRT_Api,
 }
 
+// The API table is read-only after GetPjrtApi, and PJRT C API functions may be called from
+// any thread.
+unsafe impl Send for PjrtRuntime {}
+unsafe impl Sync for PjrtRuntime {}
+
 impl PjrtRuntime {
     pub fn load(plugin_path: &Path) -> Result<Self, String> {
         let lib = unsafe { Library::new(plugin_path) }
@@ -45,7 +53,15 @@ impl PjrtRuntime {
             );
         }
 
-        Ok(Self { _lib: lib, api })
+        Ok(Self {
+            _lib: Some(lib),
+            api,
+        })
+    }
+
+    #[cfg(test)]
+    pub(crate) fn from_api(api: &'static PJRT_Api) -> Self {
+        Self { _lib: None, api }
     }
 
     pub fn api(&self) -> &PJRT_Api {
@@ -93,7 +109,19 @@ impl PjrtRuntime {
         decode_named_values(args.attributes, args.num_attributes)
     }
 
+    pub fn plugin_attributes_typed(&self) -> Result<PluginAttributes, String> {
+        self.plugin_attributes().map(PluginAttributes::new)
+    }
+
     pub fn create_client(&self) -> Result<*mut PJRT_Client, String> {
+        self.create_client_with_options(&[])
+    }
+
+    pub fn create_client_with_options(
+        &self,
+        create_options: &[(&str, PJRTNamedValue)],
+    ) -> Result<*mut PJRT_Client, String> {
+        let create_options = encode_named_values(create_options);
         let f = self
             .api()
             .PJRT_Client_Create
@@ -102,8 +130,8 @@ impl PjrtRuntime {
         let mut args = PJRT_Client_Create_Args {
             struct_size: PJRT_Client_Create_Args_STRUCT_SIZE as usize,
             extension_start: ptr::null_mut(),
-            create_options: ptr::null(),
-            num_options: 0,
+            create_options: create_options.as_ptr(),
+            num_options: create_options.len(),
             kv_get_callback: None,
             kv_get_user_arg: ptr::null_mut(),
             kv_put_callback: None,
@@ -130,6 +158,14 @@ impl PjrtRuntime {
         Ok(PJRTClient::new(self, raw))
     }
 
+    pub fn create_client_raii_with_options(
+        &self,
+        create_options: &[(&str, PJRTNamedValue)],
+    ) -> Result<PJRTClient<'_>, String> {
+        let raw = self.create_client_with_options(create_options)?;
+        Ok(PJRTClient::new(self, raw))
+    }
+
     pub fn destroy_client(&self, client: *mut PJRT_Client) -> Result<(), String> {
         let f = self
             .api()
@@ -192,76 +228,6 @@ impl PjrtRuntime {
     }
 }
 
-fn decode_named_values(
-    attrs: *const PJRT_NamedValue,
-    num_attrs: usize,
-) -> Result<Vec<PJRTNamedAttribute>, String> {
-    const NV_STRING: PJRT_NamedValue_Type = PJRT_NamedValue_Type_PJRT_NamedValue_kString;
-    const NV_INT64: PJRT_NamedValue_Type = PJRT_NamedValue_Type_PJRT_NamedValue_kInt64;
-    const NV_INT64_LIST: PJRT_NamedValue_Type = PJRT_NamedValue_Type_PJRT_NamedValue_kInt64List;
-    const NV_FLOAT: PJRT_NamedValue_Type = PJRT_NamedValue_Type_PJRT_NamedValue_kFloat;
-    const NV_BOOL: PJRT_NamedValue_Type = PJRT_NamedValue_Type_PJRT_NamedValue_kBool;
-
-    if num_attrs == 0 {
-        return Ok(Vec::new());
-    }
-    if attrs.is_null() {
-        return Err("NamedValue pointer is null with nonzero count".to_string());
-    }
-
-    let values = unsafe { from_raw_parts(attrs, num_attrs) };
-    let mut out = Vec::with_capacity(values.len());
-    for value in values {
-        if value.name.is_null() && value.name_size != 0 {
-            return Err("NamedValue name pointer is null".to_string());
-        }
-
-        let name_bytes = if value.name_size == 0 {
-            &[][..]
-        } else {
-            unsafe { from_raw_parts(value.name as *const u8, value.name_size) }
-        };
-        let name = String::from_utf8_lossy(name_bytes).into_owned();
-
-        let parsed = match value.type_ {
-            NV_STRING => {
-                let ptr = unsafe { value.__bindgen_anon_1.string_value };
-                if ptr.is_null() && value.value_size != 0 {
-                    return Err(format!("NamedValue '{name}' has null string pointer"));
-                }
-                let bytes = if value.value_size == 0 {
-                    &[][..]
-                } else {
-                    unsafe { from_raw_parts(ptr as *const u8, value.value_size) }
-                };
-                PJRTNamedValue::String(String::from_utf8_lossy(bytes).into_owned())
-            }
-            NV_INT64 => PJRTNamedValue::Int64(unsafe { value.__bindgen_anon_1.int64_value }),
-            NV_INT64_LIST => {
-                let ptr = unsafe { value.__bindgen_anon_1.int64_array_value };
-                if ptr.is_null() && value.value_size != 0 {
-                    return Err(format!("NamedValue '{name}' has null int64 list pointer"));
-                }
-                let ints = if value.value_size == 0 {
-                    Vec::new()
-                } else {
-                    unsafe { from_raw_parts(ptr, value.value_size).to_vec() }
-                };
-                PJRTNamedValue::Int64List(ints)
-            }
-            NV_FLOAT => PJRTNamedValue::Float(unsafe { value.__bindgen_anon_1.float_value }),
-            NV_BOOL => PJRTNamedValue::Bool(unsafe { value.__bindgen_anon_1.bool_value }),
-            other => return Err(format!("NamedValue '{name}' has unknown type tag {other}")),
-        };
-
-        out.push(PJRTNamedAttribute {
-            name,
-            value: parsed,
-        });
-    }
-    Ok(out)
-}
-
 pub(crate) fn error_to_string(api: &PJRT_Api, error: *mut PJRT_Error) -> String {
     if error.is_null() {
         return "unknown PJRT error".to_string();
@@ -305,6 +271,29 @@ pub(crate) fn error_to_string(api: &PJRT_Api, error: *mut PJRT_Error) -> String
     msg
 }
 
+// First extension of type `ty` in a PJRT extension chain such as PJRT_Api's. `start` must be
+// null or the head of a valid chain.
+pub(crate) unsafe fn find_extension(
+    start: *const PJRT_Extension_Base,
+    ty: PJRT_Extension_Type,
+) -> Option<*const PJRT_Extension_Base> {
+    let mut next = start;
+    while !next.is_null() {
+        let ext = unsafe { &*next };
+        if ext.type_ == ty {
+            return Some(next);
+        }
+        next = ext.next;
+    }
+    None
+}
+
+// Whether an extension struct that reports `base.struct_size` is long enough to hold the
+// function pointer at `offset`; older plugins ship shorter structs.
+pub(crate) fn extension_has_field(base: &PJRT_Extension_Base, offset: usize) -> bool {
+    base.struct_size >= offset + std::mem::size_of::<usize>()
+}
+
 #[cfg(test)]
 mod pjrt_runtime_tests {
     use crate::pjrt::loader::PjrtRuntime;
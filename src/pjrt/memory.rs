@@ -1,9 +1,12 @@
+use std::fmt;
 use std::ptr;
 use std::ptr::null_mut;
 use std::slice::from_raw_parts;
 
 use crate::pjrt::device::PJRTDevice;
-use crate::pjrt::loader::{error_to_string, PjrtRuntime};
+use crate::pjrt::error::{PJRTError, PjrtErrorCode};
+use crate::pjrt::loader::PjrtRuntime;
+use crate::pjrt::utils::DebugResult;
 use crate::pjrt_sys::*;
 
 pub struct PJRTMemory<'a> {
@@ -11,83 +14,276 @@ pub struct PJRTMemory<'a> {
     pub raw: *mut PJRT_Memory,
 }
 
-impl <'a> PJRTMemory<'a> {
+impl fmt::Debug for PJRTMemory<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PJRTMemory")
+            .field("id", &DebugResult::debug(self.id().map_err(String::from)))
+            .field(
+                "kind",
+                &DebugResult::debug(self.kind().map_err(String::from)),
+            )
+            .field(
+                "kind_id",
+                &DebugResult::debug(self.kind_id().map_err(String::from)),
+            )
+            .finish()
+    }
+}
+
+// Prints the plugin's ToString form, falling back to the kind when that isn't available.
+impl fmt::Display for PJRTMemory<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.to_string(), self.kind()) {
+            (Ok(s), _) if !s.is_empty() => f.write_str(&s),
+            (_, Ok(kind)) => write!(f, "{kind} memory"),
+            _ => f.write_str("<unknown memory>"),
+        }
+    }
+}
+
+// Properties of a memory space at the time of the snapshot. Fields are None when the
+// plugin fails the corresponding query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct MemoryInfo {
+    pub id: Option<usize>,
+    pub kind: Option<String>,
+    pub kind_id: Option<i32>,
+    pub description: Option<String>,
+    pub debug_string: Option<String>,
+    pub addressable_by_device_ids: Option<Vec<i32>>,
+}
+
+// The memory kinds XLA defines. Plugins may report others, which stay plain strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MemoryKind {
+    Device,
+    PinnedHost,
+    UnpinnedHost,
+}
+
+// XLA derives kind ids from the kind name with tsl::Fingerprint32 (farmhashmk Hash32),
+// except that stream-executor plugins report device memory as 0.
+const DEVICE_KIND_ID: i32 = fingerprint32(b"device") as i32;
+const PINNED_HOST_KIND_ID: i32 = fingerprint32(b"pinned_host") as i32;
+const UNPINNED_HOST_KIND_ID: i32 = fingerprint32(b"unpinned_host") as i32;
+
+impl MemoryKind {
+    pub const ALL: [MemoryKind; 3] = [
+        MemoryKind::Device,
+        MemoryKind::PinnedHost,
+        MemoryKind::UnpinnedHost,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MemoryKind::Device => "device",
+            MemoryKind::PinnedHost => "pinned_host",
+            MemoryKind::UnpinnedHost => "unpinned_host",
+        }
+    }
+
+    pub fn id(self) -> i32 {
+        match self {
+            MemoryKind::Device => DEVICE_KIND_ID,
+            MemoryKind::PinnedHost => PINNED_HOST_KIND_ID,
+            MemoryKind::UnpinnedHost => UNPINNED_HOST_KIND_ID,
+        }
+    }
+
+    pub fn from_id(id: i32) -> Option<Self> {
+        match id {
+            0 | DEVICE_KIND_ID => Some(MemoryKind::Device),
+            PINNED_HOST_KIND_ID => Some(MemoryKind::PinnedHost),
+            UNPINNED_HOST_KIND_ID => Some(MemoryKind::UnpinnedHost),
+            _ => None,
+        }
+    }
+}
+
+impl std::str::FromStr for MemoryKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        MemoryKind::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == s)
+            .ok_or_else(|| format!("unknown memory kind {s:?}"))
+    }
+}
+
+impl AsRef<str> for MemoryKind {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Display for MemoryKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// farmhashmk Hash32 for inputs of up to 24 bytes, which covers every kind name above.
+const fn fingerprint32(s: &[u8]) -> u32 {
+    const C1: u32 = 0xcc9e2d51;
+    const C2: u32 = 0x1b873593;
+
+    const fn fetch(s: &[u8], i: usize) -> u32 {
+        u32::from_le_bytes([s[i], s[i + 1], s[i + 2], s[i + 3]])
+    }
+    const fn fmix(mut h: u32) -> u32 {
+        h ^= h >> 16;
+        h = h.wrapping_mul(0x85ebca6b);
+        h ^= h >> 13;
+        h = h.wrapping_mul(0xc2b2ae35);
+        h ^ (h >> 16)
+    }
+    const fn mur(a: u32, h: u32) -> u32 {
+        let a = a.wrapping_mul(C1).rotate_right(17).wrapping_mul(C2);
+        let h = (h ^ a).rotate_right(19);
+        h.wrapping_mul(5).wrapping_add(0xe6546b64)
+    }
+
+    let len = s.len();
+    assert!(len <= 24, "fingerprint32 only handles short names");
+    if len <= 4 {
+        let mut b: u32 = 0;
+        let mut c: u32 = 9;
+        let mut i = 0;
+        while i < len {
+            b = b.wrapping_mul(C1).wrapping_add(s[i] as i8 as u32);
+            c ^= b;
+            i += 1;
+        }
+        fmix(mur(b, mur(len as u32, c)))
+    } else if len <= 12 {
+        let a = (len as u32).wrapping_add(fetch(s, 0));
+        let b = (len as u32 * 5).wrapping_add(fetch(s, len - 4));
+        let c = 9u32.wrapping_add(fetch(s, (len >> 1) & 4));
+        let d = len as u32 * 5;
+        fmix(mur(c, mur(b, mur(a, d))))
+    } else {
+        let mut a = fetch(s, (len >> 1) - 4);
+        let b = fetch(s, 4);
+        let c = fetch(s, len - 8);
+        let d = fetch(s, len >> 1);
+        let e = fetch(s, 0);
+        let f = fetch(s, len - 4);
+        let mut h = d.wrapping_mul(C1).wrapping_add(len as u32);
+        a = a.rotate_right(12).wrapping_add(f);
+        h = mur(c, h).wrapping_add(a);
+        a = a.rotate_right(3).wrapping_add(c);
+        h = mur(e, h).wrapping_add(a);
+        a = a.wrapping_add(f).rotate_right(12).wrapping_add(d);
+        h = mur(b, h).wrapping_add(a);
+        fmix(h)
+    }
+}
+
+impl<'a> PJRTMemory<'a> {
     pub(crate) fn new(rt: &'a PjrtRuntime, raw: *mut PJRT_Memory) -> Self {
         Self { rt, raw }
     }
 
-    fn raw_checked(&self) -> Result<*mut PJRT_Memory, String> {
+    fn raw_checked(&self) -> Result<*mut PJRT_Memory, PJRTError<'a>> {
         if self.raw.is_null() {
-            Err("PJRT_Memory is null".to_string())
+            Err(PJRTError::with_code(
+                self.rt,
+                PjrtErrorCode::InvalidArgument,
+                "PJRT_Memory is null",
+            ))
         } else {
             Ok(self.raw)
         }
     }
 
-    pub fn id(&self) -> Result<usize, String> {
+    fn missing(&self, symbol: &str) -> PJRTError<'a> {
+        PJRTError::missing_symbol(self.rt, symbol)
+    }
+
+    // Copies a plugin-owned string, rejecting a null pointer paired with a nonzero size.
+    fn owned_string(
+        &self,
+        what: &str,
+        data: *const std::ffi::c_char,
+        size: usize,
+    ) -> Result<String, PJRTError<'a>> {
+        if size == 0 {
+            return Ok(String::new());
+        }
+        if data.is_null() {
+            return Err(PJRTError::with_code(
+                self.rt,
+                PjrtErrorCode::Internal,
+                format!("{what} returned a null string with nonzero size"),
+            ));
+        }
+        let bytes = unsafe { from_raw_parts(data.cast::<u8>(), size) };
+        Ok(String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    pub fn id(&self) -> Result<usize, PJRTError<'a>> {
         let raw = self.raw_checked()?;
 
-        let func = self.rt
-            .api().PJRT_Memory_Id
-            .ok_or("PJRT_Memory_Id symbol not found")?;
+        let f = self
+            .rt
+            .api()
+            .PJRT_Memory_Id
+            .ok_or_else(|| self.missing("PJRT_Memory_Id"))?;
 
         let mut args = PJRT_Memory_Id_Args {
             struct_size: PJRT_Memory_Id_Args_STRUCT_SIZE as usize,
             extension_start: null_mut(),
             memory: raw,
-            id: 0
-        };
-
-        let err = unsafe {
-            func(&mut args)
+            id: 0,
         };
 
+        let err = unsafe { f(&mut args) };
         if !err.is_null() {
-            Err(error_to_string(self.rt.api(), err).to_string())
-        } else {
-            Ok(args.id as usize)
+            return Err(PJRTError::new(self.rt, err));
         }
+        usize::try_from(args.id).map_err(|_| {
+            PJRTError::with_code(
+                self.rt,
+                PjrtErrorCode::Internal,
+                format!("PJRT_Memory_Id returned negative id {}", args.id),
+            )
+        })
     }
 
-    pub fn kind(&self) -> Result<String, String> {
+    pub fn kind(&self) -> Result<String, PJRTError<'a>> {
         let raw = self.raw_checked()?;
 
-        let func = self.rt
-            .api().PJRT_Memory_Kind
-            .ok_or("PJRT_Memory_Kind symbol not found")?;
+        let f = self
+            .rt
+            .api()
+            .PJRT_Memory_Kind
+            .ok_or_else(|| self.missing("PJRT_Memory_Kind"))?;
 
         let mut args = PJRT_Memory_Kind_Args {
             struct_size: PJRT_Memory_Kind_Args_STRUCT_SIZE as usize,
             extension_start: null_mut(),
             memory: raw,
             kind: ptr::null(),
-            kind_size: 0
-        };
-
-        let err = unsafe {
-            func(&mut args)
+            kind_size: 0,
         };
 
+        let err = unsafe { f(&mut args) };
         if !err.is_null() {
-            Err(error_to_string(self.rt.api(), err).to_string())
-        } else if args.kind_size == 0 {
-            Ok(String::new())
-        } else if args.kind.is_null() {
-            Err("PJRT_Memory_Kind returned null kind with nonzero size".to_string())
-        } else {
-            let bytes = unsafe { from_raw_parts(args.kind as *const u8, args.kind_size) };
-            Ok(String::from_utf8_lossy(bytes).into_owned())
+            return Err(PJRTError::new(self.rt, err));
         }
+        self.owned_string("PJRT_Memory_Kind", args.kind, args.kind_size)
     }
 
-    pub fn kind_id(&self) -> Result<i32, String> {
+    pub fn kind_id(&self) -> Result<i32, PJRTError<'a>> {
         let raw = self.raw_checked()?;
 
-        let func = self
+        let f = self
             .rt
             .api()
             .PJRT_Memory_Kind_Id
-            .ok_or("PJRT_Memory_Kind_Id symbol not found")?;
+            .ok_or_else(|| self.missing("PJRT_Memory_Kind_Id"))?;
 
         let mut args = PJRT_Memory_Kind_Id_Args {
             struct_size: PJRT_Memory_Kind_Id_Args_STRUCT_SIZE as usize,
@@ -96,83 +292,85 @@ impl <'a> PJRTMemory<'a> {
             kind_id: 0,
         };
 
-        let err = unsafe { func(&mut args) };
+        let err = unsafe { f(&mut args) };
         if !err.is_null() {
-            Err(error_to_string(self.rt.api(), err))
-        } else {
-            Ok(args.kind_id)
+            return Err(PJRTError::new(self.rt, err));
+        }
+        Ok(args.kind_id)
+    }
+
+    // None for kinds outside MemoryKind. Tries the integer id first, which needs no copy.
+    pub fn memory_kind(&self) -> Result<Option<MemoryKind>, PJRTError<'a>> {
+        if let Some(kind) = self.kind_id().ok().and_then(MemoryKind::from_id) {
+            return Ok(Some(kind));
         }
+        Ok(self.kind()?.parse().ok())
     }
 
-    pub fn debug_string(&self) -> Result<String, String> {
+    pub fn is_kind(&self, kind: MemoryKind) -> bool {
+        matches!(self.memory_kind(), Ok(Some(k)) if k == kind)
+    }
+
+    pub fn debug_string(&self) -> Result<String, PJRTError<'a>> {
         let raw = self.raw_checked()?;
 
-        let funct = self.rt
-            .api().PJRT_Memory_DebugString
-            .ok_or("PJRT_Memory_DebugString symbol not found")?;
+        let f = self
+            .rt
+            .api()
+            .PJRT_Memory_DebugString
+            .ok_or_else(|| self.missing("PJRT_Memory_DebugString"))?;
 
         let mut args = PJRT_Memory_DebugString_Args {
             struct_size: PJRT_Memory_DebugString_Args_STRUCT_SIZE as usize,
             extension_start: null_mut(),
             memory: raw,
             debug_string: ptr::null(),
-            debug_string_size: 0
-        };
-
-        let err = unsafe {
-            funct(&mut args)
+            debug_string_size: 0,
         };
 
+        let err = unsafe { f(&mut args) };
         if !err.is_null() {
-            Err(error_to_string(self.rt.api(), err).to_string())
-        } else if args.debug_string_size == 0 {
-            Ok(String::new())
-        } else if args.debug_string.is_null() {
-            Err("PJRT_Memory_DebugString returned null debug string with nonzero size".to_string())
-        } else {
-            let bytes = unsafe { from_raw_parts(args.debug_string as *const u8, args.debug_string_size) };
-            Ok(String::from_utf8_lossy(bytes).into_owned())
+            return Err(PJRTError::new(self.rt, err));
         }
+        self.owned_string(
+            "PJRT_Memory_DebugString",
+            args.debug_string,
+            args.debug_string_size,
+        )
     }
-    
-    
-    pub fn to_string(&self) -> Result<String, String> {
+
+    pub fn to_string(&self) -> Result<String, PJRTError<'a>> {
         let raw = self.raw_checked()?;
 
-        let function = self.rt
-            .api().PJRT_Memory_ToString
-            .ok_or("PJRT_Memory_ToString symbol not found")?;
+        let f = self
+            .rt
+            .api()
+            .PJRT_Memory_ToString
+            .ok_or_else(|| self.missing("PJRT_Memory_ToString"))?;
 
         let mut args = PJRT_Memory_ToString_Args {
             struct_size: PJRT_Memory_ToString_Args_STRUCT_SIZE as usize,
             extension_start: null_mut(),
             memory: raw,
             to_string: ptr::null(),
-            to_string_size: 0
-        };
-
-        let err = unsafe {
-            function(&mut args)
+            to_string_size: 0,
         };
 
+        let err = unsafe { f(&mut args) };
         if !err.is_null() {
-            Err(error_to_string(self.rt.api(), err).to_string())
-        } else if args.to_string_size == 0 {
-            Ok(String::new())
-        } else if args.to_string.is_null() {
-            Err("PJRT_Memory_ToString returned null string with nonzero size".to_string())
-        } else {
-            let bytes = unsafe { from_raw_parts(args.to_string as *const u8, args.to_string_size) };
-            Ok(String::from_utf8_lossy(bytes).into_owned())
+            return Err(PJRTError::new(self.rt, err));
         }
+        self.owned_string("PJRT_Memory_ToString", args.to_string, args.to_string_size)
     }
 
-    pub fn addressable_by_device(&self) -> Result<Vec<PJRTDevice<'a>>, String> {
+    pub fn addressable_by_devices(&self) -> Result<Vec<PJRTDevice<'a>>, PJRTError<'a>> {
         let raw = self.raw_checked()?;
 
-        let function = self.rt
-            .api().PJRT_Memory_AddressableByDevices
-            .ok_or("PJRT_Memory_AddressableByDevices symbol not found")?;
+        let f = self
+            .rt
+            .api()
+            .PJRT_Memory_AddressableByDevices
+            .ok_or_else(|| self.missing("PJRT_Memory_AddressableByDevices"))?;
 
         let mut args = PJRT_Memory_AddressableByDevices_Args {
             struct_size: PJRT_Memory_AddressableByDevices_Args_STRUCT_SIZE as usize,
@@ -182,25 +380,93 @@ impl <'a> PJRTMemory<'a> {
             num_devices: 0,
         };
 
-        let err = unsafe { function(&mut args) };
+        let err = unsafe { f(&mut args) };
         if !err.is_null() {
-            return Err(error_to_string(self.rt.api(), err));
+            return Err(PJRTError::new(self.rt, err));
         }
         if args.num_devices == 0 {
             return Ok(Vec::new());
         }
         if args.devices.is_null() {
-            return Err(
-                "PJRT_Memory_AddressableByDevices returned null devices with nonzero count"
-                    .to_string(),
-            );
+            return Err(PJRTError::with_code(
+                self.rt,
+                PjrtErrorCode::Internal,
+                "PJRT_Memory_AddressableByDevices returned null devices with nonzero count",
+            ));
         }
 
         let devices = unsafe { from_raw_parts(args.devices, args.num_devices) };
-        Ok(devices
+        devices
             .iter()
-            .copied()
-            .map(|raw_device| PJRTDevice::new(self.rt, raw_device))
-            .collect())
+            .map(|&raw_device| {
+                PJRTDevice::new(self.rt, raw_device).map_err(|e| {
+                    PJRTError::from_wrapper_message(self.rt, PjrtErrorCode::Internal, e)
+                })
+            })
+            .collect()
+    }
+
+    // The devices that can address this memory, i.e. where a computation reading it may run.
+    pub fn devices(&self) -> Result<Vec<PJRTDevice<'a>>, PJRTError<'a>> {
+        self.addressable_by_devices()
+    }
+
+    pub fn info(&self) -> MemoryInfo {
+        MemoryInfo {
+            id: self.id().ok(),
+            kind: self.kind().ok(),
+            kind_id: self.kind_id().ok(),
+            description: self.to_string().ok(),
+            debug_string: self.debug_string().ok(),
+            addressable_by_device_ids: self
+                .addressable_by_devices()
+                .ok()
+                .map(|devices| devices.iter().map(PJRTDevice::id).collect()),
+        }
+    }
+
+    #[deprecated(note = "use addressable_by_devices")]
+    pub fn addressable_by_device(&self) -> Result<Vec<PJRTDevice<'a>>, String> {
+        Ok(self.addressable_by_devices()?)
+    }
+}
+
+#[cfg(test)]
+mod memory_tests {
+    use super::*;
+
+    #[test]
+    fn memory_kinds_round_trip_through_strings_and_ids() {
+        for kind in MemoryKind::ALL {
+            assert_eq!(kind.as_str().parse::<MemoryKind>(), Ok(kind));
+            assert_eq!(MemoryKind::from_id(kind.id()), Some(kind));
+            assert_eq!(kind.to_string(), kind.as_ref());
+        }
+        let ids: Vec<i32> = MemoryKind::ALL.iter().map(|kind| kind.id()).collect();
+        assert!(ids.iter().all(|&id| id != 0));
+        assert!(ids[0] != ids[1] && ids[1] != ids[2] && ids[0] != ids[2]);
+    }
+
+    #[test]
+    fn unknown_memory_kinds_are_rejected() {
+        assert_eq!(MemoryKind::from_id(0), Some(MemoryKind::Device));
+        assert_eq!(MemoryKind::from_id(1), None);
+        assert!("Device".parse::<MemoryKind>().is_err());
+        assert!("tpu_hbm".parse::<MemoryKind>().is_err());
+    }
+
+    #[test]
+    fn fingerprint_covers_every_short_length() {
+        let name = b"abcdefghijklmnopqrstuvwx";
+        let hashes: Vec<u32> = (0..=name.len())
+            .map(|n| fingerprint32(&name[..n]))
+            .collect();
+        for (i, a) in hashes.iter().enumerate() {
+            assert!(
+                hashes[i + 1..].iter().all(|b| b != a),
+                "collision at length {i}"
+            );
+        }
+        assert_eq!(fingerprint32(b"pinned_host"), fingerprint32(b"pinned_host"));
     }
 }
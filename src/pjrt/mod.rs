@@ -1,13 +1,29 @@
 pub mod buffer;
 pub mod client;
 pub mod compile;
+pub mod compile_options;
+pub mod copy_to_device_stream;
+pub mod cross_host;
 pub mod device;
+pub mod dlpack;
+pub mod error;
 pub mod event;
-pub mod execute_context;
 pub mod executable;
+pub mod executable_cache;
+pub mod executable_file;
+pub mod execute_callbacks;
+pub mod execute_context;
+pub mod ffi;
+pub mod host_channel;
+pub mod host_to_device_manager;
+pub mod layout;
+pub mod layouts;
 pub mod loader;
-pub mod topology_desc;
 pub mod memory;
-pub mod error;
-pub mod host_to_device_manager;
-pub mod copy_to_device_stream;
+#[cfg(feature = "npy")]
+pub mod npy;
+pub mod platform;
+pub mod stream;
+pub mod topology_desc;
+pub mod topology_file;
+pub mod utils;
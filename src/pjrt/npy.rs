@@ -0,0 +1,265 @@
+use crate::pjrt::utils::BufferType;
+
+const MAGIC: &[u8; 6] = b"\x93NUMPY";
+// numpy pads the preamble plus header to a multiple of 64 bytes.
+const HEADER_ALIGN: usize = 64;
+
+// Only little-endian (or byte-order-free) descriptors are accepted.
+const DESCRS: [(BufferType, &str); 14] = [
+    (BufferType::Pred, "|b1"),
+    (BufferType::S8, "|i1"),
+    (BufferType::U8, "|u1"),
+    (BufferType::S16, "<i2"),
+    (BufferType::S32, "<i4"),
+    (BufferType::S64, "<i8"),
+    (BufferType::U16, "<u2"),
+    (BufferType::U32, "<u4"),
+    (BufferType::U64, "<u8"),
+    (BufferType::F16, "<f2"),
+    (BufferType::F32, "<f4"),
+    (BufferType::F64, "<f8"),
+    (BufferType::C64, "<c8"),
+    (BufferType::C128, "<c16"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NpyHeader {
+    pub element_type: BufferType,
+    pub dims: Vec<i64>,
+    pub fortran_order: bool,
+}
+
+pub fn npy_descr(ty: BufferType) -> Option<&'static str> {
+    DESCRS.iter().find(|(t, _)| *t == ty).map(|&(_, d)| d)
+}
+
+pub fn buffer_type_from_descr(descr: &str) -> Result<BufferType, String> {
+    // '<', '|' and (on little-endian hosts) '=' all mean the bytes need no swapping.
+    let normalized = match descr.split_at_checked(1) {
+        Some(("=", rest)) if cfg!(target_endian = "little") => format!("<{rest}"),
+        Some((">", _)) => {
+            return Err(format!(
+                "npy dtype {descr:?} is big-endian; only little-endian data is supported"
+            ))
+        }
+        _ => descr.to_string(),
+    };
+    DESCRS
+        .iter()
+        .find(|(_, d)| {
+            *d == normalized || (d.starts_with('|') && normalized.get(1..) == d.get(1..))
+        })
+        .map(|&(t, _)| t)
+        .ok_or_else(|| format!("npy dtype {descr:?} has no PJRT element type"))
+}
+
+// Splits a .npy file into its header and data section.
+pub fn parse_npy(bytes: &[u8]) -> Result<(NpyHeader, &[u8]), String> {
+    if bytes.len() < 10 || &bytes[..6] != MAGIC {
+        return Err("not a .npy file: bad magic".to_string());
+    }
+    let (header_len, start) = match bytes[6] {
+        1 => (u16::from_le_bytes([bytes[8], bytes[9]]) as usize, 10usize),
+        2 | 3 => {
+            if bytes.len() < 12 {
+                return Err("truncated .npy header".to_string());
+            }
+            let len = u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]);
+            (len as usize, 12)
+        }
+        major => return Err(format!("unsupported .npy format version {major}")),
+    };
+    let end = start
+        .checked_add(header_len)
+        .filter(|&end| end <= bytes.len())
+        .ok_or("truncated .npy header")?;
+    let text = std::str::from_utf8(&bytes[start..end])
+        .map_err(|_| "npy header is not valid text".to_string())?;
+
+    let descr = dict_value(text, "descr")?;
+    let descr = descr
+        .strip_prefix('\'')
+        .and_then(|d| d.strip_suffix('\''))
+        .ok_or_else(|| format!("npy descr {descr} is not a plain dtype string"))?;
+    let fortran_order = match dict_value(text, "fortran_order")? {
+        "True" => true,
+        "False" => false,
+        other => return Err(format!("npy fortran_order has unexpected value {other}")),
+    };
+    let shape = dict_value(text, "shape")?;
+    let dims = shape
+        .strip_prefix('(')
+        .and_then(|s| s.strip_suffix(')'))
+        .ok_or_else(|| format!("npy shape {shape} is not a tuple"))?
+        .split(',')
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+        .map(|d| {
+            d.parse::<i64>()
+                .map_err(|_| format!("npy shape entry {d:?} is not an integer"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let header = NpyHeader {
+        element_type: buffer_type_from_descr(descr)?,
+        dims,
+        fortran_order,
+    };
+    Ok((header, &bytes[end..]))
+}
+
+// Value text for `key` in numpy's header dict literal. Values are a quoted string, a bare
+// word or a parenthesised tuple, which is all numpy itself ever writes.
+fn dict_value<'t>(text: &'t str, key: &str) -> Result<&'t str, String> {
+    let missing = || format!("npy header has no {key:?} entry");
+    let at = text
+        .find(&format!("'{key}'"))
+        .or_else(|| text.find(&format!("\"{key}\"")))
+        .ok_or_else(missing)?;
+    let rest = text[at + key.len() + 2..].trim_start();
+    let rest = rest.strip_prefix(':').ok_or_else(missing)?.trim_start();
+    let len = match rest.chars().next() {
+        Some('(') => rest.find(')').map(|i| i + 1),
+        Some(q @ ('\'' | '"')) => rest[1..].find(q).map(|i| i + 2),
+        Some(_) => rest.find([',', '}']),
+        None => None,
+    }
+    .ok_or_else(|| format!("npy header entry {key:?} is malformed"))?;
+    Ok(rest[..len].trim())
+}
+
+// Version 1.0 when the header fits a u16 length, otherwise 2.0.
+pub fn encode_npy_header(header: &NpyHeader) -> Result<Vec<u8>, String> {
+    let descr = npy_descr(header.element_type)
+        .ok_or_else(|| format!("element type {} has no npy dtype", header.element_type))?;
+    let shape = match header.dims.as_slice() {
+        [d] => format!("({d},)"),
+        dims => format!(
+            "({})",
+            dims.iter()
+                .map(i64::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let fortran = if header.fortran_order {
+        "True"
+    } else {
+        "False"
+    };
+    let mut dict =
+        format!("{{'descr': '{descr}', 'fortran_order': {fortran}, 'shape': {shape}, }}");
+
+    let (version, preamble) = if dict.len() + 11 + HEADER_ALIGN <= u16::MAX as usize {
+        (1u8, 10)
+    } else {
+        (2u8, 12)
+    };
+    let unpadded = preamble + dict.len() + 1;
+    dict.push_str(&" ".repeat((HEADER_ALIGN - unpadded % HEADER_ALIGN) % HEADER_ALIGN));
+    dict.push('\n');
+
+    let mut out = Vec::with_capacity(preamble + dict.len());
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&[version, 0]);
+    if version == 1 {
+        out.extend_from_slice(&(dict.len() as u16).to_le_bytes());
+    } else {
+        out.extend_from_slice(&(dict.len() as u32).to_le_bytes());
+    }
+    out.extend_from_slice(dict.as_bytes());
+    Ok(out)
+}
+
+#[cfg(test)]
+mod npy_tests {
+    use super::*;
+
+    // np.save of np.arange(6, dtype='<i4').reshape(2, 3).
+    const ARANGE_I4_2X3: &[u8] = include_bytes!("../../tests/fixtures/arange_i4_2x3.npy");
+
+    #[test]
+    fn parses_numpy_fixture() {
+        let (header, data) = parse_npy(ARANGE_I4_2X3).unwrap();
+        assert_eq!(
+            header,
+            NpyHeader {
+                element_type: BufferType::S32,
+                dims: vec![2, 3],
+                fortran_order: false,
+            }
+        );
+        let values: Vec<i32> = data
+            .chunks_exact(4)
+            .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        assert_eq!(values, vec![0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn encoded_header_matches_numpy() {
+        let header = NpyHeader {
+            element_type: BufferType::S32,
+            dims: vec![2, 3],
+            fortran_order: false,
+        };
+        let encoded = encode_npy_header(&header).unwrap();
+        assert_eq!(encoded.len() % HEADER_ALIGN, 0);
+        assert_eq!(&ARANGE_I4_2X3[..encoded.len()], encoded.as_slice());
+    }
+
+    #[test]
+    fn headers_round_trip() {
+        for (element_type, dims, fortran_order) in [
+            (BufferType::F32, vec![], false),
+            (BufferType::F64, vec![7], true),
+            (BufferType::Pred, vec![2, 0, 3], false),
+            (BufferType::C128, vec![1, 2, 3, 4], true),
+        ] {
+            let header = NpyHeader {
+                element_type,
+                dims,
+                fortran_order,
+            };
+            let mut file = encode_npy_header(&header).unwrap();
+            file.extend_from_slice(&[1, 2, 3]);
+            let (parsed, data) = parse_npy(&file).unwrap();
+            assert_eq!(parsed, header);
+            assert_eq!(data, &[1, 2, 3]);
+        }
+    }
+
+    #[test]
+    fn dtype_errors_are_explicit() {
+        assert_eq!(buffer_type_from_descr("<f4"), Ok(BufferType::F32));
+        assert_eq!(buffer_type_from_descr("<i1"), Ok(BufferType::S8));
+        assert_eq!(buffer_type_from_descr("|u1"), Ok(BufferType::U8));
+        let big = buffer_type_from_descr(">f4").unwrap_err();
+        assert!(big.contains("big-endian"), "{big}");
+        let object = buffer_type_from_descr("|O").unwrap_err();
+        assert!(object.contains("no PJRT element type"), "{object}");
+        assert!(buffer_type_from_descr("<U8").is_err());
+        assert!(encode_npy_header(&NpyHeader {
+            element_type: BufferType::BF16,
+            dims: vec![1],
+            fortran_order: false,
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        assert!(parse_npy(b"not numpy").is_err());
+        let mut truncated = encode_npy_header(&NpyHeader {
+            element_type: BufferType::F32,
+            dims: vec![4],
+            fortran_order: false,
+        })
+        .unwrap();
+        truncated.truncate(20);
+        assert!(parse_npy(&truncated).is_err());
+        let mut future = truncated.clone();
+        future[6] = 9;
+        assert!(parse_npy(&future).is_err());
+    }
+}
@@ -0,0 +1,86 @@
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Platform {
+    Cpu,
+    Cuda,
+    Rocm,
+    Tpu,
+    Other(String),
+}
+
+impl Platform {
+    // Plugins disagree on naming ("gpu" vs "cuda", "host" vs "cpu"), so classify from the
+    // platform name and fall back to the version string for the generic "gpu" name.
+    pub fn classify(name: &str, version: &str) -> Self {
+        let name_lc = name.trim().to_ascii_lowercase();
+        let version_lc = version.to_ascii_lowercase();
+        match name_lc.as_str() {
+            "cpu" | "host" | "interpreter" => Platform::Cpu,
+            "cuda" | "nvidia" | "nvptx" => Platform::Cuda,
+            "rocm" | "amd" | "amdgpu" | "hip" => Platform::Rocm,
+            "tpu" => Platform::Tpu,
+            "gpu" => {
+                if version_lc.contains("rocm") || version_lc.contains("hip") {
+                    Platform::Rocm
+                } else {
+                    Platform::Cuda
+                }
+            }
+            _ => Platform::Other(name.to_string()),
+        }
+    }
+
+    pub fn is_cpu(&self) -> bool {
+        matches!(self, Platform::Cpu)
+    }
+
+    pub fn is_gpu(&self) -> bool {
+        matches!(self, Platform::Cuda | Platform::Rocm)
+    }
+
+    pub fn is_tpu(&self) -> bool {
+        matches!(self, Platform::Tpu)
+    }
+}
+
+#[cfg(test)]
+mod platform_tests {
+    use super::Platform;
+
+    #[test]
+    fn classifies_known_names() {
+        let cases = [
+            ("cpu", "", Platform::Cpu),
+            ("Host", "", Platform::Cpu),
+            ("cuda", "cuda 12.3", Platform::Cuda),
+            ("gpu", "cuda 12.3", Platform::Cuda),
+            ("gpu", "", Platform::Cuda),
+            ("gpu", "rocm 6.0", Platform::Rocm),
+            ("rocm", "", Platform::Rocm),
+            ("tpu", "libtpu 0.0.1", Platform::Tpu),
+            (" TPU ", "", Platform::Tpu),
+        ];
+        for (name, version, expected) in cases {
+            assert_eq!(
+                Platform::classify(name, version),
+                expected,
+                "{name}/{version}"
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_names_keep_original_text() {
+        assert_eq!(
+            Platform::classify("MyAccel", "1.0"),
+            Platform::Other("MyAccel".to_string())
+        );
+    }
+
+    #[test]
+    fn helpers() {
+        assert!(Platform::Cpu.is_cpu());
+        assert!(Platform::Cuda.is_gpu() && Platform::Rocm.is_gpu());
+        assert!(!Platform::Tpu.is_gpu() && Platform::Tpu.is_tpu());
+        assert!(!Platform::Other("x".into()).is_cpu());
+    }
+}
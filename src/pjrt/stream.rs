@@ -0,0 +1,195 @@
+use std::mem;
+use std::ptr;
+
+use crate::pjrt::buffer::PJRTBuffer;
+use crate::pjrt::device::PJRTDevice;
+use crate::pjrt::error::{
+    extension_missing, plugin_error, OwnedPjrtError, PjrtErrorCode, PjrtResult,
+};
+use crate::pjrt::loader::{extension_has_field, find_extension, PjrtRuntime};
+use crate::pjrt_sys::*;
+
+// A platform stream handle passed through PJRT as intptr_t, e.g. a cudaStream_t on GPU.
+// Zero means no stream.
+pub type DeviceStream = isize;
+
+// Layouts from xla/pjrt/c/pjrt_c_api_stream_extension.h, which the generated bindings
+// don't cover.
+#[repr(C)]
+struct GetStreamArgs {
+    struct_size: usize,
+    extension_start: *mut PJRT_Extension_Base,
+    device: *mut PJRT_Device,
+    stream: DeviceStream,
+}
+
+type GetStreamFn = unsafe extern "C" fn(args: *mut GetStreamArgs) -> *mut PJRT_Error;
+
+#[repr(C)]
+struct WaitStreamArgs {
+    struct_size: usize,
+    extension_start: *mut PJRT_Extension_Base,
+    stream: DeviceStream,
+    buffer: *mut PJRT_Buffer,
+}
+
+type WaitStreamFn = unsafe extern "C" fn(args: *mut WaitStreamArgs) -> *mut PJRT_Error;
+
+#[repr(C)]
+struct StreamExtension {
+    base: PJRT_Extension_Base,
+    get_stream: Option<GetStreamFn>,
+    wait_stream: Option<WaitStreamFn>,
+}
+
+unsafe fn find_stream_extension<'e>(
+    start: *const PJRT_Extension_Base,
+) -> Option<&'e StreamExtension> {
+    let ext = unsafe { find_extension(start, PJRT_Extension_Type_PJRT_Extension_Type_Stream)? };
+    let ext = unsafe { &*ext.cast::<StreamExtension>() };
+    extension_has_field(&ext.base, mem::offset_of!(StreamExtension, wait_stream)).then_some(ext)
+}
+
+fn stream_extension(rt: &PjrtRuntime) -> PjrtResult<&StreamExtension> {
+    unsafe { find_stream_extension(rt.api().extension_start) }
+        .ok_or_else(|| extension_missing("the PJRT stream extension"))
+}
+
+pub fn is_available(rt: &PjrtRuntime) -> bool {
+    unsafe { find_stream_extension(rt.api().extension_start) }.is_some()
+}
+
+// A stream on `device` whose work the runtime treats as an external ready event: buffers
+// created with it (see create_view_of_device_buffer) become ready once work enqueued on it
+// so far has finished.
+pub fn device_ready_event_stream(device: &PJRTDevice<'_>) -> PjrtResult<DeviceStream> {
+    let f = stream_extension(device.rt)?
+        .get_stream
+        .ok_or_else(|| extension_missing("PJRT_Get_Stream_For_External_Ready_Events"))?;
+    unsafe { get_stream(f, device.raw()) }.map_err(|err| {
+        plugin_error(
+            device.rt.api(),
+            "PJRT_Get_Stream_For_External_Ready_Events",
+            err,
+        )
+    })
+}
+
+unsafe fn get_stream(
+    f: GetStreamFn,
+    device: *mut PJRT_Device,
+) -> Result<DeviceStream, *mut PJRT_Error> {
+    let mut args = GetStreamArgs {
+        struct_size: mem::size_of::<GetStreamArgs>(),
+        extension_start: ptr::null_mut(),
+        device,
+        stream: 0,
+    };
+    let err = unsafe { f(&mut args) };
+    if err.is_null() {
+        Ok(args.stream)
+    } else {
+        Err(err)
+    }
+}
+
+unsafe fn wait_stream(
+    f: WaitStreamFn,
+    stream: DeviceStream,
+    buffer: *mut PJRT_Buffer,
+) -> Result<(), *mut PJRT_Error> {
+    let mut args = WaitStreamArgs {
+        struct_size: mem::size_of::<WaitStreamArgs>(),
+        extension_start: ptr::null_mut(),
+        stream,
+        buffer,
+    };
+    let err = unsafe { f(&mut args) };
+    if err.is_null() {
+        Ok(())
+    } else {
+        Err(err)
+    }
+}
+
+impl PJRTBuffer<'_> {
+    // Makes `stream` wait for this buffer's definition event without blocking the host, so
+    // work enqueued on it afterwards can read the buffer.
+    pub fn wait_ready_on_stream(&self, stream: DeviceStream) -> PjrtResult<()> {
+        let f = stream_extension(self.rt)?
+            .wait_stream
+            .ok_or_else(|| extension_missing("PJRT_Wait_Until_Buffer_Ready_On_Stream"))?;
+        if self.raw.is_null() {
+            return Err(OwnedPjrtError::new(
+                PjrtErrorCode::FailedPrecondition,
+                "buffer has been released",
+            ));
+        }
+        unsafe { wait_stream(f, stream, self.raw) }.map_err(|err| {
+            plugin_error(self.rt.api(), "PJRT_Wait_Until_Buffer_Ready_On_Stream", err)
+        })
+    }
+}
+
+#[cfg(test)]
+mod stream_tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    // Stands in for a GPU plugin's stream extension.
+    const FAKE_STREAM: DeviceStream = 0x5eed;
+    static WAITS: Mutex<Vec<(DeviceStream, usize)>> = Mutex::new(Vec::new());
+
+    unsafe extern "C" fn fake_get_stream(args: *mut GetStreamArgs) -> *mut PJRT_Error {
+        let args = unsafe { &mut *args };
+        assert_eq!(args.struct_size, mem::size_of::<GetStreamArgs>());
+        args.stream = FAKE_STREAM;
+        ptr::null_mut()
+    }
+
+    unsafe extern "C" fn fake_wait_stream(args: *mut WaitStreamArgs) -> *mut PJRT_Error {
+        let args = unsafe { &*args };
+        WAITS
+            .lock()
+            .unwrap()
+            .push((args.stream, args.buffer as usize));
+        ptr::null_mut()
+    }
+
+    fn extension(size: usize) -> StreamExtension {
+        StreamExtension {
+            base: PJRT_Extension_Base {
+                struct_size: size,
+                type_: PJRT_Extension_Type_PJRT_Extension_Type_Stream,
+                next: ptr::null_mut(),
+            },
+            get_stream: Some(fake_get_stream),
+            wait_stream: Some(fake_wait_stream),
+        }
+    }
+
+    #[test]
+    fn stream_calls_go_through_the_extension() {
+        let ext = extension(mem::size_of::<StreamExtension>());
+        let found = unsafe { find_stream_extension(ptr::addr_of!(ext).cast()) }.unwrap();
+
+        let stream = unsafe { get_stream(found.get_stream.unwrap(), ptr::null_mut()) }.unwrap();
+        assert_eq!(stream, FAKE_STREAM);
+
+        let buffer = ptr::NonNull::<PJRT_Buffer>::dangling().as_ptr();
+        unsafe { wait_stream(found.wait_stream.unwrap(), stream, buffer) }.unwrap();
+        assert_eq!(*WAITS.lock().unwrap(), [(FAKE_STREAM, buffer as usize)]);
+    }
+
+    #[test]
+    fn missing_or_old_extension_is_not_found() {
+        assert!(unsafe { find_stream_extension(ptr::null()) }.is_none());
+
+        // A struct that ends before wait_stream must not be read past its end.
+        let old = extension(mem::offset_of!(StreamExtension, wait_stream));
+        assert!(unsafe { find_stream_extension(ptr::addr_of!(old).cast()) }.is_none());
+        assert!(
+            extension_missing("the PJRT stream extension").is_code(PjrtErrorCode::Unimplemented)
+        );
+    }
+}
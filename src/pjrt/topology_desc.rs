@@ -1,10 +1,25 @@
+use std::fmt;
+use std::path::Path;
 use std::ptr;
 use std::slice::from_raw_parts;
 
-use crate::pjrt::loader::{error_to_string, PjrtRuntime};
+use crate::pjrt::client::PJRTClient;
+use crate::pjrt::compile::{check_text_format, ProgramFormat};
+use crate::pjrt::error::{
+    extension_missing, pjrt_check, plugin_error, ContextFrame, OwnedPjrtError, PjrtErrorCode,
+    PjrtResult,
+};
+use crate::pjrt::executable::{PJRTExecutableRef, PJRTLoadedExecutable};
+use crate::pjrt::loader::{error_to_string, extension_has_field, find_extension, PjrtRuntime};
+use crate::pjrt::memory::MemoryKind;
+use crate::pjrt::topology_file::{
+    decode_topology_file, encode_topology_file, TopologyFileError, TopologyFileHeader,
+};
+use crate::pjrt::utils::DebugResult;
 use crate::pjrt_sys::*;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(untagged))]
 pub enum PJRTNamedValue {
     String(String),
     Int64(i64),
@@ -13,12 +28,233 @@ pub enum PJRTNamedValue {
     Bool(bool),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct PJRTNamedAttribute {
     pub name: String,
     pub value: PJRTNamedValue,
 }
 
+// Conversion used by the attribute views' get. Only the matching variant converts.
+pub trait FromNamedValue: Sized {
+    fn from_named_value(value: &PJRTNamedValue) -> Option<Self>;
+}
+
+impl FromNamedValue for String {
+    fn from_named_value(value: &PJRTNamedValue) -> Option<Self> {
+        match value {
+            PJRTNamedValue::String(s) => Some(s.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl FromNamedValue for i64 {
+    fn from_named_value(value: &PJRTNamedValue) -> Option<Self> {
+        match value {
+            PJRTNamedValue::Int64(v) => Some(*v),
+            _ => None,
+        }
+    }
+}
+
+impl FromNamedValue for Vec<i64> {
+    fn from_named_value(value: &PJRTNamedValue) -> Option<Self> {
+        match value {
+            PJRTNamedValue::Int64List(v) => Some(v.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl FromNamedValue for f32 {
+    fn from_named_value(value: &PJRTNamedValue) -> Option<Self> {
+        match value {
+            PJRTNamedValue::Float(v) => Some(*v),
+            _ => None,
+        }
+    }
+}
+
+impl FromNamedValue for bool {
+    fn from_named_value(value: &PJRTNamedValue) -> Option<Self> {
+        match value {
+            PJRTNamedValue::Bool(v) => Some(*v),
+            _ => None,
+        }
+    }
+}
+
+// Shared lookup for the attribute views. Getters return None when the attribute is missing
+// or has an unexpected type; which ones exist depends on the plugin.
+macro_rules! impl_attribute_view {
+    ($view:ident) => {
+        impl $view {
+            pub fn new(attributes: Vec<PJRTNamedAttribute>) -> Self {
+                Self { attributes }
+            }
+
+            pub fn as_slice(&self) -> &[PJRTNamedAttribute] {
+                &self.attributes
+            }
+
+            pub fn value(&self, name: &str) -> Option<&PJRTNamedValue> {
+                self.attributes
+                    .iter()
+                    .find(|attribute| attribute.name == name)
+                    .map(|attribute| &attribute.value)
+            }
+
+            pub fn get<T: FromNamedValue>(&self, name: &str) -> Option<T> {
+                T::from_named_value(self.value(name)?)
+            }
+        }
+
+        impl From<Vec<PJRTNamedAttribute>> for $view {
+            fn from(attributes: Vec<PJRTNamedAttribute>) -> Self {
+                Self::new(attributes)
+            }
+        }
+    };
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(transparent))]
+pub struct DeviceAttributes {
+    attributes: Vec<PJRTNamedAttribute>,
+}
+
+impl_attribute_view!(DeviceAttributes);
+
+impl DeviceAttributes {
+    pub fn coords(&self) -> Option<Vec<i64>> {
+        self.get("coords")
+    }
+
+    pub fn core_on_chip(&self) -> Option<i64> {
+        self.get("core_on_chip")
+    }
+
+    pub fn num_slices(&self) -> Option<i64> {
+        self.get("num_slices")
+    }
+
+    pub fn slice_index(&self) -> Option<i64> {
+        self.get("slice_index")
+    }
+
+    pub fn compute_capability(&self) -> Option<String> {
+        self.get("compute_capability")
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(transparent))]
+pub struct TopologyAttributes {
+    attributes: Vec<PJRTNamedAttribute>,
+}
+
+impl_attribute_view!(TopologyAttributes);
+
+impl TopologyAttributes {
+    // Serialized target config used for AOT compilation, where the plugin provides one.
+    pub fn target_config(&self) -> Option<String> {
+        self.get("target_config")
+    }
+
+    pub fn chips_per_host_bounds(&self) -> Option<Vec<i64>> {
+        self.get("chips_per_host_bounds")
+    }
+
+    pub fn host_bounds(&self) -> Option<Vec<i64>> {
+        self.get("host_bounds")
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(transparent))]
+pub struct PluginAttributes {
+    attributes: Vec<PJRTNamedAttribute>,
+}
+
+impl_attribute_view!(PluginAttributes);
+
+impl PluginAttributes {
+    pub fn xla_version(&self) -> Option<i64> {
+        self.get("xla_version")
+    }
+
+    pub fn stablehlo_current_version(&self) -> Option<Vec<i64>> {
+        self.get("stablehlo_current_version")
+    }
+
+    pub fn stablehlo_minimum_version(&self) -> Option<Vec<i64>> {
+        self.get("stablehlo_minimum_version")
+    }
+}
+
+// Layouts from xla/pjrt/c/pjrt_c_api_memory_descriptions_extension.h, which the generated
+// bindings don't cover.
+#[repr(C)]
+struct PjrtMemoryDescriptionOpaque {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct DeviceDescriptionMemoryDescriptionsArgs {
+    struct_size: usize,
+    extension_start: *mut PJRT_Extension_Base,
+    device_description: *mut PJRT_DeviceDescription,
+    memory_descriptions: *const *const PjrtMemoryDescriptionOpaque,
+    num_memory_descriptions: usize,
+    // usize::MAX (-1 in C) when the device has no default memory.
+    default_memory_index: usize,
+}
+
+#[repr(C)]
+struct MemoryDescriptionKindArgs {
+    struct_size: usize,
+    extension_start: *mut PJRT_Extension_Base,
+    memory_description: *const PjrtMemoryDescriptionOpaque,
+    kind: *const libc::c_char,
+    kind_size: usize,
+    kind_id: libc::c_int,
+}
+
+#[repr(C)]
+struct MemoryDescriptionsExtension {
+    base: PJRT_Extension_Base,
+    device_description_memory_descriptions: Option<
+        unsafe extern "C" fn(args: *mut DeviceDescriptionMemoryDescriptionsArgs) -> *mut PJRT_Error,
+    >,
+    memory_description_kind:
+        Option<unsafe extern "C" fn(args: *mut MemoryDescriptionKindArgs) -> *mut PJRT_Error>,
+}
+
+// A memory space a device kind offers, known without creating a client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryDescription {
+    pub kind: String,
+    pub kind_id: i32,
+}
+
+impl MemoryDescription {
+    // None for kinds outside MemoryKind.
+    pub fn memory_kind(&self) -> Option<MemoryKind> {
+        MemoryKind::from_id(self.kind_id).or_else(|| self.kind.parse().ok())
+    }
+}
+
+fn default_memory_index(index: usize, count: usize) -> Result<Option<usize>, String> {
+    match index {
+        usize::MAX => Ok(None),
+        i if i < count => Ok(Some(i)),
+        i => Err(format!(
+            "default memory index {i} is out of range for {count} memory descriptions"
+        )),
+    }
+}
+
 pub struct PJRTDeviceDescriptionRef<'a> {
     pub rt: &'a PjrtRuntime,
     pub raw: *mut PJRT_DeviceDescription,
@@ -172,6 +408,177 @@ impl<'a> PJRTDeviceDescriptionRef<'a> {
         }
         decode_named_values(args.attributes, args.num_attributes)
     }
+
+    // The memory spaces of this device and the index of its default one, if any. Needs the
+    // plugin's memory descriptions extension.
+    pub fn memory_descriptions(&self) -> PjrtResult<(Vec<MemoryDescription>, Option<usize>)> {
+        let raw = self.raw_checked()?;
+        let ext = unsafe {
+            find_extension(
+                self.rt.api().extension_start,
+                PJRT_Extension_Type_PJRT_Extension_Type_MemoryDescriptions,
+            )
+        }
+        .ok_or_else(|| extension_missing("the PJRT memory descriptions extension"))?;
+        let ext = unsafe { &*ext.cast::<MemoryDescriptionsExtension>() };
+        if !extension_has_field(
+            &ext.base,
+            std::mem::offset_of!(MemoryDescriptionsExtension, memory_description_kind),
+        ) {
+            return Err(OwnedPjrtError::new(
+                PjrtErrorCode::Unimplemented,
+                "PJRT memory descriptions extension is too old",
+            ));
+        }
+        let list = ext
+            .device_description_memory_descriptions
+            .ok_or("PJRT_DeviceDescription_MemoryDescriptions symbol not found")?;
+        let kind = ext
+            .memory_description_kind
+            .ok_or("PJRT_MemoryDescription_Kind symbol not found")?;
+
+        let mut args = DeviceDescriptionMemoryDescriptionsArgs {
+            struct_size: std::mem::size_of::<DeviceDescriptionMemoryDescriptionsArgs>(),
+            extension_start: ptr::null_mut(),
+            device_description: raw,
+            memory_descriptions: ptr::null(),
+            num_memory_descriptions: 0,
+            default_memory_index: usize::MAX,
+        };
+        let err = unsafe { list(&mut args) };
+        if !err.is_null() {
+            return Err(plugin_error(
+                self.rt.api(),
+                "PJRT_DeviceDescription_MemoryDescriptions",
+                err,
+            ));
+        }
+        if args.num_memory_descriptions > 0 && args.memory_descriptions.is_null() {
+            return Err(OwnedPjrtError::new(
+                PjrtErrorCode::Internal,
+                "PJRT_DeviceDescription_MemoryDescriptions returned null descriptions",
+            ));
+        }
+        let default =
+            default_memory_index(args.default_memory_index, args.num_memory_descriptions)?;
+
+        let raw_descriptions = if args.num_memory_descriptions == 0 {
+            &[][..]
+        } else {
+            unsafe { from_raw_parts(args.memory_descriptions, args.num_memory_descriptions) }
+        };
+        let descriptions = raw_descriptions
+            .iter()
+            .enumerate()
+            .map(|(i, &description)| -> PjrtResult<MemoryDescription> {
+                if description.is_null() {
+                    return Err(OwnedPjrtError::new(
+                        PjrtErrorCode::Internal,
+                        format!("memory description {i} is null"),
+                    ));
+                }
+                let mut args = MemoryDescriptionKindArgs {
+                    struct_size: std::mem::size_of::<MemoryDescriptionKindArgs>(),
+                    extension_start: ptr::null_mut(),
+                    memory_description: description,
+                    kind: ptr::null(),
+                    kind_size: 0,
+                    kind_id: 0,
+                };
+                let err = unsafe { kind(&mut args) };
+                if !err.is_null() {
+                    return Err(plugin_error(
+                        self.rt.api(),
+                        "PJRT_MemoryDescription_Kind",
+                        err,
+                    ));
+                }
+                Ok(MemoryDescription {
+                    kind: bytes_to_string(args.kind, args.kind_size, "memory kind")?,
+                    kind_id: args.kind_id,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((descriptions, default))
+    }
+}
+
+// Create options for PJRTTopologyDescription::create_with_options. Setting a name twice
+// keeps the last value.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TopologyOptions {
+    values: Vec<(String, PJRTNamedValue)>,
+}
+
+impl TopologyOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(mut self, name: impl Into<String>, value: PJRTNamedValue) -> Self {
+        let name = name.into();
+        match self
+            .values
+            .iter_mut()
+            .find(|(existing, _)| *existing == name)
+        {
+            Some((_, slot)) => *slot = value,
+            None => self.values.push((name, value)),
+        }
+        self
+    }
+
+    pub fn set_str(self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.set(name, PJRTNamedValue::String(value.into()))
+    }
+
+    pub fn set_i64(self, name: impl Into<String>, value: i64) -> Self {
+        self.set(name, PJRTNamedValue::Int64(value))
+    }
+
+    pub fn set_i64_list(self, name: impl Into<String>, value: Vec<i64>) -> Self {
+        self.set(name, PJRTNamedValue::Int64List(value))
+    }
+
+    pub fn set_bool(self, name: impl Into<String>, value: bool) -> Self {
+        self.set(name, PJRTNamedValue::Bool(value))
+    }
+
+    // TPU chip layout such as "4x4x4".
+    pub fn tpu_topology(self, shape: impl Into<String>) -> Self {
+        self.set_str("topology", shape)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PJRTNamedValue> {
+        self.values
+            .iter()
+            .find(|(existing, _)| existing == name)
+            .map(|(_, value)| value)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn encode(&self) -> EncodedNamedValues {
+        encode_named_values(&self.pairs())
+    }
+
+    fn pairs(&self) -> Vec<(&str, PJRTNamedValue)> {
+        self.values
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.clone()))
+            .collect()
+    }
+}
+
+// Plugins don't always say which create option they rejected; name them when the message
+// doesn't.
+fn describe_create_error(message: String, option_names: &[&str]) -> String {
+    if option_names.is_empty() || option_names.iter().any(|name| message.contains(name)) {
+        return message;
+    }
+    format!("{message} (create options: {})", option_names.join(", "))
 }
 
 pub struct PJRTTopologyDescription<'a> {
@@ -179,6 +586,35 @@ pub struct PJRTTopologyDescription<'a> {
     raw: *mut PJRT_TopologyDescription,
 }
 
+// How many device kinds a summary lists.
+const SUMMARY_DEVICE_KINDS: usize = 4;
+
+// Best-effort overview of a topology; each field carries its own query error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TopologySummary {
+    pub platform_name: Result<String, String>,
+    pub platform_version: Result<String, String>,
+    pub device_count: Result<usize, String>,
+    // Kinds of the first few device descriptions, in topology order.
+    pub device_kinds: Result<Vec<String>, String>,
+}
+
+impl fmt::Debug for PJRTTopologyDescription<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let summary = self.summary();
+        f.debug_struct("PJRTTopologyDescription")
+            .field("platform_name", &DebugResult::debug(summary.platform_name))
+            .field(
+                "platform_version",
+                &DebugResult::debug(summary.platform_version),
+            )
+            .field("device_count", &DebugResult::debug(summary.device_count))
+            .field("device_kinds", &DebugResult::debug(summary.device_kinds))
+            .finish()
+    }
+}
+
 impl<'a> PJRTTopologyDescription<'a> {
     pub(crate) fn new(rt: &'a PjrtRuntime, raw: *mut PJRT_TopologyDescription) -> Self {
         Self { rt, raw }
@@ -191,8 +627,9 @@ impl<'a> PJRTTopologyDescription<'a> {
     pub fn create(
         rt: &'a PjrtRuntime,
         topology_name: Option<&str>,
-        create_options: &[PJRT_NamedValue],
+        options: &[(&str, PJRTNamedValue)],
     ) -> Result<Self, String> {
+        let create_options = encode_named_values(options);
         let function = rt
             .api()
             .PJRT_TopologyDescription_Create
@@ -200,7 +637,7 @@ impl<'a> PJRTTopologyDescription<'a> {
 
         let (topology_name_ptr, topology_name_size) = match topology_name {
             None => (ptr::null(), 0usize),
-            Some(name) if name.is_empty() => (ptr::null(), 0usize),
+            Some("") => (ptr::null(), 0usize),
             Some(name) => (name.as_ptr() as *const libc::c_char, name.len()),
         };
 
@@ -209,11 +646,7 @@ impl<'a> PJRTTopologyDescription<'a> {
             extension_start: ptr::null_mut(),
             topology_name: topology_name_ptr,
             topology_name_size,
-            create_options: if create_options.is_empty() {
-                ptr::null()
-            } else {
-                create_options.as_ptr()
-            },
+            create_options: create_options.as_ptr(),
             num_options: create_options.len(),
             topology: ptr::null_mut(),
         };
@@ -221,7 +654,11 @@ impl<'a> PJRTTopologyDescription<'a> {
         let err = unsafe { function(&mut args) };
 
         if !err.is_null() {
-            return Err(error_to_string(rt.api(), err));
+            let names: Vec<&str> = options.iter().map(|(name, _)| *name).collect();
+            return Err(describe_create_error(
+                error_to_string(rt.api(), err),
+                &names,
+            ));
         }
         if args.topology.is_null() {
             return Err("PJRT_TopologyDescription_Create returned null topology".to_string());
@@ -230,6 +667,14 @@ impl<'a> PJRTTopologyDescription<'a> {
         Ok(Self::new(rt, args.topology))
     }
 
+    pub fn create_with_options(
+        rt: &'a PjrtRuntime,
+        topology_name: Option<&str>,
+        options: &TopologyOptions,
+    ) -> Result<Self, String> {
+        Self::create(rt, topology_name, &options.pairs())
+    }
+
     pub fn create_default(rt: &'a PjrtRuntime) -> Result<Self, String> {
         Self::create(rt, None, &[])
     }
@@ -347,6 +792,26 @@ impl<'a> PJRTTopologyDescription<'a> {
         decode_named_values(args.attributes, args.num_attributes)
     }
 
+    pub fn attributes_typed(&self) -> Result<TopologyAttributes, String> {
+        self.attributes().map(TopologyAttributes::new)
+    }
+
+    pub fn summary(&self) -> TopologySummary {
+        let descriptions = self.device_descriptions();
+        TopologySummary {
+            platform_name: self.platform_name(),
+            platform_version: self.platform_version(),
+            device_count: descriptions.as_ref().map(Vec::len).map_err(Clone::clone),
+            device_kinds: descriptions.and_then(|descriptions| {
+                descriptions
+                    .iter()
+                    .take(SUMMARY_DEVICE_KINDS)
+                    .map(PJRTDeviceDescriptionRef::kind)
+                    .collect()
+            }),
+        }
+    }
+
     pub fn serialize(&self) -> Result<Vec<u8>, String> {
         let raw = self.raw_checked()?;
         let f = self
@@ -426,6 +891,156 @@ impl<'a> PJRTTopologyDescription<'a> {
         Ok(Self::new(rt, args.topology))
     }
 
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), TopologyFileError> {
+        let version = self.rt.api().pjrt_api_version;
+        let header = TopologyFileHeader {
+            platform_name: self.platform_name().map_err(TopologyFileError::Serialize)?,
+            platform_version: self
+                .platform_version()
+                .map_err(TopologyFileError::Serialize)?,
+            api_major: version.major_version,
+            api_minor: version.minor_version,
+        };
+        let serialized = self.serialize().map_err(TopologyFileError::Serialize)?;
+        let file = encode_topology_file(&header, &serialized).map_err(TopologyFileError::Format)?;
+        let path = path.as_ref();
+        std::fs::write(path, file).map_err(|source| TopologyFileError::Io {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    // Platform or API major mismatches are errors unless `allow_mismatch` is set, in which
+    // case they are logged like a changed plugin version.
+    pub fn load(
+        rt: &'a PjrtRuntime,
+        path: impl AsRef<Path>,
+        allow_mismatch: bool,
+    ) -> Result<Self, TopologyFileError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|source| TopologyFileError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let (header, serialized) =
+            decode_topology_file(&bytes).map_err(TopologyFileError::Format)?;
+
+        let version = rt.api().pjrt_api_version;
+        if header.api_major != version.major_version {
+            if !allow_mismatch {
+                return Err(TopologyFileError::ApiMismatch {
+                    expected: version.major_version,
+                    found: header.api_major,
+                });
+            }
+            log::warn!(
+                "topology file {} was written with PJRT API major {} but plugin is {}",
+                path.display(),
+                header.api_major,
+                version.major_version
+            );
+        }
+
+        let topology = Self::deserialize(rt, serialized).map_err(TopologyFileError::Deserialize)?;
+        let platform = topology
+            .platform_name()
+            .map_err(TopologyFileError::Deserialize)?;
+        if platform != header.platform_name {
+            if !allow_mismatch {
+                return Err(TopologyFileError::PlatformMismatch {
+                    expected: platform,
+                    found: header.platform_name,
+                });
+            }
+            log::warn!(
+                "topology file {} was written for platform {:?} but loaded as {platform:?}",
+                path.display(),
+                header.platform_name
+            );
+        }
+        if let Ok(plugin_version) = topology.platform_version() {
+            if plugin_version != header.platform_version {
+                log::warn!(
+                    "topology file {} was written by plugin version {:?} but plugin is {:?}",
+                    path.display(),
+                    header.platform_version,
+                    plugin_version
+                );
+            }
+        }
+        Ok(topology)
+    }
+
+    // Compiles for this topology and loads the result on `client`. When the client runs the
+    // same platform it compiles and loads in one PJRT_Client_Compile call; otherwise this
+    // takes the AOT path of compile_aot_and_load.
+    pub fn compile_and_load(
+        &self,
+        client: &PJRTClient<'a>,
+        program_code: &str,
+        format: ProgramFormat,
+        compile_options: &[u8],
+    ) -> Result<PJRTLoadedExecutable<'a>, String> {
+        check_text_format(format)?;
+        self.compile_bytes_and_load(client, program_code.as_bytes(), format, compile_options)
+    }
+
+    // Binary-safe variant for serialized protos and MLIR bytecode.
+    pub fn compile_bytes_and_load(
+        &self,
+        client: &PJRTClient<'a>,
+        program_code: &[u8],
+        format: ProgramFormat,
+        compile_options: &[u8],
+    ) -> Result<PJRTLoadedExecutable<'a>, String> {
+        if self.platform_name()? == client.platform_name()? {
+            return Ok(client.compile_bytes(program_code, format, compile_options)?);
+        }
+        self.compile_aot_bytes_and_load(client, program_code, format, compile_options)
+    }
+
+    // PJRT_Compile against this topology, then a serialize/deserialize round trip to load the
+    // executable on `client`. Needed only when the client can't compile for the topology
+    // itself; it relies on the plugin's executable serialization being stable.
+    pub fn compile_aot_and_load(
+        &self,
+        client: &PJRTClient<'a>,
+        program_code: &str,
+        format: ProgramFormat,
+        compile_options: &[u8],
+    ) -> Result<PJRTLoadedExecutable<'a>, String> {
+        check_text_format(format)?;
+        self.compile_aot_bytes_and_load(client, program_code.as_bytes(), format, compile_options)
+    }
+
+    pub fn compile_aot_bytes_and_load(
+        &self,
+        client: &PJRTClient<'a>,
+        program_code: &[u8],
+        format: ProgramFormat,
+        compile_options: &[u8],
+    ) -> Result<PJRTLoadedExecutable<'a>, String> {
+        if program_code.is_empty() {
+            return Err("program_code must not be empty".to_string());
+        }
+        let format = format.as_str();
+        let program = PJRT_Program {
+            struct_size: std::mem::size_of::<PJRT_Program>(),
+            extension_start: ptr::null_mut(),
+            code: program_code.as_ptr() as *mut libc::c_char,
+            code_size: program_code.len(),
+            format: format.as_ptr() as *const libc::c_char,
+            format_size: format.len(),
+        };
+        let executable = PJRTExecutableRef::new(
+            self.rt,
+            self.compile(client.raw_checked()?, &program, compile_options)?,
+        );
+        let serialized = executable.serialize()?;
+        executable.destroy()?;
+        client.deserialize_and_load(&serialized, None)
+    }
+
     pub fn compile(
         &self,
         client: *mut PJRT_Client,
@@ -474,10 +1089,11 @@ impl<'a> PJRTTopologyDescription<'a> {
             executable: ptr::null_mut(),
         };
 
-        let err = unsafe { f(&mut args) };
-        if !err.is_null() {
-            return Err(error_to_string(self.rt.api(), err));
-        }
+        pjrt_check!(
+            self.rt,
+            unsafe { f(&mut args) },
+            ContextFrame::new("PJRT_Compile")
+        )?;
         if args.executable.is_null() {
             return Err("PJRT_Compile returned null executable".to_string());
         }
@@ -523,7 +1139,97 @@ fn bytes_to_string(
     Ok(String::from_utf8_lossy(bytes).into_owned())
 }
 
-fn decode_named_values(
+// A PJRT_NamedValue array together with the names, strings and lists it points into. The
+// array is valid for as long as this value lives; moving it doesn't move the heap data.
+pub struct EncodedNamedValues {
+    values: Vec<PJRT_NamedValue>,
+    _names: Vec<String>,
+    _strings: Vec<String>,
+    _lists: Vec<Vec<i64>>,
+}
+
+impl EncodedNamedValues {
+    pub fn as_slice(&self) -> &[PJRT_NamedValue] {
+        &self.values
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    // For args structs: null when there are no values, as plugins expect.
+    pub(crate) fn as_ptr(&self) -> *const PJRT_NamedValue {
+        if self.values.is_empty() {
+            ptr::null()
+        } else {
+            self.values.as_ptr()
+        }
+    }
+}
+
+pub fn encode_named_values(values: &[(&str, PJRTNamedValue)]) -> EncodedNamedValues {
+    let mut encoded = EncodedNamedValues {
+        values: Vec::with_capacity(values.len()),
+        _names: Vec::with_capacity(values.len()),
+        _strings: Vec::new(),
+        _lists: Vec::new(),
+    };
+    for (name, value) in values {
+        let name = name.to_string();
+        let (type_, value_union, value_size) = match value {
+            PJRTNamedValue::String(s) => {
+                let s = s.clone();
+                let union = PJRT_NamedValue__bindgen_ty_1 {
+                    string_value: s.as_ptr().cast(),
+                };
+                let size = s.len();
+                encoded._strings.push(s);
+                (PJRT_NamedValue_Type_PJRT_NamedValue_kString, union, size)
+            }
+            PJRTNamedValue::Int64(v) => (
+                PJRT_NamedValue_Type_PJRT_NamedValue_kInt64,
+                PJRT_NamedValue__bindgen_ty_1 { int64_value: *v },
+                1,
+            ),
+            PJRTNamedValue::Int64List(list) => {
+                let list = list.clone();
+                let union = PJRT_NamedValue__bindgen_ty_1 {
+                    int64_array_value: list.as_ptr(),
+                };
+                let size = list.len();
+                encoded._lists.push(list);
+                (PJRT_NamedValue_Type_PJRT_NamedValue_kInt64List, union, size)
+            }
+            PJRTNamedValue::Float(v) => (
+                PJRT_NamedValue_Type_PJRT_NamedValue_kFloat,
+                PJRT_NamedValue__bindgen_ty_1 { float_value: *v },
+                1,
+            ),
+            PJRTNamedValue::Bool(v) => (
+                PJRT_NamedValue_Type_PJRT_NamedValue_kBool,
+                PJRT_NamedValue__bindgen_ty_1 { bool_value: *v },
+                1,
+            ),
+        };
+        encoded.values.push(PJRT_NamedValue {
+            struct_size: PJRT_NamedValue_STRUCT_SIZE as usize,
+            extension_start: ptr::null_mut(),
+            name: name.as_ptr().cast(),
+            name_size: name.len(),
+            type_,
+            __bindgen_anon_1: value_union,
+            value_size,
+        });
+        encoded._names.push(name);
+    }
+    encoded
+}
+
+pub(crate) fn decode_named_values(
     attrs: *const PJRT_NamedValue,
     num_attrs: usize,
 ) -> Result<Vec<PJRTNamedAttribute>, String> {
@@ -602,3 +1308,277 @@ fn decode_named_values(
     }
     Ok(out)
 }
+
+#[cfg(test)]
+mod topology_desc_tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn attribute(name: &str, value: PJRTNamedValue) -> PJRTNamedAttribute {
+        PJRTNamedAttribute {
+            name: name.to_string(),
+            value,
+        }
+    }
+
+    #[test]
+    fn encoded_named_values_round_trip_through_the_decoder() {
+        let values = vec![
+            ("name", PJRTNamedValue::String("cpu".to_string())),
+            ("empty_string", PJRTNamedValue::String(String::new())),
+            ("count", PJRTNamedValue::Int64(-3)),
+            ("dims", PJRTNamedValue::Int64List(vec![2, 0, i64::MAX])),
+            ("empty_list", PJRTNamedValue::Int64List(Vec::new())),
+            ("ratio", PJRTNamedValue::Float(0.25)),
+            ("enabled", PJRTNamedValue::Bool(true)),
+            ("", PJRTNamedValue::Bool(false)),
+        ];
+        let encoded = encode_named_values(&values);
+        // Moving the encoder must leave the pointers into its storage intact.
+        let moved = Box::new(encoded);
+        assert_eq!(moved.len(), values.len());
+
+        let decoded = decode_named_values(moved.as_ptr(), moved.len()).unwrap();
+        let expected: Vec<PJRTNamedAttribute> = values
+            .into_iter()
+            .map(|(name, value)| attribute(name, value))
+            .collect();
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn topology_options_encode_the_last_value_per_name() {
+        let options = TopologyOptions::new()
+            .tpu_topology("2x2x1")
+            .set_i64("num_slices", 2)
+            .set_i64_list("chips_per_host_bounds", vec![2, 2, 1])
+            .set_bool("enable_megacore", false)
+            .tpu_topology("4x4x4");
+        assert_eq!(
+            options.get("topology"),
+            Some(&PJRTNamedValue::String("4x4x4".to_string()))
+        );
+
+        let encoded = options.encode();
+        let decoded = decode_named_values(encoded.as_ptr(), encoded.len()).unwrap();
+        assert_eq!(
+            decoded,
+            vec![
+                attribute("topology", PJRTNamedValue::String("4x4x4".to_string())),
+                attribute("num_slices", PJRTNamedValue::Int64(2)),
+                attribute(
+                    "chips_per_host_bounds",
+                    PJRTNamedValue::Int64List(vec![2, 2, 1])
+                ),
+                attribute("enable_megacore", PJRTNamedValue::Bool(false)),
+            ]
+        );
+        assert!(TopologyOptions::new().encode().is_empty());
+    }
+
+    #[test]
+    fn create_errors_name_the_options_when_the_plugin_does_not() {
+        assert_eq!(
+            describe_create_error("bad option".to_string(), &["topology", "num_slices"]),
+            "bad option (create options: topology, num_slices)"
+        );
+        assert_eq!(
+            describe_create_error("unknown option topology".to_string(), &["topology"]),
+            "unknown option topology"
+        );
+        assert_eq!(describe_create_error("failed".to_string(), &[]), "failed");
+    }
+
+    #[test]
+    fn empty_encoding_passes_a_null_array() {
+        let encoded = encode_named_values(&[]);
+        assert!(encoded.is_empty());
+        assert!(encoded.as_ptr().is_null());
+        assert!(encoded.as_slice().is_empty());
+        assert_eq!(decode_named_values(encoded.as_ptr(), 0).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn topology_and_plugin_views_share_lookup() {
+        let topology = TopologyAttributes::from(vec![
+            attribute("target_config", PJRTNamedValue::String("cfg".to_string())),
+            attribute(
+                "chips_per_host_bounds",
+                PJRTNamedValue::Int64List(vec![2, 2, 1]),
+            ),
+            attribute("host_bounds", PJRTNamedValue::Int64(1)),
+        ]);
+        assert_eq!(topology.target_config().as_deref(), Some("cfg"));
+        assert_eq!(topology.chips_per_host_bounds(), Some(vec![2, 2, 1]));
+        // Present but with the wrong type.
+        assert_eq!(topology.host_bounds(), None);
+        assert_eq!(topology.get::<i64>("host_bounds"), Some(1));
+
+        let plugin = PluginAttributes::from(vec![
+            attribute("xla_version", PJRTNamedValue::Int64(2)),
+            attribute(
+                "stablehlo_current_version",
+                PJRTNamedValue::Int64List(vec![1, 9, 3]),
+            ),
+            attribute(
+                "stablehlo_minimum_version",
+                PJRTNamedValue::Int64List(vec![0, 9, 0]),
+            ),
+        ]);
+        assert_eq!(plugin.xla_version(), Some(2));
+        assert_eq!(plugin.stablehlo_current_version(), Some(vec![1, 9, 3]));
+        assert_eq!(plugin.stablehlo_minimum_version(), Some(vec![0, 9, 0]));
+        assert_eq!(plugin.value("xla_version"), Some(&PJRTNamedValue::Int64(2)));
+        assert!(PluginAttributes::default().as_slice().is_empty());
+    }
+
+    #[test]
+    fn device_attributes_convert_each_variant() {
+        let attributes = DeviceAttributes::from(vec![
+            attribute("coords", PJRTNamedValue::Int64List(vec![1, 0, 2])),
+            attribute("core_on_chip", PJRTNamedValue::Int64(1)),
+            attribute("num_slices", PJRTNamedValue::Int64(4)),
+            attribute("slice_index", PJRTNamedValue::Int64(3)),
+            attribute(
+                "compute_capability",
+                PJRTNamedValue::String("9.0".to_string()),
+            ),
+            attribute("clock_ghz", PJRTNamedValue::Float(1.5)),
+            attribute("has_tensor_cores", PJRTNamedValue::Bool(true)),
+        ]);
+
+        assert_eq!(attributes.coords(), Some(vec![1, 0, 2]));
+        assert_eq!(attributes.core_on_chip(), Some(1));
+        assert_eq!(attributes.num_slices(), Some(4));
+        assert_eq!(attributes.slice_index(), Some(3));
+        assert_eq!(attributes.compute_capability().as_deref(), Some("9.0"));
+        assert_eq!(attributes.get::<f32>("clock_ghz"), Some(1.5));
+        assert_eq!(attributes.get::<bool>("has_tensor_cores"), Some(true));
+        assert_eq!(attributes.as_slice().len(), 7);
+    }
+
+    #[test]
+    fn device_attributes_reject_missing_and_mistyped_values() {
+        let attributes = DeviceAttributes::new(vec![
+            attribute("coords", PJRTNamedValue::Int64(7)),
+            attribute("compute_capability", PJRTNamedValue::Float(8.6)),
+        ]);
+        assert_eq!(attributes.coords(), None);
+        assert_eq!(attributes.get::<i64>("coords"), Some(7));
+        assert_eq!(attributes.compute_capability(), None);
+        assert_eq!(attributes.core_on_chip(), None);
+        assert!(attributes.value("num_slices").is_none());
+        assert_eq!(DeviceAttributes::default().get::<bool>("anything"), None);
+    }
+
+    #[test]
+    fn default_memory_index_is_optional_and_checked() {
+        assert_eq!(default_memory_index(0, 2), Ok(Some(0)));
+        assert_eq!(default_memory_index(1, 2), Ok(Some(1)));
+        assert_eq!(default_memory_index(usize::MAX, 2), Ok(None));
+        assert_eq!(default_memory_index(usize::MAX, 0), Ok(None));
+        let err = default_memory_index(2, 2).unwrap_err();
+        assert!(err.contains("out of range for 2"), "{err}");
+    }
+    // Call counts for the entry points compile_bytes_and_load can reach, in the order
+    // PJRT_Client_Compile, PJRT_Compile, PJRT_Executable_Serialize,
+    // PJRT_Executable_DeserializeAndLoad.
+    static CALLS: [AtomicUsize; 4] = [const { AtomicUsize::new(0) }; 4];
+    const SAME_PLATFORM: usize = 0x1000;
+    const OTHER_PLATFORM: usize = 0x2000;
+
+    fn calls() -> [usize; 4] {
+        CALLS.each_ref().map(|count| count.load(Ordering::SeqCst))
+    }
+
+    fn dangling<T>() -> *mut T {
+        ptr::NonNull::dangling().as_ptr()
+    }
+
+    unsafe extern "C" fn fake_topology_platform(
+        args: *mut PJRT_TopologyDescription_PlatformName_Args,
+    ) -> *mut PJRT_Error {
+        let args = unsafe { &mut *args };
+        let name = if args.topology as usize == SAME_PLATFORM {
+            "cpu"
+        } else {
+            "tpu"
+        };
+        args.platform_name = name.as_ptr().cast();
+        args.platform_name_size = name.len();
+        ptr::null_mut()
+    }
+
+    unsafe extern "C" fn fake_client_platform(
+        args: *mut PJRT_Client_PlatformName_Args,
+    ) -> *mut PJRT_Error {
+        let args = unsafe { &mut *args };
+        args.platform_name = "cpu".as_ptr().cast();
+        args.platform_name_size = 3;
+        ptr::null_mut()
+    }
+
+    unsafe extern "C" fn fake_client_compile(
+        args: *mut PJRT_Client_Compile_Args,
+    ) -> *mut PJRT_Error {
+        CALLS[0].fetch_add(1, Ordering::SeqCst);
+        unsafe { (*args).executable = dangling() };
+        ptr::null_mut()
+    }
+
+    unsafe extern "C" fn fake_compile(args: *mut PJRT_Compile_Args) -> *mut PJRT_Error {
+        CALLS[1].fetch_add(1, Ordering::SeqCst);
+        unsafe { (*args).executable = dangling() };
+        ptr::null_mut()
+    }
+
+    unsafe extern "C" fn fake_serialize(
+        args: *mut PJRT_Executable_Serialize_Args,
+    ) -> *mut PJRT_Error {
+        CALLS[2].fetch_add(1, Ordering::SeqCst);
+        let args = unsafe { &mut *args };
+        args.serialized_bytes = b"blob".as_ptr().cast();
+        args.serialized_bytes_size = 4;
+        ptr::null_mut()
+    }
+
+    unsafe extern "C" fn fake_executable_destroy(
+        _args: *mut PJRT_Executable_Destroy_Args,
+    ) -> *mut PJRT_Error {
+        ptr::null_mut()
+    }
+
+    unsafe extern "C" fn fake_deserialize_and_load(
+        args: *mut PJRT_Executable_DeserializeAndLoad_Args,
+    ) -> *mut PJRT_Error {
+        CALLS[3].fetch_add(1, Ordering::SeqCst);
+        unsafe { (*args).loaded_executable = dangling() };
+        ptr::null_mut()
+    }
+
+    #[test]
+    fn compile_and_load_skips_the_round_trip_on_the_clients_platform() {
+        let mut api: PJRT_Api = unsafe { std::mem::zeroed() };
+        api.PJRT_TopologyDescription_PlatformName = Some(fake_topology_platform);
+        api.PJRT_Client_PlatformName = Some(fake_client_platform);
+        api.PJRT_Client_Compile = Some(fake_client_compile);
+        api.PJRT_Compile = Some(fake_compile);
+        api.PJRT_Executable_Serialize = Some(fake_serialize);
+        api.PJRT_Executable_Destroy = Some(fake_executable_destroy);
+        api.PJRT_Executable_DeserializeAndLoad = Some(fake_deserialize_and_load);
+        let rt = PjrtRuntime::from_api(Box::leak(Box::new(api)));
+        let client = PJRTClient::new(&rt, dangling());
+
+        let same = PJRTTopologyDescription::new(&rt, SAME_PLATFORM as *mut _);
+        same.compile_bytes_and_load(&client, b"module", ProgramFormat::Mlir, &[])
+            .unwrap();
+        assert_eq!(calls(), [1, 0, 0, 0]);
+
+        // Only a topology the client can't compile for pays for the round trip.
+        let other = PJRTTopologyDescription::new(&rt, OTHER_PLATFORM as *mut _);
+        other
+            .compile_bytes_and_load(&client, b"module", ProgramFormat::Mlir, &[])
+            .unwrap();
+        assert_eq!(calls(), [1, 1, 1, 1]);
+    }
+}
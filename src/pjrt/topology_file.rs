@@ -0,0 +1,145 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::pjrt::executable_file::{push_str, Reader};
+
+const MAGIC: &[u8; 8] = b"RRADTOPO";
+const FORMAT_VERSION: u32 = 1;
+
+// Recorded next to a serialized topology so AOT hosts can tell which plugin produced it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TopologyFileHeader {
+    pub platform_name: String,
+    pub platform_version: String,
+    pub api_major: i32,
+    pub api_minor: i32,
+}
+
+#[derive(Debug)]
+pub enum TopologyFileError {
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    Format(String),
+    PlatformMismatch {
+        expected: String,
+        found: String,
+    },
+    ApiMismatch {
+        expected: i32,
+        found: i32,
+    },
+    Serialize(String),
+    Deserialize(String),
+}
+
+impl fmt::Display for TopologyFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TopologyFileError::Io { path, source } => {
+                write!(f, "topology file {} I/O failed: {source}", path.display())
+            }
+            TopologyFileError::Format(e) => write!(f, "malformed topology file: {e}"),
+            TopologyFileError::PlatformMismatch { expected, found } => write!(
+                f,
+                "topology file was written for platform {found:?} but the plugin reads it as {expected:?}"
+            ),
+            TopologyFileError::ApiMismatch { expected, found } => write!(
+                f,
+                "topology file was written with PJRT API major {found} but the plugin is {expected}"
+            ),
+            TopologyFileError::Serialize(e) => write!(f, "failed to serialize topology: {e}"),
+            TopologyFileError::Deserialize(e) => write!(f, "failed to deserialize topology: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for TopologyFileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TopologyFileError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<TopologyFileError> for String {
+    fn from(e: TopologyFileError) -> Self {
+        e.to_string()
+    }
+}
+
+// Layout: magic, u32 format version, length-prefixed platform name and version, i32 API
+// major and minor, then the serialized topology. Integers are little-endian.
+pub fn encode_topology_file(
+    header: &TopologyFileHeader,
+    serialized: &[u8],
+) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(
+        MAGIC.len()
+            + 20
+            + header.platform_name.len()
+            + header.platform_version.len()
+            + serialized.len(),
+    );
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    push_str(&mut out, "platform name", &header.platform_name)?;
+    push_str(&mut out, "platform version", &header.platform_version)?;
+    out.extend_from_slice(&header.api_major.to_le_bytes());
+    out.extend_from_slice(&header.api_minor.to_le_bytes());
+    out.extend_from_slice(serialized);
+    Ok(out)
+}
+
+pub fn decode_topology_file(bytes: &[u8]) -> Result<(TopologyFileHeader, &[u8]), String> {
+    let rest = bytes
+        .strip_prefix(MAGIC.as_slice())
+        .ok_or("missing topology file magic")?;
+    let mut reader = Reader { rest };
+
+    let version = reader.u32("format version")?;
+    if version != FORMAT_VERSION {
+        return Err(format!("unsupported topology file version {version}"));
+    }
+    let header = TopologyFileHeader {
+        platform_name: reader.string("platform name")?,
+        platform_version: reader.string("platform version")?,
+        api_major: reader.u32("API major version")? as i32,
+        api_minor: reader.u32("API minor version")? as i32,
+    };
+    Ok((header, reader.rest))
+}
+
+#[cfg(test)]
+mod topology_file_tests {
+    use super::*;
+
+    fn header() -> TopologyFileHeader {
+        TopologyFileHeader {
+            platform_name: "tpu".to_string(),
+            platform_version: "libtpu 0.0.1".to_string(),
+            api_major: 0,
+            api_minor: 75,
+        }
+    }
+
+    #[test]
+    fn header_round_trips() {
+        let file = encode_topology_file(&header(), b"topology").unwrap();
+        let (decoded, payload) = decode_topology_file(&file).unwrap();
+        assert_eq!(decoded, header());
+        assert_eq!(payload, b"topology");
+    }
+
+    #[test]
+    fn rejects_executable_and_truncated_files() {
+        let err = decode_topology_file(b"RRADXEXE\x01\x00\x00\x00").unwrap_err();
+        assert!(err.contains("magic"), "{err}");
+
+        let file = encode_topology_file(&header(), b"topology").unwrap();
+        let err = decode_topology_file(&file[..MAGIC.len() + 9]).unwrap_err();
+        assert!(err.contains("truncated"), "{err}");
+    }
+}
@@ -0,0 +1,615 @@
+use std::fmt;
+
+use crate::pjrt_sys::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum MajorOrder {
+    // Last dimension varies fastest (C order).
+    #[default]
+    RowMajor,
+    // First dimension varies fastest (Fortran order).
+    ColumnMajor,
+}
+
+pub fn element_count(dims: &[i64]) -> Result<usize, String> {
+    dims.iter().try_fold(1usize, |acc, &d| {
+        if d < 0 {
+            return Err(format!("negative dimension {d} in {dims:?}"));
+        }
+        acc.checked_mul(d as usize)
+            .ok_or_else(|| format!("element count overflows for dims {dims:?}"))
+    })
+}
+
+// Byte strides for a dense array of `dims` with `elem_size` bytes per element, in the
+// form expected by PJRT_Client_BufferFromHostBuffer.
+pub fn byte_strides(dims: &[i64], elem_size: usize, order: MajorOrder) -> Result<Vec<i64>, String> {
+    element_count(dims)?;
+
+    let mut strides = vec![0i64; dims.len()];
+    let mut stride = elem_size as i64;
+    let mut assign = |i: usize| -> Result<(), String> {
+        strides[i] = stride;
+        stride = stride
+            .checked_mul(dims[i].max(1))
+            .ok_or_else(|| format!("byte stride overflows for dims {dims:?}"))?;
+        Ok(())
+    };
+
+    match order {
+        MajorOrder::RowMajor => (0..dims.len()).rev().try_for_each(&mut assign)?,
+        MajorOrder::ColumnMajor => (0..dims.len()).try_for_each(&mut assign)?,
+    }
+    Ok(strides)
+}
+
+pub fn check_data_len(dims: &[i64], data_len: usize) -> Result<(), String> {
+    let expected = element_count(dims)?;
+    if expected != data_len {
+        return Err(format!(
+            "data has {data_len} elements but dims {dims:?} require {expected}"
+        ));
+    }
+    Ok(())
+}
+
+// The plugin sizes its read of host data from `element_type`, so it has to be T's type.
+pub fn check_element_type<T: PjrtScalar>(element_type: PJRT_Buffer_Type) -> Result<(), String> {
+    if element_type == T::TYPE.raw() {
+        return Ok(());
+    }
+    let requested = BufferType::try_from(element_type)
+        .map(|ty| ty.to_string())
+        .unwrap_or_else(|_| format!("PJRT_Buffer_Type {element_type}"));
+    Err(format!(
+        "element type {requested} does not match the {} host data",
+        T::TYPE
+    ))
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum BufferType {
+    Invalid,
+    Pred,
+    S8,
+    S16,
+    S32,
+    S64,
+    U8,
+    U16,
+    U32,
+    U64,
+    F16,
+    F32,
+    F64,
+    BF16,
+    C64,
+    C128,
+    F8E5M2,
+    F8E4M3FN,
+    F8E4M3B11FNUZ,
+    F8E5M2FNUZ,
+    F8E4M3FNUZ,
+    S4,
+    U4,
+    Token,
+    S2,
+    U2,
+    F8E4M3,
+    F8E3M4,
+    F8E8M0FNU,
+    F4E2M1FN,
+}
+
+impl BufferType {
+    pub const ALL: [BufferType; 30] = [
+        BufferType::Invalid,
+        BufferType::Pred,
+        BufferType::S8,
+        BufferType::S16,
+        BufferType::S32,
+        BufferType::S64,
+        BufferType::U8,
+        BufferType::U16,
+        BufferType::U32,
+        BufferType::U64,
+        BufferType::F16,
+        BufferType::F32,
+        BufferType::F64,
+        BufferType::BF16,
+        BufferType::C64,
+        BufferType::C128,
+        BufferType::F8E5M2,
+        BufferType::F8E4M3FN,
+        BufferType::F8E4M3B11FNUZ,
+        BufferType::F8E5M2FNUZ,
+        BufferType::F8E4M3FNUZ,
+        BufferType::S4,
+        BufferType::U4,
+        BufferType::Token,
+        BufferType::S2,
+        BufferType::U2,
+        BufferType::F8E4M3,
+        BufferType::F8E3M4,
+        BufferType::F8E8M0FNU,
+        BufferType::F4E2M1FN,
+    ];
+
+    pub fn raw(self) -> PJRT_Buffer_Type {
+        match self {
+            BufferType::Invalid => PJRT_Buffer_Type_PJRT_Buffer_Type_INVALID,
+            BufferType::Pred => PJRT_Buffer_Type_PJRT_Buffer_Type_PRED,
+            BufferType::S8 => PJRT_Buffer_Type_PJRT_Buffer_Type_S8,
+            BufferType::S16 => PJRT_Buffer_Type_PJRT_Buffer_Type_S16,
+            BufferType::S32 => PJRT_Buffer_Type_PJRT_Buffer_Type_S32,
+            BufferType::S64 => PJRT_Buffer_Type_PJRT_Buffer_Type_S64,
+            BufferType::U8 => PJRT_Buffer_Type_PJRT_Buffer_Type_U8,
+            BufferType::U16 => PJRT_Buffer_Type_PJRT_Buffer_Type_U16,
+            BufferType::U32 => PJRT_Buffer_Type_PJRT_Buffer_Type_U32,
+            BufferType::U64 => PJRT_Buffer_Type_PJRT_Buffer_Type_U64,
+            BufferType::F16 => PJRT_Buffer_Type_PJRT_Buffer_Type_F16,
+            BufferType::F32 => PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+            BufferType::F64 => PJRT_Buffer_Type_PJRT_Buffer_Type_F64,
+            BufferType::BF16 => PJRT_Buffer_Type_PJRT_Buffer_Type_BF16,
+            BufferType::C64 => PJRT_Buffer_Type_PJRT_Buffer_Type_C64,
+            BufferType::C128 => PJRT_Buffer_Type_PJRT_Buffer_Type_C128,
+            BufferType::F8E5M2 => PJRT_Buffer_Type_PJRT_Buffer_Type_F8E5M2,
+            BufferType::F8E4M3FN => PJRT_Buffer_Type_PJRT_Buffer_Type_F8E4M3FN,
+            BufferType::F8E4M3B11FNUZ => PJRT_Buffer_Type_PJRT_Buffer_Type_F8E4M3B11FNUZ,
+            BufferType::F8E5M2FNUZ => PJRT_Buffer_Type_PJRT_Buffer_Type_F8E5M2FNUZ,
+            BufferType::F8E4M3FNUZ => PJRT_Buffer_Type_PJRT_Buffer_Type_F8E4M3FNUZ,
+            BufferType::S4 => PJRT_Buffer_Type_PJRT_Buffer_Type_S4,
+            BufferType::U4 => PJRT_Buffer_Type_PJRT_Buffer_Type_U4,
+            BufferType::Token => PJRT_Buffer_Type_PJRT_Buffer_Type_TOKEN,
+            BufferType::S2 => PJRT_Buffer_Type_PJRT_Buffer_Type_S2,
+            BufferType::U2 => PJRT_Buffer_Type_PJRT_Buffer_Type_U2,
+            BufferType::F8E4M3 => PJRT_Buffer_Type_PJRT_Buffer_Type_F8E4M3,
+            BufferType::F8E3M4 => PJRT_Buffer_Type_PJRT_Buffer_Type_F8E3M4,
+            BufferType::F8E8M0FNU => PJRT_Buffer_Type_PJRT_Buffer_Type_F8E8M0FNU,
+            BufferType::F4E2M1FN => PJRT_Buffer_Type_PJRT_Buffer_Type_F4E2M1FN,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BufferType::Invalid => "invalid",
+            BufferType::Pred => "pred",
+            BufferType::S8 => "s8",
+            BufferType::S16 => "s16",
+            BufferType::S32 => "s32",
+            BufferType::S64 => "s64",
+            BufferType::U8 => "u8",
+            BufferType::U16 => "u16",
+            BufferType::U32 => "u32",
+            BufferType::U64 => "u64",
+            BufferType::F16 => "f16",
+            BufferType::F32 => "f32",
+            BufferType::F64 => "f64",
+            BufferType::BF16 => "bf16",
+            BufferType::C64 => "c64",
+            BufferType::C128 => "c128",
+            BufferType::F8E5M2 => "f8e5m2",
+            BufferType::F8E4M3FN => "f8e4m3fn",
+            BufferType::F8E4M3B11FNUZ => "f8e4m3b11fnuz",
+            BufferType::F8E5M2FNUZ => "f8e5m2fnuz",
+            BufferType::F8E4M3FNUZ => "f8e4m3fnuz",
+            BufferType::S4 => "s4",
+            BufferType::U4 => "u4",
+            BufferType::Token => "token",
+            BufferType::S2 => "s2",
+            BufferType::U2 => "u2",
+            BufferType::F8E4M3 => "f8e4m3",
+            BufferType::F8E3M4 => "f8e3m4",
+            BufferType::F8E8M0FNU => "f8e8m0fnu",
+            BufferType::F4E2M1FN => "f4e2m1fn",
+        }
+    }
+
+    // Storage width of one element; 0 for INVALID and TOKEN, which carry no data.
+    pub fn size_in_bits(self) -> usize {
+        match self {
+            BufferType::Invalid | BufferType::Token => 0,
+            BufferType::Pred
+            | BufferType::S8
+            | BufferType::U8
+            | BufferType::F8E5M2
+            | BufferType::F8E4M3FN
+            | BufferType::F8E4M3B11FNUZ
+            | BufferType::F8E5M2FNUZ
+            | BufferType::F8E4M3FNUZ
+            | BufferType::F8E4M3
+            | BufferType::F8E3M4
+            | BufferType::F8E8M0FNU => 8,
+            BufferType::S16 | BufferType::U16 | BufferType::F16 | BufferType::BF16 => 16,
+            BufferType::S32 | BufferType::U32 | BufferType::F32 => 32,
+            BufferType::S64 | BufferType::U64 | BufferType::F64 | BufferType::C64 => 64,
+            BufferType::C128 => 128,
+            BufferType::S4 | BufferType::U4 | BufferType::F4E2M1FN => 4,
+            BufferType::S2 | BufferType::U2 => 2,
+        }
+    }
+
+    pub fn is_sub_byte(self) -> bool {
+        matches!(self.size_in_bits(), 1..=7)
+    }
+}
+
+impl TryFrom<PJRT_Buffer_Type> for BufferType {
+    type Error = String;
+
+    fn try_from(ty: PJRT_Buffer_Type) -> Result<Self, Self::Error> {
+        BufferType::ALL
+            .iter()
+            .copied()
+            .find(|t| t.raw() == ty)
+            .ok_or_else(|| format!("unknown PJRT_Buffer_Type {ty}"))
+    }
+}
+
+impl From<BufferType> for PJRT_Buffer_Type {
+    fn from(ty: BufferType) -> Self {
+        ty.raw()
+    }
+}
+
+impl fmt::Display for BufferType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// Bytes per element on the host for a dense array of `ty`. Sub-byte types (S4, U2, F4E2M1FN, ...)
+// are packed differently by each plugin and are rejected until supported.
+pub fn element_byte_size(ty: BufferType) -> Result<usize, String> {
+    match ty.size_in_bits() {
+        0 => Err(format!("element type {ty} has no host byte size")),
+        bits if ty.is_sub_byte() => Err(format!(
+            "sub-byte element type {ty} ({bits} bits) has no fixed host byte size"
+        )),
+        bits => Ok(bits / 8),
+    }
+}
+
+pub fn host_byte_size(dims: &[i64], ty: BufferType) -> Result<usize, String> {
+    element_count(dims)?
+        .checked_mul(element_byte_size(ty)?)
+        .ok_or_else(|| format!("host byte size overflows for dims {dims:?}"))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogicalShape {
+    pub dims: Vec<i64>,
+    pub unpadded_dims: Vec<i64>,
+    pub is_dynamic: Vec<bool>,
+}
+
+impl LogicalShape {
+    // Some plugins report no unpadded dims for static buffers; those equal the padded dims.
+    pub fn new(
+        dims: Vec<i64>,
+        unpadded_dims: Vec<i64>,
+        dynamic_indices: &[usize],
+    ) -> Result<Self, String> {
+        let unpadded_dims = if unpadded_dims.is_empty() {
+            dims.clone()
+        } else if unpadded_dims.len() != dims.len() {
+            return Err(format!(
+                "unpadded dims {unpadded_dims:?} do not match rank of dims {dims:?}"
+            ));
+        } else {
+            unpadded_dims
+        };
+
+        let mut is_dynamic = vec![false; dims.len()];
+        for &i in dynamic_indices {
+            match is_dynamic.get_mut(i) {
+                Some(d) => *d = true,
+                None => {
+                    return Err(format!(
+                        "dynamic dimension index {i} out of range for dims {dims:?}"
+                    ))
+                }
+            }
+        }
+        Ok(Self {
+            dims,
+            unpadded_dims,
+            is_dynamic,
+        })
+    }
+
+    pub fn logical_element_count(&self) -> Result<usize, String> {
+        element_count(&self.unpadded_dims)
+    }
+
+    pub fn has_dynamic_dims(&self) -> bool {
+        self.is_dynamic.iter().any(|&d| d)
+    }
+}
+
+// Debug field for a best-effort FFI query: the value on success, a placeholder on error.
+pub(crate) struct DebugResult(Result<String, String>);
+
+impl DebugResult {
+    pub(crate) fn debug<T: fmt::Debug>(result: Result<T, String>) -> Self {
+        Self(result.map(|v| format!("{v:?}")))
+    }
+
+    pub(crate) fn display<T: fmt::Display>(result: Result<T, String>) -> Self {
+        Self(result.map(|v| v.to_string()))
+    }
+}
+
+impl fmt::Debug for DebugResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Ok(v) => f.write_str(v),
+            Err(e) => write!(f, "<error: {e}>"),
+        }
+    }
+}
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// Host element types with a direct PJRT element type. Values travel as little-endian bytes.
+///
+/// The trait is sealed, so host uploads cannot be instantiated with arbitrary `Copy` types:
+///
+/// ```compile_fail
+/// use rrad_xla::pjrt::client::PJRTClient;
+///
+/// fn upload(client: &PJRTClient<'_>) {
+///     let data = [String::new()];
+///     let _ = client.buffer_from_host_slice_copy(&data, 11, &[1], None);
+/// }
+/// ```
+///
+/// ```compile_fail
+/// use rrad_xla::pjrt::utils::PjrtScalar;
+///
+/// #[derive(Clone, Copy)]
+/// struct Pixel(u8);
+///
+/// impl PjrtScalar for Pixel {
+///     const TYPE: rrad_xla::pjrt::utils::BufferType = rrad_xla::pjrt::utils::BufferType::U8;
+///     const BYTES: usize = 1;
+///     fn from_le_slice(bytes: &[u8]) -> Self {
+///         Pixel(bytes[0])
+///     }
+/// }
+/// ```
+pub trait PjrtScalar: private::Sealed + Copy + Send + Sync + 'static {
+    const TYPE: BufferType;
+    const BYTES: usize;
+
+    fn from_le_slice(bytes: &[u8]) -> Self;
+}
+
+macro_rules! impl_pjrt_scalar {
+    ($($t:ty => $ty:ident),* $(,)?) => {
+        $(
+            impl private::Sealed for $t {}
+
+            impl PjrtScalar for $t {
+                const TYPE: BufferType = BufferType::$ty;
+                const BYTES: usize = std::mem::size_of::<$t>();
+
+                fn from_le_slice(bytes: &[u8]) -> Self {
+                    let mut raw = [0u8; std::mem::size_of::<$t>()];
+                    raw.copy_from_slice(&bytes[..Self::BYTES]);
+                    <$t>::from_le_bytes(raw)
+                }
+            }
+        )*
+    };
+}
+
+impl_pjrt_scalar!(
+    f32 => F32,
+    f64 => F64,
+    i8 => S8,
+    i16 => S16,
+    i32 => S32,
+    i64 => S64,
+    u8 => U8,
+    u16 => U16,
+    u32 => U32,
+    u64 => U64,
+);
+
+#[cfg(feature = "half")]
+impl_pjrt_scalar!(
+    half::f16 => F16,
+    half::bf16 => BF16,
+);
+
+impl private::Sealed for bool {}
+
+impl PjrtScalar for bool {
+    const TYPE: BufferType = BufferType::Pred;
+    const BYTES: usize = 1;
+
+    fn from_le_slice(bytes: &[u8]) -> Self {
+        bytes[0] != 0
+    }
+}
+
+#[cfg(test)]
+mod utils_tests {
+    use super::*;
+
+    #[test]
+    fn row_major_strides() {
+        assert_eq!(
+            byte_strides(&[3, 2], 4, MajorOrder::RowMajor).unwrap(),
+            vec![8, 4]
+        );
+        assert_eq!(
+            byte_strides(&[2, 3, 4], 8, MajorOrder::RowMajor).unwrap(),
+            vec![96, 32, 8]
+        );
+    }
+
+    #[test]
+    fn column_major_strides() {
+        assert_eq!(
+            byte_strides(&[3, 2], 4, MajorOrder::ColumnMajor).unwrap(),
+            vec![4, 12]
+        );
+        assert_eq!(
+            byte_strides(&[2, 3, 4], 8, MajorOrder::ColumnMajor).unwrap(),
+            vec![8, 16, 48]
+        );
+    }
+
+    #[test]
+    fn scalar_and_vector_strides() {
+        assert!(byte_strides(&[], 4, MajorOrder::RowMajor)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            byte_strides(&[5], 2, MajorOrder::RowMajor).unwrap(),
+            vec![2]
+        );
+        assert_eq!(
+            byte_strides(&[5], 2, MajorOrder::ColumnMajor).unwrap(),
+            vec![2]
+        );
+    }
+
+    #[test]
+    fn rejects_negative_dims() {
+        assert!(byte_strides(&[2, -1], 4, MajorOrder::RowMajor).is_err());
+        assert!(element_count(&[-3]).is_err());
+    }
+
+    #[test]
+    fn data_len_must_match_dims() {
+        assert!(check_data_len(&[3, 2], 6).is_ok());
+        assert!(check_data_len(&[], 1).is_ok());
+        assert!(check_data_len(&[0, 4], 0).is_ok());
+        let err = check_data_len(&[3, 2], 5).unwrap_err();
+        assert!(err.contains("require 6"), "{err}");
+    }
+
+    #[test]
+    fn element_type_must_match_host_data() {
+        assert!(check_element_type::<f32>(BufferType::F32.raw()).is_ok());
+        let err = check_element_type::<u8>(BufferType::F64.raw()).unwrap_err();
+        assert_eq!(err, "element type f64 does not match the u8 host data");
+        assert!(check_element_type::<f32>(12345).is_err());
+    }
+
+    #[test]
+    fn scalar_types_and_widths() {
+        assert_eq!(<f32 as PjrtScalar>::TYPE, BufferType::F32);
+        assert_eq!(<i64 as PjrtScalar>::BYTES, 8);
+        assert_eq!(<bool as PjrtScalar>::TYPE, BufferType::Pred);
+        assert_eq!(<bool as PjrtScalar>::BYTES, 1);
+        assert_eq!(f32::from_le_slice(&41.5f32.to_le_bytes()), 41.5);
+        assert_eq!(i16::from_le_slice(&(-7i16).to_le_bytes()), -7);
+        assert!(bool::from_le_slice(&[1]));
+        // BYTES must agree with the dtype width used for host size math.
+        assert_eq!(
+            element_byte_size(<u16 as PjrtScalar>::TYPE),
+            Ok(<u16 as PjrtScalar>::BYTES)
+        );
+    }
+
+    #[cfg(feature = "half")]
+    #[test]
+    fn half_scalar_types() {
+        assert_eq!(<half::f16 as PjrtScalar>::TYPE, BufferType::F16);
+        assert_eq!(<half::bf16 as PjrtScalar>::BYTES, 2);
+        let v = half::bf16::from_f32(1.5);
+        assert_eq!(half::bf16::from_le_slice(&v.to_le_bytes()), v);
+    }
+
+    #[test]
+    fn buffer_type_round_trips_every_constant() {
+        for raw in
+            PJRT_Buffer_Type_PJRT_Buffer_Type_INVALID..=PJRT_Buffer_Type_PJRT_Buffer_Type_F4E2M1FN
+        {
+            let ty = BufferType::try_from(raw).unwrap();
+            assert_eq!(PJRT_Buffer_Type::from(ty), raw);
+        }
+        assert!(BufferType::try_from(PJRT_Buffer_Type_PJRT_Buffer_Type_F4E2M1FN + 1).is_err());
+        assert_eq!(BufferType::F32.to_string(), "f32");
+        assert_eq!(BufferType::BF16.to_string(), "bf16");
+        assert_eq!(BufferType::Pred.to_string(), "pred");
+    }
+
+    #[test]
+    fn element_byte_sizes_cover_every_type() {
+        for ty in BufferType::ALL {
+            let size = element_byte_size(ty);
+            match ty {
+                BufferType::Invalid
+                | BufferType::Token
+                | BufferType::S4
+                | BufferType::U4
+                | BufferType::S2
+                | BufferType::U2
+                | BufferType::F4E2M1FN => assert!(size.is_err(), "{ty} should have no byte size"),
+                _ => assert_eq!(size, Ok(ty.size_in_bits() / 8), "{ty}"),
+            }
+        }
+        assert_eq!(element_byte_size(BufferType::BF16), Ok(2));
+        assert_eq!(element_byte_size(BufferType::C128), Ok(16));
+        assert_eq!(element_byte_size(BufferType::F8E4M3FN), Ok(1));
+        let err = element_byte_size(BufferType::S4).unwrap_err();
+        assert!(err.contains("s4"), "{err}");
+    }
+
+    #[test]
+    fn host_byte_size_math() {
+        let f32_ty = BufferType::F32;
+        assert_eq!(host_byte_size(&[], f32_ty), Ok(4));
+        assert_eq!(host_byte_size(&[3, 2], f32_ty), Ok(24));
+        assert_eq!(host_byte_size(&[0, 7], f32_ty), Ok(0));
+        assert!(host_byte_size(&[i64::MAX, i64::MAX], f32_ty).is_err());
+        assert!(host_byte_size(&[4], BufferType::U4).is_err());
+    }
+
+    #[test]
+    fn debug_result_formats_values_and_placeholders() {
+        assert_eq!(
+            format!("{:?}", DebugResult::debug(Ok::<_, String>(vec![2, 3]))),
+            "[2, 3]"
+        );
+        assert_eq!(
+            format!(
+                "{:?}",
+                DebugResult::display(Ok::<_, String>(BufferType::F32))
+            ),
+            "f32"
+        );
+        assert_eq!(
+            format!(
+                "{:?}",
+                DebugResult::debug(Err::<i32, _>("boom".to_string()))
+            ),
+            "<error: boom>"
+        );
+    }
+
+    #[test]
+    fn logical_shape_assembly() {
+        let static_shape = LogicalShape::new(vec![2, 3], vec![], &[]).unwrap();
+        assert_eq!(static_shape.unpadded_dims, vec![2, 3]);
+        assert!(!static_shape.has_dynamic_dims());
+        assert_eq!(static_shape.logical_element_count(), Ok(6));
+
+        let dynamic = LogicalShape::new(vec![8, 4], vec![5, 4], &[0]).unwrap();
+        assert_eq!(dynamic.is_dynamic, vec![true, false]);
+        assert!(dynamic.has_dynamic_dims());
+        assert_eq!(dynamic.logical_element_count(), Ok(20));
+
+        assert!(LogicalShape::new(vec![8, 4], vec![5], &[0]).is_err());
+        assert!(LogicalShape::new(vec![8, 4], vec![], &[2]).is_err());
+    }
+}
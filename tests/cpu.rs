@@ -1,7 +1,43 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::error::Error;
+#[cfg(feature = "tokio")]
+use std::future::IntoFuture;
+use std::hash::{Hash, Hasher};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
+use rrad_xla::pjrt::buffer::PJRTBuffer;
+use rrad_xla::pjrt::client::{HostBufferSemantics, PJRTClient};
+use rrad_xla::pjrt::compile::{CompileFileError, CompileWait, ProgramFormat};
+use rrad_xla::pjrt::compile_options::{CompileOptionsBuilder, OptionOverride};
+use rrad_xla::pjrt::cross_host::{self, CrossHostDescriptor, ReceiveShape};
+use rrad_xla::pjrt::device::PJRTDevice;
+use rrad_xla::pjrt::error::{OwnedPjrtError, PJRTError, PjrtErrorCode, PjrtResult};
+use rrad_xla::pjrt::event::{PJRTEvent, WaitOutcome};
+use rrad_xla::pjrt::executable::{PJRTExecuteRunOptions, TaskInfo};
+use rrad_xla::pjrt::executable_cache::ExecutableCache;
+use rrad_xla::pjrt::executable_file::{
+    decode_executable_file, encode_executable_file, ExecutableFileError,
+};
+use rrad_xla::pjrt::execute_context::PJRTExecuteContext;
+use rrad_xla::pjrt::ffi::{handler_ptr, FfiBuffer, FfiBufferMut, HandlerTraits};
+use rrad_xla::pjrt::host_channel::HostChannel;
+use rrad_xla::pjrt::layouts;
 use rrad_xla::pjrt::loader::PjrtRuntime;
-use rrad_xla::pjrt_sys::PJRT_Buffer_Type_PJRT_Buffer_Type_F32;
+use rrad_xla::pjrt::memory::{MemoryKind, PJRTMemory};
+use rrad_xla::pjrt::stream;
+use rrad_xla::pjrt::topology_desc::{PJRTNamedValue, PJRTTopologyDescription, TopologyOptions};
+use rrad_xla::pjrt::topology_file::{
+    decode_topology_file, encode_topology_file, TopologyFileError,
+};
+use rrad_xla::pjrt::utils::{BufferType, MajorOrder};
+use rrad_xla::pjrt_sys::{
+    PJRT_Buffer_Type_PJRT_Buffer_Type_F32, PJRT_Buffer_Type_PJRT_Buffer_Type_F64,
+    PJRT_ExecuteContext_Destroy_Args, PJRT_ExecuteContext_Destroy_Args_STRUCT_SIZE,
+};
 
 const MODULE_ADD_ONE: &str = r#"module {
 func.func @main(%arg0: tensor<f32>) -> tensor<f32> {
@@ -11,6 +47,52 @@ func.func @main(%arg0: tensor<f32>) -> tensor<f32> {
   return %2 : tensor<f32>
 }}"#;
 
+const MODULE_ACCUMULATE: &str = r#"module {
+func.func @main(%arg0: tensor<4xf32> {tf.aliasing_output = 0 : i32}, %arg1: tensor<4xf32>) -> tensor<4xf32> {
+  %0 = mhlo.add %arg0, %arg1 : tensor<4xf32>
+  return %0 : tensor<4xf32>
+}}"#;
+
+const MODULE_TWO_OUTPUTS: &str = r#"module {
+func.func @main(%arg0: tensor<f32>) -> (tensor<f32>, tensor<f32>) {
+  %0 = mhlo.constant dense<1.000000e+00> : tensor<f32>
+  %1 = mhlo.add %arg0, %0 : tensor<f32>
+  return %arg0, %1 : tensor<f32>, tensor<f32>
+}}"#;
+
+const MODULE_MATRIX_OUTPUT: &str = r#"module {
+func.func @main(%arg0: tensor<f32>) -> tensor<2x3xf32> {
+  %0 = "mhlo.broadcast_in_dim"(%arg0) {broadcast_dimensions = dense<> : tensor<0xi64>} : (tensor<f32>) -> tensor<2x3xf32>
+  return %0 : tensor<2x3xf32>
+}}"#;
+
+// Sends its argument to the host on channel 1 and returns it unchanged.
+const MODULE_SEND_TO_HOST: &str = r#"module {
+func.func @main(%arg0: tensor<2xf32>) -> tensor<2xf32> {
+  %0 = stablehlo.create_token : !stablehlo.token
+  %1 = "stablehlo.send"(%arg0, %0) {channel_handle = #stablehlo.channel_handle<handle = 1, type = 2>, is_host_transfer = true, mhlo.frontend_attributes = {_xla_host_transfer_handler_name = "tf_rendezvous", _xla_host_transfer_rendezvous = "send_0", _xla_host_transfer_original_type = "f32"}} : (tensor<2xf32>, !stablehlo.token) -> !stablehlo.token
+  return %arg0 : tensor<2xf32>
+}}"#;
+
+// Receives a tensor<2xf32> from the host on channel 2 and returns it.
+const MODULE_RECV_FROM_HOST: &str = r#"module {
+func.func @main() -> tensor<2xf32> {
+  %0 = stablehlo.create_token : !stablehlo.token
+  %1:2 = "stablehlo.recv"(%0) {channel_handle = #stablehlo.channel_handle<handle = 2, type = 3>, is_host_transfer = true, mhlo.frontend_attributes = {_xla_host_transfer_handler_name = "tf_rendezvous", _xla_host_transfer_rendezvous = "recv_0", _xla_host_transfer_original_type = "f32"}} : (!stablehlo.token) -> (tensor<2xf32>, !stablehlo.token)
+  return %1#0 : tensor<2xf32>
+}}"#;
+
+// Doubles its argument through a host-side Rust handler registered as "rrad_cpu_double".
+const MODULE_CUSTOM_DOUBLE: &str = r#"module {
+func.func @main(%arg0: tensor<4xf32>) -> tensor<4xf32> {
+  %0 = stablehlo.custom_call @rrad_cpu_double(%arg0) {api_version = 4 : i32} : (tensor<4xf32>) -> tensor<4xf32>
+  return %0 : tensor<4xf32>
+}}"#;
+
+fn cpu_device_count_option(count: i64) -> (&'static str, PJRTNamedValue) {
+    ("cpu_device_count", PJRTNamedValue::Int64(count))
+}
+
 fn resolve_plugin_path() -> Option<PathBuf> {
     if let Ok(path) = std::env::var("PJRT_PLUGIN") {
         let p = PathBuf::from(path);
@@ -49,30 +131,2093 @@ fn cpu_end_to_end_compile_execute_download() -> Result<(), String> {
     if raw_devices.is_empty() {
         return Err("client has no devices".to_string());
     }
-    let device = raw_devices[0];
+    let device = PJRTDevice::new(&rt, raw_devices[0])?;
 
-    let executable = client.compile(MODULE_ADD_ONE, "mlir", &[])?;
+    let executable = client.compile(MODULE_ADD_ONE, ProgramFormat::Mlir, &[])?;
 
-    let input = [41.0f32];
-    let input_buffer = client.buffer_from_host_slice_copy(
-        &input,
-        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
-        &[],
-        Some(device),
-    )?;
+    let input_buffer = client.buffer_from_scalar(41.0f32, Some(&device))?;
 
-    let (outputs, done) = executable.execute(&[&input_buffer])?;
-    done.ok()?;
+    let outputs = executable.run(&[&input_buffer])?.wait()?;
     if outputs.len() != 1 {
         return Err(format!("expected exactly 1 output, got {}", outputs.len()));
     }
 
-    let mut out_bytes = [0u8; std::mem::size_of::<f32>()];
-    outputs[0].to_host_buffer_blocking(&mut out_bytes)?;
-    let out = f32::from_le_bytes(out_bytes);
+    let out: f32 = outputs[0].to_scalar()?;
     if (out - 42.0).abs() > 1e-6 {
         return Err(format!("expected 42.0, got {out}"));
     }
 
     Ok(())
 }
+
+#[test]
+fn cpu_compile_with_builder_round_trips_options() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_compile_with_builder_round_trips_options: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+
+    let options = CompileOptionsBuilder::new()
+        .num_replicas(1)
+        .num_partitions(1)
+        .override_flag("xla_cpu_enable_fast_math", false);
+    let executable = client.compile_with(MODULE_ADD_ONE, ProgramFormat::Mlir, &options)?;
+
+    let decoded = CompileOptionsBuilder::decode(&executable.get_compile_options()?)?;
+    assert_eq!(decoded.get_num_replicas(), 1);
+    assert_eq!(decoded.get_num_partitions(), 1);
+    assert!(!decoded.get_use_spmd_partitioning());
+    assert_eq!(executable.num_replicas()?, 1);
+    Ok(())
+}
+
+#[test]
+fn cpu_compile_with_flags_keeps_the_overrides() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_compile_with_flags_keeps_the_overrides: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+
+    let flags = [("xla_cpu_enable_fast_math", OptionOverride::Bool(false))];
+    let executable = client.compile_with_flags(MODULE_ADD_ONE, ProgramFormat::Mlir, &flags)?;
+
+    let decoded = CompileOptionsBuilder::decode(&executable.get_compile_options()?)?;
+    assert_eq!(
+        decoded.get_debug_flags().get("xla_cpu_enable_fast_math"),
+        Some(&OptionOverride::Bool(false))
+    );
+
+    let input = client.buffer_from_scalar(1.0f32, None)?;
+    let outputs = executable.run(&[&input])?.wait()?;
+    assert_eq!(outputs[0].to_scalar::<f32>()?, 2.0);
+    Ok(())
+}
+
+#[cfg(feature = "proto")]
+#[test]
+fn cpu_compile_options_reads_back_replicas() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_compile_options_reads_back_replicas: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii_with_options(&[cpu_device_count_option(2)])?;
+
+    let options = CompileOptionsBuilder::new()
+        .num_replicas(2)
+        .num_partitions(1);
+    let executable = client.compile_with(MODULE_ADD_ONE, ProgramFormat::Mlir, &options)?;
+    let info = executable.compile_options()?;
+    assert_eq!(info.num_replicas, 2);
+    assert_eq!(info.num_partitions, 1);
+    assert!(!info.use_spmd_partitioning);
+    Ok(())
+}
+
+#[cfg(feature = "proto")]
+#[test]
+fn cpu_device_assignment_decodes_single_device() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_device_assignment_decodes_single_device: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let executable = client.compile(MODULE_ADD_ONE, ProgramFormat::Mlir, &[])?;
+
+    let assignment = executable.device_assignment()?;
+    assert_eq!(assignment.replica_count(), 1);
+    assert_eq!(assignment.computation_count(), 1);
+    let id = assignment.device_for(0, 0)?;
+    assert_eq!(id, executable.addressable_device_ids()?[0]);
+    assert_eq!(assignment.device_ref_for(&client, 0, 0)?.id(), id);
+    Ok(())
+}
+
+#[test]
+fn cpu_compile_file_detects_format() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_compile_file_detects_format: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+
+    let dir = std::env::temp_dir().join(format!("rrad_compile_file_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let path = dir.join("add_one.mlir");
+    std::fs::write(&path, MODULE_ADD_ONE).map_err(|e| e.to_string())?;
+
+    let executable = client.compile_file(&path, &[])?;
+    assert_eq!(executable.num_replicas()?, 1);
+
+    let missing = client.compile_file(&dir.join("missing.mlir"), &[]);
+    assert!(matches!(missing, Err(CompileFileError::Io(_))));
+
+    let garbage = dir.join("garbage.mlir");
+    std::fs::write(&garbage, "not mlir").map_err(|e| e.to_string())?;
+    let broken = client.compile_file(&garbage, &[]);
+    assert!(matches!(broken, Err(CompileFileError::Compile(_))));
+
+    let _ = std::fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn cpu_compile_error_keeps_code_and_diagnostics() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_compile_error_keeps_code_and_diagnostics: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+
+    let broken = "module {\nfunc.func @main() {\n  %0 = \"no.such_op\"() : () -> tensor<f32>\n}}";
+    let err = match client.compile(broken, ProgramFormat::Mlir, &[]) {
+        Ok(_) => return Err("broken MLIR should not compile".to_string()),
+        Err(e) => e,
+    };
+    assert_ne!(err.code(), PjrtErrorCode::Ok);
+    assert_eq!(err.format(), "mlir");
+    assert!(err.snippet().starts_with("module {"));
+    assert!(
+        !err.diagnostics().is_empty(),
+        "expected at least one MLIR diagnostic in: {}",
+        err.message()
+    );
+    Ok(())
+}
+
+#[test]
+fn cpu_errors_carry_their_codes() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_errors_carry_their_codes: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+
+    let missing = PJRTError::missing_symbol(&rt, "PJRT_Not_A_Function");
+    assert!(missing.is_code(PjrtErrorCode::Unimplemented));
+    assert!(PJRTError::not_found(&rt, "no device 7").is_code(PjrtErrorCode::NotFound));
+    let wrapped = PJRTError::from_wrapper_message(&rt, PjrtErrorCode::Unknown, missing.message());
+    assert_eq!(wrapped.code(), PjrtErrorCode::Unimplemented);
+
+    let null_memory = PJRTMemory {
+        rt: &rt,
+        raw: std::ptr::null_mut(),
+    };
+    let err = null_memory.id().unwrap_err();
+    assert!(err.is_code(PjrtErrorCode::InvalidArgument), "{err}");
+
+    // Codes read from the plugin's own PJRT_Error.
+    let err = match client.compile("module {", ProgramFormat::Mlir, &[]) {
+        Ok(_) => return Err("truncated MLIR should not compile".to_string()),
+        Err(e) => e,
+    };
+    assert!(err.error().is_code(PjrtErrorCode::InvalidArgument), "{err}");
+    assert_eq!(err.error().context()[0].operation, "PJRT_Client_Compile");
+    assert!(
+        err.to_string().contains("(in PJRT_Client_Compile)"),
+        "{err}"
+    );
+    Ok(())
+}
+
+#[test]
+fn cpu_errors_leave_the_runtime_scope() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_errors_leave_the_runtime_scope: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+
+    fn compile_broken(client: &PJRTClient<'_>) -> Result<(), Box<dyn Error + Send + Sync>> {
+        match client.compile("module {", ProgramFormat::Mlir, &[]) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e.into_error().into_owned().into()),
+        }
+    }
+
+    // A borrowed PJRTError can cross a scoped thread; the owned form is 'static.
+    let err = match client.compile("module {", ProgramFormat::Mlir, &[]) {
+        Ok(_) => return Err("truncated MLIR should not compile".to_string()),
+        Err(e) => e.into_error(),
+    };
+    let code = err.code();
+    let err = std::thread::scope(|s| s.spawn(move || err).join().unwrap());
+    let owned = err.into_owned();
+    let owned = std::thread::spawn(move || owned).join().unwrap();
+    assert_eq!(owned.code(), code);
+
+    let boxed = compile_broken(&client).unwrap_err();
+    let owned = boxed
+        .downcast_ref::<OwnedPjrtError>()
+        .ok_or("boxed error is not an OwnedPjrtError")?;
+    assert_eq!(owned.code(), code);
+    Ok(())
+}
+
+#[test]
+fn cpu_owned_errors_collected_from_threads() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_owned_errors_collected_from_threads: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+
+    fn compile_broken(rt: &PjrtRuntime) -> PjrtResult<()> {
+        let client = rt.create_client_raii()?;
+        client.compile("module {", ProgramFormat::Mlir, &[])?;
+        Ok(())
+    }
+
+    fn compile_missing(rt: &PjrtRuntime) -> PjrtResult<()> {
+        let client = rt.create_client_raii()?;
+        client.compile_file(Path::new("/nonexistent/program.mlir"), &[])?;
+        Ok(())
+    }
+
+    // Each worker borrows the runtime, but what it hands back outlives the scope.
+    let errors: Vec<OwnedPjrtError> = std::thread::scope(|s| {
+        let workers = [
+            s.spawn(|| compile_broken(&rt)),
+            s.spawn(|| compile_broken(&rt)),
+            s.spawn(|| compile_missing(&rt)),
+            s.spawn(|| Err(PJRTError::missing_symbol(&rt, "PJRT_Example").into())),
+        ];
+        workers
+            .into_iter()
+            .filter_map(|worker| worker.join().unwrap().err())
+            .collect()
+    });
+    assert_eq!(errors.len(), 4);
+
+    assert_eq!(errors[0].code(), errors[1].code());
+    assert!(!errors[0].is_code(PjrtErrorCode::Ok));
+    assert!(errors[0].to_string().contains("(in PJRT_Client_Compile)"));
+    assert!(errors[2].is_code(PjrtErrorCode::NotFound));
+    assert!(errors[3].is_code(PjrtErrorCode::Unimplemented));
+    drop(rt);
+    assert!(errors[3].message().contains("PJRT_Example"));
+    Ok(())
+}
+
+static CPU_DOUBLE_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+fn cpu_double(args: &[FfiBuffer<'_>], rets: &mut [FfiBufferMut<'_>]) -> Result<(), String> {
+    CPU_DOUBLE_CALLS.fetch_add(1, Ordering::SeqCst);
+    let input = args[0].data::<f32>()?;
+    for (out, x) in rets[0].data_mut::<f32>()?.iter_mut().zip(input) {
+        *out = x * 2.0;
+    }
+    Ok(())
+}
+
+rrad_xla::xla_ffi_buffer_handler!(cpu_double_handler = cpu_double);
+
+#[test]
+fn cpu_custom_call_runs_the_registered_handler() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_custom_call_runs_the_registered_handler: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+
+    let handler = handler_ptr(cpu_double_handler);
+    match client.register_custom_call("rrad_cpu_double", "Host", handler, HandlerTraits::NONE) {
+        Ok(()) => {}
+        Err(e) if e.is_code(PjrtErrorCode::Unimplemented) => {
+            eprintln!("Skipping cpu_custom_call_runs_the_registered_handler: no FFI extension");
+            return Ok(());
+        }
+        Err(e) => return Err(e.into()),
+    }
+
+    let executable = client.compile(MODULE_CUSTOM_DOUBLE, ProgramFormat::Mlir, &[])?;
+    let f32_ty = PJRT_Buffer_Type_PJRT_Buffer_Type_F32;
+    let input = client.buffer_from_host_slice_copy(&[1.0f32, 2.0, 3.0, 4.0], f32_ty, &[4], None)?;
+    let outputs = executable.run(&[&input])?.wait()?;
+    assert_eq!(outputs[0].to_host_vec::<f32>()?, vec![2.0, 4.0, 6.0, 8.0]);
+    assert_eq!(CPU_DOUBLE_CALLS.load(Ordering::SeqCst), 1);
+    Ok(())
+}
+
+#[test]
+fn cpu_default_layout_feeds_back_into_an_upload() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_default_layout_feeds_back_into_an_upload: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+
+    let f32_ty = PJRT_Buffer_Type_PJRT_Buffer_Type_F32;
+    let layout = match layouts::default_layout_for(&client, BufferType::F32, &[16, 16]) {
+        Ok(layout) => layout,
+        Err(e) if e.is_code(PjrtErrorCode::Unimplemented) => {
+            assert!(!layouts::is_available(&rt));
+            eprintln!(
+                "Skipping cpu_default_layout_feeds_back_into_an_upload: no layouts extension"
+            );
+            return Ok(());
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let text = layouts::serialize_layout(&layout)?;
+    assert_eq!(layouts::deserialize_layout(&text)?, layout);
+
+    let data: Vec<f32> = (0..256).map(|i| i as f32).collect();
+    let buffer =
+        client.buffer_from_host_slice_with_layout(&data, f32_ty, &[16, 16], &layout, None)?;
+    assert_eq!(buffer.get_memory_layout()?, layout);
+    assert_eq!(buffer.to_host_vec::<f32>()?, data);
+    Ok(())
+}
+
+// CPU plugins have no stream extension, so this checks the gating; on a GPU plugin it
+// exercises the stream calls themselves.
+#[test]
+fn cpu_stream_interop_is_gated_on_the_extension() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_stream_interop_is_gated_on_the_extension: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let device = PJRTDevice::new(&rt, client.devices()?[0])?;
+    let buffer = client.buffer_from_scalar(1.0f32, Some(&device))?;
+
+    if !stream::is_available(&rt) {
+        let err = stream::device_ready_event_stream(&device).unwrap_err();
+        assert!(err.is_code(PjrtErrorCode::Unimplemented), "{err}");
+        let err = buffer.wait_ready_on_stream(0).unwrap_err();
+        assert!(err.is_code(PjrtErrorCode::Unimplemented), "{err}");
+        return Ok(());
+    }
+
+    let stream = stream::device_ready_event_stream(&device)?;
+    assert_ne!(stream, 0);
+    buffer.wait_ready_on_stream(stream)?;
+    Ok(())
+}
+
+// Kind ids from the memory itself and from the memory descriptions extension must name
+// the same kind as the string.
+#[test]
+fn cpu_memory_kind_ids_match_kind_strings() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_memory_kind_ids_match_kind_strings: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let device = PJRTDevice::new(&rt, client.devices()?[0])?;
+
+    for (memory, kind) in device.memories_with_kinds()? {
+        let Ok(expected) = kind.parse::<MemoryKind>() else {
+            continue;
+        };
+        assert_eq!(
+            MemoryKind::from_id(memory.kind_id()?),
+            Some(expected),
+            "{kind}"
+        );
+        assert!(memory.is_kind(expected));
+        assert_eq!(device.memory_by_kind(expected)?.kind()?, kind);
+    }
+    assert!(device.memory_by_kind("no_such_memory_kind").is_err());
+
+    if let Ok((descriptions, _)) = device.description()?.memory_descriptions() {
+        for description in descriptions {
+            if let Ok(expected) = description.kind.parse::<MemoryKind>() {
+                assert_eq!(MemoryKind::from_id(description.kind_id), Some(expected));
+            }
+        }
+    }
+
+    let buffer = client.buffer_from_scalar(2.0f32, Some(&device))?;
+    let copied = buffer.copy_to_memory_kind(MemoryKind::Device)?;
+    assert_eq!(copied.to_host_vec::<f32>()?, [2.0]);
+    Ok(())
+}
+
+// Two clients stand in for two hosts. CPU plugins without the extension only check the
+// gating.
+#[test]
+fn cpu_cross_host_loopback_between_two_clients() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_cross_host_loopback_between_two_clients: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let sender = rt.create_client_raii()?;
+    let receiver = rt.create_client_raii()?;
+    let send_device = PJRTDevice::new(&rt, sender.devices()?[0])?;
+    let recv_device = PJRTDevice::new(&rt, receiver.devices()?[0])?;
+    let source = sender.buffer_from_host_slice(
+        &[1.0f32, 2.0, 3.0, 4.0],
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        &[2, 2],
+        HostBufferSemantics::ImmutableOnlyDuringCall,
+        Some(send_device.raw()),
+    )?;
+    let shape = ReceiveShape::new(PJRT_Buffer_Type_PJRT_Buffer_Type_F32, &[2, 2]);
+
+    if !cross_host::is_available(&rt) {
+        let err =
+            PJRTBuffer::make_cross_host_receive_descriptors(&receiver, &recv_device, &[shape])
+                .err()
+                .unwrap();
+        assert!(err.is_code(PjrtErrorCode::Unimplemented), "{err}");
+        let descriptor = CrossHostDescriptor::from_bytes(b"unused".to_vec());
+        let err = source.copy_to_remote_device(&descriptor).err().unwrap();
+        assert!(err.is_code(PjrtErrorCode::Unimplemented), "{err}");
+        return Ok(());
+    }
+
+    let receive =
+        PJRTBuffer::make_cross_host_receive_descriptors(&receiver, &recv_device, &[shape])?;
+    let descriptors = receive.descriptors()?;
+    assert_eq!(descriptors.len(), 1);
+
+    // The bytes would normally cross the network between the two processes.
+    let shipped = CrossHostDescriptor::from_bytes(descriptors[0].as_bytes().to_vec());
+    source.copy_to_remote_device(&shipped)?.wait()?;
+    for event in receive.ready_events()? {
+        event.ok()?;
+    }
+    assert_eq!(
+        receive.buffers[0].to_host_vec::<f32>()?,
+        [1.0, 2.0, 3.0, 4.0]
+    );
+    Ok(())
+}
+
+#[test]
+fn cpu_compile_through_program_format() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_compile_through_program_format: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+
+    let format = ProgramFormat::from_name("mlir")?;
+    let executable = client.compile_bytes(MODULE_ADD_ONE.as_bytes(), format, &[])?;
+    assert_eq!(executable.num_replicas()?, 1);
+
+    let err = match client.compile(MODULE_ADD_ONE, ProgramFormat::HloProto, &[]) {
+        Ok(_) => return Err("text should not compile as a serialized HLO proto".to_string()),
+        Err(e) => e,
+    };
+    assert_eq!(err.code(), PjrtErrorCode::InvalidArgument);
+    assert_eq!(err.format(), "hlo");
+    Ok(())
+}
+
+#[test]
+fn cpu_compiles_serialized_hlo_proto() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_compiles_serialized_hlo_proto: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+
+    // HloModuleProto for `ROOT add = f32[] add(x, x)`; the encoding has NUL bytes.
+    let proto = include_bytes!("fixtures/add_self_f32.hlo.pb");
+    let topology = client.topology_description()?;
+    for executable in [
+        client.compile_bytes(proto, ProgramFormat::HloProto, &[])?,
+        topology.compile_bytes_and_load(&client, proto, ProgramFormat::HloProto, &[])?,
+    ] {
+        let input = client.buffer_from_scalar(2.5f32, None)?;
+        let outputs = executable.run(&[&input])?.wait()?;
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].to_scalar::<f32>()?, 5.0);
+    }
+    Ok(())
+}
+
+#[test]
+fn cpu_background_compiles_run_concurrently() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_background_compiles_run_concurrently: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+
+    let proto = include_bytes!("fixtures/add_self_f32.hlo.pb");
+    let (add_one, add_self) = client.compile_scope(|scope| {
+        let add_one = scope.spawn(MODULE_ADD_ONE.as_bytes(), ProgramFormat::Mlir, &[]);
+        let add_self = scope.spawn(proto, ProgramFormat::HloProto, &[]);
+
+        let add_one = match add_one.wait_timeout(Duration::from_secs(120)) {
+            CompileWait::Ready(result) => result?,
+            CompileWait::Pending(_) => return Err("background compile did not finish".to_string()),
+        };
+        while !add_self.is_done() {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        Ok::<_, String>((add_one, add_self.wait()?))
+    })?;
+
+    let input = client.buffer_from_scalar(3.0f32, None)?;
+    assert_eq!(add_one.run(&[&input])?.wait()?[0].to_scalar::<f32>()?, 4.0);
+    assert_eq!(add_self.run(&[&input])?.wait()?[0].to_scalar::<f32>()?, 6.0);
+
+    client.compile_scope(|scope| {
+        let broken = scope.spawn(b"not mlir", ProgramFormat::Mlir, &[]);
+        assert!(broken.wait().is_err());
+        // A leaked handle is still joined when the scope ends.
+        std::mem::forget(scope.spawn(b"not mlir either", ProgramFormat::Mlir, &[]));
+    });
+    Ok(())
+}
+
+#[test]
+fn cpu_wait_until_ready_with_and_without_timeout() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_wait_until_ready_with_and_without_timeout: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let device = PJRTDevice::new(&rt, client.devices()?[0])?;
+
+    let ready = client.buffer_from_scalar(1.0f32, Some(&device))?;
+    ready.wait_until_ready()?;
+    assert!(ready.wait_until_ready_timeout(Duration::from_millis(100))?);
+
+    // An alias buffer stays pending until it is fulfilled, so the wait must time out.
+    let alias = client.create_alias_buffer(
+        &[],
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        Some(device.default_memory_raw()?),
+        None,
+    )?;
+    assert!(!alias
+        .buffer()
+        .wait_until_ready_timeout(Duration::from_millis(20))?);
+    let fulfilled = alias.fulfill(&ready)?;
+    assert!(fulfilled.wait_until_ready_timeout(Duration::from_secs(10))?);
+    Ok(())
+}
+
+#[test]
+fn cpu_wait_times_out_on_never_completing_event() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_wait_times_out_on_never_completing_event: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let device = PJRTDevice::new(&rt, client.devices()?[0])?;
+
+    let never = PJRTEvent::create(&rt)?;
+    assert!(matches!(
+        never.await_timeout(Duration::from_millis(20))?,
+        WaitOutcome::TimedOut
+    ));
+
+    // Nothing ever fulfills this alias, so every wait times out and leaves it pending.
+    let alias = client.create_alias_buffer(
+        &[],
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        Some(device.default_memory_raw()?),
+        None,
+    )?;
+    for _ in 0..3 {
+        assert!(!alias
+            .buffer()
+            .wait_until_ready_timeout(Duration::from_millis(20))?);
+    }
+    assert!(!alias.buffer().ready_event()?.is_ready()?);
+    Ok(())
+}
+
+#[test]
+fn cpu_execute_against_alias_before_fulfilling_it() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_execute_against_alias_before_fulfilling_it: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let device = PJRTDevice::new(&rt, client.devices()?[0])?;
+    let executable = client.compile(MODULE_ADD_ONE, ProgramFormat::Mlir, &[])?;
+
+    let alias = client.create_alias_buffer(
+        &[],
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        Some(device.default_memory_raw()?),
+        None,
+    )?;
+    let result = executable.run(&[alias.buffer()])?;
+    // The launch consumes the alias, so its output cannot be ready until the alias is.
+    assert!(!result.outputs[0].wait_until_ready_timeout(Duration::from_millis(20))?);
+
+    let source = client.buffer_from_scalar(3.0f32, Some(&device))?;
+    let _fulfilled = alias.fulfill(&source)?;
+    assert_eq!(result.wait()?[0].to_scalar::<f32>()?, 4.0);
+    Ok(())
+}
+
+// Both checks run before the plugin is asked to read the host data.
+#[test]
+fn cpu_host_slice_uploads_reject_mismatched_type_or_length() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!(
+            "Skipping cpu_host_slice_uploads_reject_mismatched_type_or_length: PJRT plugin not found"
+        );
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let device = client.devices()?[0];
+
+    let bytes = [0u8; 4];
+    let err = client
+        .buffer_from_host_slice_copy(
+            &bytes,
+            PJRT_Buffer_Type_PJRT_Buffer_Type_F64,
+            &[4],
+            Some(device),
+        )
+        .err()
+        .unwrap();
+    assert!(err.contains("does not match the u8 host data"), "{err}");
+
+    let values = [1.0f32, 2.0];
+    let err = client
+        .buffer_from_host_slice(
+            &values,
+            PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+            &[2, 2],
+            HostBufferSemantics::ImmutableOnlyDuringCall,
+            Some(device),
+        )
+        .err()
+        .unwrap();
+    assert!(err.contains("require 4"), "{err}");
+
+    let ok = client.buffer_from_host_slice_copy(
+        &values,
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        &[2],
+        Some(device),
+    )?;
+    assert_eq!(ok.to_host_vec::<f32>()?, values);
+    Ok(())
+}
+
+#[test]
+fn cpu_nd_and_owned_uploads_reject_mismatched_type() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!(
+            "Skipping cpu_nd_and_owned_uploads_reject_mismatched_type: PJRT plugin not found"
+        );
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let device = client.devices()?[0];
+
+    let values = [1.0f32, 2.0, 3.0, 4.0];
+    let err = client
+        .buffer_from_host_nd(
+            &[2, 2],
+            &values,
+            MajorOrder::ColumnMajor,
+            PJRT_Buffer_Type_PJRT_Buffer_Type_F64,
+            Some(device),
+        )
+        .err()
+        .unwrap();
+    assert!(err.contains("does not match the f32 host data"), "{err}");
+
+    let err = client
+        .buffer_from_host_owned(
+            values.to_vec(),
+            PJRT_Buffer_Type_PJRT_Buffer_Type_F64,
+            &[4],
+            Some(device),
+        )
+        .err()
+        .unwrap();
+    assert!(err.contains("does not match the f32 host data"), "{err}");
+
+    let ok = client.buffer_from_host_owned(
+        values.to_vec(),
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        &[4],
+        Some(device),
+    )?;
+    assert_eq!(ok.to_host_vec::<f32>()?, values);
+    Ok(())
+}
+
+#[test]
+fn cpu_wrapper_types_format_key_fields() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_wrapper_types_format_key_fields: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+
+    let client_debug = format!("{client:?}");
+    assert!(client_debug.starts_with("PJRTClient {"), "{client_debug}");
+    assert!(client_debug.contains("platform:"), "{client_debug}");
+
+    let device = client
+        .device_refs()?
+        .into_iter()
+        .next()
+        .ok_or("client has no devices")?;
+    let device_debug = format!("{device:?}");
+    assert!(device_debug.contains("id: 0"), "{device_debug}");
+    assert!(device_debug.contains("kind:"), "{device_debug}");
+
+    let memory = device.default_memory()?;
+    let memory_debug = format!("{memory:?}");
+    assert!(memory_debug.starts_with("PJRTMemory {"), "{memory_debug}");
+    assert!(memory_debug.contains("kind:"), "{memory_debug}");
+
+    let buffer = client.buffer_from_host_2d(
+        2,
+        3,
+        &[1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0],
+        MajorOrder::RowMajor,
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        Some(device.raw()),
+    )?;
+    assert_eq!(buffer.to_string(), "f32[2,3]");
+    let buffer_debug = format!("{buffer:?}");
+    assert_eq!(
+        buffer_debug,
+        "PJRTBuffer { dtype: f32, dims: [2, 3], device: 0, deleted: false }"
+    );
+
+    let executable = client.compile(MODULE_ADD_ONE, ProgramFormat::Mlir, &[])?;
+    let executable_debug = format!("{executable:?}");
+    assert!(executable_debug.contains("name:"), "{executable_debug}");
+    assert!(
+        executable_debug.contains("fingerprint:"),
+        "{executable_debug}"
+    );
+    assert!(
+        executable_debug.contains("num_outputs: 1"),
+        "{executable_debug}"
+    );
+    Ok(())
+}
+
+#[test]
+fn cpu_execute_sharded_runs_every_replica() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_execute_sharded_runs_every_replica: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii_with_options(&[cpu_device_count_option(2)])?;
+    let devices = client.addressable_device_refs()?;
+    assert_eq!(devices.len(), 2);
+
+    let options = CompileOptionsBuilder::new().num_replicas(2);
+    let executable = client.compile_with(MODULE_ADD_ONE, ProgramFormat::Mlir, &options)?;
+    assert_eq!(executable.addressable_devices()?.len(), 2);
+
+    let a = client.buffer_from_scalar(1.0f32, Some(&devices[0]))?;
+    let b = client.buffer_from_scalar(10.0f32, Some(&devices[1]))?;
+    let outputs =
+        executable.execute_sharded(&[vec![&a], vec![&b]], &PJRTExecuteRunOptions::default())?;
+    assert_eq!(outputs.len(), 2);
+    assert_eq!(outputs[0][0].to_scalar::<f32>()?, 2.0);
+    assert_eq!(outputs[1][0].to_scalar::<f32>()?, 11.0);
+    assert_eq!(outputs[0][0].device_id()?, devices[0].id());
+    assert_eq!(outputs[1][0].device_id()?, devices[1].id());
+
+    let err = executable
+        .execute_sharded(&[vec![&a]], &PJRTExecuteRunOptions::default())
+        .unwrap_err();
+    assert!(err.contains("2 addressable devices"), "{err}");
+    let err = executable
+        .execute_sharded(&[vec![&a], vec![]], &PJRTExecuteRunOptions::default())
+        .unwrap_err();
+    assert!(err.contains("differ in arity"), "{err}");
+    Ok(())
+}
+
+#[test]
+fn cpu_execute_replicated_splits_a_batch_across_devices() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!(
+            "Skipping cpu_execute_replicated_splits_a_batch_across_devices: PJRT plugin not found"
+        );
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii_with_options(&[cpu_device_count_option(2)])?;
+    let devices = client.addressable_device_refs()?;
+    assert_eq!(devices.len(), 2);
+
+    let options = CompileOptionsBuilder::new().num_replicas(2);
+    let executable = client.compile_with(MODULE_ADD_ONE, ProgramFormat::Mlir, &options)?;
+
+    // Both halves of the batch start on device 0; the second must be moved to device 1.
+    let batch = [1.0f32, 10.0];
+    let shards = batch
+        .iter()
+        .map(|&x| Ok(vec![client.buffer_from_scalar(x, Some(&devices[0]))?]))
+        .collect::<Result<Vec<_>, String>>()?;
+    let outputs = executable
+        .execute_replicated(shards, &PJRTExecuteRunOptions::default())?
+        .wait()?;
+    assert_eq!(outputs.len(), 2);
+    assert_eq!(outputs[0][0].to_scalar::<f32>()?, 2.0);
+    assert_eq!(outputs[1][0].to_scalar::<f32>()?, 11.0);
+    assert_eq!(outputs[0][0].device_id()?, devices[0].id());
+    assert_eq!(outputs[1][0].device_id()?, devices[1].id());
+
+    let one = vec![vec![client.buffer_from_scalar(1.0f32, None)?]];
+    match executable.execute_replicated(one, &PJRTExecuteRunOptions::default()) {
+        Ok(_) => return Err("one shard for two devices should be rejected".to_string()),
+        Err(e) => assert!(e.contains("1 shards for 2 addressable devices"), "{e}"),
+    }
+    let ragged = vec![vec![client.buffer_from_scalar(1.0f32, None)?], vec![]];
+    match executable.execute_replicated(ragged, &PJRTExecuteRunOptions::default()) {
+        Ok(_) => return Err("ragged shards should be rejected".to_string()),
+        Err(e) => assert!(e.contains("differ in arity"), "{e}"),
+    }
+    Ok(())
+}
+
+#[test]
+fn cpu_execution_event_completes_within_timeout() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_execution_event_completes_within_timeout: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let executable = client.compile(MODULE_ADD_ONE, ProgramFormat::Mlir, &[])?;
+    let input = client.buffer_from_scalar(1.0f32, None)?;
+
+    let result = executable.run(&[&input])?;
+    match result
+        .done
+        .await_timeout(Duration::from_secs(30))
+        .map_err(|e| e.to_string())?
+    {
+        WaitOutcome::Ready(status) => status.map_err(|e| e.to_string())?,
+        WaitOutcome::TimedOut => return Err("execution did not finish in 30s".to_string()),
+    }
+    assert_eq!(result.wait()?[0].to_scalar::<f32>()?, 2.0);
+    Ok(())
+}
+
+#[test]
+fn cpu_execute_timed_reports_monotonic_timings() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_execute_timed_reports_monotonic_timings: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let executable = client.compile(MODULE_ADD_ONE, ProgramFormat::Mlir, &[])?;
+
+    let input = client.buffer_from_scalar(2.0f32, None)?;
+    let (outputs, timings) =
+        executable.execute_timed(&[&input], &PJRTExecuteRunOptions::default())?;
+    assert_eq!(outputs[0].to_scalar::<f32>()?, 3.0);
+    assert!(timings.enqueue > Duration::ZERO, "{timings:?}");
+    assert!(timings.total >= timings.enqueue, "{timings:?}");
+    assert!(timings.total >= timings.completion, "{timings:?}");
+    Ok(())
+}
+
+#[test]
+fn cpu_execute_consuming_donates_leading_arguments() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!(
+            "Skipping cpu_execute_consuming_donates_leading_arguments: PJRT plugin not found"
+        );
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let executable = client.compile(MODULE_ACCUMULATE, ProgramFormat::Mlir, &[])?;
+
+    let f32_ty = PJRT_Buffer_Type_PJRT_Buffer_Type_F32;
+    let state = client.buffer_from_host_slice_copy(&[1.0f32, 2.0, 3.0, 4.0], f32_ty, &[4], None)?;
+    let delta = client.buffer_from_host_slice_copy(&[1.0f32; 4], f32_ty, &[4], None)?;
+    let outputs = executable
+        .run_consuming(vec![state], &[&delta], &PJRTExecuteRunOptions::default())?
+        .wait()?;
+    assert_eq!(outputs[0].to_host_vec::<f32>()?, vec![2.0, 3.0, 4.0, 5.0]);
+    assert!(!delta.is_deleted()?);
+
+    // Through the borrowing API the aliased input is donated as well; with validation on,
+    // reusing it must be reported instead of reaching the plugin.
+    let outputs = executable.run(&[&outputs[0], &delta])?.wait()?;
+    assert_eq!(outputs[0].to_host_vec::<f32>()?, vec![3.0, 4.0, 5.0, 6.0]);
+    let stale = client.buffer_from_host_slice_copy(&[0.0f32; 4], f32_ty, &[4], None)?;
+    executable.run(&[&stale, &delta])?.wait()?;
+    assert!(stale.is_deleted()?);
+    let validating = PJRTExecuteRunOptions::default().validate_outputs(true);
+    let err = match executable.run_with_options(&[&stale, &delta], &validating) {
+        Err(e) => e,
+        Ok(_) => return Err("execute accepted a donated buffer".to_string()),
+    };
+    assert!(err.contains("was donated"), "{err}");
+    Ok(())
+}
+
+#[test]
+fn cpu_execute_on_targets_each_device() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_execute_on_targets_each_device: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii_with_options(&[cpu_device_count_option(2)])?;
+    let devices = client.addressable_device_refs()?;
+    let executable = client.compile(MODULE_ADD_ONE, ProgramFormat::Mlir, &[])?;
+
+    for (i, device) in devices.iter().enumerate() {
+        let input = client.buffer_from_scalar(i as f32, Some(device))?;
+        let outputs = executable.run_on(device, &[&input])?.wait()?;
+        assert_eq!(outputs[0].device_id()?, device.id());
+        assert_eq!(outputs[0].to_scalar::<f32>()?, i as f32 + 1.0);
+    }
+
+    let stray = client.buffer_from_scalar(5.0f32, Some(&devices[0]))?;
+    match executable.run_on(&devices[1], &[&stray]) {
+        Err(err) => assert!(err.contains("argument 0 lives on device"), "{err}"),
+        Ok(_) => return Err("execute_on accepted an argument on another device".to_string()),
+    }
+
+    let options = PJRTExecuteRunOptions::new().copy_inputs_to_device(true);
+    let outputs = executable
+        .run_on_with_options(&devices[1], &[&stray], &options)?
+        .wait()?;
+    assert_eq!(outputs[0].device_id()?, devices[1].id());
+    assert_eq!(outputs[0].to_scalar::<f32>()?, 6.0);
+    Ok(())
+}
+
+#[test]
+fn cpu_send_callback_closure_captures_chunks() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_send_callback_closure_captures_chunks: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let executable = client.compile(MODULE_SEND_TO_HOST, ProgramFormat::Mlir, &[])?;
+    let input = client.buffer_from_host_slice_copy(
+        &[1.5f32, -2.0],
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        &[2],
+        None,
+    )?;
+
+    let chunks: Arc<Mutex<Vec<Vec<u8>>>> = Arc::default();
+    let sink = Arc::clone(&chunks);
+    let options = PJRTExecuteRunOptions::new().send_callback(1, move |invocation| {
+        sink.lock().unwrap().push(invocation.chunk.data().to_vec());
+        Ok(())
+    });
+
+    let outputs = executable.run_with_options(&[&input], &options)?.wait()?;
+    assert_eq!(options.take_callback_error(), None);
+    assert_eq!(outputs[0].to_host_vec::<f32>()?, vec![1.5, -2.0]);
+
+    let received: Vec<u8> = chunks.lock().unwrap().concat();
+    let expected: Vec<u8> = [1.5f32, -2.0]
+        .iter()
+        .flat_map(|v| v.to_ne_bytes())
+        .collect();
+    assert_eq!(received, expected);
+    Ok(())
+}
+
+#[test]
+fn cpu_recv_callback_streams_payload_in_granules() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_recv_callback_streams_payload_in_granules: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let executable = client.compile(MODULE_RECV_FROM_HOST, ProgramFormat::Mlir, &[])?;
+
+    let payload: Vec<u8> = [3.0f32, 4.5].iter().flat_map(|v| v.to_ne_bytes()).collect();
+    let progress: Arc<Mutex<Vec<i64>>> = Arc::default();
+    let sink = Arc::clone(&progress);
+    let options = PJRTExecuteRunOptions::new().recv_callback(2, move |invocation| {
+        let stream = &invocation.stream;
+        let total = stream.total_bytes()?;
+        assert_eq!(total, payload.len() as i64);
+        let granule = stream.granule_size()?.max(1) as usize;
+        assert!(stream
+            .add_chunk_bytes(vec![0; payload.len() + granule])
+            .is_err());
+
+        for chunk in payload.chunks(granule) {
+            stream.add_chunk_bytes(chunk.to_vec())?.ok()?;
+            sink.lock().unwrap().push(stream.current_bytes()?);
+        }
+        Ok(())
+    });
+
+    let outputs = executable.run_with_options(&[], &options)?.wait()?;
+    assert_eq!(options.take_callback_error(), None);
+    assert_eq!(outputs[0].to_host_vec::<f32>()?, vec![3.0, 4.5]);
+    assert_eq!(progress.lock().unwrap().last(), Some(&8));
+    Ok(())
+}
+
+#[test]
+fn cpu_host_channels_exchange_typed_values() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_host_channels_exchange_typed_values: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+
+    let send = client.compile(MODULE_SEND_TO_HOST, ProgramFormat::Mlir, &[])?;
+    let input = client.buffer_from_host_slice_copy(
+        &[1.5f32, -2.0],
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        &[2],
+        None,
+    )?;
+    let (options, from_device) = HostChannel::<f32>::new(1).outfeed(PJRTExecuteRunOptions::new());
+    send.run_with_options(&[&input], &options)?.wait()?;
+    assert_eq!(options.take_callback_error(), None);
+    assert_eq!(from_device.try_recv(), Ok(vec![1.5, -2.0]));
+
+    let recv = client.compile(MODULE_RECV_FROM_HOST, ProgramFormat::Mlir, &[])?;
+    let (options, to_device) = HostChannel::<f32>::new(2).infeed(PJRTExecuteRunOptions::new());
+    to_device.send(vec![3.0, 4.5]).map_err(|e| e.to_string())?;
+    let outputs = recv.run_with_options(&[], &options)?.wait()?;
+    assert_eq!(options.take_callback_error(), None);
+    assert_eq!(outputs[0].to_host_vec::<f32>()?, vec![3.0, 4.5]);
+    Ok(())
+}
+
+#[test]
+fn cpu_poisoned_launch_reports_the_injected_error() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_poisoned_launch_reports_the_injected_error: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let recv = client.compile(MODULE_RECV_FROM_HOST, ProgramFormat::Mlir, &[])?;
+
+    // The launch stays in flight until the host sends a value. Plugins that run the
+    // program inline only return after the fallback send below.
+    let (options, to_device) = HostChannel::<f32>::new(2).infeed(PJRTExecuteRunOptions::new());
+    let (poisoned_tx, poisoned_rx) = std::sync::mpsc::channel::<()>();
+    let sender = std::thread::spawn(move || {
+        let _ = poisoned_rx.recv_timeout(Duration::from_millis(500));
+        let _ = to_device.send(vec![3.0, 4.5]);
+    });
+
+    let result = recv.run_with_options(&[], &options)?;
+    assert!(result.handle.launch_id() > 0);
+    assert_eq!(result.handle.launch_id(), result.launch_id);
+    let poisoned = result
+        .handle
+        .poison(PjrtErrorCode::Aborted, "poisoned by test");
+    let _ = poisoned_tx.send(());
+    let status = result.done.status();
+    sender
+        .join()
+        .map_err(|_| "sender thread panicked".to_string())?;
+
+    match poisoned {
+        Ok(true) => {
+            let err = status.expect_err("a poisoned launch must fail");
+            assert_eq!(err.code, PjrtErrorCode::Aborted);
+            assert!(err.message.contains("poisoned by test"), "{err}");
+        }
+        Ok(false) => eprintln!("launch finished before it could be poisoned"),
+        Err(e) => eprintln!("Skipping poison check: {e}"),
+    }
+    Ok(())
+}
+
+#[test]
+fn cpu_execute_accepts_call_location_and_tasks() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_execute_accepts_call_location_and_tasks: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let executable = client.compile(MODULE_ADD_ONE, ProgramFormat::Mlir, &[])?;
+    let input = client.buffer_from_scalar(1.0f32, None)?;
+
+    let options = PJRTExecuteRunOptions::new()
+        .call_location("tests/cpu.rs:1")
+        .tasks(TaskInfo::zip(&[0], &[1])?);
+    let outputs = executable.run_with_options(&[&input], &options)?.wait()?;
+    assert_eq!(outputs[0].to_scalar::<f32>()?, 2.0);
+    Ok(())
+}
+
+#[test]
+fn cpu_output_dimensions_cover_every_output() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_output_dimensions_cover_every_output: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+
+    let two_outputs = client.compile(MODULE_TWO_OUTPUTS, ProgramFormat::Mlir, &[])?;
+    assert_eq!(two_outputs.output_dimensions()?, vec![vec![], vec![]]);
+
+    let matrix = client.compile(MODULE_MATRIX_OUTPUT, ProgramFormat::Mlir, &[])?;
+    assert_eq!(matrix.output_dimensions()?, vec![vec![2, 3]]);
+    #[allow(deprecated)]
+    let first = matrix.output_dimension()?;
+    assert_eq!(first, 2);
+    Ok(())
+}
+
+#[test]
+fn cpu_executable_handle_survives_early_release() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_executable_handle_survives_early_release: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let mut executable = client.compile(MODULE_TWO_OUTPUTS, ProgramFormat::Mlir, &[])?;
+
+    let input = client.buffer_from_scalar(1.0f32, None)?;
+    for _ in 0..3 {
+        let outputs = executable.run(&[&input])?.wait()?;
+        assert_eq!(outputs.len(), 2);
+    }
+    let fingerprint = executable.executable_fingerprint()?;
+    // Metadata queries share one cached PJRT_Executable instead of fetching one each.
+    for _ in 0..100 {
+        assert_eq!(
+            executable.metadata()?.fingerprint.as_ref(),
+            Some(&fingerprint)
+        );
+    }
+
+    // Releasing the cached handle must not break later metadata queries or launches.
+    executable.destroy_executable_handle()?;
+    executable.destroy_executable_handle()?;
+    assert_eq!(executable.executable_fingerprint()?, fingerprint);
+    let outputs = executable.run(&[&input])?.wait()?;
+    assert_eq!(outputs[1].to_scalar::<f32>()?, 2.0);
+    Ok(())
+}
+
+#[test]
+fn cpu_validated_outputs_match_declared_shapes() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_validated_outputs_match_declared_shapes: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let executable = client.compile(MODULE_MATRIX_OUTPUT, ProgramFormat::Mlir, &[])?;
+
+    let input = client.buffer_from_scalar(7.0f32, None)?;
+    let options = PJRTExecuteRunOptions::new().validate_outputs(true);
+    for _ in 0..2 {
+        let outputs = executable.run_with_options(&[&input], &options)?.wait()?;
+        assert_eq!(outputs[0].dimensions()?, vec![2, 3]);
+    }
+    Ok(())
+}
+
+#[test]
+fn cpu_metadata_gathers_static_info() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_metadata_gathers_static_info: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let executable = client.compile(MODULE_TWO_OUTPUTS, ProgramFormat::Mlir, &[])?;
+
+    let metadata = executable.metadata()?;
+    assert!(!metadata.name.unwrap_or_default().is_empty());
+    assert!(!metadata.fingerprint.unwrap_or_default().is_empty());
+    assert_eq!(metadata.num_outputs, Some(2));
+    assert_eq!(
+        metadata.output_element_types,
+        Some(vec![BufferType::F32, BufferType::F32])
+    );
+    Ok(())
+}
+
+#[test]
+fn cpu_identity_matches_for_identical_programs() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_identity_matches_for_identical_programs: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let first = client
+        .compile(MODULE_ADD_ONE, ProgramFormat::Mlir, &[])?
+        .identity()?;
+    let again = client
+        .compile(MODULE_ADD_ONE, ProgramFormat::Mlir, &[])?
+        .identity()?;
+    let other = client
+        .compile(MODULE_TWO_OUTPUTS, ProgramFormat::Mlir, &[])?
+        .identity()?;
+
+    assert_eq!(first, again);
+    assert_ne!(first, other);
+    assert!(!first.to_string().is_empty());
+    let unique: HashSet<_> = [first, again, other].into_iter().collect();
+    assert_eq!(unique.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn cpu_output_shapes_size_host_readback() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_output_shapes_size_host_readback: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let executable = client.compile(MODULE_MATRIX_OUTPUT, ProgramFormat::Mlir, &[])?;
+
+    let shapes = executable.output_shapes()?;
+    assert_eq!(shapes.len(), 1);
+    assert_eq!(shapes[0].element_type, BufferType::F32);
+    assert_eq!(shapes[0].dims, vec![2, 3]);
+    assert_eq!(shapes[0].host_byte_size()?, 24);
+
+    let input = client.buffer_from_scalar(7.0f32, None)?;
+    let outputs = executable.run(&[&input])?.wait()?;
+    let mut host = vec![0u8; shapes[0].host_byte_size()?];
+    outputs[0].copy_raw_to_host_blocking(&mut host, 0)?;
+    assert_eq!(&host[..4], &7.0f32.to_ne_bytes());
+    Ok(())
+}
+
+#[test]
+fn cpu_optimized_program_returns_code() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_optimized_program_returns_code: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let executable = client.compile(MODULE_ADD_ONE, ProgramFormat::Mlir, &[])?;
+
+    let program = executable.optimized_program()?;
+    assert!(!program.format.is_empty());
+    assert!(!program.code.is_empty());
+    // Serialized HLO keeps opcode names as plain strings.
+    assert!(
+        program.code.windows(3).any(|w| w == b"add"),
+        "{}",
+        program.text()
+    );
+    Ok(())
+}
+
+#[test]
+fn cpu_cost_analysis_reports_numeric_properties() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_cost_analysis_reports_numeric_properties: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let executable = client.compile(MODULE_ADD_ONE, ProgramFormat::Mlir, &[])?;
+
+    let properties = executable.get_cost_analysis()?;
+    let numeric: Vec<f64> = properties
+        .iter()
+        .filter_map(|property| match property.value {
+            PJRTNamedValue::Float(v) => Some(v as f64),
+            PJRTNamedValue::Int64(v) => Some(v as f64),
+            _ => None,
+        })
+        .collect();
+    assert!(!numeric.is_empty(), "{properties:?}");
+    assert!(numeric.iter().all(|v| *v >= 0.0), "{properties:?}");
+    if let Some(flops) = executable.flops()? {
+        assert!(flops >= 0.0);
+    }
+    assert!(executable.cost_analysis_summary()?.contains('='));
+    Ok(())
+}
+
+#[test]
+fn cpu_client_deserialize_and_load_without_executable() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!(
+            "Skipping cpu_client_deserialize_and_load_without_executable: PJRT plugin not found"
+        );
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let serialized = {
+        let client = rt.create_client_raii()?;
+        client
+            .compile(MODULE_ADD_ONE, ProgramFormat::Mlir, &[])?
+            .serialize()?
+    };
+
+    // A fresh client with no executables restores purely from the bytes.
+    let client = rt.create_client_raii()?;
+    let restored = client.deserialize_and_load(&serialized, None)?;
+    let input = client.buffer_from_scalar(4.0f32, None)?;
+    let outputs = restored.run(&[&input])?.wait()?;
+    assert_eq!(outputs[0].to_scalar::<f32>()?, 5.0);
+
+    let err = client.deserialize_and_load(&[], None).unwrap_err();
+    assert!(err.contains("must not be empty"), "{err}");
+    Ok(())
+}
+
+#[test]
+fn cpu_executable_file_round_trip() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_executable_file_round_trip: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let executable = client.compile(MODULE_ADD_ONE, ProgramFormat::Mlir, &[])?;
+
+    let dir = std::env::temp_dir().join(format!("rrad_xla_exe_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let path = dir.join("add_one.rradexe");
+    executable.save_to_file(&client, &path)?;
+
+    let loaded = client.load_executable_file(&path)?;
+    let input = client.buffer_from_scalar(41.0f32, None)?;
+    let expected = executable.run(&[&input])?.wait()?;
+    let actual = loaded.run(&[&input])?.wait()?;
+    assert_eq!(
+        actual[0].to_scalar::<f32>()?,
+        expected[0].to_scalar::<f32>()?
+    );
+
+    let bytes = std::fs::read(&path).map_err(|e| e.to_string())?;
+    let (mut header, serialized) = decode_executable_file(&bytes)?;
+    header.platform_name = "not-a-platform".to_string();
+    let foreign = dir.join("foreign.rradexe");
+    std::fs::write(&foreign, encode_executable_file(&header, serialized)?)
+        .map_err(|e| e.to_string())?;
+    assert!(matches!(
+        client.load_executable_file(&foreign),
+        Err(ExecutableFileError::PlatformMismatch { .. })
+    ));
+    assert!(client.load_executable_file_with(&foreign, true).is_ok());
+    assert!(matches!(
+        client.load_executable_file(dir.join("missing.rradexe")),
+        Err(ExecutableFileError::Io(_))
+    ));
+
+    std::fs::remove_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[test]
+fn cpu_executable_cache_skips_second_compile() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_executable_cache_skips_second_compile: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+
+    let dir = std::env::temp_dir().join(format!("rrad_xla_cache_{}", std::process::id()));
+    let cache = ExecutableCache::new(&dir)?;
+    let first = cache.get_or_compile(&client, MODULE_ADD_ONE, ProgramFormat::Mlir, &[])?;
+    assert_eq!((cache.hits(), cache.misses()), (0, 1));
+    let second = cache.get_or_compile(&client, MODULE_ADD_ONE, ProgramFormat::Mlir, &[])?;
+    assert_eq!((cache.hits(), cache.misses()), (1, 1));
+    assert_eq!(
+        first.executable_fingerprint()?,
+        second.executable_fingerprint()?
+    );
+
+    // A corrupt entry falls back to compiling and is rewritten.
+    let entry = cache.entry_path(&client, MODULE_ADD_ONE, ProgramFormat::Mlir, &[])?;
+    std::fs::write(&entry, b"garbage").map_err(|e| e.to_string())?;
+    let input = client.buffer_from_scalar(1.0f32, None)?;
+    let recompiled = cache.get_or_compile(&client, MODULE_ADD_ONE, ProgramFormat::Mlir, &[])?;
+    assert_eq!((cache.hits(), cache.misses()), (1, 2));
+    let outputs = recompiled.run(&[&input])?.wait()?;
+    assert_eq!(outputs[0].to_scalar::<f32>()?, 2.0);
+    cache.get_or_compile(&client, MODULE_ADD_ONE, ProgramFormat::Mlir, &[])?;
+    assert_eq!(cache.hits(), 2);
+
+    std::fs::remove_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[test]
+fn cpu_execute_context_owns_user_values() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_execute_context_owns_user_values: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let mut context = PJRTExecuteContext::create(&rt)?;
+
+    let value = Arc::new(Mutex::new(0u32));
+    match context.add_user_value(1, Arc::clone(&value)) {
+        Err(e) if e.is_code(PjrtErrorCode::Unimplemented) => {
+            eprintln!("Skipping cpu_execute_context_owns_user_values: no FFI extension");
+            return Ok(());
+        }
+        result => result?,
+    }
+    assert_eq!(Arc::strong_count(&value), 2);
+    drop(context);
+    assert_eq!(Arc::strong_count(&value), 1);
+    Ok(())
+}
+
+#[test]
+fn cpu_execute_context_shared_by_overlapping_launches() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!(
+            "Skipping cpu_execute_context_shared_by_overlapping_launches: PJRT plugin not found"
+        );
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let executable = client.compile(MODULE_ADD_ONE, ProgramFormat::Mlir, &[])?;
+    let mut context = PJRTExecuteContext::create(&rt)?;
+
+    // Run options built on two threads share the one context.
+    let (first, second) = std::thread::scope(|s| {
+        let build = || PJRTExecuteRunOptions::new().execute_context(&context);
+        let first = s.spawn(build);
+        let second = s.spawn(build);
+        (first.join().unwrap(), second.join().unwrap())
+    });
+    assert!(first.has_execute_context() && second.has_execute_context());
+    let err = context.add_user_value(1, 0u32).unwrap_err();
+    assert!(err.is_code(PjrtErrorCode::FailedPrecondition));
+    assert!(err.message().contains("shared"), "{err}");
+
+    // Both launches are in flight before either is waited on, and the context is released
+    // by its owner while they run.
+    let input = client.buffer_from_scalar(1.0f32, None)?;
+    let a = executable.run_with_options(&[&input], &first)?;
+    let b = executable.run_with_options(&[&input], &second)?;
+    context.destroy()?;
+    assert!(!context.is_alive());
+    drop((first, second));
+    for result in [a, b] {
+        assert_eq!(result.wait()?[0].to_scalar::<f32>()?, 2.0);
+    }
+
+    let err = context.add_user_value(1, 0u32).unwrap_err();
+    assert!(err.is_code(PjrtErrorCode::FailedPrecondition));
+    assert!(err.message().contains("destroyed"), "{err}");
+    let stale = PJRTExecuteRunOptions::new().execute_context(&context);
+    assert!(executable.run_with_options(&[&input], &stale).is_err());
+
+    // After into_raw the caller destroys the context itself.
+    let raw = PJRTExecuteContext::create(&rt)?.into_raw();
+    let destroy = rt
+        .api()
+        .PJRT_ExecuteContext_Destroy
+        .ok_or("no destroy symbol")?;
+    let mut args = PJRT_ExecuteContext_Destroy_Args {
+        struct_size: PJRT_ExecuteContext_Destroy_Args_STRUCT_SIZE as usize,
+        extension_start: std::ptr::null_mut(),
+        context: raw,
+    };
+    assert!(unsafe { destroy(&mut args) }.is_null());
+    Ok(())
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn cpu_execute_async_joins_concurrent_launches() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_execute_async_joins_concurrent_launches: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let executable = client.compile(MODULE_ADD_ONE, ProgramFormat::Mlir, &[])?;
+
+    let inputs = [
+        client.buffer_from_scalar(1.0f32, None)?,
+        client.buffer_from_scalar(2.0f32, None)?,
+        client.buffer_from_scalar(3.0f32, None)?,
+    ];
+    let options = PJRTExecuteRunOptions::default();
+    let (a, b, c) = tokio::join!(
+        executable.execute_async(&[&inputs[0]], &options),
+        executable.execute_async(&[&inputs[1]], &options),
+        executable.execute_async(&[&inputs[2]], &options),
+    );
+    assert_eq!(a?.outputs[0].to_scalar::<f32>()?, 2.0);
+    assert_eq!(b?.outputs[0].to_scalar::<f32>()?, 3.0);
+    assert_eq!(c?.outputs[0].to_scalar::<f32>()?, 4.0);
+
+    // A launch whose future is dropped still runs to completion without leaking.
+    drop(executable.execute_async(&[&inputs[0]], &options));
+    let result = executable.execute_async(&[&inputs[0]], &options).await?;
+    assert_eq!(result.wait()?[0].to_scalar::<f32>()?, 2.0);
+    Ok(())
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn cpu_events_can_be_awaited_directly() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_events_can_be_awaited_directly: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let executable = client.compile(MODULE_ADD_ONE, ProgramFormat::Mlir, &[])?;
+    let input = client.buffer_from_scalar(1.0f32, None)?;
+
+    let result = executable.run(&[&input])?;
+    let (outputs, done) = result.into_parts();
+    let ready = outputs[0].ready_event()?;
+    let (done, ready) = tokio::join!(done.into_future(), ready.into_future());
+    done.map_err(|e| e.to_string())?;
+    ready.map_err(|e| e.to_string())?;
+    assert_eq!(outputs[0].to_scalar::<f32>()?, 2.0);
+
+    // Dropping the future before it resolves leaves the event with its registration.
+    let again = executable.run(&[&input])?;
+    let (outputs, done) = again.into_parts();
+    drop(done.into_future());
+    outputs[0].ready_event()?.await.map_err(|e| e.to_string())?;
+    assert_eq!(outputs[0].to_scalar::<f32>()?, 2.0);
+    Ok(())
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn cpu_awaited_events_can_be_spawned() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_awaited_events_can_be_spawned: PJRT plugin not found");
+        return Ok(());
+    };
+
+    // tokio::spawn needs a 'static future, so the runtime has to outlive the test.
+    let rt: &'static PjrtRuntime = Box::leak(Box::new(PjrtRuntime::load(&plugin_path)?));
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let executable = client.compile(MODULE_ADD_ONE, ProgramFormat::Mlir, &[])?;
+    let input = client.buffer_from_scalar(1.0f32, None)?;
+
+    let (outputs, done) = executable.run(&[&input])?.into_parts();
+    tokio::spawn(done.into_future())
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+    assert_eq!(outputs[0].to_scalar::<f32>()?, 2.0);
+    Ok(())
+}
+
+#[test]
+fn cpu_device_memories_report_their_kinds() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_device_memories_report_their_kinds: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    for device in client.addressable_device_refs()? {
+        let memories = device.memories_with_kinds()?;
+        assert!(!memories.is_empty(), "device has no addressable memories");
+        for (memory, kind) in &memories {
+            assert!(!kind.is_empty());
+            assert_eq!(&memory.kind()?, kind);
+        }
+
+        let default = device.default_memory()?;
+        assert_eq!(default.raw, device.default_memory_raw()?);
+        let default_kind = device.memory_kind_of_default()?;
+        assert!(
+            memories
+                .iter()
+                .any(|(memory, kind)| memory.raw == default.raw && *kind == default_kind),
+            "default memory {default_kind:?} is not among the addressable memories"
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn cpu_client_memories_describe_themselves() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_client_memories_describe_themselves: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let memories = client.addressable_memory_refs()?;
+    assert!(!memories.is_empty(), "client has no addressable memories");
+    for memory in &memories {
+        let kind = memory.kind()?;
+        assert!(
+            !kind.is_empty(),
+            "memory {} has an empty kind",
+            memory.id()?
+        );
+        memory.kind_id()?;
+        memory.debug_string()?;
+        memory.to_string()?;
+
+        let devices = memory.addressable_by_devices()?;
+        assert!(
+            !devices.is_empty(),
+            "{kind} memory is not addressable by any device"
+        );
+        for device in &devices {
+            assert!(device
+                .addressable_memory_refs()?
+                .iter()
+                .any(|m| m.raw == memory.raw));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn cpu_memory_formats_include_its_kind() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_memory_formats_include_its_kind: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let device = client
+        .addressable_device_refs()?
+        .into_iter()
+        .next()
+        .ok_or("client has no devices")?;
+    let memory = device.default_memory()?;
+    let kind = memory.kind()?;
+
+    let debug = format!("{memory:?}");
+    assert!(debug.contains(&format!("{kind:?}")), "{debug}");
+    assert!(
+        debug.contains(&format!("kind_id: {}", memory.kind_id()?)),
+        "{debug}"
+    );
+    let display = format!("{memory}");
+    assert!(!display.is_empty());
+
+    let info = memory.info();
+    assert_eq!(info.kind.as_deref(), Some(kind.as_str()));
+    assert_eq!(info.id, Some(memory.id()?));
+    let device_ids = info.addressable_by_device_ids.unwrap_or_default();
+    assert!(device_ids.contains(&device.id()), "{device_ids:?}");
+    Ok(())
+}
+
+#[test]
+fn cpu_buffer_reports_its_resident_device_and_memory() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!(
+            "Skipping cpu_buffer_reports_its_resident_device_and_memory: PJRT plugin not found"
+        );
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii_with_options(&[cpu_device_count_option(2)])?;
+    let target = client.addressable_device_refs()?.remove(1);
+    let buffer = client.buffer_from_host_slice_copy(
+        &[1.0f32, 2.0],
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        &[2],
+        Some(target.raw()),
+    )?;
+
+    let (device, memory) = buffer.resident_device_and_memory()?;
+    assert_eq!(device, target);
+    assert_eq!(memory.raw, buffer.memory()?);
+    assert!(memory.devices()?.contains(&device));
+    Ok(())
+}
+
+#[test]
+fn cpu_memory_watch_tracks_buffer_lifetimes() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_memory_watch_tracks_buffer_lifetimes: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let devices = client.addressable_device_refs()?;
+    let mut watch = match devices[0].memory_watch() {
+        Ok(watch) => watch,
+        Err(e) => {
+            eprintln!("Skipping cpu_memory_watch_tracks_buffer_lifetimes: no memory stats ({e})");
+            return Ok(());
+        }
+    };
+
+    let data = vec![0.5f32; 1 << 20];
+    let buffers = (0..3)
+        .map(|_| {
+            client.buffer_from_host_slice_copy(
+                &data,
+                PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+                &[data.len() as i64],
+                Some(devices[0].raw()),
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let grown = watch.report()?;
+    if grown.delta.bytes_in_use == 0 {
+        eprintln!("Skipping cpu_memory_watch_tracks_buffer_lifetimes: bytes_in_use not tracked");
+        return Ok(());
+    }
+    assert!(
+        grown.delta.bytes_in_use >= 3 * 4 * data.len() as i64,
+        "{grown:?}"
+    );
+
+    drop(buffers);
+    let shrunk = watch.report()?;
+    assert!(
+        shrunk.delta.bytes_in_use < grown.delta.bytes_in_use,
+        "{shrunk:?}"
+    );
+    assert_eq!(shrunk.peak_bytes_in_use, grown.peak_bytes_in_use);
+    assert!(shrunk.peak_bytes_in_use >= watch.baseline().bytes_in_use + grown.delta.bytes_in_use);
+    Ok(())
+}
+
+#[test]
+fn cpu_devices_compare_by_global_id() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_devices_compare_by_global_id: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii_with_options(&[cpu_device_count_option(2)])?;
+    let first = client.addressable_device_refs()?;
+    let second = client.addressable_device_refs()?;
+    assert_eq!(first.len(), 2);
+
+    let hash = |device: &PJRTDevice<'_>| {
+        let mut hasher = DefaultHasher::new();
+        device.hash(&mut hasher);
+        hasher.finish()
+    };
+    assert_eq!(first[0], second[0]);
+    assert_eq!(hash(&first[0]), hash(&second[0]));
+    assert!(first[0].same_device(&second[0]));
+
+    assert_ne!(first[0], first[1]);
+    assert!(!first[0].same_device(&first[1]));
+    let unique: HashSet<_> = first.iter().chain(second.iter()).collect();
+    assert_eq!(unique.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn cpu_single_host_devices_form_one_process_group() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_single_host_devices_form_one_process_group: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii_with_options(&[cpu_device_count_option(2)])?;
+    assert_eq!(client.device_count()?, 2);
+    assert_eq!(client.addressable_device_count()?, 2);
+
+    let groups = client.devices_by_process()?;
+    assert_eq!(groups.len(), 1);
+    let (process_index, devices) = groups.into_iter().next().unwrap();
+    assert_eq!(process_index, client.process_index()?);
+    assert_eq!(devices, client.device_refs()?);
+
+    assert_eq!(client.local_devices()?, client.addressable_device_refs()?);
+    Ok(())
+}
+
+#[test]
+fn cpu_default_topology_from_empty_options() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_default_topology_from_empty_options: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let options = TopologyOptions::new();
+    let topology = match PJRTTopologyDescription::create_with_options(&rt, None, &options) {
+        Ok(topology) => topology,
+        Err(e) => {
+            eprintln!("Skipping cpu_default_topology_from_empty_options: {e}");
+            return Ok(());
+        }
+    };
+    assert!(!topology.platform_name()?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn cpu_topology_file_round_trip() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_topology_file_round_trip: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let topology = client.topology_description()?;
+
+    let dir = std::env::temp_dir().join(format!("rrad_xla_topo_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let path = dir.join("cpu.rradtopo");
+    if let Err(e) = topology.save(&path) {
+        eprintln!("Skipping cpu_topology_file_round_trip: {e}");
+        std::fs::remove_dir_all(&dir).map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+
+    let loaded = PJRTTopologyDescription::load(&rt, &path, false)?;
+    assert_eq!(loaded.platform_name()?, topology.platform_name()?);
+
+    let bytes = std::fs::read(&path).map_err(|e| e.to_string())?;
+    let (mut header, serialized) = decode_topology_file(&bytes)?;
+    header.platform_name = "not-a-platform".to_string();
+    let foreign = dir.join("foreign.rradtopo");
+    std::fs::write(&foreign, encode_topology_file(&header, serialized)?)
+        .map_err(|e| e.to_string())?;
+    assert!(matches!(
+        PJRTTopologyDescription::load(&rt, &foreign, false),
+        Err(TopologyFileError::PlatformMismatch { .. })
+    ));
+    assert!(PJRTTopologyDescription::load(&rt, &foreign, true).is_ok());
+
+    let missing = dir.join("missing.rradtopo");
+    match PJRTTopologyDescription::load(&rt, &missing, false) {
+        Err(e @ TopologyFileError::Io { .. }) => {
+            assert!(e.to_string().contains("missing.rradtopo"), "{e}")
+        }
+        Err(e) => return Err(format!("expected an I/O error, got {e}")),
+        Ok(_) => return Err("loading a missing file succeeded".to_string()),
+    }
+
+    std::fs::remove_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[test]
+fn cpu_topology_compile_and_aot_paths_agree() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_topology_compile_and_aot_paths_agree: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let topology = client.topology_description()?;
+    let input = client.buffer_from_scalar(1.0f32, None)?;
+
+    let direct = topology.compile_and_load(&client, MODULE_ADD_ONE, ProgramFormat::Mlir, &[])?;
+    let outputs = direct.run(&[&input])?.wait()?;
+    assert_eq!(outputs[0].to_scalar::<f32>()?, 2.0);
+
+    match topology.compile_aot_and_load(&client, MODULE_ADD_ONE, ProgramFormat::Mlir, &[]) {
+        Ok(aot) => {
+            let outputs = aot.run(&[&input])?.wait()?;
+            assert_eq!(outputs[0].to_scalar::<f32>()?, 2.0);
+        }
+        Err(e) => eprintln!("Skipping the AOT comparison: {e}"),
+    }
+    Ok(())
+}
+
+#[test]
+fn cpu_topology_and_plugin_attribute_views() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_topology_and_plugin_attribute_views: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let plugin = rt.plugin_attributes_typed()?;
+    assert_eq!(plugin.as_slice().len(), rt.plugin_attributes()?.len());
+
+    let client = rt.create_client_raii()?;
+    let topology = client.topology_description()?;
+    let attributes = topology.attributes_typed()?;
+    for attribute in attributes.as_slice() {
+        assert_eq!(attributes.value(&attribute.name), Some(&attribute.value));
+    }
+    Ok(())
+}
+
+#[test]
+fn cpu_topology_debug_summarizes_the_platform() -> Result<(), String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping cpu_topology_debug_summarizes_the_platform: PJRT plugin not found");
+        return Ok(());
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    let client = rt.create_client_raii()?;
+    let topology = client.topology_description()?;
+    let platform = topology.platform_name()?;
+
+    let debug = format!("{topology:?}");
+    assert!(debug.starts_with("PJRTTopologyDescription {"), "{debug}");
+    assert!(debug.contains(&format!("{platform:?}")), "{debug}");
+
+    let summary = topology.summary();
+    assert_eq!(summary.platform_name, Ok(platform));
+    assert_eq!(summary.device_count, Ok(client.device_count()?));
+    let kinds = summary.device_kinds?;
+    assert!(!kinds.is_empty() && kinds.len() <= 4, "{kinds:?}");
+    Ok(())
+}
@@ -43,8 +43,8 @@ fn cpu_runtime_smoke() -> Result<(), String> {
         "expected at least one addressable device"
     );
 
-    let first = PJRTDevice::new(&rt, raw_devices[0]);
-    let first_id = first.id()?;
+    let first = PJRTDevice::new(&rt, raw_devices[0])?;
+    let first_id = first.id();
     let first_kind = first.kind()?;
     assert!(first_id >= 0, "expected non-negative device id");
     assert!(!first_kind.is_empty(), "expected non-empty device kind");
@@ -73,7 +73,7 @@ fn cpu_runtime_smoke() -> Result<(), String> {
     let dims = buffer.dimensions()?;
     assert_eq!(dims, vec![host.len() as i64]);
     assert_eq!(
-        buffer.element_type()?,
+        buffer.element_type_raw()?,
         PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
         "expected f32 element type"
     );
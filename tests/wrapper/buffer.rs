@@ -0,0 +1,530 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use rrad_xla::pjrt::dlpack::DLDeviceType;
+use rrad_xla::pjrt::error::{PJRTError, PjrtErrorCode};
+use rrad_xla::pjrt::event::PJRTEvent;
+use rrad_xla::pjrt::layout::MemoryLayout;
+use rrad_xla::pjrt::loader::PjrtRuntime;
+use rrad_xla::pjrt::utils::MajorOrder;
+use rrad_xla::pjrt_sys::PJRT_Buffer_Type_PJRT_Buffer_Type_F32;
+
+fn resolve_plugin_path() -> Option<PathBuf> {
+    if let Ok(path) = std::env::var("PJRT_PLUGIN") {
+        let p = PathBuf::from(path);
+        if p.is_file() {
+            return Some(p);
+        }
+    }
+
+    let candidates = [
+        "xla/bazel-bin/xla/pjrt/c/pjrt_c_api_cpu_plugin.so",
+        "xla/bazel-bin/xla/pjrt/c/pjrt_c_api_cpu_plugin.dylib",
+        "xla/bazel-bin/xla/pjrt/c/pjrt_c_api_cpu_plugin",
+    ];
+    for candidate in candidates {
+        let p = Path::new(candidate).to_path_buf();
+        if p.is_file() {
+            return Some(p);
+        }
+    }
+
+    None
+}
+
+fn runtime_or_skip() -> Result<Option<PjrtRuntime>, String> {
+    let Some(plugin_path) = resolve_plugin_path() else {
+        eprintln!("Skipping wrapper::buffer tests: PJRT plugin not found");
+        return Ok(None);
+    };
+
+    let rt = PjrtRuntime::load(&plugin_path)?;
+    rt.initialize_plugin()?;
+    Ok(Some(rt))
+}
+
+#[test]
+fn buffer_copy_raw_to_host_with_closure() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let client = rt.create_client_raii()?;
+    let host = [1.0f32, 2.0, 3.0, 4.0];
+    let buffer = client.buffer_from_host_nd(
+        &[4],
+        &host,
+        MajorOrder::RowMajor,
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        None,
+    )?;
+
+    let (tx, rx) = mpsc::channel();
+    let event = buffer.copy_raw_to_host_with(4, 8, move |result| {
+        let checked = result.map(|alloc| {
+            let values: Vec<f32> = alloc
+                .bytes()
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect();
+            (alloc.offset(), values)
+        });
+        let _ = tx.send(checked);
+    })?;
+    // The closure is owned by the event registration, not by this handle.
+    drop(event);
+
+    let (offset, values) = rx
+        .recv_timeout(Duration::from_secs(10))
+        .map_err(|e| format!("copy callback never ran: {e}"))??;
+    assert_eq!(offset, 4);
+    assert_eq!(values, vec![2.0, 3.0]);
+    Ok(())
+}
+
+#[test]
+fn buffer_copy_raw_to_host_future_dropped_destination_cancels() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let client = rt.create_client_raii()?;
+    let buffer = client.buffer_from_scalar(1.0f32, None)?;
+    let (event, destination) = buffer.copy_raw_to_host_future(0, 4)?;
+    drop(destination);
+    assert!(
+        event.ok().is_err(),
+        "copy should fail once its destination is dropped"
+    );
+    Ok(())
+}
+
+#[test]
+fn buffer_donate_with_control_dependency_returns_immediately() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let client = rt.create_client_raii()?;
+    let host = [1.0f32, 2.0, 3.0, 4.0];
+    let buffer = client.buffer_from_host_nd(
+        &[4],
+        &host,
+        MajorOrder::RowMajor,
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        None,
+    )?;
+    buffer.ready_event()?.await_ready()?;
+    let dependency = PJRTEvent::create(&rt)?;
+
+    // The donation returns while the dependency is still pending, and only completes once
+    // the event is set.
+    let (donated, handle) = buffer.donate_with_control_dependency(&dependency)?;
+    assert!(
+        handle.try_wait().is_none(),
+        "donation completed before its dependency"
+    );
+
+    dependency.set(&PJRTError::with_code(&rt, PjrtErrorCode::Ok, ""))?;
+    handle.wait()?;
+    assert_eq!(donated.to_host_vec::<f32>()?, host.to_vec());
+    Ok(())
+}
+
+#[test]
+fn buffer_external_reference_guards_nest() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let client = rt.create_client_raii()?;
+    let host = [1.0f32, 2.0, 3.0, 4.0];
+    let buffer = client.buffer_from_host_nd(
+        &[4],
+        &host,
+        MajorOrder::RowMajor,
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        None,
+    )?;
+
+    {
+        let outer = buffer.hold_external_reference()?;
+        let inner = buffer.hold_external_reference()?;
+        assert_eq!(
+            outer.opaque_device_memory_data_pointer()?,
+            inner.opaque_device_memory_data_pointer()?
+        );
+        inner.release()?;
+        assert_eq!(outer.buffer().to_host_vec::<f32>()?, host.to_vec());
+    }
+
+    // Both references were returned exactly once, so there is nothing left to decrease.
+    assert!(buffer.decrease_external_ref().is_err());
+    assert_eq!(buffer.to_host_vec::<f32>()?, host.to_vec());
+    Ok(())
+}
+
+#[test]
+fn buffer_copy_to_memory_kind_round_trips_through_host() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let client = rt.create_client_raii()?;
+    let host = [1.0f32, 2.0, 3.0, 4.0];
+    let buffer = client.buffer_from_host_nd(
+        &[4],
+        &host,
+        MajorOrder::RowMajor,
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        None,
+    )?;
+
+    let err = match buffer.copy_to_memory_kind("no_such_memory_kind") {
+        Ok(_) => return Err("unknown memory kind should be rejected".to_string()),
+        Err(e) => e,
+    };
+    assert!(err.contains("available kinds"), "unexpected error: {err}");
+
+    let copied = match buffer.copy_to_memory_kind("pinned_host") {
+        Ok(copied) => copied,
+        Err(e) if e.contains("available kinds") => {
+            eprintln!("Skipping host memory copy: {e}");
+            return Ok(());
+        }
+        Err(e) => return Err(e),
+    };
+    assert_eq!(copied.to_host_vec::<f32>()?, host.to_vec());
+    Ok(())
+}
+
+#[test]
+fn buffer_dlpack_round_trip() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let client = rt.create_client_raii()?;
+    let host = [1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0];
+    let buffer = client.buffer_from_host_nd(
+        &[2, 3],
+        &host,
+        MajorOrder::RowMajor,
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        None,
+    )?;
+    buffer.ready_event()?.ok()?;
+
+    let tensor = buffer.to_dlpack()?;
+    {
+        let dl = unsafe { &(*tensor).dl_tensor };
+        assert_eq!(dl.device.device_type, DLDeviceType::CPU);
+        assert_eq!(dl.ndim, 2);
+        assert_eq!(dl.dtype.bits, 32);
+    }
+
+    let imported = match unsafe { client.buffer_from_dlpack(tensor) } {
+        Ok(imported) => imported,
+        Err(e) => {
+            if let Some(deleter) = unsafe { (*tensor).deleter } {
+                unsafe { deleter(tensor) };
+            }
+            return Err(e);
+        }
+    };
+    assert_eq!(imported.dimensions()?, vec![2, 3]);
+    assert_eq!(imported.to_host_vec::<f32>()?, host.to_vec());
+
+    // Dropping the view runs the DLPack deleter, which returns the external reference and
+    // destroys the exported buffer.
+    drop(imported);
+    Ok(())
+}
+
+#[test]
+fn buffer_duplicate_is_independent_of_source() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let client = rt.create_client_raii()?;
+    let host = [1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0];
+    let buffer = client.buffer_from_host_nd(
+        &[3, 2],
+        &host,
+        MajorOrder::RowMajor,
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        None,
+    )?;
+
+    let copy = buffer.duplicate()?;
+    assert_ne!(copy.raw(), buffer.raw());
+    assert_eq!(copy.dimensions()?, vec![3, 2]);
+
+    buffer.delete()?;
+    assert!(buffer.is_deleted()?);
+    assert!(!copy.is_deleted()?);
+    assert_eq!(copy.to_host_vec::<f32>()?, host.to_vec());
+    Ok(())
+}
+
+#[test]
+fn buffer_to_host_into_reuses_destination() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let client = rt.create_client_raii()?;
+    let host: Vec<f32> = (0..64).map(|i| i as f32 * 0.5).collect();
+    let buffer = client.buffer_from_host_nd(
+        &[8, 8],
+        &host,
+        MajorOrder::RowMajor,
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        None,
+    )?;
+
+    let mut dst = vec![0.0f32; host.len()];
+    let data_ptr = dst.as_ptr();
+    for _ in 0..100 {
+        dst.fill(-1.0);
+        buffer.to_host_into(&mut dst)?;
+        assert_eq!(dst, host);
+    }
+    assert_eq!(dst.as_ptr(), data_ptr);
+
+    let mut short = vec![0.0f32; 3];
+    let err = buffer.to_host_into(&mut short).unwrap_err();
+    assert_eq!(err.code(), PjrtErrorCode::InvalidArgument);
+    let mut wrong_type = vec![0i32; host.len()];
+    assert!(buffer.to_host_into(&mut wrong_type).is_err());
+    Ok(())
+}
+
+#[test]
+fn buffer_memory_layout_round_trips_through_plugin() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let client = rt.create_client_raii()?;
+    let host = [1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0];
+    let buffer = client.buffer_from_host_nd(
+        &[2, 3],
+        &host,
+        MajorOrder::RowMajor,
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        None,
+    )?;
+
+    let layout = buffer.get_memory_layout()?;
+    assert_eq!(layout, MemoryLayout::row_major(2));
+
+    // Feed the decoded layout back in as the host layout of a readback.
+    let mut bytes = vec![0u8; host.len() * 4];
+    buffer.to_host_buffer_blocking_with_layout(&mut bytes, &layout)?;
+    let values: Vec<f32> = bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect();
+    assert_eq!(values, host.to_vec());
+    Ok(())
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn buffer_to_host_future_reads_concurrently() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let client = rt.create_client_raii()?;
+    let hosts: Vec<Vec<f32>> = (0..3)
+        .map(|k| (0..16).map(|i| (k * 100 + i) as f32).collect())
+        .collect();
+    let mut buffers = Vec::new();
+    for host in &hosts {
+        buffers.push(client.buffer_from_host_nd(
+            &[4, 4],
+            host,
+            MajorOrder::RowMajor,
+            PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+            None,
+        )?);
+    }
+
+    let (a, b, c) = tokio::join!(
+        buffers[0].to_host_future::<f32>(),
+        buffers[1].to_host_future::<f32>(),
+        buffers[2].to_host_future::<f32>(),
+    );
+    assert_eq!(a?, hosts[0]);
+    assert_eq!(b?, hosts[1]);
+    assert_eq!(c?, hosts[2]);
+
+    // Dropping a pending readback must not free memory the plugin is still writing to.
+    drop(buffers[0].to_host_future::<f32>());
+    assert_eq!(buffers[0].to_host_future::<f32>().await?, hosts[0]);
+    Ok(())
+}
+
+#[cfg(feature = "npy")]
+#[test]
+fn buffer_npy_round_trips_and_reads_fortran_order() -> Result<(), String> {
+    use rrad_xla::pjrt::npy::{encode_npy_header, NpyHeader};
+    use rrad_xla::pjrt::utils::BufferType;
+
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let client = rt.create_client_raii()?;
+    let dir = std::env::temp_dir().join(format!("rrad_xla_npy_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    let fixture = client.buffer_from_npy("tests/fixtures/arange_i4_2x3.npy", None)?;
+    assert_eq!(fixture.dimensions()?, vec![2, 3]);
+    assert_eq!(fixture.to_host_vec::<i32>()?, vec![0, 1, 2, 3, 4, 5]);
+
+    let path = dir.join("round_trip.npy");
+    fixture.write_npy(&path)?;
+    let reloaded = client.buffer_from_npy(&path, None)?;
+    assert_eq!(reloaded.to_host_vec::<i32>()?, vec![0, 1, 2, 3, 4, 5]);
+
+    // Column-major file holding [[1, 2, 3], [4, 5, 6]].
+    let mut fortran = encode_npy_header(&NpyHeader {
+        element_type: BufferType::F32,
+        dims: vec![2, 3],
+        fortran_order: true,
+    })?;
+    for v in [1.0f32, 4.0, 2.0, 5.0, 3.0, 6.0] {
+        fortran.extend_from_slice(&v.to_le_bytes());
+    }
+    let fortran_path = dir.join("fortran.npy");
+    std::fs::write(&fortran_path, fortran).map_err(|e| e.to_string())?;
+    let from_fortran = client.buffer_from_npy(&fortran_path, None)?;
+    assert_eq!(
+        from_fortran.to_host_vec::<f32>()?,
+        vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]
+    );
+
+    let dict = "{'descr': '<V2', 'fortran_order': False, 'shape': (1,), }\n";
+    let mut void = b"\x93NUMPY\x01\x00".to_vec();
+    void.extend_from_slice(&(dict.len() as u16).to_le_bytes());
+    void.extend_from_slice(dict.as_bytes());
+    void.extend_from_slice(&[0, 0]);
+    let void_path = dir.join("void.npy");
+    std::fs::write(&void_path, void).map_err(|e| e.to_string())?;
+    let err = client.buffer_from_npy(&void_path, None).unwrap_err();
+    assert!(err.contains("no PJRT element type"), "{err}");
+
+    let _ = std::fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn buffer_host_view_aliases_device_memory() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let client = rt.create_client_raii()?;
+    let host: Vec<f32> = (0..12).map(|i| i as f32).collect();
+    let buffer = client.buffer_from_host_nd(
+        &[3, 4],
+        &host,
+        MajorOrder::RowMajor,
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        None,
+    )?;
+    if !buffer.is_on_cpu()? {
+        return Ok(());
+    }
+
+    let Some(view) = buffer.host_view::<f32>()? else {
+        eprintln!("Skipping host view check: plugin does not expose a dense host pointer");
+        return Ok(());
+    };
+    assert_eq!(&view[..], buffer.to_host_vec::<f32>()?.as_slice());
+    let opaque = buffer.opaque_device_memory_data_pointer()?;
+    assert_eq!(opaque, Some(view.as_ptr().cast_mut().cast()));
+    drop(view);
+
+    assert!(buffer.host_view::<i32>().is_err());
+    Ok(())
+}
+
+#[test]
+fn buffer_copy_raw_to_host_chunked_reports_progress() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let client = rt.create_client_raii()?;
+    // 3 MiB plus a partial trailing chunk.
+    let host: Vec<u8> = (0..3 * 1024 * 1024 + 1000)
+        .map(|i| (i % 251) as u8)
+        .collect();
+    let buffer = client.buffer_from_host_nd(
+        &[host.len() as i64],
+        &host,
+        MajorOrder::RowMajor,
+        rrad_xla::pjrt_sys::PJRT_Buffer_Type_PJRT_Buffer_Type_U8,
+        None,
+    )?;
+
+    let mut dst = vec![0u8; host.len()];
+    let mut reports = Vec::new();
+    buffer.copy_raw_to_host_chunked(&mut dst, 64 * 1024, |done, total| {
+        reports.push((done, total))
+    })?;
+    assert_eq!(dst, host);
+    assert_eq!(reports.len(), host.len().div_ceil(64 * 1024));
+    assert_eq!(
+        reports.last(),
+        Some(&(host.len() as u64, host.len() as u64))
+    );
+    assert!(reports.windows(2).all(|w| w[0].0 < w[1].0));
+
+    // A chunk larger than the buffer is a single transfer.
+    let mut whole = vec![0u8; host.len()];
+    let mut calls = 0;
+    buffer.copy_raw_to_host_chunked(&mut whole, usize::MAX, |_, _| calls += 1)?;
+    assert_eq!((calls, whole == host), (1, true));
+
+    // Reading past the end fails at the first out-of-range chunk.
+    let mut too_big = vec![0u8; host.len() + 64 * 1024];
+    let err = buffer
+        .copy_raw_to_host_chunked(&mut too_big, 1024 * 1024, |_, _| {})
+        .unwrap_err();
+    assert!(err.contains("failed at offset 3145728"), "{err}");
+    assert!(buffer
+        .copy_raw_to_host_chunked(&mut whole, 0, |_, _| {})
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn buffer_logical_shape_of_static_buffer() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let client = rt.create_client_raii()?;
+    let buffer = client.buffer_from_host_nd(
+        &[2, 3],
+        &[0.0f32; 6],
+        MajorOrder::RowMajor,
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        None,
+    )?;
+
+    let shape = buffer.logical_shape()?;
+    assert_eq!(shape.dims, vec![2, 3]);
+    assert_eq!(shape.unpadded_dims, vec![2, 3]);
+    assert_eq!(shape.is_dynamic, vec![false, false]);
+    assert!(!shape.has_dynamic_dims());
+    assert_eq!(shape.logical_element_count()?, 6);
+    Ok(())
+}
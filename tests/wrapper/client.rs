@@ -1,7 +1,16 @@
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
+use rrad_xla::pjrt::buffer::PJRTBuffer;
+use rrad_xla::pjrt::client::{HostArray, HostBufferSemantics};
 use rrad_xla::pjrt::device::PJRTDevice;
+use rrad_xla::pjrt::error::PjrtErrorCode;
+use rrad_xla::pjrt::layout::MemoryLayout;
 use rrad_xla::pjrt::loader::PjrtRuntime;
+use rrad_xla::pjrt::platform::Platform;
+use rrad_xla::pjrt::utils::MajorOrder;
+use rrad_xla::pjrt_sys::PJRT_Buffer_Type_PJRT_Buffer_Type_F32;
 
 fn resolve_plugin_path() -> Option<PathBuf> {
     if let Ok(path) = std::env::var("PJRT_PLUGIN") {
@@ -48,12 +57,22 @@ fn client_basic_metadata_smoke() -> Result<(), String> {
     let platform_version = client.platform_version()?;
     let process_index = client.process_index()?;
 
-    assert!(!platform_name.is_empty(), "platform_name should not be empty");
+    assert!(
+        !platform_name.is_empty(),
+        "platform_name should not be empty"
+    );
     assert!(
         !platform_version.is_empty(),
         "platform_version should not be empty"
     );
     assert!(process_index >= 0, "process_index should be non-negative");
+
+    let platform = client.platform()?;
+    assert_eq!(
+        platform,
+        Platform::classify(&platform_name, &platform_version)
+    );
+    assert_eq!(client.is_cpu()?, platform.is_cpu());
     Ok(())
 }
 
@@ -67,15 +86,38 @@ fn client_lookup_first_device_smoke() -> Result<(), String> {
     let raw_devices = client.devices()?;
     assert!(!raw_devices.is_empty(), "expected at least one device");
 
-    let first_device_ref = PJRTDevice::new(&rt, raw_devices[0]);
-    let first_id = first_device_ref.id()?;
+    let first_device_ref = PJRTDevice::new(&rt, raw_devices[0])?;
+    let first_id = first_device_ref.id();
     let local_hardware_id = first_device_ref.local_hardware_id()?;
 
     let by_id = client.lookup_device(first_id)?;
     assert!(!by_id.is_null(), "lookup_device returned null");
 
     let by_local = client.lookup_addressable_device(local_hardware_id)?;
-    assert!(!by_local.is_null(), "lookup_addressable_device returned null");
+    assert!(
+        !by_local.is_null(),
+        "lookup_addressable_device returned null"
+    );
+    Ok(())
+}
+
+#[test]
+fn client_consecutive_device_lookups() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let client = rt.create_client_raii()?;
+    let first_id = PJRTDevice::new(&rt, client.devices()?[0])?.id();
+
+    // Both devices stay usable while the client is borrowed again in between.
+    let a = client.lookup_device_ref(first_id)?;
+    let b = client.lookup_device_ref(first_id)?;
+    let _ = client.platform_name()?;
+    assert_eq!(a.raw(), b.raw());
+
+    let local = client.lookup_addressable_device_ref(a.local_hardware_id()?)?;
+    assert_eq!(local.id(), b.id());
     Ok(())
 }
 
@@ -107,4 +149,488 @@ fn client_topology_and_assignment_smoke() -> Result<(), String> {
     Ok(())
 }
 
+#[test]
+fn client_alias_buffer_fulfill_smoke() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let client = rt.create_client_raii()?;
+    let raw_devices = client.devices()?;
+    assert!(!raw_devices.is_empty(), "expected at least one device");
+    let memory = PJRTDevice::new(&rt, raw_devices[0])?.default_memory_raw()?;
+
+    let host = [1.0f32, 2.0, 3.0, 4.0];
+    let alias = client.create_alias_buffer(
+        &[host.len() as i64],
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        Some(memory),
+        None,
+    )?;
+    assert!(
+        !alias.buffer().raw().is_null(),
+        "alias buffer should not be null"
+    );
+
+    let source = client.buffer_from_host_slice_copy(
+        &host,
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        &[host.len() as i64],
+        Some(raw_devices[0]),
+    )?;
+    let fulfilled = alias.fulfill(&source)?;
+
+    let mut out = [0u8; 4 * std::mem::size_of::<f32>()];
+    fulfilled.to_host_buffer_blocking(&mut out)?;
+    let values: Vec<f32> = out
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect();
+    assert_eq!(values, host.to_vec());
+    Ok(())
+}
+
+#[test]
+fn client_alias_buffer_fulfill_error_smoke() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let client = rt.create_client_raii()?;
+    let raw_devices = client.devices()?;
+    assert!(!raw_devices.is_empty(), "expected at least one device");
+    let memory = PJRTDevice::new(&rt, raw_devices[0])?.default_memory_raw()?;
+
+    let alias = client.create_alias_buffer(
+        &[2],
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        Some(memory),
+        None,
+    )?;
+    alias.fulfill_error(PjrtErrorCode::Internal, "producer failed")?;
+    Ok(())
+}
+
+#[test]
+fn client_view_of_device_buffer_runs_on_delete_closure() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let client = rt.create_client_raii()?;
+    let raw_devices = client.devices()?;
+    assert!(!raw_devices.is_empty(), "expected at least one device");
+
+    let mut host = vec![1.0f32, 2.0, 3.0, 4.0];
+    let data_ptr = host.as_mut_ptr().cast::<std::ffi::c_void>();
+    let deleted = Arc::new(AtomicBool::new(false));
+    let observer = Arc::clone(&deleted);
 
+    let view = client.create_view_of_device_buffer_with_on_delete(
+        data_ptr,
+        &[host.len() as i64],
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        Some(raw_devices[0]),
+        None,
+        None,
+        0,
+        Some(Box::new(move |ptr| {
+            assert_eq!(ptr, host.as_mut_ptr().cast::<std::ffi::c_void>());
+            drop(host);
+            observer.store(true, Ordering::SeqCst);
+        })),
+    )?;
+    assert!(
+        !deleted.load(Ordering::SeqCst),
+        "closure ran before the view was dropped"
+    );
+
+    drop(view);
+    assert!(
+        deleted.load(Ordering::SeqCst),
+        "on_delete closure did not run"
+    );
+    Ok(())
+}
+
+fn read_f32s(buffer: &PJRTBuffer<'_>, len: usize) -> Result<Vec<f32>, String> {
+    let values = buffer.to_host_vec::<f32>()?;
+    assert_eq!(values.len(), len, "unexpected element count");
+    Ok(values)
+}
+
+#[test]
+fn client_host_upload_owned_each_semantics() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let client = rt.create_client_raii()?;
+    let semantics = [
+        HostBufferSemantics::ImmutableOnlyDuringCall,
+        HostBufferSemantics::ImmutableUntilTransferCompletes,
+        HostBufferSemantics::ImmutableZeroCopy,
+        HostBufferSemantics::MutableZeroCopy,
+    ];
+
+    for sem in semantics {
+        let expected = vec![1.0f32, 2.0, 3.0, 4.0];
+        // The source Vec is moved into the call, so the caller can no longer keep it alive.
+        let buffer = client.buffer_from_host_owned_with(
+            expected.clone(),
+            PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+            &[expected.len() as i64],
+            sem,
+            None,
+        )?;
+
+        // Churn the allocator so a freed source allocation would likely be reused.
+        let scratch: Vec<Vec<f32>> = (0..64).map(|_| vec![-1.0f32; expected.len()]).collect();
+        assert_eq!(read_f32s(&buffer, expected.len())?, expected, "{sem:?}");
+        drop(scratch);
+    }
+    Ok(())
+}
+
+#[test]
+fn client_host_upload_arc_zero_copy() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let client = rt.create_client_raii()?;
+    let data: Arc<[f32]> = Arc::from(vec![5.0f32, 6.0, 7.0]);
+    let buffer = client.buffer_from_host_owned_with(
+        Arc::clone(&data),
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        &[3],
+        HostBufferSemantics::ImmutableZeroCopy,
+        None,
+    )?;
+    drop(data);
+    assert_eq!(read_f32s(&buffer, 3)?, vec![5.0, 6.0, 7.0]);
+
+    let mutable = client.buffer_from_host_owned_with(
+        Arc::<[f32]>::from(vec![1.0f32]),
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        &[1],
+        HostBufferSemantics::MutableZeroCopy,
+        None,
+    );
+    assert!(
+        mutable.is_err(),
+        "MutableZeroCopy must reject shared Arc data"
+    );
+    Ok(())
+}
+
+#[test]
+fn client_host_upload_borrowed_rejects_zero_copy() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let client = rt.create_client_raii()?;
+    let host = [1.0f32, 2.0];
+    for sem in [
+        HostBufferSemantics::ImmutableZeroCopy,
+        HostBufferSemantics::MutableZeroCopy,
+    ] {
+        let res = client.buffer_from_host_slice(
+            &host,
+            PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+            &[2],
+            sem,
+            None,
+        );
+        assert!(res.is_err(), "{sem:?} should be rejected for borrowed data");
+    }
+
+    let buffer = client.buffer_from_host_slice(
+        &host,
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        &[2],
+        HostBufferSemantics::ImmutableUntilTransferCompletes,
+        None,
+    )?;
+    assert_eq!(read_f32s(&buffer, 2)?, host.to_vec());
+    Ok(())
+}
+
+#[test]
+fn client_host_upload_column_major_2d() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let client = rt.create_client_raii()?;
+    // 3x2 matrix [[1, 2], [3, 4], [5, 6]] stored column by column.
+    let column_major = [1.0f32, 3.0, 5.0, 2.0, 4.0, 6.0];
+    let buffer = client.buffer_from_host_2d(
+        3,
+        2,
+        &column_major,
+        MajorOrder::ColumnMajor,
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        None,
+    )?;
+    assert_eq!(buffer.dimensions()?, vec![3, 2]);
+    assert_eq!(read_f32s(&buffer, 6)?, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+
+    let mismatched = client.buffer_from_host_nd(
+        &[3, 2],
+        &column_major[..5],
+        MajorOrder::RowMajor,
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        None,
+    );
+    assert!(mismatched.is_err(), "data length must match dims");
+    Ok(())
+}
+
+#[test]
+fn client_scalar_roundtrip() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let client = rt.create_client_raii()?;
+    let device = PJRTDevice::new(&rt, client.devices()?[0])?;
+
+    assert_eq!(
+        client
+            .buffer_from_scalar(41.5f32, Some(&device))?
+            .to_scalar::<f32>()?,
+        41.5
+    );
+    assert_eq!(
+        client.buffer_from_scalar(-3i64, None)?.to_scalar::<i64>()?,
+        -3
+    );
+    assert_eq!(
+        client.buffer_from_scalar(200u8, None)?.to_scalar::<u8>()?,
+        200
+    );
+    assert!(client.buffer_from_scalar(true, None)?.to_scalar::<bool>()?);
+
+    let err = client
+        .buffer_from_scalar(1.0f64, None)?
+        .to_scalar::<f32>()
+        .expect_err("dtype mismatch should fail");
+    assert!(
+        err.contains("f64"),
+        "error should name the actual type: {err}"
+    );
+
+    let vector = client.buffer_from_host_slice_copy(
+        &[1.0f32, 2.0],
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        &[2],
+        None,
+    )?;
+    assert!(
+        vector.to_scalar::<f32>().is_err(),
+        "rank-1 buffer is not a scalar"
+    );
+    Ok(())
+}
+
+#[test]
+fn client_host_upload_owned_large_vec() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let client = rt.create_client_raii()?;
+    let len = 1 << 20;
+    let data: Vec<f32> = (0..len).map(|i| i as f32).collect();
+    let buffer = client.buffer_from_host_owned(
+        data,
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        &[len as i64],
+        None,
+    )?;
+    buffer.ready_event()?.await_ready()?;
+
+    let values = read_f32s(&buffer, len)?;
+    assert!(values.iter().enumerate().all(|(i, v)| *v == i as f32));
+    Ok(())
+}
+
+#[test]
+fn client_host_upload_keepalive_released_after_transfer() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let client = rt.create_client_raii()?;
+    let mut shared = Vec::new();
+    for i in 0..64 {
+        let data: Arc<[f32]> = Arc::from(vec![i as f32; 4096]);
+        let buffer = client.buffer_from_host_owned_with(
+            Arc::clone(&data),
+            PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+            &[4096],
+            HostBufferSemantics::ImmutableUntilTransferCompletes,
+            None,
+        )?;
+        // Dropping the buffer early must not release the host data before the transfer.
+        if i % 2 == 0 {
+            drop(buffer);
+        } else {
+            buffer.ready_event()?.await_ready()?;
+            assert!(read_f32s(&buffer, 4096)?.iter().all(|v| *v == i as f32));
+        }
+        shared.push(data);
+    }
+
+    // Every keepalive clone is dropped once its done event fires.
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+    while shared.iter().any(|d| Arc::strong_count(d) > 1) {
+        if std::time::Instant::now() > deadline {
+            return Err("host data still referenced after transfers completed".to_string());
+        }
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+    Ok(())
+}
+
+#[test]
+fn client_upload_batch_many_tensors() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let client = rt.create_client_raii()?;
+    let host: Vec<Vec<f32>> = (0..50).map(|i| vec![i as f32; 8]).collect();
+    let dims = [2i64, 4];
+    let items: Vec<HostArray<'_>> = host.iter().map(|v| HostArray::new(v, &dims)).collect();
+
+    let buffers = client.upload_batch(&items, None)?;
+    assert_eq!(buffers.len(), 50);
+    for (i, buf) in buffers.iter().enumerate() {
+        assert_eq!(read_f32s(buf, 8)?, host[i], "tensor {i}");
+    }
+
+    let bad_dims = [3i64];
+    let mut bad = items;
+    bad.push(HostArray::new(&host[0], &bad_dims));
+    let err = match client.upload_batch(&bad, None) {
+        Ok(_) => return Err("mismatched dims should fail".to_string()),
+        Err(e) => e,
+    };
+    assert!(
+        err.contains("[50]"),
+        "error should name the failing index: {err}"
+    );
+    Ok(())
+}
+
+#[test]
+fn client_raw_and_wrapped_device_lists_agree() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let client = rt.create_client_raii()?;
+    let raw: Vec<_> = client.devices()?;
+    let wrapped: Vec<_> = client.device_refs()?.iter().map(|d| d.raw()).collect();
+    assert_eq!(raw, wrapped);
+
+    let addressable = client.addressable_devices()?;
+    assert!(
+        !addressable.is_empty(),
+        "expected at least one addressable device"
+    );
+    let addressable_refs = client.addressable_device_refs()?;
+    assert_eq!(addressable.len(), addressable_refs.len());
+    Ok(())
+}
+
+#[test]
+fn client_buffer_host_byte_size_and_short_destination() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let client = rt.create_client_raii()?;
+    let buffer = client.buffer_from_host_2d(
+        3,
+        2,
+        &[0.0f32; 6],
+        MajorOrder::RowMajor,
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        None,
+    )?;
+    assert_eq!(buffer.element_count()?, 6);
+    assert_eq!(buffer.host_byte_size()?, 24);
+
+    let mut short = [0u8; 8];
+    let err = match buffer.to_host_buffer_async(&mut short) {
+        Ok(_) => return Err("short destination should be rejected".to_string()),
+        Err(e) => e,
+    };
+    assert!(err.contains("needs 24"), "{err}");
+    Ok(())
+}
+
+#[test]
+fn client_to_host_with_transposed_layout() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let client = rt.create_client_raii()?;
+    let host = [1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0];
+    let buffer = client.buffer_from_host_2d(
+        2,
+        3,
+        &host,
+        MajorOrder::RowMajor,
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        None,
+    )?;
+
+    let decode = |bytes: &[u8]| -> Vec<f32> {
+        bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect()
+    };
+
+    let mut default_bytes = [0u8; 24];
+    buffer.to_host_buffer_blocking(&mut default_bytes)?;
+    assert_eq!(decode(&default_bytes), host.to_vec());
+
+    let mut transposed_bytes = [0u8; 24];
+    buffer.to_host_buffer_blocking_with_layout(
+        &mut transposed_bytes,
+        &MemoryLayout::column_major(2),
+    )?;
+    assert_eq!(
+        decode(&transposed_bytes),
+        vec![1.0, 4.0, 2.0, 5.0, 3.0, 6.0]
+    );
+    Ok(())
+}
+
+#[test]
+fn client_topology_memory_descriptions_smoke() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let client = rt.create_client_raii()?;
+    let topology = client.topology_description()?;
+    let descs = topology.device_descriptions()?;
+    let (memories, default) = descs[0].memory_descriptions()?;
+    assert!(
+        !memories.is_empty(),
+        "device should offer at least one memory kind"
+    );
+    assert!(memories.iter().all(|m| !m.kind.is_empty()), "{memories:?}");
+    if let Some(default) = default {
+        assert!(default < memories.len());
+    }
+    Ok(())
+}
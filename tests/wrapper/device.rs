@@ -1,6 +1,6 @@
-use std::path::{Path, PathBuf};
 use rrad_xla::pjrt::device::PJRTDevice;
 use rrad_xla::pjrt::loader::PjrtRuntime;
+use std::path::{Path, PathBuf};
 
 fn resolve_plugin_path() -> Option<PathBuf> {
     if let Ok(path) = std::env::var("PJRT_PLUGIN") {
@@ -41,17 +41,23 @@ fn general_hardware_smoke() -> Result<(), String> {
     let Some(rt) = runtime_or_skip()? else {
         return Ok(());
     };
-    
+
     let client = rt.create_client_raii()?;
     let raw_devices = client.devices()?;
     for device in raw_devices {
         assert!(!device.is_null(), "raw device should not be null");
-        let device_ = PJRTDevice::new(&rt, device);
+        let device_ = PJRTDevice::new(&rt, device)?;
         let hardware_id = device_.local_hardware_id()?;
         let async_tracking_event = device_.create_async_tracking_event("test")?;
-        assert!(hardware_id.is_negative(), "local hardware id should be negative");
-        assert!(async_tracking_event.raw().is_null(), "async tracking event should be null");
-    };
+        assert!(
+            hardware_id.is_negative(),
+            "local hardware id should be negative"
+        );
+        assert!(
+            async_tracking_event.raw().is_null(),
+            "async tracking event should be null"
+        );
+    }
     Ok(())
 }
 
@@ -64,11 +70,17 @@ fn device_basic_metadata_smoke() -> Result<(), String> {
     let client = rt.create_client_raii()?;
     let raw_devices = client.devices()?;
     assert!(!raw_devices.is_empty(), "expected at least one device");
-    assert!(!raw_devices[0].is_null(), "first raw device should not be null");
+    assert!(
+        !raw_devices[0].is_null(),
+        "first raw device should not be null"
+    );
 
-    let device = PJRTDevice::new(&rt, raw_devices[0]);
-    assert!(device.id()? >= 0, "device id should be non-negative");
-    assert!(!device.kind()?.is_empty(), "device kind should be non-empty");
+    let device = PJRTDevice::new(&rt, raw_devices[0])?;
+    assert!(device.id() >= 0, "device id should be non-negative");
+    assert!(
+        !device.kind()?.is_empty(),
+        "device kind should be non-empty"
+    );
     Ok(())
 }
 
@@ -82,11 +94,14 @@ fn device_description_smoke() -> Result<(), String> {
     let raw_devices = client.devices()?;
     assert!(!raw_devices.is_empty(), "expected at least one device");
 
-    let device = PJRTDevice::new(&rt, raw_devices[0]);
+    let device = PJRTDevice::new(&rt, raw_devices[0])?;
     let desc = device.description()?;
 
     assert!(desc.id()? >= 0, "description id should be non-negative");
-    assert!(!desc.kind()?.is_empty(), "description kind should be non-empty");
+    assert!(
+        !desc.kind()?.is_empty(),
+        "description kind should be non-empty"
+    );
     assert!(
         !desc.to_string()?.is_empty(),
         "description to_string should be non-empty"
@@ -104,7 +119,7 @@ fn device_is_addressable_smoke() -> Result<(), String> {
     let raw_devices = client.devices()?;
     assert!(!raw_devices.is_empty(), "expected at least one device");
 
-    let device = PJRTDevice::new(&rt, raw_devices[0]);
+    let device = PJRTDevice::new(&rt, raw_devices[0])?;
     assert!(
         device.is_addressable()?,
         "first runtime device should be addressable"
@@ -1,7 +1,12 @@
 use std::path::{Path, PathBuf};
 use std::ptr::null_mut;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
 
-use rrad_xla::pjrt::event::PJRTEvent;
+use rrad_xla::pjrt::buffer::PJRTBuffer;
+use rrad_xla::pjrt::error::{PJRTError, PjrtErrorCode};
+use rrad_xla::pjrt::event::{PJRTEvent, PjrtEventError, WaitOutcome};
 use rrad_xla::pjrt::loader::PjrtRuntime;
 use rrad_xla::pjrt_sys::PJRT_Buffer_Type_PJRT_Buffer_Type_F32;
 
@@ -28,6 +33,15 @@ fn resolve_plugin_path() -> Option<PathBuf> {
     None
 }
 
+// Records that it was dropped.
+struct Flag(Arc<AtomicBool>);
+
+impl Drop for Flag {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
 fn runtime_or_skip() -> Result<Option<PjrtRuntime>, String> {
     let Some(plugin_path) = resolve_plugin_path() else {
         eprintln!("Skipping wrapper::event tests: PJRT plugin not found");
@@ -61,8 +75,8 @@ fn event_on_ready_requires_callback() -> Result<(), String> {
 
     let event = PJRTEvent::create(&rt)?;
     let err = event
-        .on_ready(None, null_mut())
-        .expect_err("on_ready(None, ..) should return an error");
+        .on_ready_raw(None, null_mut())
+        .expect_err("on_ready_raw(None, ..) should return an error");
     assert!(
         err.contains("callback"),
         "expected callback validation error, got: {err}"
@@ -98,3 +112,277 @@ fn event_from_buffer_ready_event_smoke() -> Result<(), String> {
     Ok(())
 }
 
+#[test]
+fn event_on_ready_closure_sees_success() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let client = rt.create_client_raii()?;
+    let buffer = client.buffer_from_host_slice_copy(
+        &[1.0f32, 2.0],
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        &[2],
+        None,
+    )?;
+
+    let (tx, rx) = mpsc::channel();
+    buffer.ready_event()?.on_ready(move |status| {
+        let _ = tx.send(status.map_err(|e| e.to_string()));
+    })?;
+    let status = rx
+        .recv_timeout(Duration::from_secs(10))
+        .map_err(|e| format!("on_ready callback never ran: {e}"))?;
+    assert_eq!(status, Ok(()));
+    Ok(())
+}
+
+#[test]
+fn event_on_ready_closure_sees_error() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    // Already failed, so the plugin may run the callback inside PJRT_Event_OnReady.
+    let event = PJRTEvent::create(&rt)?;
+    event.set(&PJRTError::with_code(
+        &rt,
+        PjrtErrorCode::Aborted,
+        "cancelled by test",
+    ))?;
+
+    let (tx, rx) = mpsc::channel();
+    event.on_ready(move |status| {
+        let _ = tx.send(status.map_err(|e| (e.code(), e.message().to_string())));
+    })?;
+    let status = rx
+        .recv_timeout(Duration::from_secs(10))
+        .map_err(|e| format!("on_ready callback never ran: {e}"))?;
+    assert_eq!(
+        status,
+        Err((PjrtErrorCode::Aborted, "cancelled by test".to_string()))
+    );
+    Ok(())
+}
+
+#[test]
+fn event_await_timeout_leaves_pending_event_usable() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    // Nothing ever sets this event until the test does.
+    let event = PJRTEvent::create(&rt)?;
+    let outcome = event
+        .await_timeout(Duration::from_millis(20))
+        .map_err(|e| e.to_string())?;
+    assert!(matches!(outcome, WaitOutcome::TimedOut), "{outcome:?}");
+    assert!(!event.is_ready()?);
+
+    event.set(&PJRTError::with_code(
+        &rt,
+        PjrtErrorCode::Aborted,
+        "stopped",
+    ))?;
+    match event
+        .await_timeout(Duration::from_secs(10))
+        .map_err(|e| e.to_string())?
+    {
+        WaitOutcome::Ready(Err(e)) => assert_eq!(e.code(), PjrtErrorCode::Aborted),
+        other => return Err(format!("expected the set error, got {other:?}")),
+    }
+    assert!(event.await_ready().is_err());
+    Ok(())
+}
+
+#[test]
+fn event_status_round_trips_error_code() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    for code in [
+        PjrtErrorCode::ResourceExhausted,
+        PjrtErrorCode::InvalidArgument,
+    ] {
+        let event = PJRTEvent::create(&rt)?;
+        event.set(&PJRTError::with_code(&rt, code, "from test"))?;
+        assert_eq!(
+            event.status(),
+            Err(PjrtEventError {
+                code,
+                message: "from test".to_string(),
+            })
+        );
+        assert_eq!(event.ok(), Err("from test".to_string()));
+    }
+    Ok(())
+}
+
+// Ready events of `ok` at even indices, events failed with INTERNAL at odd ones.
+fn mixed_events<'a>(
+    rt: &'a PjrtRuntime,
+    ok: &PJRTBuffer<'a>,
+) -> Result<Vec<PJRTEvent<'a>>, String> {
+    let mut events = Vec::new();
+    for i in 0..4 {
+        if i % 2 == 0 {
+            events.push(ok.ready_event()?);
+        } else {
+            let failed = PJRTEvent::create(rt)?;
+            failed.set(&PJRTError::with_code(
+                rt,
+                PjrtErrorCode::Internal,
+                format!("event {i} failed"),
+            ))?;
+            events.push(failed);
+        }
+    }
+    Ok(events)
+}
+
+fn assert_odd_failures(failures: Vec<(usize, PJRTError<'_>)>) {
+    let reported: Vec<(usize, String)> = failures
+        .iter()
+        .map(|(i, e)| (*i, e.message().to_string()))
+        .collect();
+    assert_eq!(
+        reported,
+        vec![
+            (1, "event 1 failed".to_string()),
+            (3, "event 3 failed".to_string())
+        ]
+    );
+    assert!(failures
+        .iter()
+        .all(|(_, e)| e.code() == PjrtErrorCode::Internal));
+}
+
+#[test]
+fn event_join_all_reports_every_failure() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+    let client = rt.create_client_raii()?;
+    let ok = client.buffer_from_host_slice_copy(
+        &[1.0f32],
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        &[1],
+        None,
+    )?;
+
+    assert!(PJRTEvent::join_all(Vec::new()).is_ok());
+    match PJRTEvent::join_all(mixed_events(&rt, &ok)?) {
+        Ok(()) => return Err("join_all should report the failed events".to_string()),
+        Err(failures) => assert_odd_failures(failures),
+    }
+    Ok(())
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn event_join_all_async_reports_every_failure() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+    let client = rt.create_client_raii()?;
+    let ok = client.buffer_from_host_slice_copy(
+        &[1.0f32],
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        &[1],
+        None,
+    )?;
+
+    match PJRTEvent::join_all_async(mixed_events(&rt, &ok)?).await {
+        Ok(()) => return Err("join_all_async should report the failed events".to_string()),
+        Err(failures) => assert_odd_failures(failures),
+    }
+    Ok(())
+}
+
+#[test]
+fn event_keepalive_outlives_event_destroy() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let dropped = Arc::new(AtomicBool::new(false));
+    let mut event = PJRTEvent::create(&rt)?;
+    event.attach_keepalive(Box::new(Flag(Arc::clone(&dropped))));
+    event.attach_keepalive(Box::new(vec![0u8; 16]));
+    assert!(!event.is_ready()?);
+    assert!(!dropped.load(Ordering::SeqCst));
+
+    drop(event);
+    assert!(dropped.load(Ordering::SeqCst));
+    assert_eq!(Arc::strong_count(&dropped), 1);
+    Ok(())
+}
+
+#[test]
+fn event_try_status_polls_without_waiting() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    let client = rt.create_client_raii()?;
+    let buffer = client.buffer_from_host_slice_copy(
+        &[1.0f32],
+        PJRT_Buffer_Type_PJRT_Buffer_Type_F32,
+        &[1],
+        None,
+    )?;
+    let ready = buffer.ready_event()?;
+    ready.await_ready()?;
+    assert!(matches!(ready.try_status()?, Some(Ok(()))));
+
+    // Pending until the test sets it, then reports the final status on every poll.
+    let delayed = PJRTEvent::create(&rt)?;
+    for _ in 0..3 {
+        assert!(delayed.try_status()?.is_none());
+    }
+    delayed.set(&PJRTError::with_code(
+        &rt,
+        PjrtErrorCode::DeadlineExceeded,
+        "too slow",
+    ))?;
+    for _ in 0..2 {
+        match delayed.try_status()? {
+            Some(Err(e)) => assert_eq!(e.code(), PjrtErrorCode::DeadlineExceeded),
+            other => return Err(format!("expected the set error, got {other:?}")),
+        }
+    }
+    assert!(delayed.is_ready()?);
+    Ok(())
+}
+
+#[test]
+fn event_destroy_and_raw_handoff_keep_keepalive() -> Result<(), String> {
+    let Some(rt) = runtime_or_skip()? else {
+        return Ok(());
+    };
+
+    // Eager destroy reports success and drops the keepalive with the wrapper.
+    let dropped = Arc::new(AtomicBool::new(false));
+    let mut event = PJRTEvent::create(&rt)?;
+    event.attach_keepalive(Box::new(Flag(Arc::clone(&dropped))));
+    event.destroy()?;
+    assert!(dropped.load(Ordering::SeqCst));
+
+    // Handing the raw event off returns the keepalive instead of dropping it.
+    let dropped = Arc::new(AtomicBool::new(false));
+    let mut event = PJRTEvent::create(&rt)?;
+    event.attach_keepalive(Box::new(Flag(Arc::clone(&dropped))));
+    let (raw, keepalive) = event.into_raw_parts();
+    assert!(!raw.is_null());
+    assert_eq!(keepalive.len(), 1);
+    assert!(!dropped.load(Ordering::SeqCst));
+
+    // Taking the event back destroys it exactly once, through destroy() and not again on drop.
+    let event = unsafe { PJRTEvent::from_raw(&rt, raw) };
+    event.set(&PJRTError::with_code(&rt, PjrtErrorCode::Aborted, "done"))?;
+    event.destroy()?;
+    drop(keepalive);
+    assert!(dropped.load(Ordering::SeqCst));
+    Ok(())
+}
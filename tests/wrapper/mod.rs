@@ -1,5 +1,6 @@
+pub mod buffer;
 pub mod client;
 pub mod device;
+pub mod event;
 pub mod memory;
 pub mod unified;
-pub mod event;